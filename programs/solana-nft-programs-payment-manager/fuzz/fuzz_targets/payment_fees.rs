@@ -0,0 +1,162 @@
+//! Feeds arbitrary payments, payment manager fee settings and metadata royalties into the fee
+//! computation and checks that it does not panic and that every token the payer is debited is
+//! paid out to exactly one recipient
+//!
+//! Run with `cargo fuzz run payment_fees` from the program directory. The input is decoded with a
+//! fixed little endian layout, missing bytes read as zero, so the seed corpus stays meaningful:
+//!
+//! | bytes  | field                                |
+//! | ------ | ------------------------------------ |
+//! | 0..8   | payment amount                       |
+//! | 8..10  | maker fee basis points               |
+//! | 10..12 | taker fee basis points               |
+//! | 12..14 | metadata seller fee basis points     |
+//! | 14..16 | royalty fee share                    |
+//! | 16..24 | max creators fee                     |
+//! | 24..26 | max royalty basis points             |
+//! | 26..28 | flags, see `FeeInput::from_bytes`    |
+//! | 28..   | creator shares, one byte per creator |
+#![no_main]
+
+use {
+    anchor_lang::prelude::Pubkey,
+    libfuzzer_sys::fuzz_target,
+    mpl_token_metadata::types::Creator,
+    solana_nft_programs_payment_manager::{
+        state::{FeeMode, PaymentManager, PayoutOrder, CREATOR_SHARE_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE, MAX_CREATORS},
+        util::{buy_side_fee_amount, creator_payouts, maker_taker_fees, payer_buy_side_fee, payer_fees, payment_fees, validate_fee_config},
+    },
+};
+
+struct FeeInput {
+    payment_amount: u64,
+    payment_manager: PaymentManager,
+    metadata_seller_fee_basis_points: Option<u16>,
+    creator_shares: Option<Vec<u8>>,
+}
+
+impl FeeInput {
+    fn from_bytes(data: &[u8]) -> Self {
+        let mut bytes = [0u8; 28];
+        let header_len = data.len().min(bytes.len());
+        bytes[..header_len].copy_from_slice(&data[..header_len]);
+        let u16_at = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+        let u64_at = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        // 0 include seller fee, 1 seller fee additive, 2 clamp royalties, 3 taker fee collector,
+        // 4 taker covers maker fee, 5 buy side additive, 6 remainder to collector, 7 metadata seller
+        // fee, 8 royalty fee share, 9 max creators fee, 10 max royalty, 11 creators
+        let flags = u16_at(26);
+        let flag = |bit: u16| flags & (1 << bit) != 0;
+
+        let payment_manager = PaymentManager {
+            bump: 255,
+            fee_collector: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            maker_fee_basis_points: u16_at(8),
+            taker_fee_basis_points: u16_at(10),
+            name: "fuzz".to_string(),
+            include_seller_fee_basis_points: flag(0),
+            royalty_fee_share: flag(8).then(|| u64::from(u16_at(14))),
+            flat_fee_amount: None,
+            royalty_conversion_rate_bounds: None,
+            strict_remaining_accounts: false,
+            authority_threshold: None,
+            admins: vec![],
+            max_royalty_basis_points: flag(10).then(|| u16_at(24)),
+            clamp_royalties: flag(2),
+            payout_order: PayoutOrder::CreatorsFirst as u8,
+            royalty_min_price: None,
+            quote_conversion_rate_bounds: None,
+            handle_primary_sales: false,
+            min_payment_interval_slots: None,
+            max_creators_fee: flag(9).then(|| u64_at(16)),
+            royalty_floor_basis_points: None,
+            pending_fee_schedule: None,
+            taker_fee_collector: flag(3).then(Pubkey::new_unique),
+            required_collection: None,
+            seller_fee_within_fees: flag(1),
+            fee_mode: FeeMode::BasisPoints as u8,
+            record_payments: false,
+            primary_sale_target: None,
+            fallback_royalty_to_update_authority: false,
+            taker_covers_maker_fee: flag(4),
+            claim_royalties: false,
+            bridged_metadata_program: None,
+            accepted_mints: vec![],
+            remainder_to_collector: flag(6),
+            skip_mismatched_creator_mints: false,
+            fee_tiers: vec![],
+            buy_side_additive: flag(5),
+            metadata_program_id: None,
+        };
+        FeeInput {
+            payment_amount: u64_at(0),
+            payment_manager,
+            metadata_seller_fee_basis_points: flag(7).then(|| u16_at(12)),
+            creator_shares: flag(11).then(|| data.get(bytes.len()..).unwrap_or_default().iter().take(MAX_CREATORS).copied().collect()),
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let FeeInput {
+        payment_amount,
+        payment_manager,
+        metadata_seller_fee_basis_points,
+        creator_shares,
+    } = FeeInput::from_bytes(data);
+    // the program refuses to store any other fee config
+    if validate_fee_config(&payment_manager).is_err() {
+        return;
+    }
+    // the payer has to be able to cover the payment and the fees on top of it
+    let (maker_fee, taker_fee) = maker_taker_fees(&payment_manager, payment_amount);
+    let buy_side_fee = buy_side_fee_amount(payment_amount, DEFAULT_BUY_SIDE_FEE_SHARE);
+    if maker_fee
+        .checked_add(taker_fee)
+        .and_then(|fees| fees.checked_add(buy_side_fee))
+        .and_then(|fees| payment_amount.checked_add(fees))
+        .is_none()
+    {
+        return;
+    }
+    // metadata with shares adding up to more than 100 is rejected by the metadata program
+    if creator_shares
+        .as_ref()
+        .map_or(false, |shares| shares.iter().map(|share| u16::from(*share)).sum::<u16>() > CREATOR_SHARE_DIVISOR.into())
+    {
+        return;
+    }
+
+    // royalties above the limit are an error, not a panic
+    let Ok(fees) = payment_fees(&payment_manager, payment_amount, metadata_seller_fee_basis_points) else {
+        return;
+    };
+    assert!(fees.taker_fee_collector_fee <= fees.total_fees);
+    assert!(fees.total_creators_fee <= fees.total_fees - fees.taker_fee_collector_fee);
+    assert!(fees.buy_side_fee <= payment_amount);
+
+    let creators: Option<Vec<Creator>> = creator_shares.map(|shares| {
+        shares
+            .iter()
+            .map(|share| Creator {
+                address: Pubkey::new_unique(),
+                verified: true,
+                share: *share,
+            })
+            .collect()
+    });
+    let fees_paid_out: u64 = creators.map_or(0, |creators| {
+        creator_payouts(&creators, fees.total_creators_fee, payment_manager.remainder_to_collector)
+            .iter()
+            .map(|(_, amount)| *amount)
+            .sum()
+    });
+    assert!(fees_paid_out <= fees.total_creators_fee);
+
+    // whatever the creators and the taker fee collector do not receive is left to the fee collector
+    let fee_collector_fee = fees.total_fees - fees.taker_fee_collector_fee - fees_paid_out;
+    let outflow = u128::from(fee_collector_fee) + u128::from(fees_paid_out) + u128::from(fees.taker_fee_collector_fee) + u128::from(fees.buy_side_fee) + u128::from(fees.principal_amount);
+    let debited = u128::from(payment_amount) + u128::from(payer_fees(&payment_manager, maker_fee, taker_fee)) + u128::from(payer_buy_side_fee(&payment_manager, fees.buy_side_fee));
+    assert_eq!(outflow, debited);
+});
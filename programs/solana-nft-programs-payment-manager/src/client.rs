@@ -0,0 +1,185 @@
+use {
+    crate::{state::PaymentManager, util::find_royalty_claim_address},
+    anchor_lang::{prelude::*, solana_program::instruction::AccountMeta},
+    mpl_token_metadata::types::Creator,
+    spl_associated_token_account::get_associated_token_address,
+};
+
+/// Remaining accounts of `handle_payment_with_royalties` in the order the handler consumes them.
+/// Each creator with a share comes first, their royalty claim when the payment manager has creators
+/// claim royalties later and otherwise their token account of the payment mint, followed by their
+/// royalty claim when unreceivable royalties are escrowed. Without creators the update authority's
+/// token account takes their place when royalties fall back to it. The taker fee collector's token
+/// account and the buy side recipient's token account follow.
+/// `creators` are the royalty creators of the mint, the creator config's when one is set, and both
+/// `creators` and `update_authority` are `None` when no royalties are paid, e.g. when they are waived
+pub fn handle_payment_with_royalties_remaining_accounts(
+    payment_manager_id: &Pubkey,
+    payment_manager: &PaymentManager,
+    payment_mint: &Pubkey,
+    creators: Option<&[Creator]>,
+    update_authority: Option<&Pubkey>,
+    buy_side_recipient: Option<&Pubkey>,
+) -> Vec<AccountMeta> {
+    let mut remaining_accounts = Vec::new();
+    match (creators, update_authority) {
+        (Some(creators), _) => {
+            // creators without a share are paid nothing and need no account
+            let paid_creators = creators.iter().filter(|creator| creator.share != 0);
+            for creator in paid_creators {
+                let royalty_claim = find_royalty_claim_address(payment_manager_id, payment_mint, &creator.address).0;
+                if payment_manager.claim_royalties {
+                    remaining_accounts.push(AccountMeta::new(royalty_claim, false));
+                    continue;
+                }
+                remaining_accounts.push(AccountMeta::new(get_associated_token_address(&creator.address, payment_mint), false));
+                if payment_manager.escrow_unreceivable_royalties {
+                    remaining_accounts.push(AccountMeta::new(royalty_claim, false));
+                }
+            }
+        }
+        (None, Some(update_authority)) if payment_manager.fallback_royalty_to_update_authority => {
+            remaining_accounts.push(AccountMeta::new(get_associated_token_address(update_authority, payment_mint), false));
+        }
+        _ => {}
+    }
+    if let Some(taker_fee_collector) = payment_manager.taker_fee_collector {
+        remaining_accounts.push(AccountMeta::new(get_associated_token_address(&taker_fee_collector, payment_mint), false));
+    }
+    if let Some(buy_side_recipient) = buy_side_recipient {
+        remaining_accounts.push(AccountMeta::new(get_associated_token_address(buy_side_recipient, payment_mint), false));
+    }
+    remaining_accounts
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::util::{
+            creator_payouts,
+            tests::{metadata_creator as creator, payment_manager},
+        },
+    };
+
+    fn keys(remaining_accounts: &[AccountMeta]) -> Vec<Pubkey> {
+        remaining_accounts.iter().map(|account| account.pubkey).collect()
+    }
+
+    #[test]
+    fn test_remaining_accounts_order() {
+        let payment_manager_id = Pubkey::new_unique();
+        let payment_mint = Pubkey::new_unique();
+        let buy_side_recipient = Pubkey::new_unique();
+        let taker_fee_collector = Pubkey::new_unique();
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.taker_fee_collector = Some(taker_fee_collector);
+        let creators = vec![creator(60), creator(0), creator(40)];
+
+        let remaining_accounts = handle_payment_with_royalties_remaining_accounts(&payment_manager_id, &payment_manager, &payment_mint, Some(&creators), None, Some(&buy_side_recipient));
+        // creators without a share are skipped the same way creator_payouts skips them
+        assert_eq!(
+            keys(&remaining_accounts),
+            vec![
+                get_associated_token_address(&creators[0].address, &payment_mint),
+                get_associated_token_address(&creators[2].address, &payment_mint),
+                get_associated_token_address(&taker_fee_collector, &payment_mint),
+                get_associated_token_address(&buy_side_recipient, &payment_mint),
+            ]
+        );
+        assert!(remaining_accounts.iter().all(|account| account.is_writable && !account.is_signer));
+    }
+
+    #[test]
+    fn test_remaining_accounts_match_creator_payouts() {
+        let payment_manager_id = Pubkey::new_unique();
+        let payment_mint = Pubkey::new_unique();
+        let payment_manager = payment_manager(500, 300);
+        let creators = vec![creator(0), creator(20), creator(30), creator(0), creator(50)];
+
+        let remaining_accounts = handle_payment_with_royalties_remaining_accounts(&payment_manager_id, &payment_manager, &payment_mint, Some(&creators), None, None);
+        let payout_accounts: Vec<Pubkey> = creator_payouts(&creators, 1_000, false)
+            .into_iter()
+            .map(|(creator, _)| get_associated_token_address(&creator, &payment_mint))
+            .collect();
+        assert_eq!(keys(&remaining_accounts), payout_accounts);
+    }
+
+    #[test]
+    fn test_remaining_accounts_royalty_claims() {
+        let payment_manager_id = Pubkey::new_unique();
+        let payment_mint = Pubkey::new_unique();
+        let buy_side_recipient = Pubkey::new_unique();
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.claim_royalties = true;
+        let creators = vec![creator(70), creator(30)];
+
+        let remaining_accounts = handle_payment_with_royalties_remaining_accounts(&payment_manager_id, &payment_manager, &payment_mint, Some(&creators), None, Some(&buy_side_recipient));
+        assert_eq!(
+            keys(&remaining_accounts),
+            vec![
+                find_royalty_claim_address(&payment_manager_id, &payment_mint, &creators[0].address).0,
+                find_royalty_claim_address(&payment_manager_id, &payment_mint, &creators[1].address).0,
+                get_associated_token_address(&buy_side_recipient, &payment_mint),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remaining_accounts_escrowed_royalties() {
+        let payment_manager_id = Pubkey::new_unique();
+        let payment_mint = Pubkey::new_unique();
+        let buy_side_recipient = Pubkey::new_unique();
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.escrow_unreceivable_royalties = true;
+        let creators = vec![creator(70), creator(30)];
+
+        // each creator's royalty claim follows their token account
+        let remaining_accounts = handle_payment_with_royalties_remaining_accounts(&payment_manager_id, &payment_manager, &payment_mint, Some(&creators), None, Some(&buy_side_recipient));
+        assert_eq!(
+            keys(&remaining_accounts),
+            vec![
+                get_associated_token_address(&creators[0].address, &payment_mint),
+                find_royalty_claim_address(&payment_manager_id, &payment_mint, &creators[0].address).0,
+                get_associated_token_address(&creators[1].address, &payment_mint),
+                find_royalty_claim_address(&payment_manager_id, &payment_mint, &creators[1].address).0,
+                get_associated_token_address(&buy_side_recipient, &payment_mint),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remaining_accounts_without_creators() {
+        let payment_manager_id = Pubkey::new_unique();
+        let payment_mint = Pubkey::new_unique();
+        let update_authority = Pubkey::new_unique();
+        let buy_side_recipient = Pubkey::new_unique();
+        let mut payment_manager = payment_manager(500, 300);
+
+        // without the fallback the update authority is not paid
+        let remaining_accounts = handle_payment_with_royalties_remaining_accounts(&payment_manager_id, &payment_manager, &payment_mint, None, Some(&update_authority), Some(&buy_side_recipient));
+        assert_eq!(keys(&remaining_accounts), vec![get_associated_token_address(&buy_side_recipient, &payment_mint)]);
+
+        payment_manager.fallback_royalty_to_update_authority = true;
+        let remaining_accounts = handle_payment_with_royalties_remaining_accounts(&payment_manager_id, &payment_manager, &payment_mint, None, Some(&update_authority), Some(&buy_side_recipient));
+        assert_eq!(
+            keys(&remaining_accounts),
+            vec![
+                get_associated_token_address(&update_authority, &payment_mint),
+                get_associated_token_address(&buy_side_recipient, &payment_mint)
+            ]
+        );
+
+        // creators that are listed take precedence over the update authority, even without shares
+        let creators = vec![creator(0)];
+        let remaining_accounts = handle_payment_with_royalties_remaining_accounts(
+            &payment_manager_id,
+            &payment_manager,
+            &payment_mint,
+            Some(&creators),
+            Some(&update_authority),
+            Some(&buy_side_recipient),
+        );
+        assert_eq!(keys(&remaining_accounts), vec![get_associated_token_address(&buy_side_recipient, &payment_mint)]);
+    }
+}
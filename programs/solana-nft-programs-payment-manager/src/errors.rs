@@ -10,6 +10,7 @@ pub enum ErrorCode {
     InvalidMintMetadata,
     #[msg("Invalid creator address")]
     InvalidCreatorAddress,
+    // no longer returned, kept so the codes of the errors after it do not shift
     #[msg("Invalid token account")]
     InvalidTokenAccount,
     #[msg("Invalid payment manager")]
@@ -18,4 +19,90 @@ pub enum ErrorCode {
     InvalidMintMetadataOwner,
     #[msg("Invalid fee collector")]
     InvalidFeeCollector,
+    #[msg("Invalid conversion rate")]
+    InvalidConversionRate,
+    #[msg("Invalid buy side account")]
+    InvalidBuySideAccount,
+    #[msg("Unexpected remaining accounts")]
+    UnexpectedRemainingAccounts,
+    #[msg("Invalid authority threshold")]
+    InvalidAuthorityThreshold,
+    #[msg("Royalties exceed limit")]
+    RoyaltiesExceedLimit,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Invalid payout order")]
+    InvalidPayoutOrder,
+    #[msg("Invalid creator config")]
+    InvalidCreatorConfig,
+    #[msg("Failed to deserialize mint metadata")]
+    MetadataDeserializationFailed,
+    #[msg("Fee config exceeds limit")]
+    FeeConfigExceedsLimit,
+    #[msg("Invalid protocol config")]
+    InvalidProtocolConfig,
+    #[msg("Invalid payer rate limit")]
+    InvalidPayerRateLimit,
+    #[msg("Payment rate limited")]
+    PaymentRateLimited,
+    #[msg("Insufficient payer balance")]
+    InsufficientPayerBalance,
+    #[msg("Invalid proceeds escrow")]
+    InvalidProceedsEscrow,
+    #[msg("Invalid taker fee collector token account")]
+    InvalidTakerFeeCollectorTokenAccount,
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+    #[msg("Mint is not part of the verified required collection")]
+    UnverifiedCollection,
+    #[msg("Invalid fee mode")]
+    InvalidFeeMode,
+    #[msg("Token program does not own the mint")]
+    InvalidTokenProgram,
+    #[msg("Invalid fill fraction")]
+    InvalidFillFraction,
+    #[msg("Invalid payment record")]
+    InvalidPaymentRecord,
+    #[msg("Invalid primary sale target token account")]
+    InvalidPrimarySaleTargetTokenAccount,
+    #[msg("Token account is owned by the wrong wallet")]
+    InvalidTokenAccountOwner,
+    #[msg("Token account holds the wrong mint")]
+    InvalidTokenAccountMint,
+    #[msg("Failed to deserialize token account")]
+    TokenAccountDeserializationFailed,
+    #[msg("Missing payment token account")]
+    MissingPaymentTokenAccount,
+    #[msg("Invalid royalty claim")]
+    InvalidRoyaltyClaim,
+    #[msg("Payment mint is not accepted by the payment manager")]
+    UnsupportedPaymentMint,
+    #[msg("Too many accepted mints")]
+    TooManyAcceptedMints,
+    #[msg("Payment token account cannot be the fee collector token account")]
+    TargetEqualsFeeCollector,
+    #[msg("Too many remaining accounts")]
+    TooManyAccounts,
+    #[msg("Invalid fee tiers")]
+    InvalidFeeTiers,
+    #[msg("Invalid payer stats")]
+    InvalidPayerStats,
+    #[msg("Invalid seller fee")]
+    InvalidSellerFee,
+    #[msg("Invalid escrow seeds")]
+    InvalidEscrowSeeds,
+    #[msg("Payment exceeds the max total spend")]
+    ExceedsMaxSpend,
+    #[msg("Creators do not match the expected creator hash")]
+    CreatorHashMismatch,
+    #[msg("Invalid maker token account")]
+    InvalidMakerTokenAccount,
+    #[msg("Buy side token account does not hold the payment mint")]
+    InvalidBuySideMint,
+    #[msg("Token account balance does not match the amount transferred")]
+    BalanceMismatch,
+    #[msg("Invalid collection policy")]
+    InvalidCollectionPolicy,
+    #[msg("Payment is below the expected amount")]
+    Underpayment,
 }
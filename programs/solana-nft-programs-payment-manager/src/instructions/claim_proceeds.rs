@@ -0,0 +1,68 @@
+use {
+    crate::{errors::ErrorCode, state::*},
+    anchor_lang::prelude::*,
+    anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, TransferChecked},
+};
+
+#[derive(Accounts)]
+pub struct ClaimProceedsCtx<'info> {
+    #[account(seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(
+        mut,
+        seeds = [PROCEEDS_ESCROW_SEED.as_bytes(), payment_manager.key().as_ref(), payment_mint.key().as_ref(), seller.key().as_ref()], bump,
+        token::mint = payment_mint,
+        token::authority = proceeds_escrow,
+    )]
+    proceeds_escrow: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = seller_token_account.owner == seller.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = seller_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
+    seller_token_account: Box<Account<'info, TokenAccount>>,
+    payment_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut)]
+    seller: Signer<'info>,
+    #[account(constraint = token_program.key() == *payment_mint.to_account_info().owner @ ErrorCode::InvalidTokenProgram)]
+    token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<ClaimProceedsCtx>) -> Result<()> {
+    let payment_manager_id = ctx.accounts.payment_manager.key();
+    let payment_mint_id = ctx.accounts.payment_mint.key();
+    let seller_id = ctx.accounts.seller.key();
+    let proceeds_escrow_seeds = &[
+        PROCEEDS_ESCROW_SEED.as_bytes(),
+        payment_manager_id.as_ref(),
+        payment_mint_id.as_ref(),
+        seller_id.as_ref(),
+        &[*ctx.bumps.get("proceeds_escrow").unwrap()],
+    ];
+    let proceeds_escrow_signer = &[&proceeds_escrow_seeds[..]];
+
+    if ctx.accounts.proceeds_escrow.amount > 0 {
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.proceeds_escrow.to_account_info(),
+            mint: ctx.accounts.payment_mint.to_account_info(),
+            to: ctx.accounts.seller_token_account.to_account_info(),
+            authority: ctx.accounts.proceeds_escrow.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(proceeds_escrow_signer);
+        token::transfer_checked(cpi_context, ctx.accounts.proceeds_escrow.amount, ctx.accounts.payment_mint.decimals)?;
+    }
+
+    // the escrow is created again by the next payment into it
+    let cpi_accounts = CloseAccount {
+        account: ctx.accounts.proceeds_escrow.to_account_info(),
+        destination: ctx.accounts.seller.to_account_info(),
+        authority: ctx.accounts.proceeds_escrow.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(proceeds_escrow_signer);
+    token::close_account(cpi_context)?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use {
+    crate::{errors::ErrorCode, state::*},
+    anchor_lang::prelude::*,
+    anchor_spl::token::{self, Mint, Token, TokenAccount, TransferChecked},
+};
+
+#[derive(Accounts)]
+pub struct ClaimRoyaltyCtx<'info> {
+    #[account(seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(
+        mut,
+        seeds = [ROYALTY_CLAIM_SEED.as_bytes(), payment_manager.key().as_ref(), payment_mint.key().as_ref(), creator.key().as_ref()], bump = royalty_claim.bump,
+        constraint = royalty_claim.creator == creator.key() @ ErrorCode::InvalidRoyaltyClaim,
+    )]
+    royalty_claim: Box<Account<'info, RoyaltyClaim>>,
+    #[account(
+        mut,
+        seeds = [ROYALTY_ESCROW_SEED.as_bytes(), payment_manager.key().as_ref(), payment_mint.key().as_ref()], bump,
+        token::mint = payment_mint,
+        token::authority = royalty_escrow,
+    )]
+    royalty_escrow: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = creator_token_account.owner == creator.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = creator_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
+    creator_token_account: Box<Account<'info, TokenAccount>>,
+    payment_mint: Box<Account<'info, Mint>>,
+
+    creator: Signer<'info>,
+    #[account(constraint = token_program.key() == *payment_mint.to_account_info().owner @ ErrorCode::InvalidTokenProgram)]
+    token_program: Program<'info, Token>,
+}
+
+/// Pays a creator the royalties accrued in their royalty claim
+pub fn handler(ctx: Context<ClaimRoyaltyCtx>) -> Result<()> {
+    let payment_manager_id = ctx.accounts.payment_manager.key();
+    let payment_mint_id = ctx.accounts.payment_mint.key();
+    let royalty_escrow_seeds = &[
+        ROYALTY_ESCROW_SEED.as_bytes(),
+        payment_manager_id.as_ref(),
+        payment_mint_id.as_ref(),
+        &[*ctx.bumps.get("royalty_escrow").unwrap()],
+    ];
+    let royalty_escrow_signer = &[&royalty_escrow_seeds[..]];
+
+    let amount = ctx.accounts.royalty_claim.amount;
+    if amount > 0 {
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.royalty_escrow.to_account_info(),
+            mint: ctx.accounts.payment_mint.to_account_info(),
+            to: ctx.accounts.creator_token_account.to_account_info(),
+            authority: ctx.accounts.royalty_escrow.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(royalty_escrow_signer);
+        token::transfer_checked(cpi_context, amount, ctx.accounts.payment_mint.decimals)?;
+    }
+
+    // the claim is kept for the royalties of later payments
+    ctx.accounts.royalty_claim.amount = 0;
+    Ok(())
+}
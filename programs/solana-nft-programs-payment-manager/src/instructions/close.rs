@@ -1,21 +1,21 @@
 use {
-    crate::{errors::ErrorCode, state::*},
+    crate::{errors::ErrorCode, state::*, util::assert_admin_signers},
     anchor_lang::prelude::*,
 };
 
 #[derive(Accounts)]
 pub struct CloseCtx<'info> {
-    #[account(mut, close = collector)]
+    #[account(mut, close = collector, seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
     payment_manager: Box<Account<'info, PaymentManager>>,
 
     #[account(mut)]
     /// CHECK: This is not dangerous because this is just the pubkey that collects the closing account lamports
     collector: UncheckedAccount<'info>,
 
-    #[account(mut, constraint = closer.key() == payment_manager.authority @ ErrorCode::InvalidAuthority)]
+    #[account(mut, constraint = payment_manager.authority_threshold.is_some() || closer.key() == payment_manager.authority @ ErrorCode::InvalidAuthority)]
     closer: Signer<'info>,
 }
 
-pub fn handler(_ctx: Context<CloseCtx>) -> Result<()> {
-    Ok(())
+pub fn handler(ctx: Context<CloseCtx>) -> Result<()> {
+    assert_admin_signers(&ctx.accounts.payment_manager, &ctx.accounts.closer, ctx.remaining_accounts)
 }
@@ -1,8 +1,13 @@
-use mpl_token_metadata::accounts::Metadata;
-use mpl_utils::assert_derivation;
-
 use {
-    crate::{errors::ErrorCode, state::*},
+    crate::{
+        errors::ErrorCode,
+        metadata::resolve_mint_metadata,
+        state::*,
+        util::{
+            activate_fee_schedule, assert_accepted_mint, assert_native_funds, assert_payment_remaining_accounts, assert_required_collection, buy_side_fee_amount, creator_payouts, creator_shares_sum,
+            creators_fee_amount, is_primary_sale, is_seller_fee_additive, maker_taker_fees, payer_buy_side_fee, payer_fees, royalties_waived, royalty_creators, seller_fee_amount,
+        },
+    },
     anchor_lang::prelude::*,
     anchor_spl::token::Mint,
     solana_program::{program::invoke, system_instruction::transfer},
@@ -11,7 +16,7 @@ use {
 #[derive(Accounts)]
 #[instruction(amount: u64)]
 pub struct HandleNativePaymentWithRoyaltiesCtx<'info> {
-    #[account(mut)]
+    #[account(mut, seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
     payment_manager: Box<Account<'info, PaymentManager>>,
 
     #[account(mut, constraint = fee_collector.key() == payment_manager.fee_collector @ ErrorCode::InvalidFeeCollector)]
@@ -26,113 +31,117 @@ pub struct HandleNativePaymentWithRoyaltiesCtx<'info> {
     mint: Box<Account<'info, Mint>>,
     /// CHECK: This is not dangerous because we don't read or write from this account
     mint_metadata: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because of the derivation and it is only read when initialized
+    #[account(seeds = [CREATOR_CONFIG_SEED.as_bytes(), payment_manager.key().as_ref(), mint.key().as_ref()], bump)]
+    creator_config: UncheckedAccount<'info>,
 
+    // lamports move through the system program, there is no payment mint or token program to check
+    // the accounts against unlike the token payment instructions
     system_program: Program<'info, System>,
     // > Remaining accounts for each mint creator
     // creator
 }
 
 pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, HandleNativePaymentWithRoyaltiesCtx<'info>>, payment_amount: u64) -> Result<()> {
-    let payment_manager = &mut ctx.accounts.payment_manager;
+    // queued fees take over once their activation time is reached
+    activate_fee_schedule(&mut ctx.accounts.payment_manager, Clock::get()?.unix_timestamp);
+    // only accepted payment mints can be paid in, native payments as the default pubkey
+    assert_accepted_mint(&ctx.accounts.payment_manager, &Pubkey::default())?;
+    assert_payment_remaining_accounts(&ctx.accounts.payment_manager, ctx.remaining_accounts)?;
+    let payment_manager = &ctx.accounts.payment_manager;
     // maker-taker fees
-    let maker_fee = payment_amount
-        .checked_mul(payment_manager.maker_fee_basis_points.into())
-        .expect("Multiplication error")
-        .checked_div(BASIS_POINTS_DIVISOR.into())
-        .expect("Division error");
-    let taker_fee = payment_amount
-        .checked_mul(payment_manager.taker_fee_basis_points.into())
-        .expect("Multiplication error")
-        .checked_div(BASIS_POINTS_DIVISOR.into())
-        .expect("Division error");
+    let (maker_fee, taker_fee) = maker_taker_fees(payment_manager, payment_amount);
     let mut total_fees = maker_fee.checked_add(taker_fee).expect("Add error");
-
-    // assert metadata account derivation
-    assert_derivation(
-        &mpl_token_metadata::ID,
-        &ctx.accounts.mint_metadata.to_account_info(),
-        &["metadata".to_string().as_bytes(), mpl_token_metadata::ID.as_ref(), ctx.accounts.mint.key().as_ref()],
-        error!(ErrorCode::InvalidMintMetadataOwner),
+    let buy_side_fee = buy_side_fee_amount(payment_amount, DEFAULT_BUY_SIDE_FEE_SHARE);
+    assert_native_funds(
+        &ctx.accounts.payer.to_account_info(),
+        payment_amount
+            .checked_add(payer_fees(payment_manager, maker_fee, taker_fee))
+            .expect("Add error")
+            .checked_add(payer_buy_side_fee(payment_manager, buy_side_fee))
+            .expect("Add error"),
     )?;
 
+    // royalty terms from the Metaplex metadata or the bridged metadata of the mint
+    let mint_metadata = resolve_mint_metadata(payment_manager, &ctx.accounts.mint.key(), &ctx.accounts.mint_metadata)?;
+    // only mints of the required collection can be paid for
+    assert_required_collection(payment_manager, mint_metadata.as_ref().and_then(|mint_metadata| mint_metadata.collection.as_ref()))?;
+    // primary sale proceeds go to the creators instead of the payment target
+    let primary_sale = mint_metadata.as_ref().map_or(false, |mint_metadata| {
+        is_primary_sale(
+            payment_manager,
+            mint_metadata.primary_sale_happened,
+            mint_metadata.creators.is_some() || !ctx.accounts.creator_config.data_is_empty(),
+        )
+    });
+    // royalties are waived below the royalty min price
+    let mint_metadata = mint_metadata.filter(|_| primary_sale || !royalties_waived(payment_manager, payment_amount));
+
+    // royalty fees
+    let mut total_creators_fee: u64 = 0;
+    if let Some(mint_metadata) = mint_metadata.as_ref().filter(|_| !primary_sale) {
+        let seller_fee = seller_fee_amount(payment_manager, payment_amount, mint_metadata.seller_fee_basis_points)?;
+        total_creators_fee = creators_fee_amount(payment_manager, total_fees, seller_fee);
+        if is_seller_fee_additive(payment_manager) {
+            total_fees = total_fees.checked_add(seller_fee).expect("Add error");
+        }
+    }
+
+    // calculate fees
+    let mut principal_amount = payment_amount
+        .checked_add(payer_fees(payment_manager, maker_fee, taker_fee))
+        .expect("Add error")
+        .checked_add(payer_buy_side_fee(payment_manager, buy_side_fee))
+        .expect("Add error")
+        .checked_sub(total_fees)
+        .expect("Sub error")
+        .checked_sub(buy_side_fee)
+        .expect("Sub error");
+    if primary_sale {
+        total_creators_fee = principal_amount;
+        total_fees = total_fees.checked_add(principal_amount).expect("Add error");
+        principal_amount = 0;
+    }
+    let principal_first = payment_manager.payout_order == PayoutOrder::PrincipalFirst as u8;
+    if principal_first {
+        // pay target before royalties
+        pay_target(ctx.accounts, principal_amount)?;
+    }
+
     // royalties
     let mut fees_paid_out: u64 = 0;
     let remaining_accs = &mut ctx.remaining_accounts.iter();
-    if !ctx.accounts.mint_metadata.data_is_empty() {
-        if ctx.accounts.mint_metadata.to_account_info().owner.key() != mpl_token_metadata::ID {
-            return Err(error!(ErrorCode::InvalidMintMetadataOwner));
-        }
-        let mint_metadata_data = ctx.accounts.mint_metadata.try_borrow_mut_data().expect("Failed to borrow data");
-        let mint_metadata = Metadata::deserialize(&mut mint_metadata_data.as_ref()).expect("Failed to deserialize metadata");
-        if mint_metadata.mint != ctx.accounts.mint.key() {
-            return Err(error!(ErrorCode::InvalidMintMetadata));
-        }
-        let seller_fee = if payment_manager.include_seller_fee_basis_points {
-            payment_amount
-                .checked_mul(mint_metadata.seller_fee_basis_points.into())
-                .expect("Multiplication error")
-                .checked_div(BASIS_POINTS_DIVISOR.into())
-                .expect("Division error")
-        } else {
-            0
-        };
-        let total_creators_fee = total_fees
-            .checked_mul(payment_manager.royalty_fee_share.unwrap_or(DEFAULT_ROYALTY_FEE_SHARE))
-            .unwrap()
-            .checked_div(BASIS_POINTS_DIVISOR.into())
-            .expect("Div error")
-            .checked_add(seller_fee)
-            .expect("Add error");
-        total_fees = total_fees.checked_add(seller_fee).expect("Add error");
-
-        if let Some(creators) = mint_metadata.creators {
-            let creator_amounts: Vec<u64> = creators
-                .clone()
-                .into_iter()
-                .map(|creator| total_creators_fee.checked_mul(u64::try_from(creator.share).expect("Could not cast u8 to u64")).unwrap())
-                .collect();
-            let creator_amounts_sum: u64 = creator_amounts.iter().sum();
-            let mut creators_fee_remainder = total_creators_fee.checked_sub(creator_amounts_sum.checked_div(100).expect("Div error")).expect("Sub error");
-            for creator in creators {
-                if creator.share != 0 {
-                    let creator_info = next_account_info(remaining_accs)?;
-                    if creator_info.key() != creator.address {
-                        return Err(error!(ErrorCode::InvalidCreatorAddress));
-                    }
-                    let share = u64::try_from(creator.share).expect("Could not cast u8 to u64");
-                    let creator_fee_remainder_amount = u64::from(creators_fee_remainder > 0);
-                    let creator_fee_amount = total_creators_fee
-                        .checked_mul(share)
-                        .unwrap()
-                        .checked_div(100)
-                        .expect("Div error")
-                        .checked_add(creator_fee_remainder_amount)
-                        .expect("Add error");
-                    creators_fee_remainder = creators_fee_remainder.checked_sub(creator_fee_remainder_amount).expect("Sub error");
-
-                    if creator_fee_amount > 0 {
-                        fees_paid_out = fees_paid_out.checked_add(creator_fee_amount).expect("Add error");
-                        invoke(
-                            &transfer(&ctx.accounts.payer.key(), &creator_info.key(), creator_fee_amount),
-                            &[ctx.accounts.payer.to_account_info(), creator_info.to_account_info(), ctx.accounts.system_program.to_account_info()],
-                        )?;
-                    }
+    if let Some(mint_metadata) = mint_metadata {
+        if let Some(creators) = royalty_creators(&ctx.accounts.creator_config, mint_metadata.creators)? {
+            // malformed metadata whose creators all have a zero share pays no royalties, the creators fee goes to the fee collector
+            if creator_shares_sum(&creators) == 0 {
+                msg!("Creators have no shares, creators fee goes to fee collector");
+            }
+            for (creator, creator_fee_amount) in creator_payouts(&creators, total_creators_fee, payment_manager.remainder_to_collector) {
+                let creator_info = next_account_info(remaining_accs)?;
+                if creator_info.key() != creator {
+                    return Err(error!(ErrorCode::InvalidCreatorAddress));
+                }
+
+                if creator_fee_amount > 0 {
+                    // the creator's share is settled either way, a payer who is a creator keeps theirs
+                    fees_paid_out = fees_paid_out.checked_add(creator_fee_amount).expect("Add error");
+                }
+                if creator_fee_amount > 0 && creator_info.key() != ctx.accounts.payer.key() {
+                    invoke(
+                        &transfer(&ctx.accounts.payer.key(), &creator_info.key(), creator_fee_amount),
+                        &[ctx.accounts.payer.to_account_info(), creator_info.to_account_info(), ctx.accounts.system_program.to_account_info()],
+                    )?;
                 }
             }
         }
     }
 
-    // calculate fees
-    let buy_side_fee = payment_amount
-        .checked_mul(DEFAULT_BUY_SIDE_FEE_SHARE)
-        .unwrap()
-        .checked_div(BASIS_POINTS_DIVISOR.into())
-        .expect("Div error");
     let mut fee_collector_fee = total_fees.checked_add(buy_side_fee).expect("Add error").checked_sub(fees_paid_out).expect("Sub error");
 
-    // pay buy side fee
+    // pay buy side fee, a zero buy side fee is not worth a transfer CPI
     let buy_side_info = next_account_info(remaining_accs);
-    if buy_side_info.is_ok() {
+    if buy_side_info.is_ok() && buy_side_fee > 0 {
         let buy_side = buy_side_info?;
         invoke(
             &transfer(&ctx.accounts.payer.key(), &buy_side.key(), buy_side_fee),
@@ -142,6 +151,10 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, HandleNativePaymentWithRoy
         fee_collector_fee = fee_collector_fee.checked_sub(buy_side_fee).expect("Sub error");
     }
 
+    if payment_manager.strict_remaining_accounts && remaining_accs.next().is_some() {
+        return Err(error!(ErrorCode::UnexpectedRemainingAccounts));
+    }
+
     if fee_collector_fee > 0 {
         // pay remaining fees to fee_colector
         invoke(
@@ -154,25 +167,18 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, HandleNativePaymentWithRoy
         )?;
     }
 
-    // pay target
+    if !principal_first {
+        // pay target
+        pay_target(ctx.accounts, principal_amount)?;
+    }
+
+    Ok(())
+}
+
+fn pay_target(accounts: &HandleNativePaymentWithRoyaltiesCtx, amount: u64) -> Result<()> {
     invoke(
-        &transfer(
-            &ctx.accounts.payer.key(),
-            &ctx.accounts.payment_target.key(),
-            payment_amount
-                .checked_add(taker_fee)
-                .expect("Add error")
-                .checked_sub(total_fees)
-                .expect("Sub error")
-                .checked_sub(buy_side_fee)
-                .expect("Sub error"),
-        ),
-        &[
-            ctx.accounts.payer.to_account_info(),
-            ctx.accounts.payment_target.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
-        ],
+        &transfer(&accounts.payer.key(), &accounts.payment_target.key(), amount),
+        &[accounts.payer.to_account_info(), accounts.payment_target.to_account_info(), accounts.system_program.to_account_info()],
     )?;
-
     Ok(())
 }
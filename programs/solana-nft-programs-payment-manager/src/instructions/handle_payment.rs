@@ -1,59 +1,74 @@
 use {
-    crate::{errors::ErrorCode, state::*},
+    crate::{
+        errors::ErrorCode,
+        state::*,
+        util::{activate_fee_schedule, assert_accepted_mint, maker_taker_fees, payer_fees, transfer_tokens},
+    },
     anchor_lang::prelude::*,
-    anchor_spl::token::{self, Token, TokenAccount, Transfer},
+    anchor_spl::token::{Mint, Token, TokenAccount},
 };
 
 #[derive(Accounts)]
 #[instruction(amount: u64)]
 pub struct HandlePaymentCtx<'info> {
-    #[account(mut)]
+    #[account(mut, seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
     payment_manager: Box<Account<'info, PaymentManager>>,
 
-    #[account(mut)]
+    #[account(mut, constraint = payer_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint)]
     payer_token_account: Box<Account<'info, TokenAccount>>,
-    #[account(mut, constraint = fee_collector_token_account.owner == payment_manager.fee_collector @ ErrorCode::InvalidFeeCollectorTokenAccount)]
+    #[account(
+        mut,
+        constraint = fee_collector_token_account.owner == payment_manager.fee_collector @ ErrorCode::InvalidFeeCollectorTokenAccount,
+        constraint = fee_collector_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
     fee_collector_token_account: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = payment_token_account.key() != fee_collector_token_account.key() @ ErrorCode::TargetEqualsFeeCollector,
+        constraint = payment_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
     payment_token_account: Box<Account<'info, TokenAccount>>,
+    payment_mint: Box<Account<'info, Mint>>,
 
     payer: Signer<'info>,
+    #[account(constraint = token_program.key() == *payment_mint.to_account_info().owner @ ErrorCode::InvalidTokenProgram)]
     token_program: Program<'info, Token>,
 }
 
 pub fn handler(ctx: Context<HandlePaymentCtx>, payment_amount: u64) -> Result<()> {
     let payment_manager = &mut ctx.accounts.payment_manager;
+    // queued fees take over once their activation time is reached
+    activate_fee_schedule(payment_manager, Clock::get()?.unix_timestamp);
+    // only accepted payment mints can be paid in
+    assert_accepted_mint(payment_manager, &ctx.accounts.payment_mint.key())?;
 
-    let maker_fee = payment_amount
-        .checked_mul(payment_manager.maker_fee_basis_points.into())
-        .expect("Multiplication error")
-        .checked_div(BASIS_POINTS_DIVISOR.into())
-        .expect("Division error");
-    let taker_fee = payment_amount
-        .checked_mul(payment_manager.taker_fee_basis_points.into())
-        .expect("Multiplication error")
-        .checked_div(BASIS_POINTS_DIVISOR.into())
-        .expect("Division error");
+    let (maker_fee, taker_fee) = maker_taker_fees(payment_manager, payment_amount);
 
     if maker_fee.checked_add(taker_fee).expect("Add error") > 0 {
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.payer_token_account.to_account_info(),
-            to: ctx.accounts.fee_collector_token_account.to_account_info(),
-            authority: ctx.accounts.payer.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_context, maker_fee.checked_add(taker_fee).expect("Add error"))?;
+        transfer_tokens(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.payer_token_account.to_account_info(),
+            &ctx.accounts.payment_mint,
+            ctx.accounts.fee_collector_token_account.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            maker_fee.checked_add(taker_fee).expect("Add error"),
+        )?;
     }
 
-    let cpi_accounts = Transfer {
-        from: ctx.accounts.payer_token_account.to_account_info(),
-        to: ctx.accounts.payment_token_account.to_account_info(),
-        authority: ctx.accounts.payer.to_account_info(),
-    };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_context, payment_amount.checked_sub(maker_fee).expect("Sub error"))?;
+    // the maker fee comes out of the payment unless the taker covers it
+    let principal_amount = payment_amount
+        .checked_add(payer_fees(payment_manager, maker_fee, taker_fee))
+        .expect("Add error")
+        .checked_sub(maker_fee.checked_add(taker_fee).expect("Add error"))
+        .expect("Sub error");
+    transfer_tokens(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.payer_token_account.to_account_info(),
+        &ctx.accounts.payment_mint,
+        ctx.accounts.payment_token_account.to_account_info(),
+        ctx.accounts.payer.to_account_info(),
+        principal_amount,
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,209 @@
+use {
+    crate::{
+        errors::ErrorCode,
+        metadata::resolve_mint_metadata,
+        state::*,
+        util::{
+            activate_fee_schedule, assert_accepted_mint, assert_payment_remaining_accounts, assert_required_collection, assert_token_account, buy_side_fee_amount, convert_amount, creator_payouts,
+            creator_shares_sum, creators_fee_amount, is_seller_fee_additive, maker_taker_fees, payer_buy_side_fee, payer_fees, remaining_token_account, royalties_waived, royalty_creators,
+            seller_fee_amount, transfer_tokens, validate_conversion_rate,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct HandlePaymentWithConvertedRoyaltiesIx {
+    pub payment_amount: u64,
+    // royalty mint base units per payment mint base unit scaled by CONVERSION_RATE_DIVISOR, so
+    // the rate carries any difference in decimals between the two mints
+    pub conversion_rate: u64,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: HandlePaymentWithConvertedRoyaltiesIx)]
+pub struct HandlePaymentWithConvertedRoyaltiesCtx<'info> {
+    #[account(mut, seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(mut)]
+    payer_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = fee_collector_token_account.owner == payment_manager.fee_collector @ ErrorCode::InvalidFeeCollectorTokenAccount)]
+    fee_collector_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = payment_token_account.key() != fee_collector_token_account.key() @ ErrorCode::TargetEqualsFeeCollector)]
+    payment_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = royalty_payer_token_account.mint == royalty_mint.key() @ ErrorCode::InvalidTokenAccountMint)]
+    royalty_payer_token_account: Box<Account<'info, TokenAccount>>,
+
+    payment_mint: Box<Account<'info, Mint>>,
+    royalty_mint: Box<Account<'info, Mint>>,
+    mint: Box<Account<'info, Mint>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    mint_metadata: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because of the derivation and it is only read when initialized
+    #[account(seeds = [CREATOR_CONFIG_SEED.as_bytes(), payment_manager.key().as_ref(), mint.key().as_ref()], bump)]
+    creator_config: UncheckedAccount<'info>,
+
+    payer: Signer<'info>,
+    #[account(
+        constraint = token_program.key() == *payment_mint.to_account_info().owner @ ErrorCode::InvalidTokenProgram,
+        constraint = token_program.key() == *royalty_mint.to_account_info().owner @ ErrorCode::InvalidTokenProgram,
+    )]
+    token_program: Program<'info, Token>,
+    // > Remaining accounts for each mint creator
+    // creator royalty mint token account
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, HandlePaymentWithConvertedRoyaltiesCtx<'info>>, ix: HandlePaymentWithConvertedRoyaltiesIx) -> Result<()> {
+    // queued fees take over once their activation time is reached
+    activate_fee_schedule(&mut ctx.accounts.payment_manager, Clock::get()?.unix_timestamp);
+    // only accepted payment mints can be paid in
+    assert_accepted_mint(&ctx.accounts.payment_manager, &ctx.accounts.payment_mint.key())?;
+    assert_payment_remaining_accounts(&ctx.accounts.payment_manager, ctx.remaining_accounts)?;
+    let payment_manager = &ctx.accounts.payment_manager;
+    validate_conversion_rate(ix.conversion_rate, &payment_manager.royalty_conversion_rate_bounds)?;
+    let payment_amount = ix.payment_amount;
+
+    // maker-taker fees
+    let (maker_fee, taker_fee) = maker_taker_fees(payment_manager, payment_amount);
+    let mut total_fees = maker_fee.checked_add(taker_fee).expect("Add error");
+
+    // royalty terms from the Metaplex metadata or the bridged metadata of the mint
+    let mint_metadata = resolve_mint_metadata(payment_manager, &ctx.accounts.mint.key(), &ctx.accounts.mint_metadata)?;
+    // only mints of the required collection can be paid for
+    assert_required_collection(payment_manager, mint_metadata.as_ref().and_then(|mint_metadata| mint_metadata.collection.as_ref()))?;
+    // royalties are waived below the royalty min price
+    let mint_metadata = mint_metadata.filter(|_| !royalties_waived(payment_manager, payment_amount));
+
+    // royalty fees
+    let mut total_creators_fee: u64 = 0;
+    if let Some(mint_metadata) = &mint_metadata {
+        let seller_fee = seller_fee_amount(payment_manager, payment_amount, mint_metadata.seller_fee_basis_points)?;
+        total_creators_fee = creators_fee_amount(payment_manager, total_fees, seller_fee);
+        if is_seller_fee_additive(payment_manager) {
+            total_fees = total_fees.checked_add(seller_fee).expect("Add error");
+        }
+    }
+
+    // calculate fees
+    let buy_side_fee = buy_side_fee_amount(payment_amount, DEFAULT_BUY_SIDE_FEE_SHARE);
+    let principal_amount = payment_amount
+        .checked_add(payer_fees(payment_manager, maker_fee, taker_fee))
+        .expect("Add error")
+        .checked_add(payer_buy_side_fee(payment_manager, buy_side_fee))
+        .expect("Add error")
+        .checked_sub(total_fees)
+        .expect("Sub error")
+        .checked_sub(buy_side_fee)
+        .expect("Sub error");
+    let principal_first = payment_manager.payout_order == PayoutOrder::PrincipalFirst as u8;
+    if principal_first {
+        // pay target before royalties
+        pay_target(ctx.accounts, principal_amount)?;
+    }
+
+    // royalties
+    // creators are paid in the royalty mint out of royalty_payer_token_account so their
+    // portion of the fees is never debited from payer_token_account
+    let mut fees_paid_out: u64 = 0;
+    let remaining_accs = &mut ctx.remaining_accounts.iter();
+    if let Some(mint_metadata) = mint_metadata {
+        if let Some(creators) = royalty_creators(&ctx.accounts.creator_config, mint_metadata.creators)? {
+            // malformed metadata whose creators all have a zero share pays no royalties, the creators fee goes to the fee collector
+            if creator_shares_sum(&creators) == 0 {
+                msg!("Creators have no shares, creators fee goes to fee collector");
+            } else {
+                fees_paid_out = total_creators_fee;
+            }
+            // thresholds like royalty_min_price and max_creators_fee are in payment mint base units
+            // and are applied above, only the final creators fee is converted
+            let total_converted_creators_fee = convert_amount(total_creators_fee, ix.conversion_rate);
+            // the fee collector is not paid in the royalty mint, the remainder always goes to the creators
+            for (creator, creator_fee_amount) in creator_payouts(&creators, total_converted_creators_fee, false) {
+                let creator_token_account_info = next_account_info(remaining_accs)?;
+                let creator_token_account = remaining_token_account(creator_token_account_info)?;
+                assert_token_account(&creator_token_account, &creator, &ctx.accounts.royalty_mint.key())?;
+
+                // a payer who is a creator keeps their share
+                if creator_fee_amount > 0 && creator_token_account_info.key() != ctx.accounts.royalty_payer_token_account.key() {
+                    transfer_tokens(
+                        ctx.accounts.token_program.to_account_info(),
+                        ctx.accounts.royalty_payer_token_account.to_account_info(),
+                        &ctx.accounts.royalty_mint,
+                        creator_token_account_info.to_account_info(),
+                        ctx.accounts.payer.to_account_info(),
+                        creator_fee_amount,
+                    )?;
+                }
+            }
+        }
+    }
+
+    let mut fee_collector_fee = total_fees.checked_add(buy_side_fee).expect("Add error").checked_sub(fees_paid_out).expect("Sub error");
+
+    // pay buy side fee
+    let buy_side_token_account_info = next_account_info(remaining_accs);
+    if buy_side_token_account_info.is_ok() {
+        let buy_side_token_account = Account::<TokenAccount>::try_from(buy_side_token_account_info?);
+        if let Ok(buy_side_token_account) = buy_side_token_account {
+            // a buy side token account of another mint would be paid in the wrong currency
+            if buy_side_token_account.mint != ctx.accounts.payment_mint.key() {
+                return Err(error!(ErrorCode::InvalidBuySideMint));
+            }
+            // a zero buy side fee is not worth a transfer CPI
+            if buy_side_fee > 0 {
+                transfer_tokens(
+                    ctx.accounts.token_program.to_account_info(),
+                    ctx.accounts.payer_token_account.to_account_info(),
+                    &ctx.accounts.payment_mint,
+                    buy_side_token_account.to_account_info(),
+                    ctx.accounts.payer.to_account_info(),
+                    buy_side_fee,
+                )?;
+
+                // remove buy side fee out of fee collector fee
+                fee_collector_fee = fee_collector_fee.checked_sub(buy_side_fee).expect("Sub error");
+            }
+        } else {
+            if payment_manager.strict_remaining_accounts {
+                return Err(error!(ErrorCode::InvalidBuySideAccount));
+            }
+            msg!("Buy side account is not a valid token account, buy side fee goes to fee collector");
+        }
+    }
+
+    if payment_manager.strict_remaining_accounts && remaining_accs.next().is_some() {
+        return Err(error!(ErrorCode::UnexpectedRemainingAccounts));
+    }
+
+    if fee_collector_fee > 0 {
+        // pay remaining fees to fee_colector
+        transfer_tokens(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.payer_token_account.to_account_info(),
+            &ctx.accounts.payment_mint,
+            ctx.accounts.fee_collector_token_account.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            fee_collector_fee,
+        )?;
+    }
+
+    if !principal_first {
+        // pay target
+        pay_target(ctx.accounts, principal_amount)?;
+    }
+
+    Ok(())
+}
+
+fn pay_target(accounts: &HandlePaymentWithConvertedRoyaltiesCtx, amount: u64) -> Result<()> {
+    transfer_tokens(
+        accounts.token_program.to_account_info(),
+        accounts.payer_token_account.to_account_info(),
+        &accounts.payment_mint,
+        accounts.payment_token_account.to_account_info(),
+        accounts.payer.to_account_info(),
+        amount,
+    )
+}
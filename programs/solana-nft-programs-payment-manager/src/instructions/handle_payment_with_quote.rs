@@ -0,0 +1,83 @@
+use {
+    crate::{
+        errors::ErrorCode,
+        state::*,
+        util::{activate_fee_schedule, assert_accepted_mint, maker_taker_fees, payer_fees, quote_payment_amount, transfer_tokens, validate_conversion_rate},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct HandlePaymentWithQuoteIx {
+    pub quote_amount: u64,
+    // payment mint base units per quote currency base unit scaled by CONVERSION_RATE_DIVISOR
+    pub conversion_rate: u64,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: HandlePaymentWithQuoteIx)]
+pub struct HandlePaymentWithQuoteCtx<'info> {
+    #[account(mut, seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(mut, constraint = payer_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint)]
+    payer_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = fee_collector_token_account.owner == payment_manager.fee_collector @ ErrorCode::InvalidFeeCollectorTokenAccount,
+        constraint = fee_collector_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
+    fee_collector_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = payment_token_account.key() != fee_collector_token_account.key() @ ErrorCode::TargetEqualsFeeCollector,
+        constraint = payment_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
+    payment_token_account: Box<Account<'info, TokenAccount>>,
+    payment_mint: Box<Account<'info, Mint>>,
+
+    payer: Signer<'info>,
+    #[account(constraint = token_program.key() == *payment_mint.to_account_info().owner @ ErrorCode::InvalidTokenProgram)]
+    token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<HandlePaymentWithQuoteCtx>, ix: HandlePaymentWithQuoteIx) -> Result<()> {
+    // queued fees take over once their activation time is reached
+    activate_fee_schedule(&mut ctx.accounts.payment_manager, Clock::get()?.unix_timestamp);
+    // only accepted payment mints can be paid in
+    assert_accepted_mint(&ctx.accounts.payment_manager, &ctx.accounts.payment_mint.key())?;
+    let payment_manager = &ctx.accounts.payment_manager;
+    validate_conversion_rate(ix.conversion_rate, &payment_manager.quote_conversion_rate_bounds)?;
+    let payment_amount = quote_payment_amount(ix.quote_amount, ix.conversion_rate);
+
+    let (maker_fee, taker_fee) = maker_taker_fees(payment_manager, payment_amount);
+
+    if maker_fee.checked_add(taker_fee).expect("Add error") > 0 {
+        transfer_tokens(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.payer_token_account.to_account_info(),
+            &ctx.accounts.payment_mint,
+            ctx.accounts.fee_collector_token_account.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            maker_fee.checked_add(taker_fee).expect("Add error"),
+        )?;
+    }
+
+    // the maker fee comes out of the payment unless the taker covers it
+    let principal_amount = payment_amount
+        .checked_add(payer_fees(payment_manager, maker_fee, taker_fee))
+        .expect("Add error")
+        .checked_sub(maker_fee.checked_add(taker_fee).expect("Add error"))
+        .expect("Sub error");
+    transfer_tokens(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.payer_token_account.to_account_info(),
+        &ctx.accounts.payment_mint,
+        ctx.accounts.payment_token_account.to_account_info(),
+        ctx.accounts.payer.to_account_info(),
+        principal_amount,
+    )?;
+
+    Ok(())
+}
@@ -1,188 +1,778 @@
-use mpl_token_metadata::accounts::Metadata;
-use mpl_utils::assert_derivation;
-
 use {
-    crate::{errors::ErrorCode, state::*},
+    crate::{
+        errors::ErrorCode,
+        metadata::resolve_mint_metadata,
+        state::*,
+        util::{
+            activate_fee_schedule, apply_collection_policy, assert_accepted_mint, assert_creator_hash, assert_max_total_spend, assert_payment_remaining_accounts, assert_required_collection,
+            assert_token_account, creator_payouts, creator_shares_sum, creator_token_account_payable, creator_token_account_receivable, exact_output_payment_amount, exempt_payment_fees, fill_amount,
+            find_royalty_claim_address, is_fee_free, is_primary_sale, is_seller_fee_additive, log_compute_units, mint_collection_policy, overpayment_refund, payment_fees, pays_primary_sale_target,
+            protocol_fee_amount, rebate_amount, remaining_token_account, royalties_waived, royalty_creators, stable_value, tiered_payment_manager, transfer_tokens, transfer_tokens_checked,
+            within_payment_interval, PaymentFees,
+        },
+    },
     anchor_lang::prelude::*,
-    anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer},
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::{
+        program::{invoke, invoke_signed},
+        system_instruction,
+    },
+    std::collections::BTreeMap,
 };
 
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct HandlePaymentWithRoyaltiesIx {
+    pub payment_amount: u64,
+    // payment_amount is what the payment target receives and the payer covers the fees on top
+    pub exact_output: bool,
+    // the principal is held in this seller's proceeds escrow instead of the payment token account
+    pub escrow_seller: Option<Pubkey>,
+    // basis points of payment_amount settled by a partial fill, the whole payment_amount when not set
+    pub fill_fraction: Option<u16>,
+    // the most the payer is debited, the payment with every fee on top of it
+    pub max_total_spend: Option<u64>,
+    // util::creator_hash of the creators the payer expects the metadata to list
+    pub expected_creator_hash: Option<[u8; 32]>,
+    // what the sale requires, a payment_amount above it is settled at expected_amount and the
+    // excess is refunded to the payer, one below it is rejected
+    pub expected_amount: Option<u64>,
+    // the seller, when set their proceeds net of the maker fee go to the maker token account
+    pub seller: Option<Pubkey>,
+    // chosen by the payer so each payment of a mint gets its own payment record, a nonce already
+    // recorded fails the payment instead of overwriting its record
+    pub record_nonce: u64,
+}
+
 #[derive(Accounts)]
-#[instruction(amount: u64)]
+#[instruction(ix: HandlePaymentWithRoyaltiesIx)]
 pub struct HandlePaymentWithRoyaltiesCtx<'info> {
-    #[account(mut)]
+    // mut as a due fee schedule is activated by the payment
+    #[account(mut, seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
     payment_manager: Box<Account<'info, PaymentManager>>,
 
     #[account(mut)]
     payer_token_account: Box<Account<'info, TokenAccount>>,
     #[account(mut, constraint = fee_collector_token_account.owner == payment_manager.fee_collector @ ErrorCode::InvalidFeeCollectorTokenAccount)]
     fee_collector_token_account: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
-    payment_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = payment_token_account.key() != fee_collector_token_account.key() @ ErrorCode::TargetEqualsFeeCollector)]
+    payment_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    // fees and royalties are debited from this account instead of payer_token_account, both have to
+    // hold the payment mint
+    #[account(
+        mut,
+        constraint = fee_source_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+        constraint = payer_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
+    fee_source_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    // the seller's token account, it stands in for the payment token account so the maker fee is
+    // provably taken out of the seller's proceeds
+    #[account(
+        mut,
+        constraint = Some(maker_token_account.owner) == ix.seller @ ErrorCode::InvalidMakerTokenAccount,
+        constraint = maker_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+        constraint = maker_token_account.key() != fee_collector_token_account.key() @ ErrorCode::TargetEqualsFeeCollector,
+    )]
+    maker_token_account: Option<Box<Account<'info, TokenAccount>>>,
 
     payment_mint: Box<Account<'info, Mint>>,
     mint: Box<Account<'info, Mint>>,
     /// CHECK: This is not dangerous because we don't read or write from this account
     mint_metadata: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because of the derivation and it is only read when initialized
+    #[account(seeds = [CREATOR_CONFIG_SEED.as_bytes(), payment_manager.key().as_ref(), mint.key().as_ref()], bump)]
+    creator_config: UncheckedAccount<'info>,
+    #[account(seeds = [PROTOCOL_CONFIG_SEED.as_bytes()], bump = protocol_config.bump)]
+    protocol_config: Option<Box<Account<'info, ProtocolConfig>>>,
+    #[account(mut)]
+    protocol_treasury_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        constraint = payment_manager.primary_sale_target == Some(primary_sale_target_token_account.owner) @ ErrorCode::InvalidPrimarySaleTargetTokenAccount,
+        constraint = primary_sale_target_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
+    primary_sale_target_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PAYER_RATE_LIMIT_SIZE,
+        seeds = [PAYER_RATE_LIMIT_SEED.as_bytes(), payment_manager.key().as_ref(), payer.key().as_ref()], bump,
+    )]
+    payer_rate_limit: Option<Box<Account<'info, PayerRateLimit>>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PAYER_STATS_SIZE,
+        seeds = [PAYER_STATS_SEED.as_bytes(), payment_manager.key().as_ref(), payer.key().as_ref()], bump,
+    )]
+    payer_stats: Option<Box<Account<'info, PayerStats>>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [PROCEEDS_ESCROW_SEED.as_bytes(), payment_manager.key().as_ref(), payment_mint.key().as_ref(), ix.escrow_seller.unwrap_or_default().as_ref()], bump,
+        token::mint = payment_mint,
+        token::authority = proceeds_escrow,
+    )]
+    proceeds_escrow: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        init,
+        payer = payer,
+        space = PAYMENT_RECORD_SIZE,
+        seeds = [PAYMENT_RECORD_SEED.as_bytes(), payment_manager.key().as_ref(), payer.key().as_ref(), mint.key().as_ref(), ix.record_nonce.to_le_bytes().as_ref()], bump,
+    )]
+    payment_record: Option<Box<Account<'info, PaymentRecord>>>,
+    #[account(seeds = [EXEMPT_PAYER_SEED.as_bytes(), payment_manager.key().as_ref(), payer.key().as_ref()], bump = exempt_payer.bump)]
+    exempt_payer: Option<Box<Account<'info, ExemptPayer>>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [ROYALTY_ESCROW_SEED.as_bytes(), payment_manager.key().as_ref(), payment_mint.key().as_ref()], bump,
+        token::mint = payment_mint,
+        token::authority = royalty_escrow,
+    )]
+    royalty_escrow: Option<Box<Account<'info, TokenAccount>>>,
+    // the fees of the payment are reported in stable value when the price oracle is passed
+    #[account(seeds = [PRICE_ORACLE_SEED.as_bytes(), payment_manager.key().as_ref(), payment_mint.key().as_ref()], bump = price_oracle.bump)]
+    price_oracle: Option<Box<Account<'info, PriceOracle>>>,
+    // royalty terms the collection authority enforces on the mints of the collection
+    /// CHECK: This is not dangerous because of the derivation from the verified collection in the handler and it is only read when initialized
+    collection_policy: UncheckedAccount<'info>,
 
+    #[account(mut)]
     payer: Signer<'info>,
+    #[account(constraint = token_program.key() == *payment_mint.to_account_info().owner @ ErrorCode::InvalidTokenProgram)]
     token_program: Program<'info, Token>,
+    system_program: Option<Program<'info, System>>,
     // > Remaining accounts for each mint creator
-    // creator token account
+    // creator token account, or the creator's royalty claim when royalties are claimed
 }
 
-pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, HandlePaymentWithRoyaltiesCtx<'info>>, payment_amount: u64) -> Result<()> {
-    let payment_manager = &mut ctx.accounts.payment_manager;
-    // maker-taker fees
-    let maker_fee = payment_amount
-        .checked_mul(payment_manager.maker_fee_basis_points.into())
-        .expect("Multiplication error")
-        .checked_div(BASIS_POINTS_DIVISOR.into())
-        .expect("Division error");
-    let taker_fee = payment_amount
-        .checked_mul(payment_manager.taker_fee_basis_points.into())
-        .expect("Multiplication error")
-        .checked_div(BASIS_POINTS_DIVISOR.into())
-        .expect("Division error");
-    let mut total_fees = maker_fee.checked_add(taker_fee).expect("Add error");
-
-    // assert metadata account derivation
-    assert_derivation(
-        &mpl_token_metadata::ID,
-        &ctx.accounts.mint_metadata.to_account_info(),
-        &["metadata".to_string().as_bytes(), mpl_token_metadata::ID.as_ref(), ctx.accounts.mint.key().as_ref()],
-        error!(ErrorCode::InvalidMintMetadataOwner),
-    )?;
+/// Fees of a payment valued at the payment manager's price of the payment mint, so analytics can
+/// compare payments across payment mints
+#[event]
+pub struct PaymentFeesInStable {
+    pub payment_manager: Pubkey,
+    pub payment_mint: Pubkey,
+    pub mint: Pubkey,
+    pub payer: Pubkey,
+    // everything the payer paid on top of what the payment target received, in the payment mint
+    pub total_fees: u64,
+    pub price: u64,
+    pub fees_in_stable: u64,
+}
 
-    // royalties
-    let mut fees_paid_out: u64 = 0;
-    let remaining_accs = &mut ctx.remaining_accounts.iter();
-    if !ctx.accounts.mint_metadata.data_is_empty() {
-        if ctx.accounts.mint_metadata.to_account_info().owner.key() != mpl_token_metadata::ID {
-            return Err(error!(ErrorCode::InvalidMintMetadataOwner));
+/// Maker fee of a payment, it joins the fees the creators and the fee collector are paid out of
+#[event]
+pub struct MakerFeeCharged {
+    pub payment_manager: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    // the fee collector
+    pub recipient: Pubkey,
+}
+
+/// Taker fee of a payment, charged to the payer on top of the payment amount
+#[event]
+pub struct TakerFeeCharged {
+    pub payment_manager: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    // the taker fee collector when the taker fee is routed to it, the fee collector otherwise
+    pub recipient: Pubkey,
+}
+
+/// A creator's part of the creators fee, paid to them, escrowed for their claim, or paid to the
+/// update authority in place of the creators
+#[event]
+pub struct SellerFeeDistributed {
+    pub payment_manager: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+}
+
+#[event]
+pub struct BuySideFeePaid {
+    pub payment_manager: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    // owner of the buy side token account
+    pub recipient: Pubkey,
+}
+
+/// What is left of the fees for the fee collector once everyone else is paid
+#[event]
+pub struct FeeCollectorPaid {
+    pub payment_manager: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, HandlePaymentWithRoyaltiesCtx<'info>>, ix: HandlePaymentWithRoyaltiesIx) -> Result<()> {
+    handle_payment(ctx, ix, false)
+}
+
+/// Pays out a payment, with `checked` every transfer asserts the balance of its destination grew by
+/// exactly the amount transferred
+pub(crate) fn handle_payment<'info>(ctx: Context<'_, '_, '_, 'info, HandlePaymentWithRoyaltiesCtx<'info>>, ix: HandlePaymentWithRoyaltiesIx, checked: bool) -> Result<()> {
+    // queued fees take over once their activation time is reached
+    activate_fee_schedule(&mut ctx.accounts.payment_manager, Clock::get()?.unix_timestamp);
+    // only accepted payment mints can be paid in
+    assert_accepted_mint(&ctx.accounts.payment_manager, &ctx.accounts.payment_mint.key())?;
+    assert_payment_remaining_accounts(&ctx.accounts.payment_manager, ctx.remaining_accounts)?;
+    // throttle payments from the same payer
+    if let Some(min_payment_interval_slots) = ctx.accounts.payment_manager.min_payment_interval_slots {
+        let payment_manager_id = ctx.accounts.payment_manager.key();
+        let payer_id = ctx.accounts.payer.key();
+        let payer_rate_limit = ctx.accounts.payer_rate_limit.as_mut().ok_or(error!(ErrorCode::InvalidPayerRateLimit))?;
+        let slot = Clock::get()?.slot;
+        if within_payment_interval(payer_rate_limit.last_payment_slot, slot, min_payment_interval_slots) {
+            return Err(error!(ErrorCode::PaymentRateLimited));
+        }
+        payer_rate_limit.bump = *ctx.bumps.get("payer_rate_limit").unwrap();
+        payer_rate_limit.payment_manager = payment_manager_id;
+        payer_rate_limit.payer = payer_id;
+        payer_rate_limit.last_payment_slot = slot;
+    }
+
+    if ix.escrow_seller.is_some() != ctx.accounts.proceeds_escrow.is_some() {
+        return Err(error!(ErrorCode::InvalidProceedsEscrow));
+    }
+    if ix.seller.is_some() != ctx.accounts.maker_token_account.is_some() {
+        return Err(error!(ErrorCode::InvalidMakerTokenAccount));
+    }
+    if ctx.accounts.payment_manager.record_payments && ctx.accounts.payment_record.is_none() {
+        return Err(error!(ErrorCode::InvalidPaymentRecord));
+    }
+    if (ctx.accounts.payment_manager.claim_royalties || ctx.accounts.payment_manager.escrow_unreceivable_royalties) && (ctx.accounts.royalty_escrow.is_none() || ctx.accounts.system_program.is_none())
+    {
+        return Err(error!(ErrorCode::InvalidRoyaltyClaim));
+    }
+    if !ctx.accounts.payment_manager.fee_tiers.is_empty() && ctx.accounts.payer_stats.is_none() {
+        return Err(error!(ErrorCode::InvalidPayerStats));
+    }
+
+    let payment_manager = &ctx.accounts.payment_manager;
+
+    // royalty terms from the Metaplex metadata or the bridged metadata of the mint
+    let mint_metadata = resolve_mint_metadata(payment_manager, &ctx.accounts.mint.key(), &ctx.accounts.mint_metadata)?;
+    // only mints of the required collection can be paid for
+    assert_required_collection(payment_manager, mint_metadata.as_ref().and_then(|mint_metadata| mint_metadata.collection.as_ref()))?;
+    // the creators have not changed since the payer looked at the metadata
+    assert_creator_hash(mint_metadata.as_ref().and_then(|mint_metadata| mint_metadata.creators.as_ref()), ix.expected_creator_hash)?;
+
+    // primary sale proceeds go to the creators instead of the payment target
+    let primary_sale = mint_metadata.as_ref().map_or(false, |mint_metadata| {
+        is_primary_sale(
+            payment_manager,
+            mint_metadata.primary_sale_happened,
+            mint_metadata.creators.is_some() || !ctx.accounts.creator_config.data_is_empty(),
+        )
+    });
+    // otherwise they can go to the primary sale target, e.g. a launchpad treasury
+    let primary_sale_target = mint_metadata
+        .as_ref()
+        .map_or(false, |mint_metadata| pays_primary_sale_target(payment_manager, mint_metadata.primary_sale_happened, primary_sale));
+
+    // an overpayment, e.g. from a stale quote, is not principal, it and the fees on it stay with the payer
+    let refund_amount = overpayment_refund(ix.payment_amount, ix.expected_amount)?;
+    if refund_amount > 0 {
+        msg!("Refunding overpayment of {}", refund_amount);
+    }
+    // a partial fill settles its fraction of the payment, every fee below scales with it
+    let filled_amount = fill_amount(ix.payment_amount.checked_sub(refund_amount).expect("Sub error"), ix.fill_fraction)?;
+
+    // the fee tier of a payment is the one the payer's volume before it reached
+    let payer_volume = ctx.accounts.payer_stats.as_ref().map_or(0, |payer_stats| payer_stats.volume);
+    let mut fee_payment_manager = tiered_payment_manager(payment_manager, payer_volume);
+    // the policy of the mint's collection takes precedence over the payment manager's royalty terms
+    if let Some(collection_policy) = mint_collection_policy(&ctx.accounts.collection_policy, mint_metadata.as_ref().and_then(|mint_metadata| mint_metadata.collection.as_ref()))? {
+        apply_collection_policy(&mut fee_payment_manager, &collection_policy);
+    }
+
+    // exempt payers pay no fees
+    let exempt = ctx.accounts.exempt_payer.as_ref().map_or(false, |exempt_payer| exempt_payer.exempt);
+
+    // royalties are waived below the royalty min price, an exact output payment is compared before
+    // the seller fee is grossed up as it is not charged once royalties are waived
+    let royalty_waiver_amount = if ix.exact_output && !exempt {
+        exact_output_payment_amount(&fee_payment_manager, filled_amount, None)?
+    } else {
+        filled_amount
+    };
+    let mint_metadata = mint_metadata.filter(|_| primary_sale || !royalties_waived(&fee_payment_manager, royalty_waiver_amount));
+
+    // gross up the payment so the payment target nets exactly the filled amount
+    let payment_amount = if ix.exact_output && !exempt {
+        let seller_fee_basis_points = mint_metadata
+            .as_ref()
+            .filter(|_| !primary_sale && is_seller_fee_additive(payment_manager))
+            .map(|mint_metadata| mint_metadata.seller_fee_basis_points);
+        exact_output_payment_amount(&fee_payment_manager, filled_amount, seller_fee_basis_points)?
+    } else {
+        filled_amount
+    };
+
+    // calculate fees
+    let seller_fee_basis_points = mint_metadata.as_ref().filter(|_| !primary_sale).map(|mint_metadata| mint_metadata.seller_fee_basis_points);
+    let PaymentFees {
+        maker_fee,
+        taker_fee,
+        taker_fee_collector_fee,
+        mut total_fees,
+        mut total_creators_fee,
+        buy_side_fee,
+        mut principal_amount,
+        ..
+    } = if exempt {
+        exempt_payment_fees(payment_amount)
+    } else {
+        payment_fees(&fee_payment_manager, payment_amount, seller_fee_basis_points)?
+    };
+    // fail before any transfer rather than deep in the payouts when the payer cannot cover everything
+    let payer_total = principal_amount.checked_add(total_fees).expect("Add error").checked_add(buy_side_fee).expect("Add error");
+    assert_max_total_spend(payer_total, ix.max_total_spend)?;
+    // fees and royalties come out of the fee source token account when one is passed, primary sale
+    // proceeds are principal and are paid out of the payer token account like the fees around them
+    let fee_source_token_account = ctx.accounts.fee_source_token_account.as_ref().filter(|_| !primary_sale);
+    match fee_source_token_account {
+        Some(fee_source_token_account) => {
+            // the payer token account only covers what the payment target nets
+            let target_amount = if ix.exact_output { filled_amount } else { principal_amount };
+            if ctx.accounts.payer_token_account.amount < target_amount || fee_source_token_account.amount < payer_total.checked_sub(target_amount).expect("Sub error") {
+                return Err(error!(ErrorCode::InsufficientPayerBalance));
+            }
         }
-        let mint_metadata_data = ctx.accounts.mint_metadata.try_borrow_mut_data().expect("Failed to borrow data");
-        let mint_metadata = Metadata::deserialize(&mut mint_metadata_data.as_ref()).expect("Failed to deserialize metadata");
-        if mint_metadata.mint != ctx.accounts.mint.key() {
-            return Err(error!(ErrorCode::InvalidMintMetadata));
+        None => {
+            if ctx.accounts.payer_token_account.amount < payer_total {
+                return Err(error!(ErrorCode::InsufficientPayerBalance));
+            }
         }
-        let seller_fee = if payment_manager.include_seller_fee_basis_points {
-            payment_amount
-                .checked_mul(mint_metadata.seller_fee_basis_points.into())
-                .expect("Multiplication error")
-                .checked_div(BASIS_POINTS_DIVISOR.into())
-                .expect("Division error")
-        } else {
-            0
-        };
-        let total_creators_fee = total_fees
-            .checked_mul(payment_manager.royalty_fee_share.unwrap_or(DEFAULT_ROYALTY_FEE_SHARE))
-            .unwrap()
-            .checked_div(BASIS_POINTS_DIVISOR.into())
-            .expect("Div error")
-            .checked_add(seller_fee)
-            .expect("Add error");
-        total_fees = total_fees.checked_add(seller_fee).expect("Add error");
-
-        if let Some(creators) = mint_metadata.creators {
-            let creator_amounts: Vec<u64> = creators
-                .clone()
-                .into_iter()
-                .map(|creator| total_creators_fee.checked_mul(u64::try_from(creator.share).expect("Could not cast u8 to u64")).unwrap())
-                .collect();
-            let creator_amounts_sum: u64 = creator_amounts.iter().sum();
-            let mut creators_fee_remainder = total_creators_fee.checked_sub(creator_amounts_sum.checked_div(100).expect("Div error")).expect("Sub error");
-            for creator in creators {
-                if creator.share != 0 {
-                    let creator_token_account_info = next_account_info(remaining_accs)?;
-                    let creator_token_account = Account::<TokenAccount>::try_from(creator_token_account_info)?;
-                    if creator_token_account.owner != creator.address || creator_token_account.mint != ctx.accounts.payment_mint.key() {
-                        return Err(error!(ErrorCode::InvalidTokenAccount));
-                    }
-                    let share = u64::try_from(creator.share).expect("Could not cast u8 to u64");
-                    let creator_fee_remainder_amount = u64::from(creators_fee_remainder > 0);
-                    let creator_fee_amount = total_creators_fee
-                        .checked_mul(share)
-                        .unwrap()
-                        .checked_div(100)
-                        .expect("Div error")
-                        .checked_add(creator_fee_remainder_amount)
-                        .expect("Add error");
-                    creators_fee_remainder = creators_fee_remainder.checked_sub(creator_fee_remainder_amount).expect("Sub error");
+    }
+    let fee_source = fee_source_token_account.map_or(ctx.accounts.payer_token_account.to_account_info(), |fee_source_token_account| {
+        fee_source_token_account.to_account_info()
+    });
+    log_compute_units("fee calculation");
 
+    // rounding dust from the gross up goes to the fee collector
+    let mut exact_output_excess: u64 = 0;
+    if ix.exact_output {
+        exact_output_excess = principal_amount.checked_sub(filled_amount).expect("Sub error");
+        principal_amount = filled_amount;
+    }
+    // the fees the payer is charged, the rebate is on these and not on primary sale proceeds
+    let fees_charged = total_fees;
+    if primary_sale {
+        total_creators_fee = principal_amount;
+        total_fees = total_fees.checked_add(principal_amount).expect("Add error");
+        principal_amount = 0;
+    }
+    // a payment without any fee is a single transfer to the payment target, none of the payouts below apply
+    if is_fee_free(total_fees, buy_side_fee, exact_output_excess) {
+        pay_target(ctx.accounts, principal_amount, primary_sale_target, checked)?;
+        log_compute_units("fee free transfer");
+        return record_payment(ctx.accounts, &ctx.bumps, ix.record_nonce, payment_amount, payer_total, principal_amount);
+    }
+    // each fee is reported on its own for indexers
+    let payment_manager_id = payment_manager.key();
+    let mint_id = ctx.accounts.mint.key();
+    if maker_fee > 0 {
+        emit!(MakerFeeCharged {
+            payment_manager: payment_manager_id,
+            mint: mint_id,
+            amount: maker_fee,
+            recipient: payment_manager.fee_collector,
+        });
+    }
+    if taker_fee > 0 {
+        emit!(TakerFeeCharged {
+            payment_manager: payment_manager_id,
+            mint: mint_id,
+            amount: taker_fee,
+            recipient: payment_manager.taker_fee_collector.unwrap_or(payment_manager.fee_collector),
+        });
+    }
+    let principal_first = payment_manager.payout_order == PayoutOrder::PrincipalFirst as u8;
+    if principal_first {
+        // pay target before royalties
+        pay_target(ctx.accounts, principal_amount, primary_sale_target, checked)?;
+    }
+
+    // royalties
+    let mut fees_paid_out: u64 = 0;
+    let remaining_accs = &mut ctx.remaining_accounts.iter();
+    if let Some(mint_metadata) = mint_metadata {
+        if let Some(creators) = royalty_creators(&ctx.accounts.creator_config, mint_metadata.creators)? {
+            // malformed metadata whose creators all have a zero share pays no royalties, the creators fee goes to the fee collector
+            if creator_shares_sum(&creators) == 0 {
+                msg!("Creators have no shares, creators fee goes to fee collector");
+            }
+            let mut royalties_claimed: u64 = 0;
+            for (creator, creator_fee_amount) in creator_payouts(&creators, total_creators_fee, payment_manager.remainder_to_collector) {
+                if payment_manager.claim_royalties {
+                    // creators withdraw their royalty later, no creator token account has to exist at payment time
+                    accrue_royalty(ctx.accounts, next_account_info(remaining_accs)?, creator, creator_fee_amount)?;
+                    royalties_claimed = royalties_claimed.checked_add(creator_fee_amount).expect("Add error");
+                    fees_paid_out = fees_paid_out.checked_add(creator_fee_amount).expect("Add error");
                     if creator_fee_amount > 0 {
+                        emit_seller_fee_distributed(payment_manager_id, mint_id, creator_fee_amount, creator);
+                    }
+                    continue;
+                }
+                let creator_token_account_info = next_account_info(remaining_accs)?;
+                if payment_manager.escrow_unreceivable_royalties {
+                    // the creator's royalty claim follows their token account, a creator who cannot receive does not fail the payment
+                    let royalty_claim_info = next_account_info(remaining_accs)?;
+                    let creator_token_account = remaining_token_account(creator_token_account_info).ok();
+                    if !creator_token_account_receivable(creator_token_account.as_deref(), &creator, &ctx.accounts.payment_mint.key())? {
+                        msg!("Creator token account cannot receive, creator share is escrowed for later claim");
+                        accrue_royalty(ctx.accounts, royalty_claim_info, creator, creator_fee_amount)?;
+                        royalties_claimed = royalties_claimed.checked_add(creator_fee_amount).expect("Add error");
                         fees_paid_out = fees_paid_out.checked_add(creator_fee_amount).expect("Add error");
-                        let cpi_accounts = Transfer {
-                            from: ctx.accounts.payer_token_account.to_account_info(),
-                            to: creator_token_account_info.to_account_info(),
-                            authority: ctx.accounts.payer.to_account_info(),
-                        };
-                        let cpi_program = ctx.accounts.token_program.to_account_info();
-                        let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
-                        token::transfer(cpi_context, creator_fee_amount)?;
+                        if creator_fee_amount > 0 {
+                            emit_seller_fee_distributed(payment_manager_id, mint_id, creator_fee_amount, creator);
+                        }
+                        continue;
+                    }
+                } else {
+                    let creator_token_account = remaining_token_account(creator_token_account_info)?;
+                    if !creator_token_account_payable(payment_manager, &creator_token_account, &creator, &ctx.accounts.payment_mint.key())? {
+                        msg!("Creator token account holds another mint, creator share goes to fee collector");
+                        continue;
                     }
                 }
+
+                if creator_fee_amount > 0 {
+                    // the creator's share is settled either way, a payer who is a creator keeps theirs
+                    fees_paid_out = fees_paid_out.checked_add(creator_fee_amount).expect("Add error");
+                    emit_seller_fee_distributed(payment_manager_id, mint_id, creator_fee_amount, creator);
+                }
+                if creator_fee_amount > 0 && creator_token_account_info.key() != fee_source.key() {
+                    transfer(
+                        checked,
+                        ctx.accounts.token_program.to_account_info(),
+                        fee_source.to_account_info(),
+                        &ctx.accounts.payment_mint,
+                        creator_token_account_info.to_account_info(),
+                        ctx.accounts.payer.to_account_info(),
+                        creator_fee_amount,
+                    )?;
+                }
+            }
+            // claimed royalties are held together in the royalty escrow
+            if royalties_claimed > 0 {
+                let royalty_escrow = ctx.accounts.royalty_escrow.as_ref().ok_or(error!(ErrorCode::InvalidRoyaltyClaim))?;
+                transfer(
+                    checked,
+                    ctx.accounts.token_program.to_account_info(),
+                    fee_source.to_account_info(),
+                    &ctx.accounts.payment_mint,
+                    royalty_escrow.to_account_info(),
+                    ctx.accounts.payer.to_account_info(),
+                    royalties_claimed,
+                )?;
+            }
+        } else if payment_manager.fallback_royalty_to_update_authority {
+            // without creators the update authority is paid the creators fee in their place, its token account takes the place of the creator token accounts
+            let update_authority_token_account_info = next_account_info(remaining_accs)?;
+            let update_authority_token_account = remaining_token_account(update_authority_token_account_info)?;
+            assert_token_account(&update_authority_token_account, &mint_metadata.update_authority, &ctx.accounts.payment_mint.key())?;
+
+            fees_paid_out = fees_paid_out.checked_add(total_creators_fee).expect("Add error");
+            emit_seller_fee_distributed(payment_manager_id, mint_id, total_creators_fee, mint_metadata.update_authority);
+            if total_creators_fee > 0 && update_authority_token_account_info.key() != fee_source.key() {
+                transfer(
+                    checked,
+                    ctx.accounts.token_program.to_account_info(),
+                    fee_source.to_account_info(),
+                    &ctx.accounts.payment_mint,
+                    update_authority_token_account_info.to_account_info(),
+                    ctx.accounts.payer.to_account_info(),
+                    total_creators_fee,
+                )?;
             }
         }
     }
 
-    // calculate fees
-    let buy_side_fee = payment_amount
-        .checked_mul(DEFAULT_BUY_SIDE_FEE_SHARE)
-        .unwrap()
-        .checked_div(BASIS_POINTS_DIVISOR.into())
-        .expect("Div error");
-    let mut fee_collector_fee = total_fees.checked_add(buy_side_fee).expect("Add error").checked_sub(fees_paid_out).expect("Sub error");
+    log_compute_units("creator payouts");
+
+    let mut fee_collector_fee = total_fees
+        .checked_add(buy_side_fee)
+        .expect("Add error")
+        .checked_add(exact_output_excess)
+        .expect("Add error")
+        .checked_sub(fees_paid_out)
+        .expect("Sub error");
+
+    // pay taker fee to the taker fee collector, its token account follows the creator token accounts
+    if let Some(taker_fee_collector) = payment_manager.taker_fee_collector {
+        let taker_fee_collector_token_account_info = next_account_info(remaining_accs)?;
+        let taker_fee_collector_token_account = Account::<TokenAccount>::try_from(taker_fee_collector_token_account_info)?;
+        if taker_fee_collector_token_account.owner != taker_fee_collector || taker_fee_collector_token_account.mint != ctx.accounts.payment_mint.key() {
+            return Err(error!(ErrorCode::InvalidTakerFeeCollectorTokenAccount));
+        }
+        if taker_fee_collector_fee > 0 {
+            transfer(
+                checked,
+                ctx.accounts.token_program.to_account_info(),
+                fee_source.to_account_info(),
+                &ctx.accounts.payment_mint,
+                taker_fee_collector_token_account_info.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                taker_fee_collector_fee,
+            )?;
+            fee_collector_fee = fee_collector_fee.checked_sub(taker_fee_collector_fee).expect("Sub error");
+        }
+    }
 
     // pay buy side fee
     let buy_side_token_account_info = next_account_info(remaining_accs);
     if buy_side_token_account_info.is_ok() {
         let buy_side_token_account = Account::<TokenAccount>::try_from(buy_side_token_account_info?);
-        if buy_side_token_account.is_ok() {
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.payer_token_account.to_account_info(),
-                to: buy_side_token_account?.to_account_info(),
-                authority: ctx.accounts.payer.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
-            token::transfer(cpi_context, buy_side_fee)?;
-
-            // remove buy side fee out of fee collector fee
-            fee_collector_fee = fee_collector_fee.checked_sub(buy_side_fee).expect("Sub error");
+        if let Ok(buy_side_token_account) = buy_side_token_account {
+            // a buy side token account of another mint would be paid in the wrong currency
+            if buy_side_token_account.mint != ctx.accounts.payment_mint.key() {
+                return Err(error!(ErrorCode::InvalidBuySideMint));
+            }
+            // a zero buy side fee is not worth a transfer CPI
+            if buy_side_fee > 0 {
+                transfer(
+                    checked,
+                    ctx.accounts.token_program.to_account_info(),
+                    fee_source.to_account_info(),
+                    &ctx.accounts.payment_mint,
+                    buy_side_token_account.to_account_info(),
+                    ctx.accounts.payer.to_account_info(),
+                    buy_side_fee,
+                )?;
+                emit!(BuySideFeePaid {
+                    payment_manager: payment_manager_id,
+                    mint: mint_id,
+                    amount: buy_side_fee,
+                    recipient: buy_side_token_account.owner,
+                });
+
+                // remove buy side fee out of fee collector fee
+                fee_collector_fee = fee_collector_fee.checked_sub(buy_side_fee).expect("Sub error");
+            }
+        } else {
+            if payment_manager.strict_remaining_accounts {
+                return Err(error!(ErrorCode::InvalidBuySideAccount));
+            }
+            msg!("Buy side account is not a valid token account, buy side fee goes to fee collector");
         }
     }
 
+    if payment_manager.strict_remaining_accounts && remaining_accs.next().is_some() {
+        return Err(error!(ErrorCode::UnexpectedRemainingAccounts));
+    }
+
+    // pay protocol fee out of the fee collector fee
+    if let Some(protocol_config) = &ctx.accounts.protocol_config {
+        let protocol_treasury_token_account = match &ctx.accounts.protocol_treasury_token_account {
+            Some(token_account) if token_account.owner == protocol_config.treasury && token_account.mint == ctx.accounts.payment_mint.key() => token_account,
+            _ => return Err(error!(ErrorCode::InvalidProtocolConfig)),
+        };
+        let protocol_fee = protocol_fee_amount(protocol_config, payment_amount, fee_collector_fee);
+        if protocol_fee > 0 {
+            transfer(
+                checked,
+                ctx.accounts.token_program.to_account_info(),
+                fee_source.to_account_info(),
+                &ctx.accounts.payment_mint,
+                protocol_treasury_token_account.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                protocol_fee,
+            )?;
+            fee_collector_fee = fee_collector_fee.checked_sub(protocol_fee).expect("Sub error");
+        }
+    }
+
+    // the payer's rebate is withheld from the fee collector fee, the payer keeps it
+    let rebate = rebate_amount(payment_manager, fees_charged, fee_collector_fee);
+    if rebate > 0 {
+        msg!("Rebating {} of the fees to the payer", rebate);
+        fee_collector_fee = fee_collector_fee.checked_sub(rebate).expect("Sub error");
+    }
+
     if fee_collector_fee > 0 {
         // pay remaining fees to fee_colector
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.payer_token_account.to_account_info(),
-            to: ctx.accounts.fee_collector_token_account.to_account_info(),
-            authority: ctx.accounts.payer.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_context, fee_collector_fee)?;
+        transfer(
+            checked,
+            ctx.accounts.token_program.to_account_info(),
+            fee_source.to_account_info(),
+            &ctx.accounts.payment_mint,
+            ctx.accounts.fee_collector_token_account.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            fee_collector_fee,
+        )?;
+        emit!(FeeCollectorPaid {
+            payment_manager: payment_manager_id,
+            mint: mint_id,
+            amount: fee_collector_fee,
+            recipient: payment_manager.fee_collector,
+        });
     }
 
-    // pay target
-    let cpi_accounts = Transfer {
-        from: ctx.accounts.payer_token_account.to_account_info(),
-        to: ctx.accounts.payment_token_account.to_account_info(),
-        authority: ctx.accounts.payer.to_account_info(),
-    };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(
-        cpi_context,
-        payment_amount
-            .checked_add(taker_fee)
-            .expect("Add error")
-            .checked_sub(total_fees)
-            .expect("Sub error")
-            .checked_sub(buy_side_fee)
-            .expect("Sub error"),
-    )?;
+    if !principal_first {
+        // pay target
+        pay_target(ctx.accounts, principal_amount, primary_sale_target, checked)?;
+    }
+    log_compute_units("final transfer");
+
+    // the payer's outflow is net of the rebate
+    let payer_total = payer_total.checked_sub(rebate).expect("Sub error");
+    record_payment(ctx.accounts, &ctx.bumps, ix.record_nonce, payment_amount, payer_total, principal_amount)
+}
+
+/// Bookkeeping of a payment once it is paid out: its payment record, its fees in stable value and
+/// the payer's volume
+fn record_payment(accounts: &mut HandlePaymentWithRoyaltiesCtx, bumps: &BTreeMap<String, u8>, record_nonce: u64, payment_amount: u64, payer_total: u64, principal_amount: u64) -> Result<()> {
+    // structured receipt of the payment for accounting
+    if accounts.payment_manager.record_payments {
+        let payment_manager_id = accounts.payment_manager.key();
+        let payer_id = accounts.payer.key();
+        let mint_id = accounts.mint.key();
+        let payment_record = accounts.payment_record.as_mut().ok_or(error!(ErrorCode::InvalidPaymentRecord))?;
+        payment_record.bump = *bumps.get("payment_record").unwrap();
+        payment_record.payment_manager = payment_manager_id;
+        payment_record.payer = payer_id;
+        payment_record.mint = mint_id;
+        payment_record.nonce = record_nonce;
+        payment_record.payment_amount = payment_amount;
+        // everything the payer paid on top of what the payment target received
+        payment_record.total_fees = payer_total.checked_sub(principal_amount).expect("Sub error");
+        payment_record.timestamp = Clock::get()?.unix_timestamp;
+    }
+
+    if let Some(price_oracle) = &accounts.price_oracle {
+        let total_fees = payer_total.checked_sub(principal_amount).expect("Sub error");
+        emit!(PaymentFeesInStable {
+            payment_manager: accounts.payment_manager.key(),
+            payment_mint: accounts.payment_mint.key(),
+            mint: accounts.mint.key(),
+            payer: accounts.payer.key(),
+            total_fees,
+            price: price_oracle.price,
+            fees_in_stable: stable_value(total_fees, price_oracle.price),
+        });
+    }
+
+    // the payment counts towards the payer's volume for the fee tiers of later payments
+    let payment_manager_id = accounts.payment_manager.key();
+    let payer_id = accounts.payer.key();
+    if let Some(payer_stats) = accounts.payer_stats.as_mut() {
+        payer_stats.bump = *bumps.get("payer_stats").unwrap();
+        payer_stats.payment_manager = payment_manager_id;
+        payer_stats.payer = payer_id;
+        payer_stats.volume = payer_stats.volume.saturating_add(payment_amount);
+    }
 
     Ok(())
 }
+
+fn emit_seller_fee_distributed(payment_manager: Pubkey, mint: Pubkey, amount: u64, recipient: Pubkey) {
+    if amount > 0 {
+        emit!(SellerFeeDistributed {
+            payment_manager,
+            mint,
+            amount,
+            recipient,
+        });
+    }
+}
+
+fn pay_target(accounts: &HandlePaymentWithRoyaltiesCtx, amount: u64, primary_sale_target: bool, checked: bool) -> Result<()> {
+    // primary sale proceeds go to the primary sale target, otherwise the seller's proceeds escrow
+    // or the maker token account stands in for the payment token account
+    let payment_target = match (
+        primary_sale_target,
+        &accounts.primary_sale_target_token_account,
+        &accounts.proceeds_escrow,
+        &accounts.maker_token_account,
+        &accounts.payment_token_account,
+    ) {
+        (true, Some(primary_sale_target_token_account), _, _, _) => primary_sale_target_token_account.to_account_info(),
+        (true, None, _, _, _) => return Err(error!(ErrorCode::InvalidPrimarySaleTargetTokenAccount)),
+        (false, _, Some(proceeds_escrow), _, _) => proceeds_escrow.to_account_info(),
+        (false, _, None, Some(maker_token_account), _) => maker_token_account.to_account_info(),
+        (false, _, None, None, Some(payment_token_account)) => payment_token_account.to_account_info(),
+        (false, _, None, None, None) => return Err(error!(ErrorCode::MissingPaymentTokenAccount)),
+    };
+    transfer(
+        checked,
+        accounts.token_program.to_account_info(),
+        accounts.payer_token_account.to_account_info(),
+        &accounts.payment_mint,
+        payment_target,
+        accounts.payer.to_account_info(),
+        amount,
+    )
+}
+
+// a checked payment reloads the destination of each transfer to assert what it was credited
+fn transfer<'info>(
+    checked: bool,
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: &Account<'info, Mint>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if checked {
+        return transfer_tokens_checked(token_program, from, mint, to, authority, amount);
+    }
+    transfer_tokens(token_program, from, mint, to, authority, amount)
+}
+
+// adds a creator's royalty to their royalty claim, the claim is created on their first royalty so a
+// share rounded down to nothing neither creates nor touches it
+fn accrue_royalty<'info>(accounts: &HandlePaymentWithRoyaltiesCtx<'info>, royalty_claim_info: &AccountInfo<'info>, creator: Pubkey, amount: u64) -> Result<()> {
+    let payment_manager_id = accounts.payment_manager.key();
+    let payment_mint_id = accounts.payment_mint.key();
+    let (royalty_claim_id, bump) = find_royalty_claim_address(&payment_manager_id, &payment_mint_id, &creator);
+    if royalty_claim_info.key() != royalty_claim_id {
+        return Err(error!(ErrorCode::InvalidRoyaltyClaim));
+    }
+    if amount == 0 {
+        return Ok(());
+    }
+
+    if royalty_claim_info.data_is_empty() {
+        let system_program = accounts.system_program.as_ref().ok_or(error!(ErrorCode::InvalidRoyaltyClaim))?;
+        let royalty_claim_signer: &[&[&[u8]]] = &[&[ROYALTY_CLAIM_SEED.as_bytes(), payment_manager_id.as_ref(), payment_mint_id.as_ref(), creator.as_ref(), &[bump]]];
+        // topped up, allocated and assigned rather than created, anyone can send lamports to the
+        // claim address beforehand and creating an account holding lamports fails
+        let top_up_lamports = Rent::get()?.minimum_balance(ROYALTY_CLAIM_SIZE).saturating_sub(royalty_claim_info.lamports());
+        if top_up_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(&accounts.payer.key(), &royalty_claim_id, top_up_lamports),
+                &[accounts.payer.to_account_info(), royalty_claim_info.clone(), system_program.to_account_info()],
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(&royalty_claim_id, ROYALTY_CLAIM_SIZE as u64),
+            &[royalty_claim_info.clone(), system_program.to_account_info()],
+            royalty_claim_signer,
+        )?;
+        invoke_signed(
+            &system_instruction::assign(&royalty_claim_id, &crate::ID),
+            &[royalty_claim_info.clone(), system_program.to_account_info()],
+            royalty_claim_signer,
+        )?;
+        let royalty_claim = RoyaltyClaim {
+            bump,
+            payment_manager: payment_manager_id,
+            payment_mint: payment_mint_id,
+            creator,
+            amount,
+        };
+        let mut royalty_claim_data = royalty_claim_info.try_borrow_mut_data()?;
+        return royalty_claim.try_serialize(&mut &mut royalty_claim_data[..]);
+    }
+
+    let mut royalty_claim = Account::<RoyaltyClaim>::try_from(royalty_claim_info)?;
+    royalty_claim.amount = royalty_claim.amount.checked_add(amount).expect("Add error");
+    royalty_claim.exit(&crate::ID)
+}
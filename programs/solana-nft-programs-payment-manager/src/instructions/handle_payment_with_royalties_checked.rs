@@ -0,0 +1,11 @@
+use {
+    crate::instructions::handle_payment_with_royalties::{handle_payment, HandlePaymentWithRoyaltiesCtx, HandlePaymentWithRoyaltiesIx},
+    anchor_lang::prelude::*,
+};
+
+/// Pays out a payment like `handle_payment_with_royalties` and reloads the token account of every
+/// payout to assert it was credited exactly the amount computed for it. High value payments fail
+/// with `BalanceMismatch` rather than settle short, e.g. to a mint that takes a cut of transfers
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, HandlePaymentWithRoyaltiesCtx<'info>>, ix: HandlePaymentWithRoyaltiesIx) -> Result<()> {
+    handle_payment(ctx, ix, true)
+}
@@ -1,4 +1,10 @@
-use {crate::state::*, anchor_lang::prelude::*};
+use {
+    crate::{
+        state::*,
+        util::{validate_admins, validate_fee_collector, validate_fee_config},
+    },
+    anchor_lang::prelude::*,
+};
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct InitIx {
@@ -8,6 +14,36 @@ pub struct InitIx {
     pub taker_fee_basis_points: u16,
     pub include_seller_fee_basis_points: bool,
     pub royalty_fee_share: Option<u64>,
+    pub flat_fee_amount: Option<u64>,
+    pub royalty_conversion_rate_bounds: Option<ConversionRateBounds>,
+    pub strict_remaining_accounts: bool,
+    pub authority_threshold: Option<u8>,
+    pub admins: Vec<Pubkey>,
+    pub max_royalty_basis_points: Option<u16>,
+    pub clamp_royalties: bool,
+    pub payout_order: u8,
+    pub royalty_min_price: Option<u64>,
+    pub quote_conversion_rate_bounds: Option<ConversionRateBounds>,
+    pub handle_primary_sales: bool,
+    pub min_payment_interval_slots: Option<u64>,
+    pub max_creators_fee: Option<u64>,
+    pub royalty_floor_basis_points: Option<u16>,
+    pub taker_fee_collector: Option<Pubkey>,
+    pub required_collection: Option<Pubkey>,
+    pub seller_fee_within_fees: bool,
+    pub fee_mode: u8,
+    pub record_payments: bool,
+    pub primary_sale_target: Option<Pubkey>,
+    pub fallback_royalty_to_update_authority: bool,
+    pub taker_covers_maker_fee: bool,
+    pub claim_royalties: bool,
+    pub bridged_metadata_program: Option<Pubkey>,
+    pub remainder_to_collector: bool,
+    pub skip_mismatched_creator_mints: bool,
+    pub buy_side_additive: bool,
+    pub metadata_program_id: Option<Pubkey>,
+    pub escrow_unreceivable_royalties: bool,
+    pub rebate_basis_points: Option<u16>,
 }
 
 #[derive(Accounts)]
@@ -30,6 +66,11 @@ pub struct InitCtx<'info> {
 }
 
 pub fn handler(ctx: Context<InitCtx>, ix: InitIx) -> Result<()> {
+    validate_admins(&ix.admins, ix.authority_threshold)?;
+    validate_fee_collector(&ix.fee_collector)?;
+    PayoutOrder::try_from(ix.payout_order)?;
+    FeeMode::try_from(ix.fee_mode)?;
+
     let payment_manager = &mut ctx.accounts.payment_manager;
     payment_manager.bump = *ctx.bumps.get("payment_manager").unwrap();
     payment_manager.name = ix.name;
@@ -39,5 +80,36 @@ pub fn handler(ctx: Context<InitCtx>, ix: InitIx) -> Result<()> {
     payment_manager.authority = ctx.accounts.authority.key();
     payment_manager.include_seller_fee_basis_points = ix.include_seller_fee_basis_points;
     payment_manager.royalty_fee_share = ix.royalty_fee_share;
+    payment_manager.flat_fee_amount = ix.flat_fee_amount;
+    payment_manager.royalty_conversion_rate_bounds = ix.royalty_conversion_rate_bounds;
+    payment_manager.strict_remaining_accounts = ix.strict_remaining_accounts;
+    payment_manager.authority_threshold = ix.authority_threshold;
+    payment_manager.admins = ix.admins;
+    payment_manager.max_royalty_basis_points = ix.max_royalty_basis_points;
+    payment_manager.clamp_royalties = ix.clamp_royalties;
+    payment_manager.payout_order = ix.payout_order;
+    payment_manager.royalty_min_price = ix.royalty_min_price;
+    payment_manager.quote_conversion_rate_bounds = ix.quote_conversion_rate_bounds;
+    payment_manager.handle_primary_sales = ix.handle_primary_sales;
+    payment_manager.min_payment_interval_slots = ix.min_payment_interval_slots;
+    payment_manager.max_creators_fee = ix.max_creators_fee;
+    payment_manager.royalty_floor_basis_points = ix.royalty_floor_basis_points;
+    payment_manager.taker_fee_collector = ix.taker_fee_collector;
+    payment_manager.required_collection = ix.required_collection;
+    payment_manager.seller_fee_within_fees = ix.seller_fee_within_fees;
+    payment_manager.fee_mode = ix.fee_mode;
+    payment_manager.record_payments = ix.record_payments;
+    payment_manager.primary_sale_target = ix.primary_sale_target;
+    payment_manager.fallback_royalty_to_update_authority = ix.fallback_royalty_to_update_authority;
+    payment_manager.taker_covers_maker_fee = ix.taker_covers_maker_fee;
+    payment_manager.claim_royalties = ix.claim_royalties;
+    payment_manager.bridged_metadata_program = ix.bridged_metadata_program;
+    payment_manager.remainder_to_collector = ix.remainder_to_collector;
+    payment_manager.skip_mismatched_creator_mints = ix.skip_mismatched_creator_mints;
+    payment_manager.buy_side_additive = ix.buy_side_additive;
+    payment_manager.metadata_program_id = ix.metadata_program_id;
+    payment_manager.escrow_unreceivable_royalties = ix.escrow_unreceivable_royalties;
+    payment_manager.rebate_basis_points = ix.rebate_basis_points;
+    validate_fee_config(payment_manager)?;
     Ok(())
 }
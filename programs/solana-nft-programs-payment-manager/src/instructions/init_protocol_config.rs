@@ -0,0 +1,48 @@
+use {
+    crate::{errors::ErrorCode, program::SolanaNftProgramsPaymentManager, state::*},
+    anchor_lang::prelude::*,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct InitProtocolConfigIx {
+    pub authority: Pubkey,
+    pub protocol_fee_basis_points: u16,
+    pub treasury: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: InitProtocolConfigIx)]
+pub struct InitProtocolConfigCtx<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = PROTOCOL_CONFIG_SIZE,
+        seeds = [PROTOCOL_CONFIG_SEED.as_bytes()], bump,
+    )]
+    protocol_config: Box<Account<'info, ProtocolConfig>>,
+
+    // only the program upgrade authority picks the protocol authority, the protocol config applies
+    // to every payment manager
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::InvalidAuthority)]
+    program: Program<'info, SolanaNftProgramsPaymentManager>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key()) @ ErrorCode::InvalidAuthority)]
+    program_data: Account<'info, ProgramData>,
+    upgrade_authority: Signer<'info>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitProtocolConfigCtx>, ix: InitProtocolConfigIx) -> Result<()> {
+    if ix.protocol_fee_basis_points > BASIS_POINTS_DIVISOR {
+        return Err(error!(ErrorCode::InvalidProtocolConfig));
+    }
+
+    let protocol_config = &mut ctx.accounts.protocol_config;
+    protocol_config.bump = *ctx.bumps.get("protocol_config").unwrap();
+    protocol_config.authority = ix.authority;
+    protocol_config.protocol_fee_basis_points = ix.protocol_fee_basis_points;
+    protocol_config.treasury = ix.treasury;
+    Ok(())
+}
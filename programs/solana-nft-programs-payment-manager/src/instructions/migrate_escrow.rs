@@ -0,0 +1,114 @@
+use {
+    crate::{
+        errors::ErrorCode,
+        state::*,
+        util::{assert_admin_signers, validate_old_escrow_seeds},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, TransferChecked},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct MigrateEscrowIx {
+    /// Seeds the old escrow authority was derived from, without its bump
+    pub old_seeds: Vec<Vec<u8>>,
+    /// Creator the migrated balance is credited to, it is claimed through their royalty claim
+    pub creator: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: MigrateEscrowIx)]
+pub struct MigrateEscrowCtx<'info> {
+    #[account(
+        seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump,
+        constraint = payment_manager.authority_threshold.is_some() || payment_manager.authority == payer.key() @ ErrorCode::InvalidPaymentManager,
+    )]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(
+        mut,
+        constraint = old_escrow.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
+    old_escrow: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [ROYALTY_ESCROW_SEED.as_bytes(), payment_manager.key().as_ref(), payment_mint.key().as_ref()], bump,
+        token::mint = payment_mint,
+        token::authority = royalty_escrow,
+    )]
+    royalty_escrow: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = ROYALTY_CLAIM_SIZE,
+        seeds = [ROYALTY_CLAIM_SEED.as_bytes(), payment_manager.key().as_ref(), payment_mint.key().as_ref(), ix.creator.as_ref()], bump,
+    )]
+    royalty_claim: Box<Account<'info, RoyaltyClaim>>,
+    payment_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    #[account(constraint = token_program.key() == *payment_mint.to_account_info().owner @ ErrorCode::InvalidTokenProgram)]
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+}
+
+/// Moves the balance of an escrow owned by an address derived from seeds a previous program
+/// version used into the royalty escrow of the payment mint, credits it to the creator's royalty
+/// claim and closes the old escrow
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, MigrateEscrowCtx<'info>>, ix: MigrateEscrowIx) -> Result<()> {
+    assert_admin_signers(&ctx.accounts.payment_manager, &ctx.accounts.payer, ctx.remaining_accounts)?;
+
+    let payment_manager_id = ctx.accounts.payment_manager.key();
+    validate_old_escrow_seeds(&ix.old_seeds, &payment_manager_id)?;
+    let old_seeds: Vec<&[u8]> = ix.old_seeds.iter().map(|seed| seed.as_slice()).collect();
+    let (old_escrow_authority, bump) = Pubkey::try_find_program_address(&old_seeds, ctx.program_id).ok_or(error!(ErrorCode::InvalidEscrowSeeds))?;
+    if ctx.accounts.old_escrow.owner != old_escrow_authority {
+        return Err(error!(ErrorCode::InvalidEscrowSeeds));
+    }
+    let bump = [bump];
+    let old_escrow_seeds: Vec<&[u8]> = old_seeds.into_iter().chain(std::iter::once(&bump[..])).collect();
+    let old_escrow_signer = &[&old_escrow_seeds[..]];
+
+    // the authority is also the escrow itself for escrows created by the program
+    let authority = if ctx.accounts.old_escrow.key() == old_escrow_authority {
+        ctx.accounts.old_escrow.to_account_info()
+    } else {
+        ctx.remaining_accounts
+            .iter()
+            .find(|account| account.key() == old_escrow_authority)
+            .ok_or(error!(ErrorCode::InvalidEscrowSeeds))?
+            .to_account_info()
+    };
+
+    if ctx.accounts.old_escrow.amount > 0 {
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.old_escrow.to_account_info(),
+            mint: ctx.accounts.payment_mint.to_account_info(),
+            to: ctx.accounts.royalty_escrow.to_account_info(),
+            authority: authority.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(old_escrow_signer);
+        token::transfer_checked(cpi_context, ctx.accounts.old_escrow.amount, ctx.accounts.payment_mint.decimals)?;
+    }
+
+    let migrated_amount = ctx.accounts.old_escrow.amount;
+    let royalty_claim = &mut ctx.accounts.royalty_claim;
+    royalty_claim.bump = *ctx.bumps.get("royalty_claim").unwrap();
+    royalty_claim.payment_manager = payment_manager_id;
+    royalty_claim.payment_mint = ctx.accounts.payment_mint.key();
+    royalty_claim.creator = ix.creator;
+    royalty_claim.amount = royalty_claim.amount.checked_add(migrated_amount).expect("Add error");
+
+    let cpi_accounts = CloseAccount {
+        account: ctx.accounts.old_escrow.to_account_info(),
+        destination: ctx.accounts.payer.to_account_info(),
+        authority,
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(old_escrow_signer);
+    token::close_account(cpi_context)?;
+    Ok(())
+}
@@ -1,13 +1,79 @@
+pub mod claim_proceeds;
+pub mod claim_royalty;
 pub mod close;
 pub mod handle_native_payment_with_royalties;
 pub mod handle_payment;
+pub mod handle_payment_with_converted_royalties;
+pub mod handle_payment_with_quote;
 pub mod handle_payment_with_royalties;
+pub mod handle_payment_with_royalties_checked;
 pub mod init;
+pub mod init_protocol_config;
+pub mod migrate_escrow;
+pub mod queue_fee_schedule;
+pub mod resize_payment_manager;
+pub mod set_accepted_mints;
+pub mod set_collection_policy;
+pub mod set_creator_config;
+pub mod set_exempt_payer;
+pub mod set_fee_collector;
+pub mod set_fee_tiers;
+pub mod set_include_seller_fee;
+pub mod set_price_oracle;
+pub mod set_protocol_config;
 pub mod update;
+pub mod validate_payment_accounts;
 
-pub use close::*;
-pub use handle_native_payment_with_royalties::*;
-pub use handle_payment::*;
-pub use handle_payment_with_royalties::*;
-pub use init::*;
-pub use update::*;
+// every module has its own handler, so the instruction types are re-exported by name
+pub use claim_proceeds::ClaimProceedsCtx;
+pub use claim_royalty::ClaimRoyaltyCtx;
+pub use close::CloseCtx;
+pub use handle_native_payment_with_royalties::HandleNativePaymentWithRoyaltiesCtx;
+pub use handle_payment::HandlePaymentCtx;
+pub use handle_payment_with_converted_royalties::{HandlePaymentWithConvertedRoyaltiesCtx, HandlePaymentWithConvertedRoyaltiesIx};
+pub use handle_payment_with_quote::{HandlePaymentWithQuoteCtx, HandlePaymentWithQuoteIx};
+pub use handle_payment_with_royalties::{
+    BuySideFeePaid, FeeCollectorPaid, HandlePaymentWithRoyaltiesCtx, HandlePaymentWithRoyaltiesIx, MakerFeeCharged, PaymentFeesInStable, SellerFeeDistributed, TakerFeeCharged,
+};
+pub use init::{InitCtx, InitIx};
+pub use init_protocol_config::{InitProtocolConfigCtx, InitProtocolConfigIx};
+pub use migrate_escrow::{MigrateEscrowCtx, MigrateEscrowIx};
+pub use queue_fee_schedule::{QueueFeeScheduleCtx, QueueFeeScheduleIx};
+pub use resize_payment_manager::ResizePaymentManagerCtx;
+pub use set_accepted_mints::SetAcceptedMintsCtx;
+pub use set_collection_policy::{SetCollectionPolicyCtx, SetCollectionPolicyIx};
+pub use set_creator_config::{SetCreatorConfigCtx, SetCreatorConfigIx};
+pub use set_exempt_payer::{SetExemptPayerCtx, SetExemptPayerIx};
+pub use set_fee_collector::{FeeCollectorChanged, SetFeeCollectorCtx};
+pub use set_fee_tiers::SetFeeTiersCtx;
+pub use set_include_seller_fee::SetIncludeSellerFeeCtx;
+pub use set_price_oracle::{SetPriceOracleCtx, SetPriceOracleIx};
+pub use set_protocol_config::{SetProtocolConfigCtx, SetProtocolConfigIx};
+pub use update::{UpdateCtx, UpdateIx};
+pub use validate_payment_accounts::ValidatePaymentAccountsCtx;
+
+// client accounts modules of the instruction contexts, the program macro looks them up at the crate root
+pub(crate) use {
+    claim_proceeds::__client_accounts_claim_proceeds_ctx, claim_royalty::__client_accounts_claim_royalty_ctx, close::__client_accounts_close_ctx,
+    handle_native_payment_with_royalties::__client_accounts_handle_native_payment_with_royalties_ctx, handle_payment::__client_accounts_handle_payment_ctx,
+    handle_payment_with_converted_royalties::__client_accounts_handle_payment_with_converted_royalties_ctx, handle_payment_with_quote::__client_accounts_handle_payment_with_quote_ctx,
+    handle_payment_with_royalties::__client_accounts_handle_payment_with_royalties_ctx, init::__client_accounts_init_ctx, init_protocol_config::__client_accounts_init_protocol_config_ctx,
+    migrate_escrow::__client_accounts_migrate_escrow_ctx, queue_fee_schedule::__client_accounts_queue_fee_schedule_ctx, resize_payment_manager::__client_accounts_resize_payment_manager_ctx,
+    set_accepted_mints::__client_accounts_set_accepted_mints_ctx, set_collection_policy::__client_accounts_set_collection_policy_ctx, set_creator_config::__client_accounts_set_creator_config_ctx,
+    set_exempt_payer::__client_accounts_set_exempt_payer_ctx, set_fee_collector::__client_accounts_set_fee_collector_ctx, set_fee_tiers::__client_accounts_set_fee_tiers_ctx,
+    set_include_seller_fee::__client_accounts_set_include_seller_fee_ctx, set_price_oracle::__client_accounts_set_price_oracle_ctx, set_protocol_config::__client_accounts_set_protocol_config_ctx,
+    update::__client_accounts_update_ctx, validate_payment_accounts::__client_accounts_validate_payment_accounts_ctx,
+};
+#[cfg(feature = "cpi")]
+pub(crate) use {
+    claim_proceeds::__cpi_client_accounts_claim_proceeds_ctx, claim_royalty::__cpi_client_accounts_claim_royalty_ctx, close::__cpi_client_accounts_close_ctx,
+    handle_native_payment_with_royalties::__cpi_client_accounts_handle_native_payment_with_royalties_ctx, handle_payment::__cpi_client_accounts_handle_payment_ctx,
+    handle_payment_with_converted_royalties::__cpi_client_accounts_handle_payment_with_converted_royalties_ctx, handle_payment_with_quote::__cpi_client_accounts_handle_payment_with_quote_ctx,
+    handle_payment_with_royalties::__cpi_client_accounts_handle_payment_with_royalties_ctx, init::__cpi_client_accounts_init_ctx, init_protocol_config::__cpi_client_accounts_init_protocol_config_ctx,
+    migrate_escrow::__cpi_client_accounts_migrate_escrow_ctx, queue_fee_schedule::__cpi_client_accounts_queue_fee_schedule_ctx,
+    resize_payment_manager::__cpi_client_accounts_resize_payment_manager_ctx, set_accepted_mints::__cpi_client_accounts_set_accepted_mints_ctx,
+    set_collection_policy::__cpi_client_accounts_set_collection_policy_ctx, set_creator_config::__cpi_client_accounts_set_creator_config_ctx,
+    set_exempt_payer::__cpi_client_accounts_set_exempt_payer_ctx, set_fee_collector::__cpi_client_accounts_set_fee_collector_ctx, set_fee_tiers::__cpi_client_accounts_set_fee_tiers_ctx,
+    set_include_seller_fee::__cpi_client_accounts_set_include_seller_fee_ctx, set_price_oracle::__cpi_client_accounts_set_price_oracle_ctx,
+    set_protocol_config::__cpi_client_accounts_set_protocol_config_ctx, update::__cpi_client_accounts_update_ctx, validate_payment_accounts::__cpi_client_accounts_validate_payment_accounts_ctx,
+};
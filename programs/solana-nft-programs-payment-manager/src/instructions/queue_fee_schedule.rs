@@ -0,0 +1,51 @@
+use {
+    crate::{
+        errors::ErrorCode,
+        state::*,
+        util::{assert_admin_signers, validate_fee_config},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct QueueFeeScheduleIx {
+    pub maker_fee_basis_points: u16,
+    pub taker_fee_basis_points: u16,
+    pub activation_time: i64,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: QueueFeeScheduleIx)]
+pub struct QueueFeeScheduleCtx<'info> {
+    #[account(
+        mut,
+        seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump,
+        constraint = payment_manager.authority_threshold.is_some() || payment_manager.authority == payer.key() @ ErrorCode::InvalidPaymentManager,
+        realloc = PAYMENT_MANAGER_SIZE,
+        realloc::payer = payer,
+        realloc::zero = false,
+    )]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<QueueFeeScheduleCtx>, ix: QueueFeeScheduleIx) -> Result<()> {
+    assert_admin_signers(&ctx.accounts.payment_manager, &ctx.accounts.payer, ctx.remaining_accounts)?;
+
+    let payment_manager = &mut ctx.accounts.payment_manager;
+    // the queued fees have to be valid for the payment manager once they activate
+    let mut activated_payment_manager = PaymentManager::clone(payment_manager);
+    activated_payment_manager.maker_fee_basis_points = ix.maker_fee_basis_points;
+    activated_payment_manager.taker_fee_basis_points = ix.taker_fee_basis_points;
+    validate_fee_config(&activated_payment_manager)?;
+
+    payment_manager.pending_fee_schedule = Some(FeeSchedule {
+        maker_fee_basis_points: ix.maker_fee_basis_points,
+        taker_fee_basis_points: ix.taker_fee_basis_points,
+        activation_time: ix.activation_time,
+    });
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use {
+    crate::{errors::ErrorCode, state::*, util::load_resized_payment_manager},
+    anchor_lang::{
+        prelude::*,
+        solana_program::{program::invoke, system_instruction},
+        Discriminator,
+    },
+};
+
+#[derive(Accounts)]
+pub struct ResizePaymentManagerCtx<'info> {
+    /// CHECK: payment managers created before the current layout are too small to deserialize, their discriminator is checked in the handler
+    #[account(mut, owner = crate::id() @ ErrorCode::InvalidPaymentManager)]
+    payment_manager: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+/// Grows a payment manager to `PAYMENT_MANAGER_SIZE` so the instructions taking it can deserialize
+/// it again. Anyone can pay for it, the zero padding only reads as the defaults of the newer fields
+pub fn handler(ctx: Context<ResizePaymentManagerCtx>) -> Result<()> {
+    let payment_manager_info = ctx.accounts.payment_manager.to_account_info();
+    if !payment_manager_info.try_borrow_data()?.starts_with(&PaymentManager::discriminator()) {
+        return Err(error!(ErrorCode::InvalidPaymentManager));
+    }
+
+    if payment_manager_info.data_len() < PAYMENT_MANAGER_SIZE {
+        let top_up_lamports = Rent::get()?.minimum_balance(PAYMENT_MANAGER_SIZE).saturating_sub(payment_manager_info.lamports());
+        if top_up_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(&ctx.accounts.payer.key(), &payment_manager_info.key(), top_up_lamports),
+                &[ctx.accounts.payer.to_account_info(), payment_manager_info.clone(), ctx.accounts.system_program.to_account_info()],
+            )?;
+        }
+        payment_manager_info.realloc(PAYMENT_MANAGER_SIZE, true)?;
+    }
+    load_resized_payment_manager(&payment_manager_info.try_borrow_data()?)?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use {
+    crate::{errors::ErrorCode, state::*, util::assert_admin_signers},
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetAcceptedMintsCtx<'info> {
+    #[account(
+        mut,
+        seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump,
+        constraint = payment_manager.authority_threshold.is_some() || payment_manager.authority == payer.key() @ ErrorCode::InvalidPaymentManager,
+        realloc = PAYMENT_MANAGER_SIZE,
+        realloc::payer = payer,
+        realloc::zero = false,
+    )]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+/// Replaces the payment mints the payment manager accepts, an empty list accepts any mint
+pub fn handler(ctx: Context<SetAcceptedMintsCtx>, accepted_mints: Vec<Pubkey>) -> Result<()> {
+    assert_admin_signers(&ctx.accounts.payment_manager, &ctx.accounts.payer, ctx.remaining_accounts)?;
+    if accepted_mints.len() > MAX_ACCEPTED_MINTS {
+        return Err(error!(ErrorCode::TooManyAcceptedMints));
+    }
+
+    ctx.accounts.payment_manager.accepted_mints = accepted_mints;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use {
+    crate::{
+        errors::ErrorCode,
+        metadata::{MetadataResolver, MetaplexResolver},
+        state::*,
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::Mint,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetCollectionPolicyIx {
+    pub enforce_royalties: bool,
+    pub enforced_basis_points: u16,
+    pub allow_waivers: bool,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: SetCollectionPolicyIx)]
+pub struct SetCollectionPolicyCtx<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = COLLECTION_POLICY_SIZE,
+        seeds = [COLLECTION_POLICY_SEED.as_bytes(), collection_mint.key().as_ref()], bump,
+    )]
+    collection_policy: Box<Account<'info, CollectionPolicy>>,
+
+    collection_mint: Box<Account<'info, Mint>>,
+    /// CHECK: This is not dangerous because of the derivation and owner checks in the handler
+    collection_metadata: AccountInfo<'info>,
+
+    collection_authority: Signer<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<SetCollectionPolicyCtx>, ix: SetCollectionPolicyIx) -> Result<()> {
+    if ix.enforced_basis_points > BASIS_POINTS_DIVISOR {
+        return Err(error!(ErrorCode::InvalidCollectionPolicy));
+    }
+
+    // the policy applies across payment managers, so it is read from the Metaplex program itself
+    let collection_metadata = MetaplexResolver { program_id: mpl_token_metadata::ID }
+        .resolve(&ctx.accounts.collection_mint.key(), &ctx.accounts.collection_metadata)?
+        .ok_or(error!(ErrorCode::InvalidMintMetadata))?;
+    // only the update authority of the collection can set its policy
+    if collection_metadata.update_authority != ctx.accounts.collection_authority.key() {
+        return Err(error!(ErrorCode::InvalidAuthority));
+    }
+
+    let collection_policy = &mut ctx.accounts.collection_policy;
+    collection_policy.bump = *ctx.bumps.get("collection_policy").unwrap();
+    collection_policy.collection = ctx.accounts.collection_mint.key();
+    collection_policy.enforce_royalties = ix.enforce_royalties;
+    collection_policy.enforced_basis_points = ix.enforced_basis_points;
+    collection_policy.allow_waivers = ix.allow_waivers;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use mpl_token_metadata::accounts::Metadata;
+use mpl_utils::assert_derivation;
+
+use {
+    crate::{errors::ErrorCode, metadata::metadata_program_id, state::*, util::validate_creator_shares},
+    anchor_lang::prelude::*,
+    anchor_spl::token::Mint,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetCreatorConfigIx {
+    pub creators: Vec<CreatorShare>,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: SetCreatorConfigIx)]
+pub struct SetCreatorConfigCtx<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = CREATOR_CONFIG_SIZE,
+        seeds = [CREATOR_CONFIG_SEED.as_bytes(), payment_manager.key().as_ref(), mint.key().as_ref()], bump,
+    )]
+    creator_config: Box<Account<'info, CreatorConfig>>,
+    #[account(seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    mint: Box<Account<'info, Mint>>,
+    /// CHECK: This is not dangerous because of the derivation and owner checks in the handler
+    mint_metadata: AccountInfo<'info>,
+
+    collection_authority: Signer<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<SetCreatorConfigCtx>, ix: SetCreatorConfigIx) -> Result<()> {
+    validate_creator_shares(&ix.creators)?;
+
+    // assert metadata account derivation
+    let metadata_program_id = metadata_program_id(&ctx.accounts.payment_manager);
+    assert_derivation(
+        &metadata_program_id,
+        &ctx.accounts.mint_metadata.to_account_info(),
+        &["metadata".to_string().as_bytes(), metadata_program_id.as_ref(), ctx.accounts.mint.key().as_ref()],
+        error!(ErrorCode::InvalidMintMetadataOwner),
+    )?;
+    if ctx.accounts.mint_metadata.to_account_info().owner.key() != metadata_program_id {
+        return Err(error!(ErrorCode::InvalidMintMetadataOwner));
+    }
+    let mint_metadata_data = ctx.accounts.mint_metadata.try_borrow_data().expect("Failed to borrow data");
+    let mint_metadata = Metadata::deserialize(&mut mint_metadata_data.as_ref()).map_err(|_| error!(ErrorCode::MetadataDeserializationFailed))?;
+    // only the update authority of the mint can set its creators
+    if mint_metadata.update_authority != ctx.accounts.collection_authority.key() {
+        return Err(error!(ErrorCode::InvalidAuthority));
+    }
+
+    let creator_config = &mut ctx.accounts.creator_config;
+    creator_config.bump = *ctx.bumps.get("creator_config").unwrap();
+    creator_config.payment_manager = ctx.accounts.payment_manager.key();
+    creator_config.mint = ctx.accounts.mint.key();
+    creator_config.creators = ix.creators;
+    Ok(())
+}
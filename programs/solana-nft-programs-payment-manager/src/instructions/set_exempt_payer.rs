@@ -0,0 +1,43 @@
+use {
+    crate::{errors::ErrorCode, state::*, util::assert_admin_signers},
+    anchor_lang::prelude::*,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetExemptPayerIx {
+    pub payer: Pubkey,
+    pub exempt: bool,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: SetExemptPayerIx)]
+pub struct SetExemptPayerCtx<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = EXEMPT_PAYER_SIZE,
+        seeds = [EXEMPT_PAYER_SEED.as_bytes(), payment_manager.key().as_ref(), ix.payer.as_ref()], bump,
+    )]
+    exempt_payer: Box<Account<'info, ExemptPayer>>,
+    #[account(
+        seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump,
+        constraint = payment_manager.authority_threshold.is_some() || payment_manager.authority == payer.key() @ ErrorCode::InvalidPaymentManager,
+    )]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+/// Payers exempted by the payment manager pay no fees, e.g. for internal or treasury transfers
+pub fn handler(ctx: Context<SetExemptPayerCtx>, ix: SetExemptPayerIx) -> Result<()> {
+    assert_admin_signers(&ctx.accounts.payment_manager, &ctx.accounts.payer, ctx.remaining_accounts)?;
+
+    let exempt_payer = &mut ctx.accounts.exempt_payer;
+    exempt_payer.bump = *ctx.bumps.get("exempt_payer").unwrap();
+    exempt_payer.payment_manager = ctx.accounts.payment_manager.key();
+    exempt_payer.payer = ix.payer;
+    exempt_payer.exempt = ix.exempt;
+    Ok(())
+}
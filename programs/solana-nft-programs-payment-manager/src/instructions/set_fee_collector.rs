@@ -0,0 +1,52 @@
+use {
+    crate::{errors::ErrorCode, state::*, util::assert_admin_signers},
+    anchor_lang::prelude::*,
+    anchor_spl::token,
+};
+
+#[derive(Accounts)]
+pub struct SetFeeCollectorCtx<'info> {
+    #[account(
+        mut,
+        seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump,
+        constraint = payment_manager.authority_threshold.is_some() || payment_manager.authority == payer.key() @ ErrorCode::InvalidPaymentManager,
+        realloc = PAYMENT_MANAGER_SIZE,
+        realloc::payer = payer,
+        realloc::zero = false,
+    )]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    /// CHECK: the new fee collector owns the fee collector token accounts, a token account or mint
+    /// passed instead would leave the fees in token accounts nobody can sign for
+    #[account(
+        constraint = *fee_collector.owner != token::ID @ ErrorCode::InvalidFeeCollector,
+        constraint = fee_collector.key() != Pubkey::default() @ ErrorCode::InvalidFeeCollector,
+    )]
+    fee_collector: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[event]
+pub struct FeeCollectorChanged {
+    pub payment_manager: Pubkey,
+    pub old_fee_collector: Pubkey,
+    pub new_fee_collector: Pubkey,
+}
+
+/// Moves the fees of every later payment to a new fee collector without a full update
+pub fn handler(ctx: Context<SetFeeCollectorCtx>) -> Result<()> {
+    assert_admin_signers(&ctx.accounts.payment_manager, &ctx.accounts.payer, ctx.remaining_accounts)?;
+
+    let payment_manager = &mut ctx.accounts.payment_manager;
+    let old_fee_collector = payment_manager.fee_collector;
+    payment_manager.fee_collector = ctx.accounts.fee_collector.key();
+    emit!(FeeCollectorChanged {
+        payment_manager: payment_manager.key(),
+        old_fee_collector,
+        new_fee_collector: payment_manager.fee_collector,
+    });
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use {
+    crate::{
+        errors::ErrorCode,
+        state::*,
+        util::{assert_admin_signers, validate_fee_tiers},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetFeeTiersCtx<'info> {
+    #[account(
+        mut,
+        seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump,
+        constraint = payment_manager.authority_threshold.is_some() || payment_manager.authority == payer.key() @ ErrorCode::InvalidPaymentManager,
+        realloc = PAYMENT_MANAGER_SIZE,
+        realloc::payer = payer,
+        realloc::zero = false,
+    )]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+/// Replaces the volume based fee tiers, an empty list charges every payer the base fees
+pub fn handler(ctx: Context<SetFeeTiersCtx>, fee_tiers: Vec<FeeTier>) -> Result<()> {
+    assert_admin_signers(&ctx.accounts.payment_manager, &ctx.accounts.payer, ctx.remaining_accounts)?;
+    validate_fee_tiers(&ctx.accounts.payment_manager, &fee_tiers)?;
+
+    ctx.accounts.payment_manager.fee_tiers = fee_tiers;
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use {
+    crate::{
+        errors::ErrorCode,
+        state::*,
+        util::{assert_admin_signers, validate_fee_config},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetIncludeSellerFeeCtx<'info> {
+    #[account(
+        mut,
+        seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump,
+        constraint = payment_manager.authority_threshold.is_some() || payment_manager.authority == payer.key() @ ErrorCode::InvalidPaymentManager,
+        realloc = PAYMENT_MANAGER_SIZE,
+        realloc::payer = payer,
+        realloc::zero = false,
+    )]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+/// Toggles include_seller_fee_basis_points without a full update
+pub fn handler(ctx: Context<SetIncludeSellerFeeCtx>, include_seller_fee_basis_points: bool) -> Result<()> {
+    assert_admin_signers(&ctx.accounts.payment_manager, &ctx.accounts.payer, ctx.remaining_accounts)?;
+
+    let payment_manager = &mut ctx.accounts.payment_manager;
+    payment_manager.include_seller_fee_basis_points = include_seller_fee_basis_points;
+    // including the seller fee can push the fees over the limit
+    validate_fee_config(payment_manager)?;
+    Ok(())
+}
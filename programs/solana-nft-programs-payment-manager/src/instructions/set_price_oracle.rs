@@ -0,0 +1,45 @@
+use {
+    crate::{errors::ErrorCode, state::*, util::assert_admin_signers},
+    anchor_lang::prelude::*,
+    anchor_spl::token::Mint,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPriceOracleIx {
+    // stable base units per payment mint base unit scaled by CONVERSION_RATE_DIVISOR
+    pub price: u64,
+}
+
+#[derive(Accounts)]
+pub struct SetPriceOracleCtx<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PRICE_ORACLE_SIZE,
+        seeds = [PRICE_ORACLE_SEED.as_bytes(), payment_manager.key().as_ref(), payment_mint.key().as_ref()], bump,
+    )]
+    price_oracle: Box<Account<'info, PriceOracle>>,
+    #[account(
+        seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump,
+        constraint = payment_manager.authority_threshold.is_some() || payment_manager.authority == payer.key() @ ErrorCode::InvalidPaymentManager,
+    )]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+    payment_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+/// Sets the price payments in the payment mint report their fees at in stable value
+pub fn handler(ctx: Context<SetPriceOracleCtx>, ix: SetPriceOracleIx) -> Result<()> {
+    assert_admin_signers(&ctx.accounts.payment_manager, &ctx.accounts.payer, ctx.remaining_accounts)?;
+
+    let price_oracle = &mut ctx.accounts.price_oracle;
+    price_oracle.bump = *ctx.bumps.get("price_oracle").unwrap();
+    price_oracle.payment_manager = ctx.accounts.payment_manager.key();
+    price_oracle.payment_mint = ctx.accounts.payment_mint.key();
+    price_oracle.price = ix.price;
+    price_oracle.updated_at = Clock::get()?.unix_timestamp;
+    Ok(())
+}
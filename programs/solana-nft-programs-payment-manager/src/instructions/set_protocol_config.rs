@@ -0,0 +1,36 @@
+use {
+    crate::{errors::ErrorCode, state::*},
+    anchor_lang::prelude::*,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetProtocolConfigIx {
+    pub authority: Pubkey,
+    pub protocol_fee_basis_points: u16,
+    pub treasury: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: SetProtocolConfigIx)]
+pub struct SetProtocolConfigCtx<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_CONFIG_SEED.as_bytes()], bump = protocol_config.bump,
+        constraint = protocol_config.authority == authority.key() @ ErrorCode::InvalidAuthority,
+    )]
+    protocol_config: Box<Account<'info, ProtocolConfig>>,
+
+    authority: Signer<'info>,
+}
+
+pub fn handler(ctx: Context<SetProtocolConfigCtx>, ix: SetProtocolConfigIx) -> Result<()> {
+    if ix.protocol_fee_basis_points > BASIS_POINTS_DIVISOR {
+        return Err(error!(ErrorCode::InvalidProtocolConfig));
+    }
+
+    let protocol_config = &mut ctx.accounts.protocol_config;
+    protocol_config.authority = ix.authority;
+    protocol_config.protocol_fee_basis_points = ix.protocol_fee_basis_points;
+    protocol_config.treasury = ix.treasury;
+    Ok(())
+}
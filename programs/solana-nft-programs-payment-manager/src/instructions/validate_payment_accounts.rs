@@ -0,0 +1,133 @@
+use {
+    crate::{
+        errors::ErrorCode,
+        metadata::resolve_mint_metadata,
+        state::*,
+        util::{
+            apply_collection_policy, assert_accepted_mint, assert_creator_hash, assert_payment_remaining_accounts, assert_required_collection, assert_token_account, creator_token_account_payable,
+            creator_token_account_receivable, find_royalty_claim_address, is_primary_sale, mint_collection_policy, remaining_token_account, royalties_waived, royalty_creators, seller_fee_amount,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, TokenAccount},
+};
+
+#[derive(Accounts)]
+#[instruction(payment_amount: u64, expected_creator_hash: Option<[u8; 32]>)]
+pub struct ValidatePaymentAccountsCtx<'info> {
+    #[account(seeds = [PAYMENT_MANAGER_SEED.as_bytes(), payment_manager.name.as_bytes()], bump = payment_manager.bump)]
+    payment_manager: Box<Account<'info, PaymentManager>>,
+
+    #[account(constraint = payer_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint)]
+    payer_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = fee_collector_token_account.owner == payment_manager.fee_collector @ ErrorCode::InvalidFeeCollectorTokenAccount,
+        constraint = fee_collector_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+    )]
+    fee_collector_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = payment_token_account.mint == payment_mint.key() @ ErrorCode::InvalidTokenAccountMint,
+        constraint = payment_token_account.key() != fee_collector_token_account.key() @ ErrorCode::TargetEqualsFeeCollector,
+    )]
+    payment_token_account: Box<Account<'info, TokenAccount>>,
+
+    payment_mint: Box<Account<'info, Mint>>,
+    mint: Box<Account<'info, Mint>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    mint_metadata: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because of the derivation and it is only read when initialized
+    #[account(seeds = [CREATOR_CONFIG_SEED.as_bytes(), payment_manager.key().as_ref(), mint.key().as_ref()], bump)]
+    creator_config: UncheckedAccount<'info>,
+    // royalty terms the collection authority enforces on the mints of the collection
+    /// CHECK: This is not dangerous because of the derivation from the verified collection in the handler and it is only read when initialized
+    collection_policy: UncheckedAccount<'info>,
+    // > Remaining accounts for each mint creator
+    // creator token account, or the update authority token account of a mint without creators
+}
+
+/// Runs the account checks of handle_payment_with_royalties without transferring anything so
+/// clients can pre-flight a payment, including the collection policy and the expected creators
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, ValidatePaymentAccountsCtx<'info>>, payment_amount: u64, expected_creator_hash: Option<[u8; 32]>) -> Result<()> {
+    let payment_manager = &ctx.accounts.payment_manager;
+    assert_accepted_mint(payment_manager, &ctx.accounts.payment_mint.key())?;
+    assert_payment_remaining_accounts(payment_manager, ctx.remaining_accounts)?;
+
+    // royalty terms from the Metaplex metadata or the bridged metadata of the mint
+    let mint_metadata = resolve_mint_metadata(payment_manager, &ctx.accounts.mint.key(), &ctx.accounts.mint_metadata)?;
+    // only mints of the required collection can be paid for
+    assert_required_collection(payment_manager, mint_metadata.as_ref().and_then(|mint_metadata| mint_metadata.collection.as_ref()))?;
+    // the creators have not changed since the payer looked at the metadata
+    assert_creator_hash(mint_metadata.as_ref().and_then(|mint_metadata| mint_metadata.creators.as_ref()), expected_creator_hash)?;
+    let primary_sale = mint_metadata.as_ref().map_or(false, |mint_metadata| {
+        is_primary_sale(
+            payment_manager,
+            mint_metadata.primary_sale_happened,
+            mint_metadata.creators.is_some() || !ctx.accounts.creator_config.data_is_empty(),
+        )
+    });
+    // the policy of the mint's collection takes precedence over the payment manager's royalty terms
+    let mut royalty_payment_manager = PaymentManager::clone(payment_manager);
+    if let Some(collection_policy) = mint_collection_policy(&ctx.accounts.collection_policy, mint_metadata.as_ref().and_then(|mint_metadata| mint_metadata.collection.as_ref()))? {
+        apply_collection_policy(&mut royalty_payment_manager, &collection_policy);
+    }
+    // royalties are waived below the royalty min price
+    let mint_metadata = mint_metadata.filter(|_| primary_sale || !royalties_waived(&royalty_payment_manager, payment_amount));
+
+    // creator token accounts
+    let remaining_accs = &mut ctx.remaining_accounts.iter();
+    if let Some(mint_metadata) = mint_metadata {
+        if !primary_sale {
+            seller_fee_amount(&royalty_payment_manager, payment_amount, mint_metadata.seller_fee_basis_points)?;
+        }
+        if let Some(creators) = royalty_creators(&ctx.accounts.creator_config, mint_metadata.creators)? {
+            for creator in creators.iter().filter(|creator| creator.share != 0) {
+                if payment_manager.claim_royalties {
+                    let (royalty_claim_id, _) = find_royalty_claim_address(&payment_manager.key(), &ctx.accounts.payment_mint.key(), &creator.address);
+                    if next_account_info(remaining_accs)?.key() != royalty_claim_id {
+                        return Err(error!(ErrorCode::InvalidRoyaltyClaim));
+                    }
+                    continue;
+                }
+                let creator_token_account_info = next_account_info(remaining_accs)?;
+                if payment_manager.escrow_unreceivable_royalties {
+                    let creator_token_account = remaining_token_account(creator_token_account_info).ok();
+                    creator_token_account_receivable(creator_token_account.as_deref(), &creator.address, &ctx.accounts.payment_mint.key())?;
+                    let (royalty_claim_id, _) = find_royalty_claim_address(&payment_manager.key(), &ctx.accounts.payment_mint.key(), &creator.address);
+                    if next_account_info(remaining_accs)?.key() != royalty_claim_id {
+                        return Err(error!(ErrorCode::InvalidRoyaltyClaim));
+                    }
+                    continue;
+                }
+                let creator_token_account = remaining_token_account(creator_token_account_info)?;
+                creator_token_account_payable(payment_manager, &creator_token_account, &creator.address, &ctx.accounts.payment_mint.key())?;
+            }
+        } else if payment_manager.fallback_royalty_to_update_authority {
+            // the update authority's token account takes the place of the creator token accounts
+            let update_authority_token_account = remaining_token_account(next_account_info(remaining_accs)?)?;
+            assert_token_account(&update_authority_token_account, &mint_metadata.update_authority, &ctx.accounts.payment_mint.key())?;
+        }
+    }
+
+    // taker fee collector token account
+    if let Some(taker_fee_collector) = payment_manager.taker_fee_collector {
+        let taker_fee_collector_token_account = Account::<TokenAccount>::try_from(next_account_info(remaining_accs)?)?;
+        if taker_fee_collector_token_account.owner != taker_fee_collector || taker_fee_collector_token_account.mint != ctx.accounts.payment_mint.key() {
+            return Err(error!(ErrorCode::InvalidTakerFeeCollectorTokenAccount));
+        }
+    }
+
+    // buy side token account
+    if let Some(buy_side_token_account_info) = remaining_accs.next() {
+        match Account::<TokenAccount>::try_from(buy_side_token_account_info) {
+            Ok(buy_side_token_account) if buy_side_token_account.mint != ctx.accounts.payment_mint.key() => return Err(error!(ErrorCode::InvalidBuySideMint)),
+            Err(_) if payment_manager.strict_remaining_accounts => return Err(error!(ErrorCode::InvalidBuySideAccount)),
+            _ => {}
+        }
+    }
+
+    if payment_manager.strict_remaining_accounts && remaining_accs.next().is_some() {
+        return Err(error!(ErrorCode::UnexpectedRemainingAccounts));
+    }
+
+    Ok(())
+}
@@ -1,8 +1,12 @@
+#[cfg(not(target_os = "solana"))]
+pub mod client;
 pub mod errors;
 pub mod instructions;
+pub mod metadata;
 pub mod state;
+pub mod util;
 
-use {anchor_lang::prelude::*, instructions::*};
+use {anchor_lang::prelude::*, instructions::*, state::FeeTier};
 
 declare_id!("pmvYY6Wgvpe3DEj3UX1FcRpMx43sMLYLJrFTVGcqpdn");
 
@@ -18,14 +22,50 @@ pub mod solana_nft_programs_payment_manager {
         handle_payment::handler(ctx, payment_amount)
     }
 
-    pub fn handle_payment_with_royalties<'info>(ctx: Context<'_, '_, '_, 'info, HandlePaymentWithRoyaltiesCtx<'info>>, payment_amount: u64) -> Result<()> {
-        handle_payment_with_royalties::handler(ctx, payment_amount)
+    pub fn handle_payment_with_quote(ctx: Context<HandlePaymentWithQuoteCtx>, ix: HandlePaymentWithQuoteIx) -> Result<()> {
+        handle_payment_with_quote::handler(ctx, ix)
+    }
+
+    pub fn handle_payment_with_royalties<'info>(ctx: Context<'_, '_, '_, 'info, HandlePaymentWithRoyaltiesCtx<'info>>, ix: HandlePaymentWithRoyaltiesIx) -> Result<()> {
+        handle_payment_with_royalties::handler(ctx, ix)
+    }
+
+    pub fn handle_payment_with_royalties_checked<'info>(ctx: Context<'_, '_, '_, 'info, HandlePaymentWithRoyaltiesCtx<'info>>, ix: HandlePaymentWithRoyaltiesIx) -> Result<()> {
+        handle_payment_with_royalties_checked::handler(ctx, ix)
     }
 
     pub fn handle_native_payment_with_royalties<'info>(ctx: Context<'_, '_, '_, 'info, HandleNativePaymentWithRoyaltiesCtx<'info>>, payment_amount: u64) -> Result<()> {
         handle_native_payment_with_royalties::handler(ctx, payment_amount)
     }
 
+    pub fn handle_payment_with_converted_royalties<'info>(ctx: Context<'_, '_, '_, 'info, HandlePaymentWithConvertedRoyaltiesCtx<'info>>, ix: HandlePaymentWithConvertedRoyaltiesIx) -> Result<()> {
+        handle_payment_with_converted_royalties::handler(ctx, ix)
+    }
+
+    pub fn set_creator_config(ctx: Context<SetCreatorConfigCtx>, ix: SetCreatorConfigIx) -> Result<()> {
+        set_creator_config::handler(ctx, ix)
+    }
+
+    pub fn init_protocol_config(ctx: Context<InitProtocolConfigCtx>, ix: InitProtocolConfigIx) -> Result<()> {
+        init_protocol_config::handler(ctx, ix)
+    }
+
+    pub fn set_protocol_config(ctx: Context<SetProtocolConfigCtx>, ix: SetProtocolConfigIx) -> Result<()> {
+        set_protocol_config::handler(ctx, ix)
+    }
+
+    pub fn validate_payment_accounts<'info>(ctx: Context<'_, '_, '_, 'info, ValidatePaymentAccountsCtx<'info>>, payment_amount: u64, expected_creator_hash: Option<[u8; 32]>) -> Result<()> {
+        validate_payment_accounts::handler(ctx, payment_amount, expected_creator_hash)
+    }
+
+    pub fn claim_proceeds(ctx: Context<ClaimProceedsCtx>) -> Result<()> {
+        claim_proceeds::handler(ctx)
+    }
+
+    pub fn claim_royalty(ctx: Context<ClaimRoyaltyCtx>) -> Result<()> {
+        claim_royalty::handler(ctx)
+    }
+
     pub fn close(ctx: Context<CloseCtx>) -> Result<()> {
         close::handler(ctx)
     }
@@ -33,4 +73,44 @@ pub mod solana_nft_programs_payment_manager {
     pub fn update(ctx: Context<UpdateCtx>, ix: UpdateIx) -> Result<()> {
         update::handler(ctx, ix)
     }
+
+    pub fn queue_fee_schedule(ctx: Context<QueueFeeScheduleCtx>, ix: QueueFeeScheduleIx) -> Result<()> {
+        queue_fee_schedule::handler(ctx, ix)
+    }
+
+    pub fn set_include_seller_fee(ctx: Context<SetIncludeSellerFeeCtx>, include_seller_fee_basis_points: bool) -> Result<()> {
+        set_include_seller_fee::handler(ctx, include_seller_fee_basis_points)
+    }
+
+    pub fn set_fee_collector(ctx: Context<SetFeeCollectorCtx>) -> Result<()> {
+        set_fee_collector::handler(ctx)
+    }
+
+    pub fn set_exempt_payer(ctx: Context<SetExemptPayerCtx>, ix: SetExemptPayerIx) -> Result<()> {
+        set_exempt_payer::handler(ctx, ix)
+    }
+
+    pub fn set_accepted_mints(ctx: Context<SetAcceptedMintsCtx>, accepted_mints: Vec<Pubkey>) -> Result<()> {
+        set_accepted_mints::handler(ctx, accepted_mints)
+    }
+
+    pub fn set_fee_tiers(ctx: Context<SetFeeTiersCtx>, fee_tiers: Vec<FeeTier>) -> Result<()> {
+        set_fee_tiers::handler(ctx, fee_tiers)
+    }
+
+    pub fn migrate_escrow<'info>(ctx: Context<'_, '_, '_, 'info, MigrateEscrowCtx<'info>>, ix: MigrateEscrowIx) -> Result<()> {
+        migrate_escrow::handler(ctx, ix)
+    }
+
+    pub fn set_price_oracle(ctx: Context<SetPriceOracleCtx>, ix: SetPriceOracleIx) -> Result<()> {
+        set_price_oracle::handler(ctx, ix)
+    }
+
+    pub fn set_collection_policy(ctx: Context<SetCollectionPolicyCtx>, ix: SetCollectionPolicyIx) -> Result<()> {
+        set_collection_policy::handler(ctx, ix)
+    }
+
+    pub fn resize_payment_manager(ctx: Context<ResizePaymentManagerCtx>) -> Result<()> {
+        resize_payment_manager::handler(ctx)
+    }
 }
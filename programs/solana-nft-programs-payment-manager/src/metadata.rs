@@ -0,0 +1,346 @@
+use mpl_token_metadata::accounts::Metadata;
+use mpl_utils::assert_derivation;
+
+use {
+    crate::{errors::ErrorCode, state::*},
+    anchor_lang::prelude::*,
+    mpl_token_metadata::types::{Collection, Creator},
+};
+
+/// First bytes of a bridged metadata account, the rest is a borsh serialized `BridgedMetadata`
+pub const BRIDGED_METADATA_DISCRIMINATOR: [u8; 8] = *b"bridgemd";
+
+/// Royalty terms of a mint whichever metadata account they were read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoyaltyMetadata {
+    pub update_authority: Pubkey,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<Creator>>,
+    pub collection: Option<Collection>,
+    pub primary_sale_happened: bool,
+}
+
+/// Metadata a bridge program keeps for the NFTs it wraps, their royalty terms are not in a
+/// Metaplex metadata account
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BridgedMetadata {
+    pub mint: Pubkey,
+    pub update_authority: Pubkey,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<CreatorShare>>,
+    pub collection: Option<Pubkey>,
+    pub primary_sale_happened: bool,
+}
+
+/// Reads the royalty terms of a mint out of its metadata account, none for an empty account
+pub trait MetadataResolver {
+    fn resolve(&self, mint: &Pubkey, mint_metadata: &AccountInfo) -> Result<Option<RoyaltyMetadata>>;
+}
+
+/// Metaplex token metadata, the default. `program_id` is the Metaplex program or a fork of it
+/// deployed at another address
+pub struct MetaplexResolver {
+    pub program_id: Pubkey,
+}
+
+impl MetadataResolver for MetaplexResolver {
+    fn resolve(&self, mint: &Pubkey, mint_metadata: &AccountInfo) -> Result<Option<RoyaltyMetadata>> {
+        // assert metadata account derivation
+        assert_derivation(
+            &self.program_id,
+            mint_metadata,
+            &["metadata".to_string().as_bytes(), self.program_id.as_ref(), mint.as_ref()],
+            error!(ErrorCode::InvalidMintMetadataOwner),
+        )
+        .map_err(|_| error!(ErrorCode::InvalidMintMetadataOwner))?;
+        if mint_metadata.data_is_empty() {
+            return Ok(None);
+        }
+        if *mint_metadata.owner != self.program_id {
+            return Err(error!(ErrorCode::InvalidMintMetadataOwner));
+        }
+        let mint_metadata_data = mint_metadata.try_borrow_data().expect("Failed to borrow data");
+        let mint_metadata = Metadata::deserialize(&mut mint_metadata_data.as_ref()).map_err(|_| error!(ErrorCode::MetadataDeserializationFailed))?;
+        if mint_metadata.mint != *mint {
+            return Err(error!(ErrorCode::InvalidMintMetadata));
+        }
+        Ok(Some(RoyaltyMetadata {
+            update_authority: mint_metadata.update_authority,
+            seller_fee_basis_points: mint_metadata.seller_fee_basis_points,
+            creators: mint_metadata.creators,
+            collection: mint_metadata.collection,
+            primary_sale_happened: mint_metadata.primary_sale_happened,
+        }))
+    }
+}
+
+/// Bridged metadata owned by the bridge program configured on the payment manager
+pub struct BridgedResolver {
+    pub program_id: Pubkey,
+}
+
+impl MetadataResolver for BridgedResolver {
+    fn resolve(&self, mint: &Pubkey, mint_metadata: &AccountInfo) -> Result<Option<RoyaltyMetadata>> {
+        if mint_metadata.data_is_empty() {
+            return Ok(None);
+        }
+        if *mint_metadata.owner != self.program_id {
+            return Err(error!(ErrorCode::InvalidMintMetadataOwner));
+        }
+        let mint_metadata_data = mint_metadata.try_borrow_data().expect("Failed to borrow data");
+        if !mint_metadata_data.starts_with(&BRIDGED_METADATA_DISCRIMINATOR) {
+            return Err(error!(ErrorCode::MetadataDeserializationFailed));
+        }
+        let bridged_metadata = BridgedMetadata::deserialize(&mut &mint_metadata_data[BRIDGED_METADATA_DISCRIMINATOR.len()..]).map_err(|_| error!(ErrorCode::MetadataDeserializationFailed))?;
+        if bridged_metadata.mint != *mint {
+            return Err(error!(ErrorCode::InvalidMintMetadata));
+        }
+        Ok(Some(RoyaltyMetadata {
+            update_authority: bridged_metadata.update_authority,
+            seller_fee_basis_points: bridged_metadata.seller_fee_basis_points,
+            // the bridge program vouches for its creators
+            creators: bridged_metadata.creators.map(|creators| {
+                creators
+                    .iter()
+                    .map(|creator| Creator {
+                        address: creator.address,
+                        verified: true,
+                        share: creator.share,
+                    })
+                    .collect()
+            }),
+            collection: bridged_metadata.collection.map(|key| Collection { verified: true, key }),
+            primary_sale_happened: bridged_metadata.primary_sale_happened,
+        }))
+    }
+}
+
+/// The payment manager's metadata program, the Metaplex token metadata program unless a fork is
+/// configured
+pub fn metadata_program_id(payment_manager: &PaymentManager) -> Pubkey {
+    payment_manager.metadata_program_id.unwrap_or(mpl_token_metadata::ID)
+}
+
+/// Metadata accounts owned by the payment manager's bridged metadata program are read as bridged
+/// metadata, any other account as Metaplex metadata of the payment manager's metadata program
+pub fn metadata_resolver(payment_manager: &PaymentManager, mint_metadata: &AccountInfo) -> Box<dyn MetadataResolver> {
+    match payment_manager.bridged_metadata_program {
+        Some(program_id) if *mint_metadata.owner == program_id => Box::new(BridgedResolver { program_id }),
+        _ => Box::new(MetaplexResolver {
+            program_id: metadata_program_id(payment_manager),
+        }),
+    }
+}
+
+/// Royalty terms of `mint` read with the resolver for its metadata account
+pub fn resolve_mint_metadata(payment_manager: &PaymentManager, mint: &Pubkey, mint_metadata: &AccountInfo) -> Result<Option<RoyaltyMetadata>> {
+    metadata_resolver(payment_manager, mint_metadata).resolve(mint, mint_metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bridged_metadata(mint: Pubkey) -> BridgedMetadata {
+        BridgedMetadata {
+            mint,
+            update_authority: Pubkey::new_unique(),
+            seller_fee_basis_points: 500,
+            creators: Some(vec![
+                CreatorShare {
+                    address: Pubkey::new_unique(),
+                    share: 60,
+                },
+                CreatorShare {
+                    address: Pubkey::new_unique(),
+                    share: 40,
+                },
+            ]),
+            collection: Some(Pubkey::new_unique()),
+            primary_sale_happened: true,
+        }
+    }
+
+    fn bridged_metadata_data(bridged_metadata: &BridgedMetadata) -> Vec<u8> {
+        let mut data = BRIDGED_METADATA_DISCRIMINATOR.to_vec();
+        bridged_metadata.serialize(&mut data).unwrap();
+        data
+    }
+
+    fn metaplex_metadata_data(mint: Pubkey, seller_fee_basis_points: u16) -> Vec<u8> {
+        let metadata = Metadata {
+            key: mpl_token_metadata::types::Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint,
+            name: "name".to_string(),
+            symbol: "symbol".to_string(),
+            uri: "uri".to_string(),
+            seller_fee_basis_points,
+            creators: None,
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: None,
+            collection: None,
+            uses: None,
+            collection_details: None,
+            programmable_config: None,
+        };
+        let mut data = vec![];
+        metadata.serialize(&mut data).unwrap();
+        data
+    }
+
+    fn metadata_address(mint: &Pubkey, program_id: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"metadata", program_id.as_ref(), mint.as_ref()], program_id).0
+    }
+
+    fn payment_manager(bridged_metadata_program: Option<Pubkey>, metadata_program_id: Option<Pubkey>) -> PaymentManager {
+        let mut payment_manager = crate::util::tests::payment_manager(500, 300);
+        payment_manager.bridged_metadata_program = bridged_metadata_program;
+        payment_manager.metadata_program_id = metadata_program_id;
+        payment_manager
+    }
+
+    #[test]
+    fn test_bridged_resolver() {
+        let (key, mint, bridge_program) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let bridged_metadata = bridged_metadata(mint);
+        let mut data = bridged_metadata_data(&bridged_metadata);
+        let mut lamports = 0;
+        let mint_metadata = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &bridge_program, false, 0);
+
+        let royalty_metadata = BridgedResolver { program_id: bridge_program }.resolve(&mint, &mint_metadata).unwrap().unwrap();
+        assert_eq!(royalty_metadata.update_authority, bridged_metadata.update_authority);
+        assert_eq!(royalty_metadata.seller_fee_basis_points, 500);
+        assert!(royalty_metadata.primary_sale_happened);
+        let creators = royalty_metadata.creators.unwrap();
+        assert_eq!(creators.iter().map(|creator| creator.share).collect::<Vec<u8>>(), vec![60, 40]);
+        assert!(creators.iter().all(|creator| creator.verified));
+        assert_eq!(royalty_metadata.collection.map(|collection| collection.key), bridged_metadata.collection);
+
+        // another mint's metadata
+        assert_eq!(
+            BridgedResolver { program_id: bridge_program }.resolve(&Pubkey::new_unique(), &mint_metadata),
+            Err(error!(ErrorCode::InvalidMintMetadata))
+        );
+        // owned by another program
+        assert_eq!(
+            BridgedResolver { program_id: Pubkey::new_unique() }.resolve(&mint, &mint_metadata),
+            Err(error!(ErrorCode::InvalidMintMetadataOwner))
+        );
+    }
+
+    #[test]
+    fn test_bridged_resolver_discriminator() {
+        let (key, mint, bridge_program) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = bridged_metadata_data(&bridged_metadata(mint));
+        data[0] = 0;
+        let mut lamports = 0;
+        let mint_metadata = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &bridge_program, false, 0);
+        assert_eq!(
+            BridgedResolver { program_id: bridge_program }.resolve(&mint, &mint_metadata),
+            Err(error!(ErrorCode::MetadataDeserializationFailed))
+        );
+
+        // truncated after the discriminator
+        let mut data = BRIDGED_METADATA_DISCRIMINATOR.to_vec();
+        let mut lamports = 0;
+        let mint_metadata = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &bridge_program, false, 0);
+        assert_eq!(
+            BridgedResolver { program_id: bridge_program }.resolve(&mint, &mint_metadata),
+            Err(error!(ErrorCode::MetadataDeserializationFailed))
+        );
+
+        let mut data = vec![];
+        let mut lamports = 0;
+        let mint_metadata = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &bridge_program, false, 0);
+        assert_eq!(BridgedResolver { program_id: bridge_program }.resolve(&mint, &mint_metadata), Ok(None));
+    }
+
+    #[test]
+    fn test_resolve_mint_metadata() {
+        let (mint, bridge_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let bridged_metadata = bridged_metadata(mint);
+        let mut data = bridged_metadata_data(&bridged_metadata);
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mint_metadata = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &bridge_program, false, 0);
+
+        // bridged metadata is read once its program is configured
+        let royalty_metadata = resolve_mint_metadata(&payment_manager(Some(bridge_program), None), &mint, &mint_metadata).unwrap().unwrap();
+        assert_eq!(royalty_metadata.seller_fee_basis_points, bridged_metadata.seller_fee_basis_points);
+        // otherwise it is not at the Metaplex metadata address
+        assert!(resolve_mint_metadata(&payment_manager(None, None), &mint, &mint_metadata).is_err());
+
+        // an empty Metaplex metadata account has no royalty terms
+        let metaplex_key = metadata_address(&mint, &mpl_token_metadata::ID);
+        let mut data = vec![];
+        let mut lamports = 0;
+        let system_program = anchor_lang::system_program::ID;
+        let mint_metadata = AccountInfo::new(&metaplex_key, false, false, &mut lamports, &mut data, &system_program, false, 0);
+        assert_eq!(resolve_mint_metadata(&payment_manager(Some(bridge_program), None), &mint, &mint_metadata), Ok(None));
+    }
+
+    #[test]
+    fn test_metaplex_resolver_seller_fee_above_divisor() {
+        let mint = Pubkey::new_unique();
+        let key = metadata_address(&mint, &mpl_token_metadata::ID);
+        // malformed metadata Metaplex itself would never write
+        let mut data = metaplex_metadata_data(mint, 20000);
+        let mut lamports = 0;
+        let mint_metadata = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &mpl_token_metadata::ID, false, 0);
+
+        // the resolver hands the seller fee through as is
+        let royalty_metadata = MetaplexResolver { program_id: mpl_token_metadata::ID }.resolve(&mint, &mint_metadata).unwrap().unwrap();
+        assert_eq!(royalty_metadata.seller_fee_basis_points, 20000);
+
+        // and it is rejected before any fee is computed from it
+        let mut payment_manager = payment_manager(None, None);
+        payment_manager.include_seller_fee_basis_points = true;
+        payment_manager.clamp_royalties = true;
+        assert_eq!(
+            crate::util::payment_fees(&payment_manager, 10000, Some(royalty_metadata.seller_fee_basis_points)),
+            Err(error!(ErrorCode::InvalidSellerFee))
+        );
+    }
+
+    #[test]
+    fn test_resolve_mint_metadata_custom_metadata_program() {
+        let (mint, fork_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let key = metadata_address(&mint, &fork_program);
+        let mut data = metaplex_metadata_data(mint, 500);
+        let mut lamports = 0;
+        let mint_metadata = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &fork_program, false, 0);
+
+        // metadata of the fork is read once the fork is the payment manager's metadata program
+        let royalty_metadata = resolve_mint_metadata(&payment_manager(None, Some(fork_program)), &mint, &mint_metadata).unwrap().unwrap();
+        assert_eq!(royalty_metadata.seller_fee_basis_points, 500);
+        // it is not at the Metaplex metadata address
+        assert_eq!(
+            resolve_mint_metadata(&payment_manager(None, None), &mint, &mint_metadata),
+            Err(error!(ErrorCode::InvalidMintMetadataOwner))
+        );
+
+        // Metaplex metadata is not at the fork's metadata address
+        let metaplex_key = metadata_address(&mint, &mpl_token_metadata::ID);
+        let mut data = metaplex_metadata_data(mint, 500);
+        let mut lamports = 0;
+        let mint_metadata = AccountInfo::new(&metaplex_key, false, false, &mut lamports, &mut data, &mpl_token_metadata::ID, false, 0);
+        assert!(resolve_mint_metadata(&payment_manager(None, None), &mint, &mint_metadata).unwrap().is_some());
+        assert_eq!(
+            resolve_mint_metadata(&payment_manager(None, Some(fork_program)), &mint, &mint_metadata),
+            Err(error!(ErrorCode::InvalidMintMetadataOwner))
+        );
+
+        // metadata at the fork's address owned by another program
+        let key = metadata_address(&mint, &fork_program);
+        let mut data = metaplex_metadata_data(mint, 500);
+        let mut lamports = 0;
+        let mint_metadata = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &mpl_token_metadata::ID, false, 0);
+        assert_eq!(
+            MetaplexResolver { program_id: fork_program }.resolve(&mint, &mint_metadata),
+            Err(error!(ErrorCode::InvalidMintMetadataOwner))
+        );
+    }
+}
@@ -1,10 +1,57 @@
-use anchor_lang::prelude::*;
+use {crate::errors::ErrorCode, anchor_lang::prelude::*};
 
 pub const PAYMENT_MANAGER_SEED: &str = "payment-manager";
-pub const PAYMENT_MANAGER_SIZE: usize = 8 + std::mem::size_of::<PaymentManager>() + 16;
+pub const PAYMENT_MANAGER_SIZE: usize = 8 + std::mem::size_of::<PaymentManager>() + 16 + MAX_ADMINS * 32 + MAX_ACCEPTED_MINTS * 32 + MAX_FEE_TIERS * std::mem::size_of::<FeeTier>();
+pub const MAX_ADMINS: usize = 8;
+pub const MAX_ACCEPTED_MINTS: usize = 8;
+pub const MAX_FEE_TIERS: usize = 4;
 pub const BASIS_POINTS_DIVISOR: u16 = 10000;
+/// Metaplex creator shares are whole percentages summing to 100, unlike fees which are in basis
+/// points of `BASIS_POINTS_DIVISOR`
+pub const CREATOR_SHARE_DIVISOR: u8 = 100;
 pub const DEFAULT_ROYALTY_FEE_SHARE: u64 = 5000;
 pub const DEFAULT_BUY_SIDE_FEE_SHARE: u64 = 50;
+pub const CONVERSION_RATE_DIVISOR: u64 = 1_000_000_000;
+pub const DEFAULT_MAX_ROYALTY_BASIS_POINTS: u16 = 5000;
+
+pub const CREATOR_CONFIG_SEED: &str = "creator-config";
+pub const CREATOR_CONFIG_SIZE: usize = 8 + std::mem::size_of::<CreatorConfig>() + MAX_CREATORS * std::mem::size_of::<CreatorShare>();
+pub const MAX_CREATORS: usize = 5;
+/// Payments take a token account or royalty claim per creator, the taker fee collector token
+/// account and the buy side token account as remaining accounts, anything beyond that would only
+/// grow the transaction towards its account limit
+pub const MAX_PAYMENT_REMAINING_ACCOUNTS: usize = MAX_CREATORS + 2;
+/// Payments escrowing unreceivable royalties take both the token account and the royalty claim of
+/// each creator
+pub const MAX_ESCROWED_PAYMENT_REMAINING_ACCOUNTS: usize = 2 * MAX_CREATORS + 2;
+
+pub const PAYER_RATE_LIMIT_SEED: &str = "payer-rate-limit";
+pub const PAYER_RATE_LIMIT_SIZE: usize = 8 + std::mem::size_of::<PayerRateLimit>();
+
+pub const PAYER_STATS_SEED: &str = "payer-stats";
+pub const PAYER_STATS_SIZE: usize = 8 + std::mem::size_of::<PayerStats>();
+
+pub const PROTOCOL_CONFIG_SEED: &str = "protocol-config";
+pub const PROTOCOL_CONFIG_SIZE: usize = 8 + std::mem::size_of::<ProtocolConfig>();
+
+pub const PROCEEDS_ESCROW_SEED: &str = "proceeds-escrow";
+
+pub const PAYMENT_RECORD_SEED: &str = "payment-record";
+pub const PAYMENT_RECORD_SIZE: usize = 8 + std::mem::size_of::<PaymentRecord>();
+
+pub const EXEMPT_PAYER_SEED: &str = "exempt-payer";
+pub const EXEMPT_PAYER_SIZE: usize = 8 + std::mem::size_of::<ExemptPayer>();
+
+pub const ROYALTY_ESCROW_SEED: &str = "royalty-escrow";
+
+pub const ROYALTY_CLAIM_SEED: &str = "royalty-claim";
+pub const ROYALTY_CLAIM_SIZE: usize = 8 + std::mem::size_of::<RoyaltyClaim>();
+
+pub const PRICE_ORACLE_SEED: &str = "price-oracle";
+pub const PRICE_ORACLE_SIZE: usize = 8 + std::mem::size_of::<PriceOracle>();
+
+pub const COLLECTION_POLICY_SEED: &str = "collection-policy";
+pub const COLLECTION_POLICY_SIZE: usize = 8 + std::mem::size_of::<CollectionPolicy>();
 
 #[account]
 pub struct PaymentManager {
@@ -16,4 +63,192 @@ pub struct PaymentManager {
     pub name: String,
     pub include_seller_fee_basis_points: bool,
     pub royalty_fee_share: Option<u64>,
+    pub flat_fee_amount: Option<u64>,
+    pub royalty_conversion_rate_bounds: Option<ConversionRateBounds>,
+    pub strict_remaining_accounts: bool,
+    pub authority_threshold: Option<u8>,
+    pub admins: Vec<Pubkey>,
+    pub max_royalty_basis_points: Option<u16>,
+    pub clamp_royalties: bool,
+    pub payout_order: u8,
+    pub royalty_min_price: Option<u64>,
+    pub quote_conversion_rate_bounds: Option<ConversionRateBounds>,
+    pub handle_primary_sales: bool,
+    pub min_payment_interval_slots: Option<u64>,
+    pub max_creators_fee: Option<u64>,
+    pub royalty_floor_basis_points: Option<u16>,
+    pub pending_fee_schedule: Option<FeeSchedule>,
+    pub taker_fee_collector: Option<Pubkey>,
+    pub required_collection: Option<Pubkey>,
+    pub seller_fee_within_fees: bool,
+    pub fee_mode: u8,
+    pub record_payments: bool,
+    pub primary_sale_target: Option<Pubkey>,
+    pub fallback_royalty_to_update_authority: bool,
+    pub taker_covers_maker_fee: bool,
+    pub claim_royalties: bool,
+    pub bridged_metadata_program: Option<Pubkey>,
+    pub accepted_mints: Vec<Pubkey>,
+    pub remainder_to_collector: bool,
+    pub skip_mismatched_creator_mints: bool,
+    pub fee_tiers: Vec<FeeTier>,
+    pub buy_side_additive: bool,
+    pub metadata_program_id: Option<Pubkey>,
+    pub escrow_unreceivable_royalties: bool,
+    pub rebate_basis_points: Option<u16>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PayoutOrder {
+    CreatorsFirst = 0,
+    PrincipalFirst = 1,
+}
+
+impl TryFrom<u8> for PayoutOrder {
+    type Error = Error;
+
+    fn try_from(payout_order: u8) -> Result<Self> {
+        match payout_order {
+            0 => Ok(PayoutOrder::CreatorsFirst),
+            1 => Ok(PayoutOrder::PrincipalFirst),
+            _ => Err(error!(ErrorCode::InvalidPayoutOrder)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FeeMode {
+    BasisPoints = 0,
+    Flat = 1,
+    // the flat fee amount charged on top of the maker and taker fee basis points
+    FlatPlusBasisPoints = 2,
+}
+
+impl TryFrom<u8> for FeeMode {
+    type Error = Error;
+
+    fn try_from(fee_mode: u8) -> Result<Self> {
+        match fee_mode {
+            0 => Ok(FeeMode::BasisPoints),
+            1 => Ok(FeeMode::Flat),
+            2 => Ok(FeeMode::FlatPlusBasisPoints),
+            _ => Err(error!(ErrorCode::InvalidFeeMode)),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConversionRateBounds {
+    pub min_conversion_rate: u64,
+    pub max_conversion_rate: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeSchedule {
+    pub maker_fee_basis_points: u16,
+    pub taker_fee_basis_points: u16,
+    pub activation_time: i64,
+}
+
+/// Maker and taker fees of payers whose cumulative volume reached `min_volume`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeTier {
+    pub min_volume: u64,
+    pub maker_fee_basis_points: u16,
+    pub taker_fee_basis_points: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CreatorShare {
+    pub address: Pubkey,
+    pub share: u8,
+}
+
+#[account]
+pub struct CreatorConfig {
+    pub bump: u8,
+    pub payment_manager: Pubkey,
+    pub mint: Pubkey,
+    pub creators: Vec<CreatorShare>,
+}
+
+#[account]
+pub struct ProtocolConfig {
+    pub bump: u8,
+    pub authority: Pubkey,
+    pub protocol_fee_basis_points: u16,
+    pub treasury: Pubkey,
+}
+
+#[account]
+pub struct PayerRateLimit {
+    pub bump: u8,
+    pub payment_manager: Pubkey,
+    pub payer: Pubkey,
+    pub last_payment_slot: u64,
+}
+
+#[account]
+pub struct PayerStats {
+    pub bump: u8,
+    pub payment_manager: Pubkey,
+    pub payer: Pubkey,
+    pub volume: u64,
+}
+
+#[account]
+pub struct PaymentRecord {
+    pub bump: u8,
+    pub payment_manager: Pubkey,
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    // record_nonce the payment was made with, the payer's records of a mint are told apart by it
+    pub nonce: u64,
+    pub payment_amount: u64,
+    pub total_fees: u64,
+    pub timestamp: i64,
+}
+
+#[account]
+pub struct ExemptPayer {
+    pub bump: u8,
+    pub payment_manager: Pubkey,
+    pub payer: Pubkey,
+    pub exempt: bool,
+}
+
+#[account]
+pub struct RoyaltyClaim {
+    pub bump: u8,
+    pub payment_manager: Pubkey,
+    pub payment_mint: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+}
+
+/// Price of a payment mint set by the payment manager, in stable base units per payment mint base
+/// unit scaled by `CONVERSION_RATE_DIVISOR`. It only values fees for analytics and is never charged
+#[account]
+pub struct PriceOracle {
+    pub bump: u8,
+    pub payment_manager: Pubkey,
+    pub payment_mint: Pubkey,
+    pub price: u64,
+    pub updated_at: i64,
+}
+
+/// Royalty terms the authority of a collection enforces on the payments for its mints, whichever
+/// payment manager they go through
+#[account]
+pub struct CollectionPolicy {
+    pub bump: u8,
+    pub collection: Pubkey,
+    /// royalties are charged at least at `enforced_basis_points`, even by payment managers that
+    /// do not include the seller fee
+    pub enforce_royalties: bool,
+    pub enforced_basis_points: u16,
+    /// whether the royalty min price of a payment manager can waive the royalties
+    pub allow_waivers: bool,
 }
@@ -0,0 +1,2558 @@
+use {
+    crate::{errors::ErrorCode, state::*},
+    anchor_lang::prelude::*,
+    anchor_spl::token::{self, Mint, TokenAccount, TransferChecked},
+    mpl_token_metadata::types::{Collection, Creator},
+};
+
+/// Computed in u128 so any amount can be charged, the fee never exceeds the amount for basis points
+/// up to `BASIS_POINTS_DIVISOR`
+pub fn basis_points_fee(amount: u64, basis_points: u16) -> u64 {
+    u64::try_from(
+        u128::from(amount)
+            .checked_mul(basis_points.into())
+            .expect("Multiplication error")
+            .checked_div(BASIS_POINTS_DIVISOR.into())
+            .expect("Division error"),
+    )
+    .expect("Could not cast u128 to u64")
+}
+
+/// Returns the (maker_fee, taker_fee) for a payment.
+/// A payment fills a trade between a maker, the seller whose listing is filled and who is paid
+/// through the payment target, and a taker, the payer who fills it. The payment manager never sees
+/// two parties: both fees are computed off the same `payment_amount` and both are debited from the
+/// payer's token account in one instruction. What tells them apart is who bears them:
+/// - the taker fee is charged to the payer on top of the payment amount
+/// - the maker fee is taken out of the payment amount, so the maker nets that much less principal,
+///   unless `taker_covers_maker_fee` has the payer pay it on top as well
+///
+/// Both fees count towards `total_fees`, the pool the creators fee, the taker fee collector and the
+/// fee collector are paid out of, so moving a fee between maker and taker changes who bears it but
+/// never how much is collected.
+///
+/// When the payment manager has a `flat_fee_amount` the whole fee is charged to the taker as a flat
+/// amount so that non-priced trades (e.g. NFT-for-NFT swaps with `payment_amount = 0`) can still be
+/// charged.
+/// In `FeeMode::Flat` the maker and taker fee basis points are absolute amounts instead, the maker
+/// fee is taken out of the payment so it is never more than the payment. `payment_fees` rejects a
+/// payment it leaves too little for the other fees carved out of it.
+/// In `FeeMode::FlatPlusBasisPoints` the `flat_fee_amount` is added to the taker fee, the flat part
+/// is paid on top of the payment so however large it is it never eats into the principal.
+/// Each fee is rounded down on its own, so the total can be one unit less than the combined basis
+/// points of the payment. This is intended: both fees stay what they would be if charged alone
+pub fn maker_taker_fees(payment_manager: &PaymentManager, payment_amount: u64) -> (u64, u64) {
+    if is_flat_plus_basis_points_fee_mode(payment_manager) {
+        return (
+            basis_points_fee(payment_amount, payment_manager.maker_fee_basis_points),
+            // saturated, a taker fee that large cannot be paid on top of any payment anyway
+            basis_points_fee(payment_amount, payment_manager.taker_fee_basis_points).saturating_add(payment_manager.flat_fee_amount.unwrap_or(0)),
+        );
+    }
+    if let Some(flat_fee_amount) = payment_manager.flat_fee_amount {
+        return (0, flat_fee_amount);
+    }
+    if is_flat_fee_mode(payment_manager) {
+        return (u64::from(payment_manager.maker_fee_basis_points).min(payment_amount), payment_manager.taker_fee_basis_points.into());
+    }
+    (
+        basis_points_fee(payment_amount, payment_manager.maker_fee_basis_points),
+        basis_points_fee(payment_amount, payment_manager.taker_fee_basis_points),
+    )
+}
+
+/// Fees the payer pays on top of the payment amount, the taker fee and, when the taker covers the
+/// maker fee, the maker fee as well so the maker fee is not taken out of the maker's proceeds
+pub fn payer_fees(payment_manager: &PaymentManager, maker_fee: u64, taker_fee: u64) -> u64 {
+    if payment_manager.taker_covers_maker_fee {
+        return maker_fee.checked_add(taker_fee).expect("Add error");
+    }
+    taker_fee
+}
+
+/// Buy side fee the payer pays on top of the payment amount. With `buy_side_additive` the buy side
+/// fee is an extra charge to the payer, otherwise it is carved out of the principal
+pub fn payer_buy_side_fee(payment_manager: &PaymentManager, buy_side_fee: u64) -> u64 {
+    if payment_manager.buy_side_additive {
+        return buy_side_fee;
+    }
+    0
+}
+
+/// Whether the maker and taker fee basis points of the payment manager are absolute amounts
+pub fn is_flat_fee_mode(payment_manager: &PaymentManager) -> bool {
+    payment_manager.fee_mode == FeeMode::Flat as u8
+}
+
+/// Whether the `flat_fee_amount` is charged on top of the maker and taker fee basis points
+pub fn is_flat_plus_basis_points_fee_mode(payment_manager: &PaymentManager) -> bool {
+    payment_manager.fee_mode == FeeMode::FlatPlusBasisPoints as u8
+}
+
+/// Whether the seller fee is charged on top of the maker-taker fees, which is also how payment
+/// managers created before `seller_fee_within_fees` existed are accounted for
+pub fn is_seller_fee_additive(payment_manager: &PaymentManager) -> bool {
+    !payment_manager.seller_fee_within_fees
+}
+
+/// Whether the `flat_fee_amount` replaces the maker and taker fees
+pub fn is_flat_fee_only(payment_manager: &PaymentManager) -> bool {
+    payment_manager.flat_fee_amount.is_some() && !is_flat_plus_basis_points_fee_mode(payment_manager)
+}
+
+/// Logs the compute units left at a phase of a handler, only built in with the `cu-logging`
+/// feature so profiling does not cost anything in production
+#[cfg(feature = "cu-logging")]
+pub fn log_compute_units(phase: &str) {
+    msg!("Compute units at {}", phase);
+    solana_program::log::sol_log_compute_units();
+}
+
+#[cfg(not(feature = "cu-logging"))]
+pub fn log_compute_units(_phase: &str) {}
+
+/// Moves a queued fee schedule into the maker and taker fees once its activation time is reached,
+/// returns whether the schedule was activated. The first payment after the activation time
+/// persists it, which is why the payment handlers write lock the payment manager
+pub fn activate_fee_schedule(payment_manager: &mut PaymentManager, unix_timestamp: i64) -> bool {
+    match payment_manager.pending_fee_schedule {
+        Some(fee_schedule) if unix_timestamp >= fee_schedule.activation_time => {
+            payment_manager.maker_fee_basis_points = fee_schedule.maker_fee_basis_points;
+            payment_manager.taker_fee_basis_points = fee_schedule.taker_fee_basis_points;
+            payment_manager.pending_fee_schedule = None;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The highest fee tier a payer's cumulative volume has reached, the tiers are sorted by volume
+pub fn payer_fee_tier(fee_tiers: &[FeeTier], volume: u64) -> Option<&FeeTier> {
+    fee_tiers.iter().rev().find(|fee_tier| volume >= fee_tier.min_volume)
+}
+
+/// The payment manager as a payer with `volume` sees it, with the maker and taker fees of the
+/// payer's fee tier. It is only used to compute fees and never written back
+pub fn tiered_payment_manager(payment_manager: &PaymentManager, volume: u64) -> PaymentManager {
+    let mut tiered_payment_manager = PaymentManager::clone(payment_manager);
+    if let Some(fee_tier) = payer_fee_tier(&payment_manager.fee_tiers, volume) {
+        tiered_payment_manager.maker_fee_basis_points = fee_tier.maker_fee_basis_points;
+        tiered_payment_manager.taker_fee_basis_points = fee_tier.taker_fee_basis_points;
+    }
+    tiered_payment_manager
+}
+
+/// Fee tiers are sorted by strictly increasing volume and each has to be a valid fee config for
+/// the payment manager
+pub fn validate_fee_tiers(payment_manager: &PaymentManager, fee_tiers: &[FeeTier]) -> Result<()> {
+    if fee_tiers.len() > MAX_FEE_TIERS || fee_tiers.windows(2).any(|fee_tiers| fee_tiers[0].min_volume >= fee_tiers[1].min_volume) {
+        return Err(error!(ErrorCode::InvalidFeeTiers));
+    }
+    for fee_tier in fee_tiers {
+        let mut tiered_payment_manager = PaymentManager::clone(payment_manager);
+        tiered_payment_manager.maker_fee_basis_points = fee_tier.maker_fee_basis_points;
+        tiered_payment_manager.taker_fee_basis_points = fee_tier.taker_fee_basis_points;
+        validate_fee_config(&tiered_payment_manager)?;
+    }
+    Ok(())
+}
+
+/// The fees taken out of a payment can never add up to more than the payment, even for a mint
+/// charging the highest seller fee the payment manager accepts
+pub fn validate_fee_config(payment_manager: &PaymentManager) -> Result<()> {
+    let maker_fee_basis_points = if is_flat_fee_only(payment_manager) || is_flat_fee_mode(payment_manager) || payment_manager.taker_covers_maker_fee {
+        0
+    } else {
+        payment_manager.maker_fee_basis_points
+    };
+    let max_royalty_basis_points = payment_manager.max_royalty_basis_points.unwrap_or(DEFAULT_MAX_ROYALTY_BASIS_POINTS);
+    let max_seller_fee_basis_points = if payment_manager.include_seller_fee_basis_points && is_seller_fee_additive(payment_manager) {
+        max_royalty_basis_points.min(BASIS_POINTS_DIVISOR)
+    } else {
+        0
+    };
+    let fee_basis_points = u64::from(maker_fee_basis_points) + u64::from(max_seller_fee_basis_points) + DEFAULT_BUY_SIDE_FEE_SHARE;
+    if fee_basis_points > BASIS_POINTS_DIVISOR.into()
+        || payment_manager.royalty_fee_share.unwrap_or(DEFAULT_ROYALTY_FEE_SHARE) > BASIS_POINTS_DIVISOR.into()
+        || payment_manager.rebate_basis_points.unwrap_or(0) > BASIS_POINTS_DIVISOR
+        // a royalty floor above the limit could never be honored
+        || payment_manager.royalty_floor_basis_points.unwrap_or(0) > max_royalty_basis_points
+    {
+        return Err(error!(ErrorCode::FeeConfigExceedsLimit));
+    }
+    Ok(())
+}
+
+/// The buy side's cut of a payment, the share is clamped to `BASIS_POINTS_DIVISOR` so a
+/// misconfigured share can never take more than the payment
+pub fn buy_side_fee_amount(payment_amount: u64, buy_side_fee_share: u64) -> u64 {
+    u64::try_from(
+        u128::from(payment_amount)
+            .checked_mul(buy_side_fee_share.min(BASIS_POINTS_DIVISOR.into()).into())
+            .expect("Multiplication error")
+            .checked_div(BASIS_POINTS_DIVISOR.into())
+            .expect("Division error"),
+    )
+    .expect("Could not cast u128 to u64")
+}
+
+/// The protocol's cut of a payment, taken out of the fee collector's share so it never exceeds it
+pub fn protocol_fee_amount(protocol_config: &ProtocolConfig, payment_amount: u64, fee_collector_fee: u64) -> u64 {
+    basis_points_fee(payment_amount, protocol_config.protocol_fee_basis_points).min(fee_collector_fee)
+}
+
+/// Part of the fees rebated to the payer, `rebate_basis_points` of the total fees. It is withheld
+/// from the fee collector fee, what is left of the fees once everyone else is paid, so a rebate never
+/// exceeds the fees actually collected
+pub fn rebate_amount(payment_manager: &PaymentManager, total_fees: u64, fee_collector_fee: u64) -> u64 {
+    basis_points_fee(total_fees, payment_manager.rebate_basis_points.unwrap_or(0)).min(fee_collector_fee)
+}
+
+/// A payer that has paid before must wait `min_payment_interval_slots` before paying again,
+/// a `last_payment_slot` of 0 means the payer has not paid yet
+pub fn within_payment_interval(last_payment_slot: u64, slot: u64, min_payment_interval_slots: u64) -> bool {
+    last_payment_slot != 0 && slot < last_payment_slot.saturating_add(min_payment_interval_slots)
+}
+
+/// Royalties are waived for sales below the payment manager's `royalty_min_price`
+pub fn royalties_waived(payment_manager: &PaymentManager, payment_amount: u64) -> bool {
+    payment_manager.royalty_min_price.map_or(false, |royalty_min_price| payment_amount < royalty_min_price)
+}
+
+/// Seller fee basis points from the mint metadata raised to the `royalty_floor_basis_points`, never
+/// more than `max_royalty_basis_points` so a degenerate seller_fee_basis_points cannot take the
+/// whole payment. Above the limit the seller fee is clamped when `clamp_royalties` is set and
+/// rejected otherwise, malformed metadata above `BASIS_POINTS_DIVISOR` is always rejected
+pub fn seller_fee_basis_points(payment_manager: &PaymentManager, seller_fee_basis_points: u16) -> Result<u16> {
+    if !payment_manager.include_seller_fee_basis_points {
+        return Ok(0);
+    }
+    if seller_fee_basis_points > BASIS_POINTS_DIVISOR {
+        return Err(error!(ErrorCode::InvalidSellerFee));
+    }
+    let seller_fee_basis_points = seller_fee_basis_points.max(payment_manager.royalty_floor_basis_points.unwrap_or(0));
+    let max_royalty_basis_points = payment_manager.max_royalty_basis_points.unwrap_or(DEFAULT_MAX_ROYALTY_BASIS_POINTS);
+    if seller_fee_basis_points > max_royalty_basis_points {
+        if !payment_manager.clamp_royalties {
+            return Err(error!(ErrorCode::RoyaltiesExceedLimit));
+        }
+        return Ok(max_royalty_basis_points);
+    }
+    Ok(seller_fee_basis_points)
+}
+
+/// The creators fee depends on how the seller fee is accounted for:
+/// - by default the seller fee is charged out of the principal on top of the maker-taker fees
+///   and the creators get their royalty share of the maker-taker fees plus the seller fee
+/// - with `seller_fee_within_fees` the seller fee is considered part of the royalty share, nothing is charged on top
+///   of the maker-taker fees and the creators get the larger of their royalty share and the seller
+///   fee, never more than the maker-taker fees
+///
+/// Either way the creators fee is capped by the payment manager's `max_creators_fee`, the excess
+/// stays with the fee collector
+pub fn creators_fee_amount(payment_manager: &PaymentManager, maker_taker_fees: u64, seller_fee: u64) -> u64 {
+    let royalty_fee = u128::from(maker_taker_fees)
+        .checked_mul(payment_manager.royalty_fee_share.unwrap_or(DEFAULT_ROYALTY_FEE_SHARE).into())
+        .expect("Multiplication error")
+        .checked_div(BASIS_POINTS_DIVISOR.into())
+        .expect("Division error");
+    let royalty_fee = u64::try_from(royalty_fee).expect("Could not cast u128 to u64");
+    let creators_fee = if is_seller_fee_additive(payment_manager) {
+        royalty_fee.checked_add(seller_fee).expect("Add error")
+    } else {
+        royalty_fee.max(seller_fee).min(maker_taker_fees)
+    };
+    payment_manager.max_creators_fee.map_or(creators_fee, |max_creators_fee| creators_fee.min(max_creators_fee))
+}
+
+pub fn seller_fee_amount(payment_manager: &PaymentManager, payment_amount: u64, metadata_seller_fee_basis_points: u16) -> Result<u64> {
+    Ok(basis_points_fee(payment_amount, seller_fee_basis_points(payment_manager, metadata_seller_fee_basis_points)?))
+}
+
+/// Fees of a payment before anything is paid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaymentFees {
+    /// part of `total_fees` taken out of the payment amount, unless the taker covers it
+    pub maker_fee: u64,
+    /// part of `total_fees` the payer pays on top of the payment amount
+    pub taker_fee: u64,
+    /// taker fee routed to the taker fee collector instead of being shared with the creators
+    pub taker_fee_collector_fee: u64,
+    /// maker fee and taker fee, and the seller fee when it is additive
+    pub total_fees: u64,
+    pub total_creators_fee: u64,
+    pub buy_side_fee: u64,
+    pub principal_amount: u64,
+}
+
+/// Splits a payment into its fees and the principal, `metadata_seller_fee_basis_points` is `None`
+/// when no royalties are paid. The payer is debited `payment_amount` plus the `payer_fees`, which
+/// the principal, the total fees and the buy side fee add up to
+pub fn payment_fees(payment_manager: &PaymentManager, payment_amount: u64, metadata_seller_fee_basis_points: Option<u16>) -> Result<PaymentFees> {
+    // maker-taker fees
+    let (maker_fee, taker_fee) = maker_taker_fees(payment_manager, payment_amount);
+    let mut total_fees = maker_fee.checked_add(taker_fee).ok_or(error!(ErrorCode::ArithmeticOverflow))?;
+
+    // taker fees routed to the taker fee collector are not shared with the creators
+    let taker_fee_collector_fee = if payment_manager.taker_fee_collector.is_some() { taker_fee } else { 0 };
+
+    // royalty fees
+    let mut total_creators_fee: u64 = 0;
+    if let Some(metadata_seller_fee_basis_points) = metadata_seller_fee_basis_points {
+        let seller_fee = seller_fee_amount(payment_manager, payment_amount, metadata_seller_fee_basis_points)?;
+        let shared_fees = total_fees.checked_sub(taker_fee_collector_fee).ok_or(error!(ErrorCode::ArithmeticOverflow))?;
+        // added up first, the creators fee is never more than the fees it is taken from
+        if is_seller_fee_additive(payment_manager) {
+            total_fees = total_fees.checked_add(seller_fee).ok_or(error!(ErrorCode::ArithmeticOverflow))?;
+        }
+        total_creators_fee = creators_fee_amount(payment_manager, shared_fees, seller_fee);
+    }
+
+    let buy_side_fee = buy_side_fee_amount(payment_amount, DEFAULT_BUY_SIDE_FEE_SHARE);
+    let payer_amount = payment_amount
+        .checked_add(payer_fees(payment_manager, maker_fee, taker_fee))
+        .and_then(|payer_amount| payer_amount.checked_add(payer_buy_side_fee(payment_manager, buy_side_fee)))
+        .ok_or(error!(ErrorCode::ArithmeticOverflow))?;
+    let carved_out_fees = total_fees.checked_add(buy_side_fee).ok_or(error!(ErrorCode::ArithmeticOverflow))?;
+    // a flat maker fee does not scale with the payment, together with the buy side fee or an
+    // additive seller fee it can take more than the payment carries
+    if is_flat_fee_mode(payment_manager) && carved_out_fees > payer_amount {
+        return Err(error!(ErrorCode::FeeConfigExceedsLimit));
+    }
+    // fixed before any royalty is paid out, a payment target that is also a creator receives both
+    let principal_amount = payer_amount.checked_sub(carved_out_fees).ok_or(error!(ErrorCode::ArithmeticOverflow))?;
+    Ok(PaymentFees {
+        maker_fee,
+        taker_fee,
+        taker_fee_collector_fee,
+        total_fees,
+        total_creators_fee,
+        buy_side_fee,
+        principal_amount,
+    })
+}
+
+/// Whether a payment charges no fee at all, no maker, taker, seller or buy side fee and no rounding
+/// dust from an exact output gross up, so the whole payment goes to the payment target
+pub fn is_fee_free(total_fees: u64, buy_side_fee: u64, exact_output_excess: u64) -> bool {
+    total_fees == 0 && buy_side_fee == 0 && exact_output_excess == 0
+}
+
+/// Fees of a payment by an exempt payer, the whole payment is principal
+pub fn exempt_payment_fees(payment_amount: u64) -> PaymentFees {
+    PaymentFees {
+        maker_fee: 0,
+        taker_fee: 0,
+        taker_fee_collector_fee: 0,
+        total_fees: 0,
+        total_creators_fee: 0,
+        buy_side_fee: 0,
+        principal_amount: payment_amount,
+    }
+}
+
+/// Payment amount for which the payment target nets at least `net_amount` once the maker, seller
+/// and buy side fees are taken out of the payment, rounding up. A flat maker fee is added to the
+/// net amount as it does not scale with the payment, a maker fee covered by the taker and an
+/// additive buy side fee are left out
+pub fn exact_output_payment_amount(payment_manager: &PaymentManager, net_amount: u64, metadata_seller_fee_basis_points: Option<u16>) -> Result<u64> {
+    let flat_fee_mode = payment_manager.flat_fee_amount.is_none() && is_flat_fee_mode(payment_manager);
+    let maker_fee_basis_points = if is_flat_fee_only(payment_manager) || flat_fee_mode || payment_manager.taker_covers_maker_fee {
+        0
+    } else {
+        payment_manager.maker_fee_basis_points
+    };
+    let flat_maker_fee = if flat_fee_mode && !payment_manager.taker_covers_maker_fee {
+        payment_manager.maker_fee_basis_points
+    } else {
+        0
+    };
+    let seller_fee_basis_points = match metadata_seller_fee_basis_points {
+        Some(metadata_seller_fee_basis_points) => seller_fee_basis_points(payment_manager, metadata_seller_fee_basis_points)?,
+        None => 0,
+    };
+    let buy_side_fee_share = if payment_manager.buy_side_additive { 0 } else { DEFAULT_BUY_SIDE_FEE_SHARE };
+    let fee_basis_points = u64::from(maker_fee_basis_points)
+        .checked_add(seller_fee_basis_points.into())
+        .expect("Add error")
+        .checked_add(buy_side_fee_share)
+        .expect("Add error");
+    if fee_basis_points >= BASIS_POINTS_DIVISOR.into() {
+        return Err(error!(ErrorCode::FeeConfigExceedsLimit));
+    }
+    let net_basis_points = u128::from(u64::from(BASIS_POINTS_DIVISOR) - fee_basis_points);
+    // a net amount close to u64::MAX has no payment amount that fits in a u64
+    u64::try_from(
+        u128::from(net_amount)
+            .checked_add(flat_maker_fee.into())
+            .expect("Add error")
+            .checked_mul(BASIS_POINTS_DIVISOR.into())
+            .expect("Multiplication error")
+            .checked_add(net_basis_points - 1)
+            .expect("Add error")
+            .checked_div(net_basis_points)
+            .expect("Division error"),
+    )
+    .map_err(|_| error!(ErrorCode::ArithmeticOverflow))
+}
+
+/// Part of `payment_amount` settled by a partial fill, `fill_fraction` is in basis points and has
+/// to be within (0, BASIS_POINTS_DIVISOR]
+pub fn fill_amount(payment_amount: u64, fill_fraction: Option<u16>) -> Result<u64> {
+    match fill_fraction {
+        None => Ok(payment_amount),
+        Some(fill_fraction) if fill_fraction != 0 && fill_fraction <= BASIS_POINTS_DIVISOR => Ok(basis_points_fee(payment_amount, fill_fraction)),
+        _ => Err(error!(ErrorCode::InvalidFillFraction)),
+    }
+}
+
+/// Part of `payment_amount` above the `expected_amount` the sale requires, it is refunded to the
+/// payer rather than settled. A `payment_amount` below it does not pay for the sale
+pub fn overpayment_refund(payment_amount: u64, expected_amount: Option<u64>) -> Result<u64> {
+    match expected_amount {
+        Some(expected_amount) if payment_amount < expected_amount => Err(error!(ErrorCode::Underpayment)),
+        Some(expected_amount) => Ok(payment_amount - expected_amount),
+        None => Ok(0),
+    }
+}
+
+/// Bounds the payer's full outflow, the payment with every fee on top of it, by the payer's
+/// `max_total_spend`
+pub fn assert_max_total_spend(payer_total: u64, max_total_spend: Option<u64>) -> Result<()> {
+    if max_total_spend.map_or(false, |max_total_spend| payer_total > max_total_spend) {
+        return Err(error!(ErrorCode::ExceedsMaxSpend));
+    }
+    Ok(())
+}
+
+/// Whether an account holding `lamports` can send `amount` and still keep `rent_exempt_minimum`
+pub fn has_sufficient_lamports(lamports: u64, amount: u64, rent_exempt_minimum: u64) -> bool {
+    amount.checked_add(rent_exempt_minimum).map_or(false, |required_lamports| lamports >= required_lamports)
+}
+
+/// Checked before native transfers so an underfunded payer fails with `InsufficientFunds`
+/// rather than an opaque system program error
+pub fn assert_native_funds(payer: &AccountInfo, amount: u64) -> Result<()> {
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(payer.data_len());
+    if !has_sufficient_lamports(payer.lamports(), amount, rent_exempt_minimum) {
+        return Err(error!(ErrorCode::InsufficientFunds));
+    }
+    Ok(())
+}
+
+/// A payment manager with a `required_collection` only accepts mints whose metadata has that
+/// collection verified, so spoofed mints cannot collect royalties
+pub fn assert_required_collection(payment_manager: &PaymentManager, collection: Option<&Collection>) -> Result<()> {
+    match (payment_manager.required_collection, collection) {
+        (None, _) => Ok(()),
+        (Some(required_collection), Some(collection)) if collection.verified && collection.key == required_collection => Ok(()),
+        _ => Err(error!(ErrorCode::UnverifiedCollection)),
+    }
+}
+
+/// Address and bump of the policy of a collection
+pub fn find_collection_policy_address(collection: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COLLECTION_POLICY_SEED.as_bytes(), collection.as_ref()], &crate::ID)
+}
+
+/// Policy governing a mint whose metadata has its collection verified. The account passed has to
+/// be the policy address of that collection so the payer cannot leave the policy out, and it is
+/// only read when initialized. Mints without a verified collection are governed by no policy
+pub fn mint_collection_policy(collection_policy: &AccountInfo, collection: Option<&Collection>) -> Result<Option<CollectionPolicy>> {
+    let collection = match collection {
+        Some(collection) if collection.verified => collection,
+        _ => return Ok(None),
+    };
+    if collection_policy.key() != find_collection_policy_address(&collection.key).0 {
+        return Err(error!(ErrorCode::InvalidCollectionPolicy));
+    }
+    if collection_policy.data_is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Account::<CollectionPolicy>::try_from(collection_policy)?.into_inner()))
+}
+
+/// Royalty terms of the payment manager overridden by the policy of the mint's collection. Enforced
+/// royalties include the seller fee at no less than the enforced rate, and without waivers the
+/// royalty min price no longer waives them
+pub fn apply_collection_policy(payment_manager: &mut PaymentManager, collection_policy: &CollectionPolicy) {
+    if collection_policy.enforce_royalties {
+        payment_manager.include_seller_fee_basis_points = true;
+        payment_manager.royalty_floor_basis_points = Some(payment_manager.royalty_floor_basis_points.unwrap_or(0).max(collection_policy.enforced_basis_points));
+    }
+    if !collection_policy.allow_waivers {
+        payment_manager.royalty_min_price = None;
+    }
+}
+
+/// Sha256 of the creators' addresses and shares in metadata order, the verified flag is left out
+/// as it does not change who is paid. A mint without creators hashes the empty set
+pub fn creator_hash(creators: &[Creator]) -> [u8; 32] {
+    let creator_data: Vec<u8> = creators
+        .iter()
+        .flat_map(|creator| creator.address.to_bytes().into_iter().chain(std::iter::once(creator.share)))
+        .collect();
+    anchor_lang::solana_program::hash::hash(&creator_data).to_bytes()
+}
+
+/// A payer can pin the creators a mint had when it was listed with `expected_creator_hash`, so
+/// metadata changed in between fails the payment instead of paying other creators
+pub fn assert_creator_hash(creators: Option<&Vec<Creator>>, expected_creator_hash: Option<[u8; 32]>) -> Result<()> {
+    match expected_creator_hash {
+        Some(expected_creator_hash) if creator_hash(creators.map(Vec::as_slice).unwrap_or_default()) != expected_creator_hash => Err(error!(ErrorCode::CreatorHashMismatch)),
+        _ => Ok(()),
+    }
+}
+
+/// Sales of mints whose metadata has not recorded a primary sale yet send the proceeds to
+/// the creators when the payment manager handles primary sales
+pub fn is_primary_sale(payment_manager: &PaymentManager, primary_sale_happened: bool, has_creators: bool) -> bool {
+    payment_manager.handle_primary_sales && !primary_sale_happened && has_creators
+}
+
+/// Proceeds of mints whose metadata has not recorded a primary sale yet go to the payment
+/// manager's `primary_sale_target` instead of the payment target, unless they already go to the
+/// creators as a handled primary sale
+pub fn pays_primary_sale_target(payment_manager: &PaymentManager, primary_sale_happened: bool, primary_sale: bool) -> bool {
+    payment_manager.primary_sale_target.is_some() && !primary_sale_happened && !primary_sale
+}
+
+/// Creators to pay royalties to, a creator config set for the mint takes precedence over the
+/// creators in the mutable mint metadata
+pub fn royalty_creators(creator_config: &AccountInfo, metadata_creators: Option<Vec<Creator>>) -> Result<Option<Vec<Creator>>> {
+    if creator_config.data_is_empty() {
+        return Ok(metadata_creators);
+    }
+    let creator_config = Account::<CreatorConfig>::try_from(creator_config)?;
+    Ok(Some(
+        creator_config
+            .creators
+            .iter()
+            .map(|creator| Creator {
+                address: creator.address,
+                verified: true,
+                share: creator.share,
+            })
+            .collect(),
+    ))
+}
+
+/// Deserializes a token account passed as a remaining account
+pub fn remaining_token_account<'info>(account_info: &AccountInfo<'info>) -> Result<Account<'info, TokenAccount>> {
+    Account::<TokenAccount>::try_from(account_info).map_err(|_| error!(ErrorCode::TokenAccountDeserializationFailed))
+}
+
+/// A token account paid into has to belong to its recipient and hold the mint paid in, each
+/// mismatch fails with its own error
+pub fn assert_token_account(token_account: &TokenAccount, owner: &Pubkey, mint: &Pubkey) -> Result<()> {
+    if token_account.owner != *owner {
+        return Err(error!(ErrorCode::InvalidTokenAccountOwner));
+    }
+    if token_account.mint != *mint {
+        return Err(error!(ErrorCode::InvalidTokenAccountMint));
+    }
+    Ok(())
+}
+
+/// Whether a creator is paid into their token account. A token account holding another mint fails
+/// the payment unless the payment manager skips mismatched creator mints, e.g. while creators move
+/// to a new payment mint, the skipped creator's share then stays with the fee collector
+pub fn creator_token_account_payable(payment_manager: &PaymentManager, token_account: &TokenAccount, creator: &Pubkey, mint: &Pubkey) -> Result<bool> {
+    if payment_manager.skip_mismatched_creator_mints && token_account.owner == *creator && token_account.mint != *mint {
+        return Ok(false);
+    }
+    assert_token_account(token_account, creator, mint)?;
+    Ok(true)
+}
+
+/// Whether a creator's token account can receive their royalty when unreceivable royalties are
+/// escrowed. A missing or frozen token account, or one holding another mint, cannot and the royalty
+/// is accrued to the creator's royalty claim instead. A token account of someone else still fails
+pub fn creator_token_account_receivable(token_account: Option<&TokenAccount>, creator: &Pubkey, mint: &Pubkey) -> Result<bool> {
+    match token_account {
+        Some(token_account) if token_account.owner != *creator => Err(error!(ErrorCode::InvalidTokenAccountOwner)),
+        Some(token_account) => Ok(token_account.mint == *mint && !token_account.is_frozen()),
+        None => Ok(false),
+    }
+}
+
+/// Transfers `amount` of `mint` signed by `authority`, the decimals always come from the mint
+/// so no payout can be checked against another mint's decimals
+pub fn transfer_tokens<'info>(
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: &Account<'info, Mint>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = TransferChecked {
+        from,
+        mint: mint.to_account_info(),
+        to,
+        authority,
+    };
+    let cpi_context = CpiContext::new(token_program, cpi_accounts);
+    token::transfer_checked(cpi_context, amount, mint.decimals)
+}
+
+/// Transfers like `transfer_tokens` and reloads the balance of `to` to assert it was credited
+/// exactly `amount`, a mint that takes a cut of transfers fails with `BalanceMismatch`. A transfer
+/// into its own source moves nothing and is not checked
+pub fn transfer_tokens_checked<'info>(
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: &Account<'info, Mint>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if from.key() == to.key() {
+        return transfer_tokens(token_program, from, mint, to, authority, amount);
+    }
+    let before = token_account_balance(&to)?;
+    transfer_tokens(token_program, from, mint, to.clone(), authority, amount)?;
+    assert_balance_delta(before, token_account_balance(&to)?, amount)
+}
+
+/// Balance of a token account read from its current data, including earlier transfers of the
+/// instruction
+pub fn token_account_balance(token_account: &AccountInfo) -> Result<u64> {
+    Ok(TokenAccount::try_deserialize(&mut &token_account.try_borrow_data()?[..])?.amount)
+}
+
+/// A balance went from `before` to `after` by exactly the `amount` transferred into it
+pub fn assert_balance_delta(before: u64, after: u64, amount: u64) -> Result<()> {
+    if after.checked_sub(before) != Some(amount) {
+        return Err(error!(ErrorCode::BalanceMismatch));
+    }
+    Ok(())
+}
+
+/// Address and bump of a creator's royalty claim for a payment mint
+pub fn find_royalty_claim_address(payment_manager: &Pubkey, payment_mint: &Pubkey, creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ROYALTY_CLAIM_SEED.as_bytes(), payment_manager.as_ref(), payment_mint.as_ref(), creator.as_ref()], &crate::ID)
+}
+
+/// A creator and the amount of the creators fee they are owed
+pub type CreatorPayout = (Pubkey, u64);
+
+/// Splits the creators fee between the creators by share. The remainder, what is left of the fee
+/// once each share is rounded down, is handed out one unit at a time in creator order, creators
+/// without a share are left out. Each creator gets at most one unit of it, so earlier creators are
+/// favoured only while it is smaller than the number of creators and whatever is left over, e.g.
+/// the part of the fee shares summing to less than 100 do not cover, stays with the fee collector.
+/// With shares summing to 100 the whole creators fee is paid out, 3 units split between 5 creators
+/// of 20 pay the first three creators one unit each.
+/// This is intended: the split only depends on the creators and their order in the metadata.
+/// With `remainder_to_collector` no remainder is handed out, each creator is paid exactly their
+/// rounded down share and the whole remainder stays with the fee collector.
+/// Creators whose shares sum to zero, which only malformed metadata lists, are paid nothing and the
+/// whole creators fee stays with the fee collector
+pub fn creator_payouts(creators: &[Creator], total_creators_fee: u64, remainder_to_collector: bool) -> Vec<CreatorPayout> {
+    if creator_shares_sum(creators) == 0 {
+        return vec![];
+    }
+    // the whole split is computed in u128, each payout is only cast back to u64 once it is final
+    let total_creators_fee = u128::from(total_creators_fee);
+    let creator_share_amounts_sum = creators
+        .iter()
+        .map(|creator| creator_share_amount(total_creators_fee, creator.share))
+        .fold(0u128, |sum, amount| sum.checked_add(amount).expect("Add error"));
+    let mut creators_fee_remainder = if remainder_to_collector {
+        0
+    } else {
+        total_creators_fee.checked_sub(creator_share_amounts_sum).expect("Sub error")
+    };
+    creators
+        .iter()
+        .filter(|creator| creator.share != 0)
+        .map(|creator| {
+            let creator_fee_remainder_amount = u128::from(creators_fee_remainder > 0);
+            creators_fee_remainder = creators_fee_remainder.checked_sub(creator_fee_remainder_amount).expect("Sub error");
+            let creator_fee_amount = creator_share_amount(total_creators_fee, creator.share).checked_add(creator_fee_remainder_amount).expect("Add error");
+            (creator.address, u64::try_from(creator_fee_amount).expect("Could not cast u128 to u64"))
+        })
+        .collect()
+}
+
+/// Sum of the creators' shares, zero when every creator has a zero share
+pub fn creator_shares_sum(creators: &[Creator]) -> u16 {
+    creators.iter().map(|creator| u16::from(creator.share)).sum()
+}
+
+// multiplied before the division so no precision is lost, u128 keeps large fees from overflowing
+fn creator_share_amount(total_creators_fee: u128, share: u8) -> u128 {
+    total_creators_fee
+        .checked_mul(share.into())
+        .expect("Multiplication error")
+        .checked_div(CREATOR_SHARE_DIVISOR.into())
+        .expect("Division error")
+}
+
+pub fn validate_creator_shares(creators: &[CreatorShare]) -> Result<()> {
+    if creators.is_empty() || creators.len() > MAX_CREATORS || creators.iter().map(|creator| u16::from(creator.share)).sum::<u16>() != u16::from(CREATOR_SHARE_DIVISOR) {
+        return Err(error!(ErrorCode::InvalidCreatorConfig));
+    }
+    Ok(())
+}
+
+/// Converts an amount of the payment mint into the royalty mint using a rate scaled by
+/// `CONVERSION_RATE_DIVISOR`, rounding down
+///
+/// The rate is royalty mint base units per payment mint base unit so it already carries any
+/// difference in decimals between the two mints, amounts are never rescaled by decimals here
+pub fn convert_amount(amount: u64, conversion_rate: u64) -> u64 {
+    u64::try_from(
+        u128::from(amount)
+            .checked_mul(conversion_rate.into())
+            .expect("Multiplication error")
+            .checked_div(CONVERSION_RATE_DIVISOR.into())
+            .expect("Division error"),
+    )
+    .expect("Could not cast u128 to u64")
+}
+
+/// Values an amount of the payment mint at a price oracle's price, saturating rather than failing
+/// the payment as the value is only reported
+pub fn stable_value(amount: u64, price: u64) -> u64 {
+    u64::try_from(u128::from(amount) * u128::from(price) / u128::from(CONVERSION_RATE_DIVISOR)).unwrap_or(u64::MAX)
+}
+
+/// Converts a price in the quote currency into the payment mint using a rate scaled by
+/// `CONVERSION_RATE_DIVISOR`, rounding up so the payment never settles for less than the quote
+pub fn quote_payment_amount(quote_amount: u64, conversion_rate: u64) -> u64 {
+    let divisor = u128::from(CONVERSION_RATE_DIVISOR);
+    u64::try_from(
+        u128::from(quote_amount)
+            .checked_mul(conversion_rate.into())
+            .expect("Multiplication error")
+            .checked_add(divisor - 1)
+            .expect("Add error")
+            .checked_div(divisor)
+            .expect("Division error"),
+    )
+    .expect("Could not cast u128 to u64")
+}
+
+/// Client supplied conversion rates are only accepted within the bounds configured by the
+/// payment manager authority
+pub fn validate_conversion_rate(conversion_rate: u64, bounds: &Option<ConversionRateBounds>) -> Result<()> {
+    match bounds {
+        Some(bounds) if conversion_rate != 0 && conversion_rate >= bounds.min_conversion_rate && conversion_rate <= bounds.max_conversion_rate => Ok(()),
+        _ => Err(error!(ErrorCode::InvalidConversionRate)),
+    }
+}
+
+/// Counts the distinct admins among the signer keys, an admin listed twice still counts once
+pub fn admin_signer_count(admins: &[Pubkey], signers: &[Pubkey]) -> usize {
+    admins.iter().enumerate().filter(|(index, admin)| signers.contains(admin) && !admins[..*index].contains(admin)).count()
+}
+
+/// When an authority_threshold is set admin actions need that many distinct admins to sign,
+/// the additional admin signers are passed as remaining accounts
+pub fn assert_admin_signers(payment_manager: &PaymentManager, signer: &Signer, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    if let Some(authority_threshold) = payment_manager.authority_threshold {
+        let signers: Vec<Pubkey> = std::iter::once(signer.key())
+            .chain(remaining_accounts.iter().filter(|account| account.is_signer).map(|account| account.key()))
+            .collect();
+        if admin_signer_count(&payment_manager.admins, &signers) < usize::from(authority_threshold) {
+            return Err(error!(ErrorCode::InvalidAuthority));
+        }
+    }
+    Ok(())
+}
+
+/// Admins are distinct, otherwise one admin listed twice could meet a threshold of two alone
+pub fn validate_admins(admins: &[Pubkey], authority_threshold: Option<u8>) -> Result<()> {
+    if admins.len() > MAX_ADMINS || admins.iter().enumerate().any(|(index, admin)| admins[..index].contains(admin)) {
+        return Err(error!(ErrorCode::InvalidAuthorityThreshold));
+    }
+    if let Some(authority_threshold) = authority_threshold {
+        if authority_threshold == 0 || usize::from(authority_threshold) > admins.len() {
+            return Err(error!(ErrorCode::InvalidAuthorityThreshold));
+        }
+    }
+    Ok(())
+}
+
+/// Seeds of the program derived addresses in use, an escrow derived from them is never migrated
+const LIVE_SEEDS: [&str; 12] = [
+    PAYMENT_MANAGER_SEED,
+    CREATOR_CONFIG_SEED,
+    PAYER_RATE_LIMIT_SEED,
+    PAYER_STATS_SEED,
+    PROTOCOL_CONFIG_SEED,
+    PROCEEDS_ESCROW_SEED,
+    PAYMENT_RECORD_SEED,
+    EXEMPT_PAYER_SEED,
+    ROYALTY_ESCROW_SEED,
+    ROYALTY_CLAIM_SEED,
+    PRICE_ORACLE_SEED,
+    COLLECTION_POLICY_SEED,
+];
+
+/// Old escrow seeds never start with a live seed, so no escrow in use can be migrated, and have to
+/// include the payment manager so one payment manager cannot drain another's escrows. An address is
+/// derived from the seeds concatenated, so a live seed split over several old seeds or followed by
+/// more bytes in the same seed derives a live address all the same and is rejected as well
+pub fn validate_old_escrow_seeds(old_seeds: &[Vec<u8>], payment_manager: &Pubkey) -> Result<()> {
+    let concatenated_seeds = old_seeds.concat();
+    if old_seeds.is_empty() || LIVE_SEEDS.iter().any(|seed| concatenated_seeds.starts_with(seed.as_bytes())) || !old_seeds.iter().any(|seed| seed.as_slice() == payment_manager.as_ref()) {
+        return Err(error!(ErrorCode::InvalidEscrowSeeds));
+    }
+    Ok(())
+}
+
+/// Rejects a payment passing more remaining accounts than it can use before any of them is read,
+/// a payment manager escrowing unreceivable royalties takes two accounts per creator
+pub fn assert_payment_remaining_accounts(payment_manager: &PaymentManager, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    let max_remaining_accounts = if payment_manager.escrow_unreceivable_royalties {
+        MAX_ESCROWED_PAYMENT_REMAINING_ACCOUNTS
+    } else {
+        MAX_PAYMENT_REMAINING_ACCOUNTS
+    };
+    if remaining_accounts.len() > max_remaining_accounts {
+        return Err(error!(ErrorCode::TooManyAccounts));
+    }
+    Ok(())
+}
+
+/// Payments in any mint are accepted until the payment manager lists the mints it accepts, native
+/// payments are listed as the default pubkey
+pub fn assert_accepted_mint(payment_manager: &PaymentManager, payment_mint: &Pubkey) -> Result<()> {
+    if !payment_manager.accepted_mints.is_empty() && !payment_manager.accepted_mints.contains(payment_mint) {
+        return Err(error!(ErrorCode::UnsupportedPaymentMint));
+    }
+    Ok(())
+}
+
+/// Payment managers created before the current layout only deserialize once resized to
+/// `PAYMENT_MANAGER_SIZE`, their zero padding reads as the defaults of the fields added since
+pub fn load_resized_payment_manager(data: &[u8]) -> Result<PaymentManager> {
+    if data.len() < PAYMENT_MANAGER_SIZE {
+        return Err(error!(ErrorCode::InvalidPaymentManager));
+    }
+    PaymentManager::try_deserialize(&mut &data[..])
+}
+
+/// The default pubkey is the system program, fees sent to its token accounts could never be moved
+pub fn validate_fee_collector(fee_collector: &Pubkey) -> Result<()> {
+    if *fee_collector == Pubkey::default() {
+        return Err(error!(ErrorCode::InvalidFeeCollector));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn payment_manager(maker_fee_basis_points: u16, taker_fee_basis_points: u16) -> PaymentManager {
+        PaymentManager {
+            bump: 255,
+            fee_collector: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            maker_fee_basis_points,
+            taker_fee_basis_points,
+            name: "test".to_string(),
+            include_seller_fee_basis_points: false,
+            royalty_fee_share: None,
+            flat_fee_amount: None,
+            royalty_conversion_rate_bounds: None,
+            strict_remaining_accounts: false,
+            authority_threshold: None,
+            admins: vec![],
+            max_royalty_basis_points: None,
+            clamp_royalties: false,
+            payout_order: PayoutOrder::CreatorsFirst as u8,
+            royalty_min_price: None,
+            quote_conversion_rate_bounds: None,
+            handle_primary_sales: false,
+            min_payment_interval_slots: None,
+            max_creators_fee: None,
+            royalty_floor_basis_points: None,
+            pending_fee_schedule: None,
+            taker_fee_collector: None,
+            required_collection: None,
+            seller_fee_within_fees: false,
+            fee_mode: FeeMode::BasisPoints as u8,
+            record_payments: false,
+            primary_sale_target: None,
+            fallback_royalty_to_update_authority: false,
+            taker_covers_maker_fee: false,
+            claim_royalties: false,
+            bridged_metadata_program: None,
+            accepted_mints: vec![],
+            remainder_to_collector: false,
+            skip_mismatched_creator_mints: false,
+            fee_tiers: vec![],
+            buy_side_additive: false,
+            metadata_program_id: None,
+            escrow_unreceivable_royalties: false,
+            rebate_basis_points: None,
+        }
+    }
+
+    #[test]
+    fn test_maker_taker_fees_basis_points() {
+        let payment_manager = payment_manager(500, 300);
+        assert_eq!(maker_taker_fees(&payment_manager, 1000), (50, 30));
+        assert_eq!(maker_taker_fees(&payment_manager, 0), (0, 0));
+    }
+
+    #[test]
+    fn test_maker_taker_fees_round_separately() {
+        let payment_manager = payment_manager(50, 50);
+        // 0.995 + 0.995 rounds to 0 while 1.99 would round to 1
+        assert_eq!(maker_taker_fees(&payment_manager, 199), (0, 0));
+        assert_eq!(basis_points_fee(199, 100), 1);
+        // 9.995 + 9.995 rounds to 18 while 19.99 would round to 19
+        assert_eq!(maker_taker_fees(&payment_manager, 1999), (9, 9));
+        assert_eq!(basis_points_fee(1999, 100), 19);
+        // the total is never more than one unit short
+        for payment_amount in 0..10_000 {
+            let (maker_fee, taker_fee) = maker_taker_fees(&payment_manager, payment_amount);
+            assert!(basis_points_fee(payment_amount, 100) - (maker_fee + taker_fee) <= 1);
+        }
+    }
+
+    #[cfg(feature = "cu-logging")]
+    #[test]
+    fn test_log_compute_units() {
+        use {
+            solana_program::program_stubs::{set_syscall_stubs, SyscallStubs},
+            std::sync::{Arc, Mutex},
+        };
+
+        struct LogStubs(Arc<Mutex<Vec<String>>>);
+        impl SyscallStubs for LogStubs {
+            fn sol_log(&self, message: &str) {
+                self.0.lock().unwrap().push(message.to_string());
+            }
+            fn sol_log_compute_units(&self) {
+                self.0.lock().unwrap().push("compute units".to_string());
+            }
+        }
+
+        let logs = Arc::new(Mutex::new(vec![]));
+        set_syscall_stubs(Box::new(LogStubs(logs.clone())));
+        log_compute_units("fee calculation");
+        let logs = logs.lock().unwrap();
+        let position = logs.iter().position(|log| log == "Compute units at fee calculation").expect("Missing phase log");
+        assert!(logs[position..].iter().any(|log| log == "compute units"));
+    }
+
+    #[test]
+    fn test_activate_fee_schedule() {
+        let mut payment_manager = payment_manager(500, 300);
+        assert!(!activate_fee_schedule(&mut payment_manager, 100));
+        payment_manager.pending_fee_schedule = Some(FeeSchedule {
+            maker_fee_basis_points: 200,
+            taker_fee_basis_points: 100,
+            activation_time: 100,
+        });
+        // current fees apply until the activation time
+        assert!(!activate_fee_schedule(&mut payment_manager, 99));
+        assert_eq!(maker_taker_fees(&payment_manager, 1000), (50, 30));
+        assert!(activate_fee_schedule(&mut payment_manager, 100));
+        assert_eq!(maker_taker_fees(&payment_manager, 1000), (20, 10));
+        assert_eq!(payment_manager.pending_fee_schedule, None);
+        assert!(!activate_fee_schedule(&mut payment_manager, 101));
+    }
+
+    fn fee_tier(min_volume: u64, maker_fee_basis_points: u16, taker_fee_basis_points: u16) -> FeeTier {
+        FeeTier {
+            min_volume,
+            maker_fee_basis_points,
+            taker_fee_basis_points,
+        }
+    }
+
+    #[test]
+    fn test_tiered_payment_manager() {
+        let mut payment_manager = payment_manager(500, 300);
+        // without tiers every payer pays the base fees
+        let tiered = tiered_payment_manager(&payment_manager, u64::MAX);
+        assert_eq!((tiered.maker_fee_basis_points, tiered.taker_fee_basis_points), (500, 300));
+
+        payment_manager.fee_tiers = vec![fee_tier(10_000, 400, 250), fee_tier(100_000, 200, 100)];
+        let fees = |volume| {
+            let tiered = tiered_payment_manager(&payment_manager, volume);
+            (tiered.maker_fee_basis_points, tiered.taker_fee_basis_points)
+        };
+        assert_eq!(fees(0), (500, 300));
+        assert_eq!(fees(9_999), (500, 300));
+        // a tier applies from its volume on
+        assert_eq!(fees(10_000), (400, 250));
+        assert_eq!(fees(99_999), (400, 250));
+        assert_eq!(fees(100_000), (200, 100));
+        assert_eq!(fees(u64::MAX), (200, 100));
+        // the payment manager itself keeps its fees
+        assert_eq!((payment_manager.maker_fee_basis_points, payment_manager.taker_fee_basis_points), (500, 300));
+        assert_eq!(maker_taker_fees(&tiered_payment_manager(&payment_manager, 10_000), 10_000), (400, 250));
+    }
+
+    #[test]
+    fn test_validate_fee_tiers() {
+        let payment_manager = payment_manager(500, 300);
+        assert!(validate_fee_tiers(&payment_manager, &[]).is_ok());
+        assert!(validate_fee_tiers(&payment_manager, &[fee_tier(10_000, 400, 250), fee_tier(100_000, 200, 100)]).is_ok());
+        // volumes have to increase
+        assert_eq!(
+            validate_fee_tiers(&payment_manager, &[fee_tier(100_000, 400, 250), fee_tier(10_000, 200, 100)]),
+            Err(error!(ErrorCode::InvalidFeeTiers))
+        );
+        assert_eq!(
+            validate_fee_tiers(&payment_manager, &[fee_tier(10_000, 400, 250), fee_tier(10_000, 200, 100)]),
+            Err(error!(ErrorCode::InvalidFeeTiers))
+        );
+        assert_eq!(
+            validate_fee_tiers(&payment_manager, &(0..=MAX_FEE_TIERS as u64).map(|min_volume| fee_tier(min_volume, 0, 0)).collect::<Vec<FeeTier>>()),
+            Err(error!(ErrorCode::InvalidFeeTiers))
+        );
+        // every tier is held to the payment manager's fee config
+        assert!(validate_fee_tiers(&payment_manager, &[fee_tier(10_000, BASIS_POINTS_DIVISOR, 0)]).is_err());
+    }
+
+    #[test]
+    fn test_validate_fee_config() {
+        let mut payment_manager = payment_manager(5000, 300);
+        assert!(validate_fee_config(&payment_manager).is_ok());
+        // default max royalty of 5000 stacked on the maker fee and the buy side fee
+        payment_manager.include_seller_fee_basis_points = true;
+        assert!(validate_fee_config(&payment_manager).is_err());
+        payment_manager.max_royalty_basis_points = Some(4950);
+        assert!(validate_fee_config(&payment_manager).is_ok());
+        payment_manager.max_royalty_basis_points = Some(4951);
+        assert!(validate_fee_config(&payment_manager).is_err());
+        // a flat fee takes nothing out of the payment
+        payment_manager.flat_fee_amount = Some(25);
+        assert!(validate_fee_config(&payment_manager).is_ok());
+        payment_manager.flat_fee_amount = None;
+        // nor do the basis points of a flat fee mode
+        payment_manager.fee_mode = FeeMode::Flat as u8;
+        assert!(validate_fee_config(&payment_manager).is_ok());
+        payment_manager.fee_mode = FeeMode::BasisPoints as u8;
+        payment_manager.flat_fee_amount = Some(25);
+        // creators can not get more than all of the maker-taker fees
+        payment_manager.royalty_fee_share = Some(10001);
+        assert!(validate_fee_config(&payment_manager).is_err());
+        payment_manager.royalty_fee_share = Some(10000);
+        assert!(validate_fee_config(&payment_manager).is_ok());
+        // a royalty floor can not exceed the max royalty
+        payment_manager.royalty_floor_basis_points = Some(4952);
+        assert!(validate_fee_config(&payment_manager).is_err());
+        payment_manager.royalty_floor_basis_points = Some(4951);
+        assert!(validate_fee_config(&payment_manager).is_ok());
+        // a rebate can not exceed the fees
+        payment_manager.rebate_basis_points = Some(10001);
+        assert!(validate_fee_config(&payment_manager).is_err());
+        payment_manager.rebate_basis_points = Some(10000);
+        assert!(validate_fee_config(&payment_manager).is_ok());
+    }
+
+    #[test]
+    fn test_protocol_fee_amount() {
+        let protocol_config = ProtocolConfig {
+            bump: 255,
+            authority: Pubkey::new_unique(),
+            protocol_fee_basis_points: 100,
+            treasury: Pubkey::new_unique(),
+        };
+        assert_eq!(protocol_fee_amount(&protocol_config, 10_000, 500), 100);
+        // capped at the fee collector's share
+        assert_eq!(protocol_fee_amount(&protocol_config, 10_000, 40), 40);
+        assert_eq!(protocol_fee_amount(&protocol_config, 0, 500), 0);
+    }
+
+    #[test]
+    fn test_rebate_amount() {
+        let mut payment_manager = payment_manager(500, 300);
+        let fees = payment_fees(&payment_manager, 10_000, None).unwrap();
+        // no rebate by default
+        assert_eq!(rebate_amount(&payment_manager, fees.total_fees, fees.total_fees), 0);
+
+        payment_manager.rebate_basis_points = Some(1000);
+        let rebate = rebate_amount(&payment_manager, fees.total_fees, fees.total_fees);
+        assert_eq!(rebate, 80);
+        // the payer's net fee after the rebate
+        assert_eq!(fees.total_fees - rebate, 720);
+        // capped at what the fee collector would have been paid
+        assert_eq!(rebate_amount(&payment_manager, fees.total_fees, 30), 30);
+        assert_eq!(rebate_amount(&payment_manager, fees.total_fees, 0), 0);
+        // a full rebate gives all of the fees back
+        payment_manager.rebate_basis_points = Some(10000);
+        assert_eq!(rebate_amount(&payment_manager, fees.total_fees, fees.total_fees), fees.total_fees);
+    }
+
+    #[test]
+    fn test_buy_side_fee_amount() {
+        assert_eq!(buy_side_fee_amount(10_000, DEFAULT_BUY_SIDE_FEE_SHARE), 50);
+        assert_eq!(buy_side_fee_amount(199, DEFAULT_BUY_SIDE_FEE_SHARE), 0);
+        assert_eq!(buy_side_fee_amount(u64::MAX, DEFAULT_BUY_SIDE_FEE_SHARE), basis_points_fee(u64::MAX, 50));
+        // a share above the divisor is clamped to the whole payment
+        assert_eq!(buy_side_fee_amount(10_000, BASIS_POINTS_DIVISOR.into()), 10_000);
+        assert_eq!(buy_side_fee_amount(10_000, 20_000), 10_000);
+        assert_eq!(buy_side_fee_amount(u64::MAX, u64::MAX), u64::MAX);
+        assert_eq!(buy_side_fee_amount(0, u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_maker_taker_fees_flat_fee() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.flat_fee_amount = Some(25);
+        assert_eq!(maker_taker_fees(&payment_manager, 0), (0, 25));
+        assert_eq!(maker_taker_fees(&payment_manager, 1000), (0, 25));
+        assert_eq!(maker_taker_fees(&payment_manager, u64::MAX), (0, 25));
+    }
+
+    #[test]
+    fn test_maker_taker_fees_flat_fee_mode() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.fee_mode = FeeMode::Flat as u8;
+        // the fees do not scale with the payment
+        assert_eq!(maker_taker_fees(&payment_manager, 1000), (500, 300));
+        assert_eq!(maker_taker_fees(&payment_manager, u64::MAX), (500, 300));
+        // the maker fee is capped at the payment
+        assert_eq!(maker_taker_fees(&payment_manager, 100), (100, 300));
+        assert_eq!(maker_taker_fees(&payment_manager, 0), (0, 300));
+        // a flat fee amount takes precedence
+        payment_manager.flat_fee_amount = Some(25);
+        assert_eq!(maker_taker_fees(&payment_manager, 1000), (0, 25));
+        // basis points are applied to the payment otherwise
+        payment_manager.flat_fee_amount = None;
+        payment_manager.fee_mode = FeeMode::BasisPoints as u8;
+        assert_eq!(maker_taker_fees(&payment_manager, 1000), (50, 30));
+    }
+
+    #[test]
+    fn test_payment_fees_flat_fee_mode() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.fee_mode = FeeMode::Flat as u8;
+        // the whole payment goes to the maker fee, nothing is left for the buy side fee
+        assert_eq!(payment_fees(&payment_manager, 400, None).err(), Some(error!(ErrorCode::FeeConfigExceedsLimit)));
+        // nor for an additive seller fee
+        payment_manager.include_seller_fee_basis_points = true;
+        payment_manager.seller_fee_within_fees = false;
+        assert_eq!(payment_fees(&payment_manager, 500, Some(1000)).err(), Some(error!(ErrorCode::FeeConfigExceedsLimit)));
+        // a payment carrying every fee
+        let fees = payment_fees(&payment_manager, 1000, Some(1000)).unwrap();
+        assert_eq!((fees.maker_fee, fees.total_fees, fees.buy_side_fee, fees.principal_amount), (500, 900, 5, 1000 + 300 - 900 - 5));
+        // with the buy side fee paid on top the maker fee can take the whole payment
+        payment_manager.include_seller_fee_basis_points = false;
+        payment_manager.buy_side_additive = true;
+        assert_eq!(payment_fees(&payment_manager, 400, None).unwrap().principal_amount, 0);
+    }
+
+    #[test]
+    fn test_maker_taker_fees_flat_plus_basis_points() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.fee_mode = FeeMode::FlatPlusBasisPoints as u8;
+        payment_manager.flat_fee_amount = Some(25);
+        // the flat amount is added to the taker fee
+        assert_eq!(maker_taker_fees(&payment_manager, 1000), (50, 55));
+        assert_eq!(maker_taker_fees(&payment_manager, 0), (0, 25));
+        // without a flat amount it is the basis points fee mode
+        payment_manager.flat_fee_amount = None;
+        assert_eq!(maker_taker_fees(&payment_manager, 1000), (50, 30));
+        // the maker fee basis points are still taken out of the payment
+        assert_eq!(validate_fee_config(&payment_manager), Ok(()));
+        payment_manager.maker_fee_basis_points = BASIS_POINTS_DIVISOR;
+        assert_eq!(validate_fee_config(&payment_manager), Err(error!(ErrorCode::FeeConfigExceedsLimit)));
+    }
+
+    #[test]
+    fn test_payment_fees_flat_plus_basis_points() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.fee_mode = FeeMode::FlatPlusBasisPoints as u8;
+        payment_manager.flat_fee_amount = Some(2_500);
+        let fees = payment_fees(&payment_manager, 10_000, None).unwrap();
+        assert_eq!(fees.total_fees, 2_500 + 500 + 300);
+        assert_eq!(fees.principal_amount, 10_000 - 500 - 50);
+
+        // a flat amount far above the payment is paid on top and leaves the principal untouched
+        payment_manager.flat_fee_amount = Some(1_000_000);
+        let fees = payment_fees(&payment_manager, 1000, None).unwrap();
+        assert_eq!(fees.total_fees, 1_000_000 + 50 + 30);
+        assert_eq!(fees.principal_amount, 1000 - 50 - 5);
+        let fees = payment_fees(&payment_manager, 0, None).unwrap();
+        assert_eq!(fees.total_fees, 1_000_000);
+        assert_eq!(fees.principal_amount, 0);
+
+        // the payer is debited the payment and the taker fee with the flat amount in it
+        let (maker_fee, taker_fee) = maker_taker_fees(&payment_manager, 1000);
+        let fees = payment_fees(&payment_manager, 1000, None).unwrap();
+        assert_eq!(1000 + payer_fees(&payment_manager, maker_fee, taker_fee), fees.principal_amount + fees.total_fees + fees.buy_side_fee);
+    }
+
+    #[test]
+    fn test_fee_mode() {
+        assert_eq!(FeeMode::try_from(0).unwrap(), FeeMode::BasisPoints);
+        assert_eq!(FeeMode::try_from(1).unwrap(), FeeMode::Flat);
+        assert_eq!(FeeMode::try_from(2).unwrap(), FeeMode::FlatPlusBasisPoints);
+        assert!(FeeMode::try_from(3).is_err());
+    }
+
+    #[test]
+    fn test_royalties_waived() {
+        let mut payment_manager = payment_manager(500, 300);
+        assert!(!royalties_waived(&payment_manager, 0));
+        payment_manager.royalty_min_price = Some(1000);
+        assert!(royalties_waived(&payment_manager, 0));
+        assert!(royalties_waived(&payment_manager, 999));
+        assert!(!royalties_waived(&payment_manager, 1000));
+        assert!(!royalties_waived(&payment_manager, 1001));
+    }
+
+    #[test]
+    fn test_is_primary_sale() {
+        let mut payment_manager = payment_manager(500, 300);
+        assert!(!is_primary_sale(&payment_manager, false, true));
+        payment_manager.handle_primary_sales = true;
+        assert!(is_primary_sale(&payment_manager, false, true));
+        assert!(!is_primary_sale(&payment_manager, true, true));
+        // without creators the proceeds stay with the payment target
+        assert!(!is_primary_sale(&payment_manager, false, false));
+    }
+
+    #[test]
+    fn test_pays_primary_sale_target() {
+        let mut payment_manager = payment_manager(500, 300);
+        assert!(!pays_primary_sale_target(&payment_manager, false, false));
+        payment_manager.primary_sale_target = Some(Pubkey::new_unique());
+        assert!(pays_primary_sale_target(&payment_manager, false, false));
+        // secondary sales go to the payment target
+        assert!(!pays_primary_sale_target(&payment_manager, true, false));
+        // handled primary sales go to the creators
+        assert!(!pays_primary_sale_target(&payment_manager, false, true));
+    }
+
+    #[test]
+    fn test_within_payment_interval() {
+        assert!(!within_payment_interval(0, 5, 10));
+        assert!(within_payment_interval(100, 100, 10));
+        assert!(within_payment_interval(100, 109, 10));
+        assert!(!within_payment_interval(100, 110, 10));
+        assert!(!within_payment_interval(100, 100, 0));
+        assert!(within_payment_interval(u64::MAX - 1, u64::MAX - 1, 10));
+    }
+
+    #[test]
+    fn test_creators_fee_amount() {
+        let mut payment_manager = payment_manager(500, 300);
+        // default royalty share of half the maker-taker fees
+        assert_eq!(creators_fee_amount(&payment_manager, 80, 100), 140);
+        payment_manager.royalty_fee_share = Some(4500);
+        assert_eq!(creators_fee_amount(&payment_manager, 80, 100), 136);
+        payment_manager.max_creators_fee = Some(120);
+        assert_eq!(creators_fee_amount(&payment_manager, 80, 100), 120);
+        payment_manager.max_creators_fee = Some(200);
+        assert_eq!(creators_fee_amount(&payment_manager, 80, 100), 136);
+        payment_manager.max_creators_fee = Some(0);
+        assert_eq!(creators_fee_amount(&payment_manager, 80, 100), 0);
+    }
+
+    #[test]
+    fn test_creators_fee_amount_seller_fee_accounting() {
+        // (seller_fee_additive, maker_taker_fees, seller_fee, creators_fee) with the default royalty share
+        let cases = [
+            // the seller fee is paid on top of the royalty share
+            (true, 80, 10, 50),
+            (true, 80, 100, 140),
+            (true, 0, 10, 10),
+            // the seller fee is part of the royalty share, paid out of the maker-taker fees
+            (false, 80, 10, 40),
+            (false, 80, 60, 60),
+            (false, 80, 100, 80),
+            (false, 0, 10, 0),
+        ];
+        for (seller_fee_additive, maker_taker_fees, seller_fee, creators_fee) in cases {
+            let mut payment_manager = payment_manager(500, 300);
+            payment_manager.seller_fee_within_fees = !seller_fee_additive;
+            assert_eq!(creators_fee_amount(&payment_manager, maker_taker_fees, seller_fee), creators_fee);
+        }
+    }
+
+    #[test]
+    fn test_load_resized_payment_manager() {
+        use anchor_lang::Discriminator;
+        // the original layout, allocated with 16 bytes of slack for the name
+        let (fee_collector, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut legacy_data = PaymentManager::discriminator().to_vec();
+        (254u8, fee_collector, authority, 500u16, 300u16, "legacy".to_string(), true, Some(4000u64))
+            .serialize(&mut legacy_data)
+            .unwrap();
+        legacy_data.resize(136, 0);
+        assert!(PaymentManager::try_deserialize(&mut &legacy_data[..]).is_err());
+        assert!(load_resized_payment_manager(&legacy_data).is_err());
+
+        legacy_data.resize(PAYMENT_MANAGER_SIZE, 0);
+        let payment_manager = load_resized_payment_manager(&legacy_data).unwrap();
+        assert_eq!(payment_manager.bump, 254);
+        assert_eq!(payment_manager.fee_collector, fee_collector);
+        assert_eq!(payment_manager.authority, authority);
+        assert_eq!((payment_manager.maker_fee_basis_points, payment_manager.taker_fee_basis_points), (500, 300));
+        assert_eq!(payment_manager.name, "legacy");
+        assert!(payment_manager.include_seller_fee_basis_points);
+        assert_eq!(payment_manager.royalty_fee_share, Some(4000));
+        assert_eq!(payment_manager.authority_threshold, None);
+        assert!(payment_manager.admins.is_empty());
+        assert!(is_seller_fee_additive(&payment_manager));
+        assert_eq!(payment_manager.fee_mode, FeeMode::BasisPoints as u8);
+    }
+
+    #[test]
+    fn test_legacy_payment_manager_seller_fee_is_additive() {
+        // a legacy account ends after `required_collection`, its zero padding is where the newer fields are read from
+        let serialize = |payment_manager: &PaymentManager| {
+            let mut data = Vec::new();
+            payment_manager.try_serialize(&mut data).unwrap();
+            data
+        };
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.required_collection = Some(Pubkey::new_unique());
+        let mut within_fees = payment_manager.clone();
+        within_fees.seller_fee_within_fees = true;
+        let (data, within_fees_data) = (serialize(&payment_manager), serialize(&within_fees));
+        let legacy_len = data.iter().zip(&within_fees_data).position(|(a, b)| a != b).unwrap();
+
+        let mut legacy_data = data[..legacy_len].to_vec();
+        legacy_data.resize(PAYMENT_MANAGER_SIZE, 0);
+        let legacy = PaymentManager::try_deserialize(&mut &legacy_data[..]).unwrap();
+        assert_eq!(legacy.required_collection, payment_manager.required_collection);
+        assert!(!legacy.seller_fee_within_fees);
+        assert!(is_seller_fee_additive(&legacy));
+        assert_eq!(creators_fee_amount(&legacy, 80, 10), 50);
+    }
+
+    #[test]
+    fn test_payment_fees() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.include_seller_fee_basis_points = true;
+        let fees = |payment_manager: &PaymentManager, seller_fee_basis_points| payment_fees(payment_manager, 10_000, seller_fee_basis_points).unwrap();
+        assert_eq!(
+            fees(&payment_manager, Some(600)),
+            PaymentFees {
+                maker_fee: 500,
+                taker_fee: 300,
+                taker_fee_collector_fee: 0,
+                total_fees: 1400,
+                total_creators_fee: 1000,
+                buy_side_fee: 50,
+                principal_amount: 8850,
+            }
+        );
+        // without royalties nothing goes to the creators
+        assert_eq!(
+            fees(&payment_manager, None),
+            PaymentFees {
+                maker_fee: 500,
+                taker_fee: 300,
+                taker_fee_collector_fee: 0,
+                total_fees: 800,
+                total_creators_fee: 0,
+                buy_side_fee: 50,
+                principal_amount: 9450,
+            }
+        );
+        // a non-additive seller fee comes out of the maker-taker fees
+        payment_manager.seller_fee_within_fees = true;
+        assert_eq!(fees(&payment_manager, Some(600)).total_creators_fee, 600);
+        assert_eq!(fees(&payment_manager, Some(600)).principal_amount, 9450);
+        // the taker fee collector's fee is not shared with the creators
+        payment_manager.seller_fee_within_fees = false;
+        payment_manager.taker_fee_collector = Some(Pubkey::new_unique());
+        assert_eq!(fees(&payment_manager, Some(600)).taker_fee_collector_fee, 300);
+        assert_eq!(fees(&payment_manager, Some(600)).total_creators_fee, 850);
+        // an exempt payer pays no fee at all
+        assert_eq!(exempt_payment_fees(10_000).principal_amount, 10_000);
+        assert_eq!(exempt_payment_fees(10_000).total_fees + exempt_payment_fees(10_000).buy_side_fee, 0);
+    }
+
+    #[test]
+    fn test_payment_fees_maker_taker_semantics() {
+        let mut payment_manager = payment_manager(500, 300);
+        let payment_amount = 10_000;
+        let (maker_fee, taker_fee) = maker_taker_fees(&payment_manager, payment_amount);
+        assert_eq!((maker_fee, taker_fee), (500, 300));
+        let fees = payment_fees(&payment_manager, payment_amount, None).unwrap();
+        // total fees hold both the maker and the taker fee
+        assert_eq!(fees.total_fees, maker_fee + taker_fee);
+        assert_eq!(fees.taker_fee, taker_fee);
+        // the taker fee is paid on top of the payment, the maker fee comes out of the maker's principal
+        let payer_debit = payment_amount + payer_fees(&payment_manager, maker_fee, taker_fee);
+        assert_eq!(payer_debit, payment_amount + taker_fee);
+        assert_eq!(fees.principal_amount, payment_amount - maker_fee - fees.buy_side_fee);
+        assert_eq!(fees.principal_amount + fees.total_fees + fees.buy_side_fee, payer_debit);
+
+        // covering the maker fee moves it to the payer without changing the fees collected
+        payment_manager.taker_covers_maker_fee = true;
+        let covered_fees = payment_fees(&payment_manager, payment_amount, None).unwrap();
+        assert_eq!(covered_fees.total_fees, fees.total_fees);
+        assert_eq!(covered_fees.principal_amount, fees.principal_amount + maker_fee);
+    }
+
+    #[test]
+    fn test_is_fee_free() {
+        let fees = |payment_manager: &PaymentManager, payment_amount| payment_fees(payment_manager, payment_amount, Some(500)).unwrap();
+        let fee_free = |fees: PaymentFees| is_fee_free(fees.total_fees, fees.buy_side_fee, 0);
+        // a zero fee payment manager still takes the buy side fee once it rounds to more than zero
+        let mut payment_manager = payment_manager(0, 0);
+        assert!(fee_free(fees(&payment_manager, 100)));
+        assert_eq!(fees(&payment_manager, 100).principal_amount, 100);
+        assert!(!fee_free(fees(&payment_manager, 10_000)));
+        assert!(fee_free(exempt_payment_fees(10_000)));
+        // any fee or rounding dust takes the regular payouts
+        payment_manager.include_seller_fee_basis_points = true;
+        assert!(!fee_free(fees(&payment_manager, 100)));
+        assert!(!is_fee_free(0, 0, 1));
+    }
+
+    #[test]
+    fn test_payment_fees_zero_seller_fee() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.include_seller_fee_basis_points = true;
+        for seller_fee_additive in [true, false] {
+            payment_manager.seller_fee_within_fees = !seller_fee_additive;
+            // only the royalty share of the maker-taker fees goes to the creators
+            let fees = payment_fees(&payment_manager, 10_000, Some(0)).unwrap();
+            assert_eq!(fees.total_fees, 800);
+            assert_eq!(fees.total_creators_fee, 400);
+            assert_eq!(fees.principal_amount, 9450);
+            // and it is split between the creators without dust
+            assert_eq!(
+                payout_amounts(&[metadata_creator(0), metadata_creator(60), metadata_creator(40)], fees.total_creators_fee),
+                vec![240, 160]
+            );
+        }
+        // a royalty floor still applies to a zero seller fee
+        payment_manager.royalty_floor_basis_points = Some(100);
+        assert_eq!(payment_fees(&payment_manager, 10_000, Some(0)).unwrap().total_creators_fee, 400);
+        payment_manager.seller_fee_within_fees = false;
+        assert_eq!(payment_fees(&payment_manager, 10_000, Some(0)).unwrap().total_creators_fee, 500);
+    }
+
+    #[test]
+    fn test_payment_fees_taker_covers_maker_fee() {
+        let mut payment_manager = payment_manager(500, 300);
+        let fees = payment_fees(&payment_manager, 10_000, None).unwrap();
+        assert_eq!(payer_fees(&payment_manager, 500, 300), 300);
+        assert_eq!(fees.principal_amount, 9450);
+        assert_eq!(fees.principal_amount + fees.total_fees + fees.buy_side_fee, 10_300);
+        // the maker keeps the maker fee and the payer pays it on top of the taker fee
+        payment_manager.taker_covers_maker_fee = true;
+        let covered_fees = payment_fees(&payment_manager, 10_000, None).unwrap();
+        assert_eq!(payer_fees(&payment_manager, 500, 300), 800);
+        assert_eq!(covered_fees.total_fees, fees.total_fees);
+        assert_eq!(covered_fees.principal_amount, 9950);
+        assert_eq!(covered_fees.principal_amount - fees.principal_amount, 500);
+        assert_eq!(covered_fees.principal_amount + covered_fees.total_fees + covered_fees.buy_side_fee, 10_800);
+        // the gross up only accounts for the fees still taken out of the payment
+        assert_eq!(exact_output_payment_amount(&payment_manager, 9950, None), Ok(10_000));
+    }
+
+    #[test]
+    fn test_payment_fees_buy_side_additive() {
+        let mut payment_manager = payment_manager(500, 300);
+        let fees = payment_fees(&payment_manager, 10_000, None).unwrap();
+        assert_eq!(payer_buy_side_fee(&payment_manager, fees.buy_side_fee), 0);
+        // carved out of the principal
+        assert_eq!(fees.buy_side_fee, 50);
+        assert_eq!(fees.principal_amount, 9450);
+        assert_eq!(fees.principal_amount + fees.total_fees + fees.buy_side_fee, 10_300);
+        // the payer pays the buy side fee on top of the payment and the taker fee
+        payment_manager.buy_side_additive = true;
+        let additive_fees = payment_fees(&payment_manager, 10_000, None).unwrap();
+        assert_eq!(payer_buy_side_fee(&payment_manager, additive_fees.buy_side_fee), 50);
+        assert_eq!(additive_fees.buy_side_fee, fees.buy_side_fee);
+        assert_eq!(additive_fees.total_fees, fees.total_fees);
+        assert_eq!(additive_fees.principal_amount, 9500);
+        assert_eq!(additive_fees.principal_amount + additive_fees.total_fees + additive_fees.buy_side_fee, 10_350);
+        // the gross up only accounts for the maker fee
+        assert_eq!(exact_output_payment_amount(&payment_manager, 9500, None), Ok(10_000));
+    }
+
+    #[test]
+    fn test_seller_fee_amount() {
+        let mut payment_manager = payment_manager(500, 300);
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 10000), Ok(0));
+
+        payment_manager.include_seller_fee_basis_points = true;
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 100), Ok(10));
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, DEFAULT_MAX_ROYALTY_BASIS_POINTS), Ok(500));
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 10000), Err(error!(ErrorCode::RoyaltiesExceedLimit)));
+
+        payment_manager.max_royalty_basis_points = Some(1000);
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 1000), Ok(100));
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 1001), Err(error!(ErrorCode::RoyaltiesExceedLimit)));
+
+        payment_manager.clamp_royalties = true;
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 100), Ok(10));
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 10000), Ok(100));
+    }
+
+    #[test]
+    fn test_seller_fee_amount_above_divisor() {
+        let mut payment_manager = payment_manager(500, 300);
+        // not charged so never read
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 20000), Ok(0));
+
+        payment_manager.include_seller_fee_basis_points = true;
+        payment_manager.clamp_royalties = true;
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, BASIS_POINTS_DIVISOR + 1), Err(error!(ErrorCode::InvalidSellerFee)));
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 20000), Err(error!(ErrorCode::InvalidSellerFee)));
+        assert_eq!(exact_output_payment_amount(&payment_manager, 1000, Some(20000)), Err(error!(ErrorCode::InvalidSellerFee)));
+        assert_eq!(payment_fees(&payment_manager, 1000, Some(20000)), Err(error!(ErrorCode::InvalidSellerFee)));
+    }
+
+    #[test]
+    fn test_seller_fee_amount_royalty_floor() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.royalty_floor_basis_points = Some(500);
+        // the floor only raises seller fees that are charged
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 100), Ok(0));
+
+        payment_manager.include_seller_fee_basis_points = true;
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 0), Ok(50));
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 100), Ok(50));
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 500), Ok(50));
+        assert_eq!(seller_fee_amount(&payment_manager, 1000, 1000), Ok(100));
+    }
+
+    // the payment target share of a payment the way the royalties handler computes it
+    fn net_amount(payment_manager: &PaymentManager, payment_amount: u64, metadata_seller_fee_basis_points: u16) -> u64 {
+        let (maker_fee, taker_fee) = maker_taker_fees(payment_manager, payment_amount);
+        let seller_fee = seller_fee_amount(payment_manager, payment_amount, metadata_seller_fee_basis_points).unwrap();
+        payment_amount + taker_fee - maker_fee - taker_fee - seller_fee - basis_points_fee(payment_amount, DEFAULT_BUY_SIDE_FEE_SHARE as u16)
+    }
+
+    #[test]
+    fn test_exact_output_payment_amount() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.include_seller_fee_basis_points = true;
+        for net in [0, 1, 7, 999, 1000, 123_456, 10_000_000_000] {
+            for seller_fee_basis_points in [0, 100, 333, 5000] {
+                let payment_amount = exact_output_payment_amount(&payment_manager, net, Some(seller_fee_basis_points)).unwrap();
+                let net_amount = net_amount(&payment_manager, payment_amount, seller_fee_basis_points);
+                assert!(net_amount >= net);
+                // only rounding dust on top of the requested net amount
+                assert!(net_amount - net <= 3);
+            }
+        }
+        // no metadata means no seller fee
+        assert_eq!(exact_output_payment_amount(&payment_manager, 9450, None), Ok(10000));
+
+        payment_manager.flat_fee_amount = Some(25);
+        assert_eq!(exact_output_payment_amount(&payment_manager, 995, None), Ok(1000));
+
+        payment_manager.flat_fee_amount = None;
+        payment_manager.fee_mode = FeeMode::Flat as u8;
+        for net in [0, 1, 7, 999, 1000, 123_456, 10_000_000_000] {
+            for seller_fee_basis_points in [0, 100, 333, 5000] {
+                let payment_amount = exact_output_payment_amount(&payment_manager, net, Some(seller_fee_basis_points)).unwrap();
+                let net_amount = net_amount(&payment_manager, payment_amount, seller_fee_basis_points);
+                assert!(net_amount >= net);
+                assert!(net_amount - net <= 3);
+            }
+        }
+        // 500 flat on top of the buy side fee
+        assert_eq!(exact_output_payment_amount(&payment_manager, 9450, None), Ok(10000));
+
+        payment_manager.fee_mode = FeeMode::BasisPoints as u8;
+        payment_manager.maker_fee_basis_points = 5000;
+        payment_manager.max_royalty_basis_points = Some(10000);
+        assert_eq!(exact_output_payment_amount(&payment_manager, 1000, Some(4950)), Err(error!(ErrorCode::FeeConfigExceedsLimit)));
+    }
+
+    #[test]
+    fn test_fill_amount() {
+        assert_eq!(fill_amount(1000, None), Ok(1000));
+        assert_eq!(fill_amount(1000, Some(BASIS_POINTS_DIVISOR)), Ok(1000));
+        assert_eq!(fill_amount(1000, Some(5000)), Ok(500));
+        assert_eq!(fill_amount(1000, Some(1)), Ok(0));
+        assert_eq!(fill_amount(u64::MAX, Some(BASIS_POINTS_DIVISOR)), Ok(u64::MAX));
+        assert_eq!(fill_amount(1000, Some(0)), Err(error!(ErrorCode::InvalidFillFraction)));
+        assert_eq!(fill_amount(1000, Some(BASIS_POINTS_DIVISOR + 1)), Err(error!(ErrorCode::InvalidFillFraction)));
+    }
+
+    #[test]
+    fn test_assert_max_total_spend() {
+        let payment_manager = payment_manager(500, 300);
+        let fees = payment_fees(&payment_manager, 10_000, None).unwrap();
+        let payer_total = fees.principal_amount + fees.total_fees + fees.buy_side_fee;
+        assert_eq!(payer_total, 10_300);
+        assert_eq!(assert_max_total_spend(payer_total, None), Ok(()));
+        assert_eq!(assert_max_total_spend(payer_total, Some(u64::MAX)), Ok(()));
+        assert_eq!(assert_max_total_spend(payer_total, Some(10_300)), Ok(()));
+        assert_eq!(assert_max_total_spend(payer_total, Some(10_299)), Err(error!(ErrorCode::ExceedsMaxSpend)));
+        assert_eq!(assert_max_total_spend(payer_total, Some(0)), Err(error!(ErrorCode::ExceedsMaxSpend)));
+        assert_eq!(assert_max_total_spend(0, Some(0)), Ok(()));
+    }
+
+    #[test]
+    fn test_overpayment_refund() {
+        assert_eq!(overpayment_refund(10_000, None), Ok(0));
+        assert_eq!(overpayment_refund(10_000, Some(10_000)), Ok(0));
+        assert_eq!(overpayment_refund(10_000, Some(12_000)), Err(error!(ErrorCode::Underpayment)));
+        assert_eq!(overpayment_refund(0, Some(1)), Err(error!(ErrorCode::Underpayment)));
+        assert_eq!(overpayment_refund(12_500, Some(10_000)), Ok(2_500));
+        assert_eq!(overpayment_refund(u64::MAX, Some(0)), Ok(u64::MAX));
+
+        // the fees are charged on the expected amount only
+        let payment_manager = payment_manager(500, 300);
+        let refund = overpayment_refund(12_500, Some(10_000)).unwrap();
+        let fees = payment_fees(&payment_manager, 12_500 - refund, None).unwrap();
+        assert_eq!(fees, payment_fees(&payment_manager, 10_000, None).unwrap());
+        assert_eq!(fees.principal_amount + fees.total_fees + fees.buy_side_fee, 10_300);
+    }
+
+    #[test]
+    fn test_fill_amount_half_fill_halves_fees() {
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.include_seller_fee_basis_points = true;
+        let payment_amount = 20_000;
+        let filled_amount = fill_amount(payment_amount, Some(5000)).unwrap();
+        assert_eq!(filled_amount * 2, payment_amount);
+
+        let (maker_fee, taker_fee) = maker_taker_fees(&payment_manager, payment_amount);
+        let (filled_maker_fee, filled_taker_fee) = maker_taker_fees(&payment_manager, filled_amount);
+        assert_eq!((filled_maker_fee * 2, filled_taker_fee * 2), (maker_fee, taker_fee));
+        let seller_fee = seller_fee_amount(&payment_manager, payment_amount, 600).unwrap();
+        let filled_seller_fee = seller_fee_amount(&payment_manager, filled_amount, 600).unwrap();
+        assert_eq!(filled_seller_fee * 2, seller_fee);
+        let creators_fee = creators_fee_amount(&payment_manager, maker_fee + taker_fee, seller_fee);
+        let filled_creators_fee = creators_fee_amount(&payment_manager, filled_maker_fee + filled_taker_fee, filled_seller_fee);
+        assert_eq!(filled_creators_fee * 2, creators_fee);
+        let buy_side_fee = basis_points_fee(payment_amount, DEFAULT_BUY_SIDE_FEE_SHARE as u16);
+        assert_eq!(basis_points_fee(filled_amount, DEFAULT_BUY_SIDE_FEE_SHARE as u16) * 2, buy_side_fee);
+        assert_eq!(net_amount(&payment_manager, filled_amount, 600) * 2, net_amount(&payment_manager, payment_amount, 600));
+    }
+
+    #[test]
+    fn test_has_sufficient_lamports() {
+        assert!(has_sufficient_lamports(1_000, 100, 900));
+        assert!(has_sufficient_lamports(1_001, 100, 900));
+        assert!(!has_sufficient_lamports(999, 100, 900));
+        assert!(has_sufficient_lamports(900, 0, 900));
+        assert!(!has_sufficient_lamports(u64::MAX, u64::MAX, 1));
+    }
+
+    pub(crate) fn metadata_creator(share: u8) -> Creator {
+        Creator {
+            address: Pubkey::new_unique(),
+            verified: false,
+            share,
+        }
+    }
+
+    #[test]
+    fn test_assert_creator_hash() {
+        let creators = vec![metadata_creator(60), metadata_creator(40)];
+        let expected_creator_hash = creator_hash(&creators);
+        assert_eq!(assert_creator_hash(Some(&creators), None), Ok(()));
+        assert_eq!(assert_creator_hash(Some(&creators), Some(expected_creator_hash)), Ok(()));
+        // verifying a creator does not change the hash
+        let mut verified_creators = creators.clone();
+        verified_creators[0].verified = true;
+        assert_eq!(assert_creator_hash(Some(&verified_creators), Some(expected_creator_hash)), Ok(()));
+
+        // shares, addresses and order are all part of the hash
+        let mut changed_shares = creators.clone();
+        (changed_shares[0].share, changed_shares[1].share) = (40, 60);
+        assert_eq!(assert_creator_hash(Some(&changed_shares), Some(expected_creator_hash)), Err(error!(ErrorCode::CreatorHashMismatch)));
+        let mut changed_address = creators.clone();
+        changed_address[1].address = Pubkey::new_unique();
+        assert_eq!(assert_creator_hash(Some(&changed_address), Some(expected_creator_hash)), Err(error!(ErrorCode::CreatorHashMismatch)));
+        let reordered: Vec<Creator> = creators.iter().rev().cloned().collect();
+        assert_eq!(assert_creator_hash(Some(&reordered), Some(expected_creator_hash)), Err(error!(ErrorCode::CreatorHashMismatch)));
+        assert_eq!(assert_creator_hash(None, Some(expected_creator_hash)), Err(error!(ErrorCode::CreatorHashMismatch)));
+
+        // creators removed from a mint
+        assert_eq!(assert_creator_hash(None, Some(creator_hash(&[]))), Ok(()));
+        assert_eq!(assert_creator_hash(Some(&vec![]), Some(creator_hash(&[]))), Ok(()));
+    }
+
+    fn payout_amounts(creators: &[Creator], total_creators_fee: u64) -> Vec<u64> {
+        let payouts = creator_payouts(creators, total_creators_fee, false);
+        let addresses: Vec<Pubkey> = creators.iter().filter(|creator| creator.share != 0).map(|creator| creator.address).collect();
+        assert_eq!(payouts.iter().map(|(address, _)| *address).collect::<Vec<Pubkey>>(), addresses);
+        payouts.into_iter().map(|(_, amount)| amount).collect()
+    }
+
+    #[test]
+    fn test_creator_payouts_zero_share_sum() {
+        let creators = vec![metadata_creator(0), metadata_creator(0), metadata_creator(0)];
+        assert_eq!(creator_shares_sum(&creators), 0);
+        assert_eq!(creator_payouts(&creators, 1000, false), vec![]);
+        assert_eq!(creator_payouts(&creators, 1000, true), vec![]);
+        assert_eq!(creator_payouts(&creators, 0, false), vec![]);
+        assert_eq!(creator_payouts(&[], 1000, false), vec![]);
+
+        // the whole creators fee is left to the fee collector
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.include_seller_fee_basis_points = true;
+        let fees = payment_fees(&payment_manager, 10_000, Some(500)).unwrap();
+        assert!(fees.total_creators_fee > 0);
+        let fees_paid_out: u64 = creator_payouts(&creators, fees.total_creators_fee, false).iter().map(|(_, amount)| amount).sum();
+        assert_eq!(fees_paid_out, 0);
+        assert_eq!(creator_shares_sum(&[metadata_creator(60), metadata_creator(0), metadata_creator(40)]), 100);
+    }
+
+    #[test]
+    fn test_creator_payouts() {
+        assert_eq!(payout_amounts(&[metadata_creator(100)], 1000), vec![1000]);
+        assert_eq!(payout_amounts(&[metadata_creator(60), metadata_creator(40)], 1000), vec![600, 400]);
+        // creators without a share are left out
+        assert_eq!(payout_amounts(&[metadata_creator(0), metadata_creator(60), metadata_creator(40)], 1000), vec![600, 400]);
+        // rounding dust of each share goes out one unit at a time in creator order
+        assert_eq!(payout_amounts(&[metadata_creator(50), metadata_creator(30), metadata_creator(20)], 7), vec![4, 2, 1]);
+        assert_eq!(payout_amounts(&[metadata_creator(34), metadata_creator(33), metadata_creator(33)], 10), vec![4, 3, 3]);
+        // the part the shares do not cover goes out one unit at a time in creator order
+        assert_eq!(payout_amounts(&[metadata_creator(50), metadata_creator(49)], 100), vec![51, 49]);
+        assert_eq!(payout_amounts(&[metadata_creator(40), metadata_creator(40)], 10), vec![5, 5]);
+        assert_eq!(payout_amounts(&[metadata_creator(60), metadata_creator(40)], 0), vec![0, 0]);
+        assert_eq!(payout_amounts(&[], 1000), Vec::<u64>::new());
+        // no intermediate overflow for large fees
+        assert_eq!(payout_amounts(&[metadata_creator(100)], u64::MAX), vec![u64::MAX]);
+        assert_eq!(
+            payout_amounts(&[metadata_creator(60), metadata_creator(40)], u64::MAX),
+            vec![u64::MAX / 100 * 60 + 9, u64::MAX / 100 * 40 + 6]
+        );
+        assert_eq!(payout_amounts(&[metadata_creator(0), metadata_creator(100)], u64::MAX - 1), vec![u64::MAX - 1]);
+    }
+
+    #[test]
+    fn test_creator_share_and_basis_points_divisors() {
+        let mut payment_manager = payment_manager(0, 0);
+        payment_manager.include_seller_fee_basis_points = true;
+        // the seller fee is in basis points of the payment, its split between creators in percent
+        let seller_fee = seller_fee_amount(&payment_manager, 10_000, 500).unwrap();
+        assert_eq!(seller_fee, 500);
+        assert_eq!(payout_amounts(&[metadata_creator(60), metadata_creator(40)], seller_fee), vec![300, 200]);
+        assert_eq!(basis_points_fee(10_000, BASIS_POINTS_DIVISOR), 10_000);
+        assert_eq!(payout_amounts(&[metadata_creator(CREATOR_SHARE_DIVISOR)], seller_fee), vec![seller_fee]);
+    }
+
+    #[test]
+    fn test_creator_payouts_tiny_creators_fee() {
+        let creators = |shares: &[u8]| shares.iter().map(|share| metadata_creator(*share)).collect::<Vec<Creator>>();
+        // every share rounds down to nothing and the remainder goes to the first creators
+        assert_eq!(payout_amounts(&creators(&[20, 20, 20, 20, 20]), 3), vec![1, 1, 1, 0, 0]);
+        assert_eq!(payout_amounts(&creators(&[40, 30, 15, 10, 5]), 3), vec![2, 1, 0, 0, 0]);
+        assert_eq!(payout_amounts(&creators(&[20, 20, 20, 20, 15]), 3), vec![1, 1, 1, 0, 0]);
+        assert_eq!(payout_amounts(&creators(&[10, 10, 10, 10, 10]), 3), vec![1, 1, 1, 0, 0]);
+        assert_eq!(payout_amounts(&creators(&[20, 20, 20, 20, 20]), 1), vec![1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_creator_payouts_remainder() {
+        let creators = |shares: &[u8]| shares.iter().map(|share| metadata_creator(*share)).collect::<Vec<Creator>>();
+        // total_creators_fee - sum(floor(total_creators_fee * share / 100)) is handed out
+        let remainder = |shares: &[u8], total_creators_fee: u64| {
+            let creators = creators(shares);
+            let share_amounts = u64::try_from(creators.iter().map(|creator| creator_share_amount(total_creators_fee.into(), creator.share)).sum::<u128>()).unwrap();
+            let paid_out: u64 = payout_amounts(&creators, total_creators_fee).iter().sum();
+            (total_creators_fee - share_amounts, paid_out - share_amounts)
+        };
+        // 0.33 + 0.33 + 0.34 rounds down to nothing for each share where flooring the summed shares
+        // leaves no remainder at all
+        assert_eq!(remainder(&[33, 33, 34], 1), (1, 1));
+        assert_eq!(payout_amounts(&creators(&[33, 33, 34]), 1), vec![1, 0, 0]);
+        assert_eq!(remainder(&[33, 33, 34], 101), (1, 1));
+        assert_eq!(payout_amounts(&creators(&[33, 33, 34]), 101), vec![34, 33, 34]);
+        assert_eq!(remainder(&[50, 30, 20], 7), (1, 1));
+        assert_eq!(remainder(&[25, 25, 25, 25], 3), (3, 3));
+        assert_eq!(remainder(&[60, 40], 1000), (0, 0));
+        // shares summing to less than 100 leave more than one unit per creator, the rest stays with the fee collector
+        assert_eq!(remainder(&[30, 25, 20, 10, 8], 100), (7, 5));
+        assert_eq!(remainder(&[40, 40], 10), (2, 2));
+        // with shares summing to 100 the whole creators fee is paid out
+        for total_creators_fee in 0..1000 {
+            for shares in [&[100][..], &[60, 40], &[50, 30, 20], &[34, 33, 33], &[20, 20, 20, 20, 20], &[1, 1, 98]] {
+                assert_eq!(payout_amounts(&creators(shares), total_creators_fee).iter().sum::<u64>(), total_creators_fee);
+            }
+        }
+    }
+
+    #[test]
+    fn test_creator_payouts_remainder_to_collector() {
+        let creators = |shares: &[u8]| shares.iter().map(|share| metadata_creator(*share)).collect::<Vec<Creator>>();
+        let amounts = |shares: &[u8], total_creators_fee: u64, remainder_to_collector: bool| {
+            creator_payouts(&creators(shares), total_creators_fee, remainder_to_collector)
+                .into_iter()
+                .map(|(_, amount)| amount)
+                .collect::<Vec<u64>>()
+        };
+        // a fee the shares do not divide, the remainder goes either to the creators or to the fee collector
+        assert_eq!(amounts(&[50, 30, 20], 7, false), vec![4, 2, 1]);
+        assert_eq!(amounts(&[50, 30, 20], 7, true), vec![3, 2, 1]);
+        assert_eq!(amounts(&[34, 33, 33], 10, false), vec![4, 3, 3]);
+        assert_eq!(amounts(&[34, 33, 33], 10, true), vec![3, 3, 3]);
+        assert_eq!(amounts(&[20, 20, 20, 20, 20], 3, false), vec![1, 1, 1, 0, 0]);
+        assert_eq!(amounts(&[20, 20, 20, 20, 20], 3, true), vec![0, 0, 0, 0, 0]);
+        // each creator is paid exactly their rounded down share
+        for total_creators_fee in 0..1000 {
+            for shares in [&[100][..], &[60, 40], &[50, 30, 20], &[34, 33, 33], &[30, 25, 20, 10, 8]] {
+                let expected: Vec<u64> = shares.iter().map(|share| u64::try_from(creator_share_amount(total_creators_fee.into(), *share)).unwrap()).collect();
+                assert_eq!(amounts(shares, total_creators_fee, true), expected);
+            }
+        }
+        // a fee the shares divide is paid out the same either way
+        assert_eq!(amounts(&[60, 40], 1000, true), amounts(&[60, 40], 1000, false));
+    }
+
+    #[test]
+    fn test_creator_payouts_u128_precision() {
+        let creators = |shares: &[u8]| shares.iter().map(|share| metadata_creator(*share)).collect::<Vec<Creator>>();
+        // the split in u64, which has to divide before multiplying for large fees not to overflow
+        let u64_amounts = |shares: &[u8], total_creators_fee: u64| {
+            shares
+                .iter()
+                .filter(|share| **share != 0)
+                .map(|share| total_creators_fee / u64::from(CREATOR_SHARE_DIVISOR) * u64::from(*share))
+                .collect::<Vec<u64>>()
+        };
+        // multiplying first in u64 overflows for any fee above u64::MAX / 100
+        assert_eq!((u64::MAX / 100 + 1).checked_mul(100), None);
+
+        // adversarial fees, just short of a multiple of 100 or at the top of the range, split between many small shares
+        let cases: [(&[u8], u64); 6] = [
+            (&[33, 33, 34], u64::MAX),
+            (&[1, 1, 1, 1, 96], u64::MAX),
+            (&[1, 1, 1, 1, 96], 1_000_000_000_000_000_099),
+            (&[20, 20, 20, 20, 20], 99),
+            (&[99, 1], 199),
+            (&[0, 50, 0, 50], u64::MAX - 1),
+        ];
+        for (shares, total_creators_fee) in cases {
+            let u128_amounts = payout_amounts(&creators(shares), total_creators_fee);
+            let u64_split = u64_amounts(shares, total_creators_fee);
+            // the u128 split pays out the whole creators fee
+            assert_eq!(u128_amounts.iter().map(|amount| u128::from(*amount)).sum::<u128>(), u128::from(total_creators_fee));
+            // the u64 split falls short by the fee modulo 100, up to 99 units left with the fee collector
+            let u64_shortfall = total_creators_fee - u64_split.iter().sum::<u64>();
+            assert_eq!(u64_shortfall, total_creators_fee % u64::from(CREATOR_SHARE_DIVISOR));
+            // no creator is paid less in u128, and at most their share in units more
+            let nonzero_shares = shares.iter().filter(|share| **share != 0);
+            for ((u128_amount, u64_amount), share) in u128_amounts.iter().zip(&u64_split).zip(nonzero_shares) {
+                assert!(u128_amount >= u64_amount);
+                assert!(u128_amount - u64_amount <= u64::from(*share));
+            }
+        }
+        assert_eq!(u64_amounts(&[1, 1, 1, 1, 96], 1_000_000_000_000_000_099).iter().sum::<u64>(), 1_000_000_000_000_000_000);
+        assert_eq!(payout_amounts(&creators(&[20, 20, 20, 20, 20]), 99), vec![20, 20, 20, 20, 19]);
+        assert_eq!(u64_amounts(&[20, 20, 20, 20, 20], 99), vec![0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_creator_payouts_remainder_is_deterministic() {
+        // 5 creators whose shares leave a remainder of 7 units
+        let creators = [metadata_creator(30), metadata_creator(25), metadata_creator(20), metadata_creator(10), metadata_creator(8)];
+        assert_eq!(payout_amounts(&creators, 100), vec![31, 26, 21, 11, 9]);
+        assert_eq!(creator_payouts(&creators, 100, false), creator_payouts(&creators, 100, false));
+        // with a unit for every creator reordering the metadata does not change what each creator receives
+        let sorted_payouts = |creators: &[Creator]| {
+            let mut payouts = creator_payouts(creators, 100, false);
+            payouts.sort();
+            payouts
+        };
+        let mut reordered_creators = creators.clone();
+        reordered_creators.reverse();
+        assert_eq!(sorted_payouts(&reordered_creators), sorted_payouts(&creators));
+        reordered_creators.rotate_left(2);
+        assert_eq!(sorted_payouts(&reordered_creators), sorted_payouts(&creators));
+    }
+
+    fn token_account(owner: Pubkey, mint: Pubkey) -> TokenAccount {
+        token_account_in_state(owner, mint, anchor_spl::token::spl_token::state::AccountState::Initialized)
+    }
+
+    fn token_account_in_state(owner: Pubkey, mint: Pubkey, state: anchor_spl::token::spl_token::state::AccountState) -> TokenAccount {
+        use anchor_lang::solana_program::program_pack::Pack;
+        use anchor_spl::token::spl_token::state::Account;
+
+        let mut data = vec![0; Account::LEN];
+        Account::pack(
+            Account {
+                mint,
+                owner,
+                state,
+                ..Account::default()
+            },
+            &mut data,
+        )
+        .unwrap();
+        TokenAccount::try_deserialize(&mut data.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn test_assert_token_account() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(assert_token_account(&token_account(owner, mint), &owner, &mint).is_ok());
+        assert_eq!(
+            assert_token_account(&token_account(Pubkey::new_unique(), mint), &owner, &mint),
+            Err(error!(ErrorCode::InvalidTokenAccountOwner))
+        );
+        assert_eq!(
+            assert_token_account(&token_account(owner, Pubkey::new_unique()), &owner, &mint),
+            Err(error!(ErrorCode::InvalidTokenAccountMint))
+        );
+        // the owner is checked first
+        assert_eq!(
+            assert_token_account(&token_account(Pubkey::new_unique(), Pubkey::new_unique()), &owner, &mint),
+            Err(error!(ErrorCode::InvalidTokenAccountOwner))
+        );
+    }
+
+    #[test]
+    fn test_creator_token_account_payable() {
+        let (creator, mint, other_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut payment_manager = payment_manager(500, 300);
+        // strict, a token account of another mint fails the payment
+        assert_eq!(creator_token_account_payable(&payment_manager, &token_account(creator, mint), &creator, &mint), Ok(true));
+        assert_eq!(
+            creator_token_account_payable(&payment_manager, &token_account(creator, other_mint), &creator, &mint),
+            Err(error!(ErrorCode::InvalidTokenAccountMint))
+        );
+
+        // lenient, the creator is skipped
+        payment_manager.skip_mismatched_creator_mints = true;
+        assert_eq!(creator_token_account_payable(&payment_manager, &token_account(creator, mint), &creator, &mint), Ok(true));
+        assert_eq!(creator_token_account_payable(&payment_manager, &token_account(creator, other_mint), &creator, &mint), Ok(false));
+        // the owner is still checked
+        assert_eq!(
+            creator_token_account_payable(&payment_manager, &token_account(Pubkey::new_unique(), other_mint), &creator, &mint),
+            Err(error!(ErrorCode::InvalidTokenAccountOwner))
+        );
+    }
+
+    #[test]
+    fn test_creator_token_account_receivable() {
+        use anchor_spl::token::spl_token::state::AccountState;
+
+        let (creator, mint, other_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(creator_token_account_receivable(Some(&token_account(creator, mint)), &creator, &mint), Ok(true));
+        // missing, frozen and mismatched token accounts cannot receive, the royalty is escrowed
+        assert_eq!(creator_token_account_receivable(None, &creator, &mint), Ok(false));
+        assert_eq!(
+            creator_token_account_receivable(Some(&token_account_in_state(creator, mint, AccountState::Frozen)), &creator, &mint),
+            Ok(false)
+        );
+        assert_eq!(creator_token_account_receivable(Some(&token_account(creator, other_mint)), &creator, &mint), Ok(false));
+        // the owner is still checked
+        assert_eq!(
+            creator_token_account_receivable(Some(&token_account(Pubkey::new_unique(), mint)), &creator, &mint),
+            Err(error!(ErrorCode::InvalidTokenAccountOwner))
+        );
+    }
+
+    #[test]
+    fn test_assert_balance_delta() {
+        assert_eq!(assert_balance_delta(1000, 1500, 500), Ok(()));
+        assert_eq!(assert_balance_delta(0, 0, 0), Ok(()));
+        // a transfer fee mint withholding 1% of the transfer credits less than was sent
+        assert_eq!(assert_balance_delta(1000, 1495, 500), Err(error!(ErrorCode::BalanceMismatch)));
+        assert_eq!(assert_balance_delta(1000, 1501, 500), Err(error!(ErrorCode::BalanceMismatch)));
+        assert_eq!(assert_balance_delta(1000, 900, 0), Err(error!(ErrorCode::BalanceMismatch)));
+    }
+
+    #[test]
+    fn test_token_account_balance() {
+        use anchor_lang::solana_program::program_pack::Pack;
+        use anchor_spl::token::spl_token::state::{Account, AccountState};
+
+        let (token_account_id, owner_id) = (Pubkey::new_unique(), anchor_spl::token::ID);
+        let mut data = vec![0; Account::LEN];
+        Account::pack(
+            Account {
+                mint: Pubkey::new_unique(),
+                owner: Pubkey::new_unique(),
+                amount: 1234,
+                state: AccountState::Initialized,
+                ..Account::default()
+            },
+            &mut data,
+        )
+        .unwrap();
+        let mut lamports = 0;
+        let token_account = AccountInfo::new(&token_account_id, false, true, &mut lamports, &mut data, &owner_id, false, 0);
+        assert_eq!(token_account_balance(&token_account), Ok(1234));
+    }
+
+    #[test]
+    fn test_transfer_tokens() {
+        use {
+            anchor_lang::solana_program::{
+                entrypoint::ProgramResult,
+                instruction::Instruction,
+                program_pack::Pack,
+                program_stubs::{set_syscall_stubs, SyscallStubs},
+            },
+            anchor_spl::token::spl_token,
+            std::sync::{Arc, Mutex},
+        };
+
+        struct InvokeStubs(Arc<Mutex<Vec<Instruction>>>);
+        impl SyscallStubs for InvokeStubs {
+            fn sol_invoke_signed(&self, instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+                self.0.lock().unwrap().push(instruction.clone());
+                Ok(())
+            }
+        }
+
+        let (from_id, mint_id, to_id, authority_id) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut mint_data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint::pack(
+            spl_token::state::Mint {
+                decimals: 6,
+                is_initialized: true,
+                ..spl_token::state::Mint::default()
+            },
+            &mut mint_data,
+        )
+        .unwrap();
+        let (mut token_program_lamports, mut from_lamports, mut mint_lamports, mut to_lamports, mut authority_lamports) = (0, 0, 0, 0, 0);
+        let (mut token_program_data, mut from_data, mut to_data, mut authority_data) = (vec![], vec![], vec![], vec![]);
+        let system_program_id = anchor_lang::system_program::ID;
+        let token_program = AccountInfo::new(&spl_token::ID, false, false, &mut token_program_lamports, &mut token_program_data, &system_program_id, true, 0);
+        let from = AccountInfo::new(&from_id, false, true, &mut from_lamports, &mut from_data, &spl_token::ID, false, 0);
+        let mint_info = AccountInfo::new(&mint_id, false, false, &mut mint_lamports, &mut mint_data, &spl_token::ID, false, 0);
+        let to = AccountInfo::new(&to_id, false, true, &mut to_lamports, &mut to_data, &spl_token::ID, false, 0);
+        let authority = AccountInfo::new(&authority_id, true, false, &mut authority_lamports, &mut authority_data, &system_program_id, false, 0);
+        let mint = Account::<Mint>::try_from(&mint_info).unwrap();
+
+        let instructions = Arc::new(Mutex::new(vec![]));
+        set_syscall_stubs(Box::new(InvokeStubs(instructions.clone())));
+        transfer_tokens(token_program, from, &mint, to, authority, 1000).unwrap();
+
+        // a single transfer checked against the mint's decimals, signed by the authority
+        let instructions = instructions.lock().unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(
+            instructions[0],
+            spl_token::instruction::transfer_checked(&spl_token::ID, &from_id, &mint_id, &to_id, &authority_id, &[], 1000, 6).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_assert_required_collection() {
+        let mut payment_manager = payment_manager(500, 300);
+        let required_collection = Pubkey::new_unique();
+        let collection = |verified: bool, key: Pubkey| Collection { verified, key };
+        // any mint is accepted without a required collection
+        assert!(assert_required_collection(&payment_manager, None).is_ok());
+        assert!(assert_required_collection(&payment_manager, Some(&collection(false, Pubkey::new_unique()))).is_ok());
+
+        payment_manager.required_collection = Some(required_collection);
+        assert!(assert_required_collection(&payment_manager, Some(&collection(true, required_collection))).is_ok());
+        assert_eq!(
+            assert_required_collection(&payment_manager, Some(&collection(true, Pubkey::new_unique()))),
+            Err(error!(ErrorCode::UnverifiedCollection))
+        );
+        assert_eq!(
+            assert_required_collection(&payment_manager, Some(&collection(false, required_collection))),
+            Err(error!(ErrorCode::UnverifiedCollection))
+        );
+        assert_eq!(assert_required_collection(&payment_manager, None), Err(error!(ErrorCode::UnverifiedCollection)));
+    }
+
+    fn collection_policy(enforce_royalties: bool, enforced_basis_points: u16, allow_waivers: bool) -> CollectionPolicy {
+        CollectionPolicy {
+            bump: 0,
+            collection: Pubkey::new_unique(),
+            enforce_royalties,
+            enforced_basis_points,
+            allow_waivers,
+        }
+    }
+
+    #[test]
+    fn test_mint_collection_policy() {
+        let policy = collection_policy(true, 500, false);
+        let policy_id = find_collection_policy_address(&policy.collection).0;
+        let collection = |verified: bool, key: Pubkey| Collection { verified, key };
+        let mut policy_data = vec![];
+        policy.try_serialize(&mut policy_data).unwrap();
+        let (mut lamports, mut empty_lamports) = (1, 0);
+        let mut empty_data = vec![];
+        let policy_info = AccountInfo::new(&policy_id, false, false, &mut lamports, &mut policy_data, &crate::ID, false, 0);
+        let empty_policy_info = AccountInfo::new(&policy_id, false, false, &mut empty_lamports, &mut empty_data, &anchor_lang::system_program::ID, false, 0);
+
+        let verified_collection = collection(true, policy.collection);
+        assert_eq!(
+            mint_collection_policy(&policy_info, Some(&verified_collection)).unwrap().map(|policy| policy.enforced_basis_points),
+            Some(500)
+        );
+        // the collection authority has not set a policy
+        assert!(mint_collection_policy(&empty_policy_info, Some(&verified_collection)).unwrap().is_none());
+        // the policy of another collection cannot stand in for the mint's own
+        assert_eq!(
+            mint_collection_policy(&policy_info, Some(&collection(true, Pubkey::new_unique()))).err(),
+            Some(error!(ErrorCode::InvalidCollectionPolicy))
+        );
+        // mints that do not provably belong to a collection are governed by no policy
+        assert!(mint_collection_policy(&policy_info, Some(&collection(false, policy.collection))).unwrap().is_none());
+        assert!(mint_collection_policy(&policy_info, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_apply_collection_policy() {
+        // a payment manager that does not charge the seller fee and waives royalties below 1000
+        let mut payment_manager = payment_manager(500, 300);
+        payment_manager.royalty_min_price = Some(1000);
+        assert_eq!(payment_fees(&payment_manager, 10_000, Some(200)).unwrap().total_creators_fee, 400);
+
+        // enforced royalties charge the seller fee, at no less than the enforced rate
+        let mut enforced_payment_manager = payment_manager.clone();
+        apply_collection_policy(&mut enforced_payment_manager, &collection_policy(true, 500, true));
+        assert!(enforced_payment_manager.include_seller_fee_basis_points);
+        assert_eq!(payment_fees(&enforced_payment_manager, 10_000, Some(200)).unwrap().total_creators_fee, 400 + 500);
+        assert_eq!(payment_fees(&enforced_payment_manager, 10_000, Some(700)).unwrap().total_creators_fee, 400 + 700);
+        // waivers are still allowed
+        assert!(royalties_waived(&enforced_payment_manager, 999));
+
+        // a higher floor of the payment manager is kept
+        let mut floored_payment_manager = payment_manager.clone();
+        floored_payment_manager.royalty_floor_basis_points = Some(800);
+        apply_collection_policy(&mut floored_payment_manager, &collection_policy(true, 500, true));
+        assert_eq!(floored_payment_manager.royalty_floor_basis_points, Some(800));
+
+        // without waivers the royalty min price no longer applies
+        let mut unwaived_payment_manager = payment_manager.clone();
+        apply_collection_policy(&mut unwaived_payment_manager, &collection_policy(false, 0, false));
+        assert!(!royalties_waived(&unwaived_payment_manager, 999));
+        assert!(!unwaived_payment_manager.include_seller_fee_basis_points);
+        assert_eq!(unwaived_payment_manager.royalty_floor_basis_points, None);
+    }
+
+    #[test]
+    fn test_validate_creator_shares() {
+        let creator = |share: u8| CreatorShare { address: Pubkey::new_unique(), share };
+        assert!(validate_creator_shares(&[creator(100)]).is_ok());
+        assert!(validate_creator_shares(&[creator(15), creator(30), creator(55)]).is_ok());
+        assert!(validate_creator_shares(&[creator(0), creator(100)]).is_ok());
+        assert_eq!(validate_creator_shares(&[]), Err(error!(ErrorCode::InvalidCreatorConfig)));
+        assert_eq!(validate_creator_shares(&[creator(50), creator(49)]), Err(error!(ErrorCode::InvalidCreatorConfig)));
+        assert_eq!(validate_creator_shares(&[creator(255), creator(255)]), Err(error!(ErrorCode::InvalidCreatorConfig)));
+        assert_eq!(validate_creator_shares(&[creator(20); MAX_CREATORS + 1]), Err(error!(ErrorCode::InvalidCreatorConfig)));
+    }
+
+    #[test]
+    fn test_convert_amount() {
+        assert_eq!(convert_amount(1000, CONVERSION_RATE_DIVISOR), 1000);
+        assert_eq!(convert_amount(1000, CONVERSION_RATE_DIVISOR / 2), 500);
+        assert_eq!(convert_amount(1000, CONVERSION_RATE_DIVISOR * 3), 3000);
+        // rounds down
+        assert_eq!(convert_amount(3, CONVERSION_RATE_DIVISOR / 2), 1);
+        assert_eq!(convert_amount(1, CONVERSION_RATE_DIVISOR - 1), 0);
+        // no intermediate overflow
+        assert_eq!(convert_amount(u64::MAX, CONVERSION_RATE_DIVISOR), u64::MAX);
+    }
+
+    #[test]
+    fn test_stable_value() {
+        // a payment mint worth 2.5 stable units
+        let price = 5 * CONVERSION_RATE_DIVISOR / 2;
+        assert_eq!(stable_value(1000, price), 2500);
+        assert_eq!(stable_value(0, price), 0);
+        assert_eq!(stable_value(1000, 0), 0);
+        // rounds down
+        assert_eq!(stable_value(1, CONVERSION_RATE_DIVISOR - 1), 0);
+        // an out of range value saturates instead of failing the payment
+        assert_eq!(stable_value(u64::MAX, CONVERSION_RATE_DIVISOR), u64::MAX);
+        assert_eq!(stable_value(u64::MAX, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_convert_amount_across_decimals() {
+        // 1 payment mint token with 6 decimals is worth 0.05 royalty mint tokens with 9 decimals
+        let conversion_rate = 50 * CONVERSION_RATE_DIVISOR;
+        assert_eq!(convert_amount(1_000_000, conversion_rate), 50_000_000);
+        // 1 payment mint token with 9 decimals is worth 20 royalty mint tokens with 6 decimals
+        let conversion_rate = CONVERSION_RATE_DIVISOR / 50;
+        assert_eq!(convert_amount(1_000_000_000, conversion_rate), 20_000_000);
+        // dust below one royalty mint base unit rounds down to nothing
+        assert_eq!(convert_amount(49, conversion_rate), 0);
+        assert_eq!(convert_amount(50, conversion_rate), 1);
+    }
+
+    #[test]
+    fn test_thresholds_apply_in_payment_mint_before_conversion() {
+        // payment mint with 6 decimals, royalty mint with 9 decimals at 0.05 royalty mint tokens
+        // per payment mint token
+        let conversion_rate = 50 * CONVERSION_RATE_DIVISOR;
+        let mut payment_manager = payment_manager(500, 300);
+        // 1 and 0.01 payment mint tokens
+        payment_manager.royalty_min_price = Some(1_000_000);
+        payment_manager.max_creators_fee = Some(10_000);
+
+        // the min price is compared to the payment amount in payment mint base units
+        assert!(royalties_waived(&payment_manager, 999_999));
+        assert!(!royalties_waived(&payment_manager, 1_000_000));
+        // a min price in royalty mint base units would have waived this payment
+        assert!(!royalties_waived(&payment_manager, 1_000_000 * 50 - 1));
+
+        // the cap is applied in payment mint base units and only then converted
+        let payment_amount = 10_000_000;
+        let (maker_fee, taker_fee) = maker_taker_fees(&payment_manager, payment_amount);
+        let total_creators_fee = creators_fee_amount(&payment_manager, maker_fee + taker_fee, 0);
+        assert_eq!(total_creators_fee, 10_000);
+        assert_eq!(convert_amount(total_creators_fee, conversion_rate), 500_000);
+
+        // below the cap the converted fee scales with the payment
+        payment_manager.max_creators_fee = None;
+        let total_creators_fee = creators_fee_amount(&payment_manager, maker_fee + taker_fee, 0);
+        assert_eq!(total_creators_fee, 400_000);
+        assert_eq!(convert_amount(total_creators_fee, conversion_rate), 20_000_000);
+    }
+
+    #[test]
+    fn test_quote_payment_amount() {
+        assert_eq!(quote_payment_amount(1000, CONVERSION_RATE_DIVISOR), 1000);
+        assert_eq!(quote_payment_amount(1000, CONVERSION_RATE_DIVISOR / 2), 500);
+        assert_eq!(quote_payment_amount(1000, CONVERSION_RATE_DIVISOR * 3), 3000);
+        // rounds up
+        assert_eq!(quote_payment_amount(3, CONVERSION_RATE_DIVISOR / 2), 2);
+        assert_eq!(quote_payment_amount(1, 1), 1);
+        assert_eq!(quote_payment_amount(0, CONVERSION_RATE_DIVISOR), 0);
+        // no intermediate overflow
+        assert_eq!(quote_payment_amount(u64::MAX, CONVERSION_RATE_DIVISOR), u64::MAX);
+    }
+
+    #[test]
+    fn test_validate_conversion_rate() {
+        let bounds = Some(ConversionRateBounds {
+            min_conversion_rate: CONVERSION_RATE_DIVISOR / 2,
+            max_conversion_rate: CONVERSION_RATE_DIVISOR * 2,
+        });
+        assert!(validate_conversion_rate(CONVERSION_RATE_DIVISOR, &bounds).is_ok());
+        assert!(validate_conversion_rate(CONVERSION_RATE_DIVISOR / 2, &bounds).is_ok());
+        assert!(validate_conversion_rate(CONVERSION_RATE_DIVISOR * 2, &bounds).is_ok());
+        assert_eq!(validate_conversion_rate(CONVERSION_RATE_DIVISOR / 2 - 1, &bounds), Err(error!(ErrorCode::InvalidConversionRate)));
+        assert_eq!(validate_conversion_rate(CONVERSION_RATE_DIVISOR * 2 + 1, &bounds), Err(error!(ErrorCode::InvalidConversionRate)));
+        assert_eq!(validate_conversion_rate(CONVERSION_RATE_DIVISOR, &None), Err(error!(ErrorCode::InvalidConversionRate)));
+        let zero_bounds = Some(ConversionRateBounds {
+            min_conversion_rate: 0,
+            max_conversion_rate: CONVERSION_RATE_DIVISOR,
+        });
+        assert_eq!(validate_conversion_rate(0, &zero_bounds), Err(error!(ErrorCode::InvalidConversionRate)));
+    }
+
+    #[test]
+    fn test_admin_signer_count() {
+        let admins = vec![Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let outsider = Pubkey::new_unique();
+        assert_eq!(admin_signer_count(&admins, &[]), 0);
+        assert_eq!(admin_signer_count(&admins, &[admins[0]]), 1);
+        assert_eq!(admin_signer_count(&admins, &[admins[0], outsider]), 1);
+        assert_eq!(admin_signer_count(&admins, &[admins[0], admins[2]]), 2);
+        assert_eq!(admin_signer_count(&admins, &[admins[0], admins[1], admins[2]]), 3);
+        // an admin listed twice signs once
+        let duplicate_admins = vec![admins[0], admins[0], admins[1]];
+        assert_eq!(admin_signer_count(&duplicate_admins, &[admins[0]]), 1);
+        assert_eq!(admin_signer_count(&duplicate_admins, &[admins[0], admins[1]]), 2);
+    }
+
+    #[test]
+    fn test_validate_admins() {
+        let admins = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        assert!(validate_admins(&[], None).is_ok());
+        assert!(validate_admins(&admins, Some(1)).is_ok());
+        assert!(validate_admins(&admins, Some(2)).is_ok());
+        assert_eq!(validate_admins(&admins, Some(0)), Err(error!(ErrorCode::InvalidAuthorityThreshold)));
+        assert_eq!(validate_admins(&admins, Some(3)), Err(error!(ErrorCode::InvalidAuthorityThreshold)));
+        assert_eq!(
+            validate_admins(&(0..=MAX_ADMINS).map(|_| Pubkey::new_unique()).collect::<Vec<_>>(), None),
+            Err(error!(ErrorCode::InvalidAuthorityThreshold))
+        );
+        // one admin listed twice cannot meet a threshold of two alone
+        assert_eq!(validate_admins(&[admins[0], admins[0], admins[1]], Some(2)), Err(error!(ErrorCode::InvalidAuthorityThreshold)));
+        assert_eq!(validate_admins(&[admins[0], admins[0]], None), Err(error!(ErrorCode::InvalidAuthorityThreshold)));
+    }
+
+    #[test]
+    fn test_validate_old_escrow_seeds() {
+        let payment_manager = Pubkey::new_unique();
+        let old_seeds = vec![b"escrow".to_vec(), payment_manager.to_bytes().to_vec()];
+        assert!(validate_old_escrow_seeds(&old_seeds, &payment_manager).is_ok());
+        assert_eq!(validate_old_escrow_seeds(&[], &payment_manager), Err(error!(ErrorCode::InvalidEscrowSeeds)));
+        // another payment manager's escrow
+        assert_eq!(validate_old_escrow_seeds(&old_seeds, &Pubkey::new_unique()), Err(error!(ErrorCode::InvalidEscrowSeeds)));
+        assert_eq!(validate_old_escrow_seeds(&[b"escrow".to_vec()], &payment_manager), Err(error!(ErrorCode::InvalidEscrowSeeds)));
+        // escrows still in use
+        for live_seed in LIVE_SEEDS {
+            let live_seeds = vec![live_seed.as_bytes().to_vec(), payment_manager.to_bytes().to_vec()];
+            assert_eq!(validate_old_escrow_seeds(&live_seeds, &payment_manager), Err(error!(ErrorCode::InvalidEscrowSeeds)));
+            // split over two seeds, the address derived is the same
+            let (head, tail) = live_seed.as_bytes().split_at(live_seed.len() / 2);
+            let split_seeds = vec![head.to_vec(), tail.to_vec(), payment_manager.to_bytes().to_vec()];
+            assert_eq!(validate_old_escrow_seeds(&split_seeds, &payment_manager), Err(error!(ErrorCode::InvalidEscrowSeeds)));
+            // behind an empty seed
+            let prefixed_seeds = vec![vec![], live_seed.as_bytes().to_vec(), payment_manager.to_bytes().to_vec()];
+            assert_eq!(validate_old_escrow_seeds(&prefixed_seeds, &payment_manager), Err(error!(ErrorCode::InvalidEscrowSeeds)));
+        }
+        // the live proceeds escrow split as ["proceeds-", "escrow", pm, mint, seller]
+        let proceeds_escrow_seeds = vec![
+            b"proceeds-".to_vec(),
+            b"escrow".to_vec(),
+            payment_manager.to_bytes().to_vec(),
+            Pubkey::new_unique().to_bytes().to_vec(),
+            Pubkey::new_unique().to_bytes().to_vec(),
+        ];
+        assert_eq!(proceeds_escrow_seeds[..2].concat(), PROCEEDS_ESCROW_SEED.as_bytes());
+        assert_eq!(validate_old_escrow_seeds(&proceeds_escrow_seeds, &payment_manager), Err(error!(ErrorCode::InvalidEscrowSeeds)));
+        // a live seed with more bytes in the same seed
+        let suffixed_seeds = vec![format!("{}-v0", ROYALTY_ESCROW_SEED).into_bytes(), payment_manager.to_bytes().to_vec()];
+        assert_eq!(validate_old_escrow_seeds(&suffixed_seeds, &payment_manager), Err(error!(ErrorCode::InvalidEscrowSeeds)));
+    }
+
+    #[test]
+    fn test_assert_accepted_mint() {
+        let mut payment_manager = payment_manager(500, 300);
+        let (accepted_mint, other_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // any mint without a list
+        assert!(assert_accepted_mint(&payment_manager, &other_mint).is_ok());
+        assert!(assert_accepted_mint(&payment_manager, &Pubkey::default()).is_ok());
+
+        payment_manager.accepted_mints = vec![accepted_mint];
+        assert!(assert_accepted_mint(&payment_manager, &accepted_mint).is_ok());
+        assert_eq!(assert_accepted_mint(&payment_manager, &other_mint), Err(error!(ErrorCode::UnsupportedPaymentMint)));
+        // native payments have to be listed as well
+        assert_eq!(assert_accepted_mint(&payment_manager, &Pubkey::default()), Err(error!(ErrorCode::UnsupportedPaymentMint)));
+        payment_manager.accepted_mints.push(Pubkey::default());
+        assert!(assert_accepted_mint(&payment_manager, &Pubkey::default()).is_ok());
+    }
+
+    #[test]
+    fn test_assert_payment_remaining_accounts() {
+        let keys: Vec<Pubkey> = (0..=MAX_ESCROWED_PAYMENT_REMAINING_ACCOUNTS).map(|_| Pubkey::new_unique()).collect();
+        let mut lamports = vec![0; keys.len()];
+        let mut data = vec![vec![]; keys.len()];
+        let owner = anchor_lang::system_program::ID;
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| AccountInfo::new(key, false, true, lamports, data, &owner, false, 0))
+            .collect();
+        let mut payment_manager = payment_manager(500, 300);
+        assert!(assert_payment_remaining_accounts(&payment_manager, &[]).is_ok());
+        // every creator, the taker fee collector and the buy side
+        assert!(assert_payment_remaining_accounts(&payment_manager, &accounts[..MAX_PAYMENT_REMAINING_ACCOUNTS]).is_ok());
+        assert_eq!(
+            assert_payment_remaining_accounts(&payment_manager, &accounts[..=MAX_PAYMENT_REMAINING_ACCOUNTS]),
+            Err(error!(ErrorCode::TooManyAccounts))
+        );
+
+        // 5 creators each passing their token account and royalty claim
+        payment_manager.escrow_unreceivable_royalties = true;
+        assert_eq!(MAX_CREATORS, 5);
+        assert!(assert_payment_remaining_accounts(&payment_manager, &accounts[..2 * 5 + 2]).is_ok());
+        assert_eq!(assert_payment_remaining_accounts(&payment_manager, &accounts), Err(error!(ErrorCode::TooManyAccounts)));
+    }
+
+    #[test]
+    fn test_validate_fee_collector() {
+        assert!(validate_fee_collector(&Pubkey::new_unique()).is_ok());
+        assert_eq!(validate_fee_collector(&Pubkey::default()), Err(error!(ErrorCode::InvalidFeeCollector)));
+    }
+
+    /// Extreme inputs for the fee helpers, each helper either returns the expected amount or an
+    /// error but never panics
+    mod safe_math {
+        use {super::*, proptest::prelude::*};
+
+        fn amount() -> impl Strategy<Value = u64> {
+            prop_oneof![
+                Just(0),
+                Just(1),
+                Just(u64::MAX / u64::from(BASIS_POINTS_DIVISOR)),
+                Just(u64::MAX / u64::from(BASIS_POINTS_DIVISOR) + 1),
+                Just(u64::MAX / 2),
+                Just(u64::MAX - 1),
+                Just(u64::MAX),
+                any::<u64>(),
+            ]
+        }
+
+        fn basis_points() -> impl Strategy<Value = u16> {
+            prop_oneof![Just(0), Just(1), Just(BASIS_POINTS_DIVISOR - 1), Just(BASIS_POINTS_DIVISOR), 0..=BASIS_POINTS_DIVISOR]
+        }
+
+        fn royalty_payment_manager(maker_fee_basis_points: u16, taker_fee_basis_points: u16, max_royalty_basis_points: u16, clamp_royalties: bool) -> PaymentManager {
+            let mut payment_manager = payment_manager(maker_fee_basis_points, taker_fee_basis_points);
+            payment_manager.include_seller_fee_basis_points = true;
+            payment_manager.royalty_fee_share = Some(BASIS_POINTS_DIVISOR.into());
+            payment_manager.max_royalty_basis_points = Some(max_royalty_basis_points);
+            payment_manager.clamp_royalties = clamp_royalties;
+            payment_manager
+        }
+
+        proptest! {
+            #[test]
+            fn test_basis_points_fee(amount in amount(), basis_points in basis_points()) {
+                let fee = basis_points_fee(amount, basis_points);
+                prop_assert!(fee <= amount);
+                prop_assert_eq!(u128::from(fee), u128::from(amount) * u128::from(basis_points) / u128::from(BASIS_POINTS_DIVISOR));
+            }
+
+            #[test]
+            fn test_buy_side_fee_amount(payment_amount in amount(), buy_side_fee_share in any::<u64>()) {
+                prop_assert!(buy_side_fee_amount(payment_amount, buy_side_fee_share) <= payment_amount);
+            }
+
+            #[test]
+            fn test_maker_taker_fees(payment_amount in amount(), maker_fee_basis_points in basis_points(), taker_fee_basis_points in basis_points()) {
+                let (maker_fee, taker_fee) = maker_taker_fees(&payment_manager(maker_fee_basis_points, taker_fee_basis_points), payment_amount);
+                prop_assert!(maker_fee <= payment_amount && taker_fee <= payment_amount);
+            }
+
+            #[test]
+            fn test_seller_fee_amount(
+                payment_amount in amount(),
+                max_royalty_basis_points in basis_points(),
+                metadata_seller_fee_basis_points in any::<u16>(),
+                clamp_royalties in any::<bool>(),
+            ) {
+                let payment_manager = royalty_payment_manager(0, 0, max_royalty_basis_points, clamp_royalties);
+                match seller_fee_amount(&payment_manager, payment_amount, metadata_seller_fee_basis_points) {
+                    Ok(seller_fee) => prop_assert!(seller_fee <= basis_points_fee(payment_amount, max_royalty_basis_points)),
+                    Err(error) if metadata_seller_fee_basis_points > BASIS_POINTS_DIVISOR => {
+                        prop_assert_eq!(error, error!(ErrorCode::InvalidSellerFee));
+                    }
+                    Err(error) => {
+                        prop_assert!(!clamp_royalties && metadata_seller_fee_basis_points > max_royalty_basis_points);
+                        prop_assert_eq!(error, error!(ErrorCode::RoyaltiesExceedLimit));
+                    }
+                }
+            }
+
+            #[test]
+            fn test_creators_fee_amount(
+                payment_amount in amount(),
+                maker_fee_basis_points in 0..=BASIS_POINTS_DIVISOR - u16::try_from(DEFAULT_BUY_SIDE_FEE_SHARE).unwrap(),
+                taker_fee_basis_points in basis_points(),
+                metadata_seller_fee_basis_points in basis_points(),
+            ) {
+                // the largest seller fee a valid fee config leaves room for
+                let max_royalty_basis_points = BASIS_POINTS_DIVISOR - u16::try_from(DEFAULT_BUY_SIDE_FEE_SHARE).unwrap() - maker_fee_basis_points;
+                let payment_manager = royalty_payment_manager(maker_fee_basis_points, taker_fee_basis_points, max_royalty_basis_points, true);
+                prop_assert!(validate_fee_config(&payment_manager).is_ok());
+                let (maker_fee, taker_fee) = maker_taker_fees(&payment_manager, payment_amount);
+                // the payer cannot be debited the payment and the taker fee past u64::MAX
+                if payment_amount.checked_add(taker_fee).is_none() {
+                    prop_assert_eq!(
+                        payment_fees(&payment_manager, payment_amount, Some(metadata_seller_fee_basis_points)).err(),
+                        Some(error!(ErrorCode::ArithmeticOverflow))
+                    );
+                    return Ok(());
+                }
+                let seller_fee = seller_fee_amount(&payment_manager, payment_amount, metadata_seller_fee_basis_points).unwrap();
+                prop_assert!(maker_fee + seller_fee <= payment_amount);
+                prop_assert_eq!(creators_fee_amount(&payment_manager, maker_fee + taker_fee, seller_fee), maker_fee + taker_fee + seller_fee);
+            }
+
+            #[test]
+            fn test_exact_output_payment_amount(
+                net_amount in amount(),
+                maker_fee_basis_points in basis_points(),
+                metadata_seller_fee_basis_points in proptest::option::of(basis_points()),
+            ) {
+                let payment_manager = royalty_payment_manager(maker_fee_basis_points, 0, BASIS_POINTS_DIVISOR, true);
+                match exact_output_payment_amount(&payment_manager, net_amount, metadata_seller_fee_basis_points) {
+                    Ok(payment_amount) => {
+                        let seller_fee = metadata_seller_fee_basis_points.map_or(0, |basis_points| basis_points_fee(payment_amount, basis_points));
+                        let fees = u128::from(basis_points_fee(payment_amount, maker_fee_basis_points))
+                            + u128::from(seller_fee)
+                            + u128::from(payment_amount) * u128::from(DEFAULT_BUY_SIDE_FEE_SHARE) / u128::from(BASIS_POINTS_DIVISOR);
+                        prop_assert!(u128::from(payment_amount) >= u128::from(net_amount) + fees);
+                    }
+                    Err(error) => prop_assert!(error == error!(ErrorCode::ArithmeticOverflow) || error == error!(ErrorCode::FeeConfigExceedsLimit)),
+                }
+            }
+
+            #[test]
+            fn test_creator_payouts(total_creators_fee in amount(), shares in proptest::collection::vec(0..=100u8, 0..=MAX_CREATORS), remainder_to_collector in any::<bool>()) {
+                prop_assume!(shares.iter().map(|share| u16::from(*share)).sum::<u16>() <= 100);
+                let creators: Vec<Creator> = shares.iter().map(|share| metadata_creator(*share)).collect();
+                let paid_out: u128 = creator_payouts(&creators, total_creators_fee, remainder_to_collector).iter().map(|(_, amount)| u128::from(*amount)).sum();
+                prop_assert!(paid_out <= u128::from(total_creators_fee));
+            }
+
+            #[test]
+            fn test_payment_fees_conserve_the_payment(
+                payment_amount in amount(),
+                maker_fee_basis_points in basis_points(),
+                taker_fee_basis_points in basis_points(),
+                metadata_seller_fee_basis_points in proptest::option::of(basis_points()),
+                include_seller_fee_basis_points in any::<bool>(),
+                seller_fee_additive in any::<bool>(),
+                royalty_fee_share in proptest::option::of(0..=u64::from(BASIS_POINTS_DIVISOR)),
+                max_creators_fee in proptest::option::of(amount()),
+                has_taker_fee_collector in any::<bool>(),
+                taker_covers_maker_fee in any::<bool>(),
+                buy_side_additive in any::<bool>(),
+                remainder_to_collector in any::<bool>(),
+                shares in proptest::option::of(proptest::collection::vec(0..=CREATOR_SHARE_DIVISOR, 0..=MAX_CREATORS)),
+                fee_mode in 0..=FeeMode::FlatPlusBasisPoints as u8,
+                flat_fee_amount in proptest::option::of(amount()),
+            ) {
+                let mut payment_manager = payment_manager(maker_fee_basis_points, taker_fee_basis_points);
+                payment_manager.fee_mode = fee_mode;
+                payment_manager.flat_fee_amount = flat_fee_amount;
+                payment_manager.include_seller_fee_basis_points = include_seller_fee_basis_points;
+                payment_manager.seller_fee_within_fees = !seller_fee_additive;
+                payment_manager.royalty_fee_share = royalty_fee_share;
+                payment_manager.max_creators_fee = max_creators_fee;
+                payment_manager.clamp_royalties = true;
+                payment_manager.taker_fee_collector = has_taker_fee_collector.then(Pubkey::new_unique);
+                payment_manager.taker_covers_maker_fee = taker_covers_maker_fee;
+                payment_manager.buy_side_additive = buy_side_additive;
+                payment_manager.remainder_to_collector = remainder_to_collector;
+                prop_assume!(validate_fee_config(&payment_manager).is_ok());
+                prop_assume!(shares.as_ref().map_or(true, |shares| shares.iter().map(|share| u16::from(*share)).sum::<u16>() <= CREATOR_SHARE_DIVISOR.into()));
+                let (maker_fee, taker_fee) = maker_taker_fees(&payment_manager, payment_amount);
+                let buy_side_fee = buy_side_fee_amount(payment_amount, DEFAULT_BUY_SIDE_FEE_SHARE);
+                let additive_seller_fee = metadata_seller_fee_basis_points
+                    .filter(|_| seller_fee_additive)
+                    .map_or(Ok(0), |basis_points| seller_fee_amount(&payment_manager, payment_amount, basis_points))
+                    .unwrap();
+                // neither what the payer is debited nor the fees carved out of it can add up past u64::MAX
+                let fees_on_top = if taker_covers_maker_fee { u128::from(maker_fee) + u128::from(taker_fee) } else { u128::from(taker_fee) };
+                let payer_amount = u128::from(payment_amount) + fees_on_top + if buy_side_additive { u128::from(buy_side_fee) } else { 0 };
+                let carved_out_fees = u128::from(maker_fee) + u128::from(taker_fee) + u128::from(additive_seller_fee) + u128::from(buy_side_fee);
+                let payable = payer_amount <= u128::from(u64::MAX) && carved_out_fees <= u128::from(u64::MAX);
+
+                let fees = match payment_fees(&payment_manager, payment_amount, metadata_seller_fee_basis_points) {
+                    Ok(fees) => fees,
+                    Err(error) if error == error!(ErrorCode::ArithmeticOverflow) => {
+                        prop_assert!(!payable);
+                        return Ok(());
+                    }
+                    // only a flat maker fee can leave the payment too little for the other fees
+                    Err(error) => {
+                        prop_assert!(is_flat_fee_mode(&payment_manager));
+                        prop_assert_eq!(error, error!(ErrorCode::FeeConfigExceedsLimit));
+                        return Ok(());
+                    }
+                };
+                prop_assert!(payable);
+                prop_assert!(fees.total_creators_fee <= fees.total_fees - fees.taker_fee_collector_fee);
+                let creators: Option<Vec<Creator>> = shares.map(|shares| shares.iter().map(|share| metadata_creator(*share)).collect());
+                let fees_paid_out = creators.map_or(0, |creators| creator_payouts(&creators, fees.total_creators_fee, payment_manager.remainder_to_collector).iter().map(|(_, amount)| *amount).sum());
+                // whatever the creators and the taker fee collector do not receive is left to the fee collector
+                let fee_collector_fee = fees.total_fees - fees.taker_fee_collector_fee - fees_paid_out;
+                let outflow = u128::from(fee_collector_fee)
+                    + u128::from(fees_paid_out)
+                    + u128::from(fees.taker_fee_collector_fee)
+                    + u128::from(fees.buy_side_fee)
+                    + u128::from(fees.principal_amount);
+                prop_assert_eq!(
+                    outflow,
+                    u128::from(payment_amount) + u128::from(payer_fees(&payment_manager, maker_fee, taker_fee)) + u128::from(payer_buy_side_fee(&payment_manager, fees.buy_side_fee))
+                );
+            }
+        }
+    }
+}
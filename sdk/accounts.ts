@@ -2,7 +2,18 @@ import type { Connection, PublicKey } from "@solana/web3.js";
 import type { AccountData } from "@solana-nft-programs/common";
 import { fetchIdlAccount } from "@solana-nft-programs/common";
 
-import type { PAYMENT_MANAGER_PROGRAM, PaymentManagerData } from ".";
+import type {
+  CollectionPolicyData,
+  CreatorConfigData,
+  ExemptPayerData,
+  PayerStatsData,
+  PAYMENT_MANAGER_PROGRAM,
+  PaymentManagerData,
+  PaymentRecordData,
+  PriceOracleData,
+  ProtocolConfigData,
+  RoyaltyClaimData,
+} from ".";
 import { PAYMENT_MANAGER_IDL } from ".";
 
 export const getPaymentManager = async (
@@ -16,3 +27,99 @@ export const getPaymentManager = async (
     PAYMENT_MANAGER_IDL
   );
 };
+
+export const getCreatorConfig = async (
+  connection: Connection,
+  creatorConfigId: PublicKey
+): Promise<AccountData<CreatorConfigData>> => {
+  return fetchIdlAccount<"creatorConfig", PAYMENT_MANAGER_PROGRAM>(
+    connection,
+    creatorConfigId,
+    "creatorConfig",
+    PAYMENT_MANAGER_IDL
+  );
+};
+
+export const getProtocolConfig = async (
+  connection: Connection,
+  protocolConfigId: PublicKey
+): Promise<AccountData<ProtocolConfigData>> => {
+  return fetchIdlAccount<"protocolConfig", PAYMENT_MANAGER_PROGRAM>(
+    connection,
+    protocolConfigId,
+    "protocolConfig",
+    PAYMENT_MANAGER_IDL
+  );
+};
+
+export const getPaymentRecord = async (
+  connection: Connection,
+  paymentRecordId: PublicKey
+): Promise<AccountData<PaymentRecordData>> => {
+  return fetchIdlAccount<"paymentRecord", PAYMENT_MANAGER_PROGRAM>(
+    connection,
+    paymentRecordId,
+    "paymentRecord",
+    PAYMENT_MANAGER_IDL
+  );
+};
+
+export const getExemptPayer = async (
+  connection: Connection,
+  exemptPayerId: PublicKey
+): Promise<AccountData<ExemptPayerData>> => {
+  return fetchIdlAccount<"exemptPayer", PAYMENT_MANAGER_PROGRAM>(
+    connection,
+    exemptPayerId,
+    "exemptPayer",
+    PAYMENT_MANAGER_IDL
+  );
+};
+
+export const getRoyaltyClaim = async (
+  connection: Connection,
+  royaltyClaimId: PublicKey
+): Promise<AccountData<RoyaltyClaimData>> => {
+  return fetchIdlAccount<"royaltyClaim", PAYMENT_MANAGER_PROGRAM>(
+    connection,
+    royaltyClaimId,
+    "royaltyClaim",
+    PAYMENT_MANAGER_IDL
+  );
+};
+
+export const getPayerStats = async (
+  connection: Connection,
+  payerStatsId: PublicKey
+): Promise<AccountData<PayerStatsData>> => {
+  return fetchIdlAccount<"payerStats", PAYMENT_MANAGER_PROGRAM>(
+    connection,
+    payerStatsId,
+    "payerStats",
+    PAYMENT_MANAGER_IDL
+  );
+};
+
+export const getPriceOracle = async (
+  connection: Connection,
+  priceOracleId: PublicKey
+): Promise<AccountData<PriceOracleData>> => {
+  return fetchIdlAccount<"priceOracle", PAYMENT_MANAGER_PROGRAM>(
+    connection,
+    priceOracleId,
+    "priceOracle",
+    PAYMENT_MANAGER_IDL
+  );
+};
+
+export const getCollectionPolicy = async (
+  connection: Connection,
+  collectionPolicyId: PublicKey
+): Promise<AccountData<CollectionPolicyData>> => {
+  return fetchIdlAccount<"collectionPolicy", PAYMENT_MANAGER_PROGRAM>(
+    connection,
+    collectionPolicyId,
+    "collectionPolicy",
+    PAYMENT_MANAGER_IDL
+  );
+};
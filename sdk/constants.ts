@@ -8,7 +8,22 @@ import { emptyWallet } from "@solana-nft-programs/common";
 import * as PAYMENT_MANAGER_TYPES from "./idl/solana_nft_programs_payment_manager";
 
 export const BASIS_POINTS_DIVISOR = 10000;
+export const CREATOR_SHARE_DIVISOR = 100;
 export const DEFAULT_BUY_SIDE_FEE_SHARE = 50;
+export const CONVERSION_RATE_DIVISOR = 1_000_000_000;
+export const DEFAULT_MAX_ROYALTY_BASIS_POINTS = 5000;
+
+export enum PayoutOrder {
+  CreatorsFirst = 0,
+  PrincipalFirst = 1,
+}
+
+export enum FeeMode {
+  BasisPoints = 0,
+  Flat = 1,
+  // the flat fee amount is charged on top of the basis points fees
+  FlatPlusBasisPoints = 2,
+}
 
 export const PAYMENT_MANAGER_ADDRESS = new PublicKey(
   "pmvYY6Wgvpe3DEj3UX1FcRpMx43sMLYLJrFTVGcqpdn"
@@ -19,6 +34,17 @@ export const CRANK_KEY = new PublicKey(
 );
 
 export const PAYMENT_MANAGER_SEED = "payment-manager";
+export const CREATOR_CONFIG_SEED = "creator-config";
+export const PROTOCOL_CONFIG_SEED = "protocol-config";
+export const PAYER_RATE_LIMIT_SEED = "payer-rate-limit";
+export const PAYER_STATS_SEED = "payer-stats";
+export const PROCEEDS_ESCROW_SEED = "proceeds-escrow";
+export const PAYMENT_RECORD_SEED = "payment-record";
+export const EXEMPT_PAYER_SEED = "exempt-payer";
+export const ROYALTY_ESCROW_SEED = "royalty-escrow";
+export const ROYALTY_CLAIM_SEED = "royalty-claim";
+export const PRICE_ORACLE_SEED = "price-oracle";
+export const COLLECTION_POLICY_SEED = "collection-policy";
 export const DEFAULT_PAYMENT_MANAGER_NAME = "foobar";
 
 export const PAYMENT_MANAGER_IDL = PAYMENT_MANAGER_TYPES.IDL;
@@ -31,6 +57,46 @@ export type PaymentManagerData = ParsedIdlAccountData<
   PAYMENT_MANAGER_PROGRAM
 >;
 
+export type CreatorConfigData = ParsedIdlAccountData<
+  "creatorConfig",
+  PAYMENT_MANAGER_PROGRAM
+>;
+
+export type ProtocolConfigData = ParsedIdlAccountData<
+  "protocolConfig",
+  PAYMENT_MANAGER_PROGRAM
+>;
+
+export type PaymentRecordData = ParsedIdlAccountData<
+  "paymentRecord",
+  PAYMENT_MANAGER_PROGRAM
+>;
+
+export type ExemptPayerData = ParsedIdlAccountData<
+  "exemptPayer",
+  PAYMENT_MANAGER_PROGRAM
+>;
+
+export type RoyaltyClaimData = ParsedIdlAccountData<
+  "royaltyClaim",
+  PAYMENT_MANAGER_PROGRAM
+>;
+
+export type PayerStatsData = ParsedIdlAccountData<
+  "payerStats",
+  PAYMENT_MANAGER_PROGRAM
+>;
+
+export type PriceOracleData = ParsedIdlAccountData<
+  "priceOracle",
+  PAYMENT_MANAGER_PROGRAM
+>;
+
+export type CollectionPolicyData = ParsedIdlAccountData<
+  "collectionPolicy",
+  PAYMENT_MANAGER_PROGRAM
+>;
+
 export const paymentManagerProgram = (
   connection: Connection,
   wallet?: Wallet,
@@ -1,7 +1,26 @@
 import { utils } from "@coral-xyz/anchor";
-import { PublicKey } from "@solana/web3.js";
+import {
+  BPF_LOADER_UPGRADEABLE_PROGRAM_ID,
+  PublicKey,
+} from "@solana/web3.js";
+import { findMintMetadataId } from "@solana-nft-programs/common";
+import type BN from "bn.js";
 
-import { PAYMENT_MANAGER_ADDRESS, PAYMENT_MANAGER_SEED } from ".";
+import {
+  COLLECTION_POLICY_SEED,
+  CREATOR_CONFIG_SEED,
+  EXEMPT_PAYER_SEED,
+  PAYER_RATE_LIMIT_SEED,
+  PAYER_STATS_SEED,
+  PAYMENT_MANAGER_ADDRESS,
+  PAYMENT_MANAGER_SEED,
+  PAYMENT_RECORD_SEED,
+  PRICE_ORACLE_SEED,
+  PROCEEDS_ESCROW_SEED,
+  PROTOCOL_CONFIG_SEED,
+  ROYALTY_CLAIM_SEED,
+  ROYALTY_ESCROW_SEED,
+} from ".";
 
 /**
  * Finds the address of the payment manager.
@@ -16,3 +35,233 @@ export const findPaymentManagerAddress = (name: string): PublicKey => {
     PAYMENT_MANAGER_ADDRESS
   )[0];
 };
+
+/**
+ * Finds the address of the creator config for a mint.
+ * @returns
+ */
+export const findCreatorConfigAddress = (
+  paymentManagerId: PublicKey,
+  mintId: PublicKey
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(CREATOR_CONFIG_SEED),
+      paymentManagerId.toBuffer(),
+      mintId.toBuffer(),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the protocol config.
+ * @returns
+ */
+export const findProtocolConfigAddress = (): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [utils.bytes.utf8.encode(PROTOCOL_CONFIG_SEED)],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the program data address of the payment manager program, which holds
+ * its upgrade authority.
+ * @returns
+ */
+export const findProgramDataAddress = (): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [PAYMENT_MANAGER_ADDRESS.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+  )[0];
+};
+
+/**
+ * Finds the address of the rate limit of a payer.
+ * @returns
+ */
+export const findPayerRateLimitAddress = (
+  paymentManagerId: PublicKey,
+  payerId: PublicKey
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(PAYER_RATE_LIMIT_SEED),
+      paymentManagerId.toBuffer(),
+      payerId.toBuffer(),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the cumulative volume of a payer.
+ * @returns
+ */
+export const findPayerStatsAddress = (
+  paymentManagerId: PublicKey,
+  payerId: PublicKey
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(PAYER_STATS_SEED),
+      paymentManagerId.toBuffer(),
+      payerId.toBuffer(),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the proceeds escrow of a seller.
+ * @returns
+ */
+export const findProceedsEscrowAddress = (
+  paymentManagerId: PublicKey,
+  paymentMintId: PublicKey,
+  sellerId: PublicKey
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(PROCEEDS_ESCROW_SEED),
+      paymentManagerId.toBuffer(),
+      paymentMintId.toBuffer(),
+      sellerId.toBuffer(),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the payment record of a payer's payment for a mint
+ * made with the given record nonce.
+ * @returns
+ */
+export const findPaymentRecordAddress = (
+  paymentManagerId: PublicKey,
+  payerId: PublicKey,
+  mintId: PublicKey,
+  recordNonce: BN
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(PAYMENT_RECORD_SEED),
+      paymentManagerId.toBuffer(),
+      payerId.toBuffer(),
+      mintId.toBuffer(),
+      recordNonce.toArrayLike(Buffer, "le", 8),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the fee exemption of a payer.
+ * @returns
+ */
+export const findExemptPayerAddress = (
+  paymentManagerId: PublicKey,
+  payerId: PublicKey
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(EXEMPT_PAYER_SEED),
+      paymentManagerId.toBuffer(),
+      payerId.toBuffer(),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the royalty escrow holding claimed royalties of a payment mint.
+ * @returns
+ */
+export const findRoyaltyEscrowAddress = (
+  paymentManagerId: PublicKey,
+  paymentMintId: PublicKey
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(ROYALTY_ESCROW_SEED),
+      paymentManagerId.toBuffer(),
+      paymentMintId.toBuffer(),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the royalty claim of a creator for a payment mint.
+ * @returns
+ */
+export const findRoyaltyClaimAddress = (
+  paymentManagerId: PublicKey,
+  paymentMintId: PublicKey,
+  creatorId: PublicKey
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(ROYALTY_CLAIM_SEED),
+      paymentManagerId.toBuffer(),
+      paymentMintId.toBuffer(),
+      creatorId.toBuffer(),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the price oracle of a payment mint.
+ * @returns
+ */
+export const findPriceOracleAddress = (
+  paymentManagerId: PublicKey,
+  paymentMintId: PublicKey
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(PRICE_ORACLE_SEED),
+      paymentManagerId.toBuffer(),
+      paymentMintId.toBuffer(),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the royalty policy of a collection.
+ * @returns
+ */
+export const findCollectionPolicyAddress = (
+  collectionMintId: PublicKey
+): PublicKey => {
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode(COLLECTION_POLICY_SEED),
+      collectionMintId.toBuffer(),
+    ],
+    PAYMENT_MANAGER_ADDRESS
+  )[0];
+};
+
+/**
+ * Finds the address of the metadata of a mint under the metadata program of a
+ * payment manager, the Metaplex metadata address when none is configured.
+ * @returns
+ */
+export const findMintMetadataAddress = (
+  mintId: PublicKey,
+  metadataProgramId?: PublicKey | null
+): PublicKey => {
+  if (!metadataProgramId) return findMintMetadataId(mintId);
+  return PublicKey.findProgramAddressSync(
+    [
+      utils.bytes.utf8.encode("metadata"),
+      metadataProgramId.toBuffer(),
+      mintId.toBuffer(),
+    ],
+    metadataProgramId
+  )[0];
+};
@@ -2,13 +2,39 @@ import type { Wallet } from "@coral-xyz/anchor/dist/cjs/provider";
 import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
 import type { Connection } from "@solana/web3.js";
 import { PublicKey, SystemProgram, Transaction } from "@solana/web3.js";
-import { findMintMetadataId, tryGetAccount } from "@solana-nft-programs/common";
-import type BN from "bn.js";
+import { findAta, tryGetAccount } from "@solana-nft-programs/common";
+import BN from "bn.js";
+import { randomBytes } from "crypto";
 
-import { getPaymentManager } from "./accounts";
-import { paymentManagerProgram } from "./constants";
-import { findPaymentManagerAddress } from "./pda";
-import { withRemainingAccountsForHandlePaymentWithRoyalties } from "./utils";
+import { getExemptPayer, getPaymentManager } from "./accounts";
+import {
+  BASIS_POINTS_DIVISOR,
+  FeeMode,
+  PAYMENT_MANAGER_ADDRESS,
+  paymentManagerProgram,
+  PayoutOrder,
+} from "./constants";
+import {
+  findCollectionPolicyAddress,
+  findCreatorConfigAddress,
+  findExemptPayerAddress,
+  findMintMetadataAddress,
+  findPayerRateLimitAddress,
+  findPayerStatsAddress,
+  findPaymentManagerAddress,
+  findPaymentRecordAddress,
+  findPriceOracleAddress,
+  findProceedsEscrowAddress,
+  findProgramDataAddress,
+  findProtocolConfigAddress,
+  findRoyaltyClaimAddress,
+  findRoyaltyEscrowAddress,
+} from "./pda";
+import {
+  adminSignerAccounts,
+  findMintCollectionPolicyAddress,
+  withRemainingAccountsForHandlePaymentWithRoyalties,
+} from "./utils";
 
 export const withInit = async (
   transaction: Transaction,
@@ -21,6 +47,46 @@ export const withInit = async (
     takerFeeBasisPoints: number;
     includeSellerFeeBasisPoints: boolean;
     royaltyFeeShare?: BN;
+    flatFeeAmount?: BN;
+    royaltyConversionRateBounds?: {
+      minConversionRate: BN;
+      maxConversionRate: BN;
+    };
+    strictRemainingAccounts?: boolean;
+    authorityThreshold?: number;
+    admins?: PublicKey[];
+    maxRoyaltyBasisPoints?: number;
+    clampRoyalties?: boolean;
+    payoutOrder?: PayoutOrder;
+    royaltyMinPrice?: BN;
+    quoteConversionRateBounds?: {
+      minConversionRate: BN;
+      maxConversionRate: BN;
+    };
+    handlePrimarySales?: boolean;
+    minPaymentIntervalSlots?: BN;
+    maxCreatorsFee?: BN;
+    royaltyFloorBasisPoints?: number;
+    takerFeeCollectorId?: PublicKey;
+    requiredCollectionId?: PublicKey;
+    sellerFeeWithinFees?: boolean;
+    feeMode?: FeeMode;
+    recordPayments?: boolean;
+    primarySaleTargetId?: PublicKey;
+    fallbackRoyaltyToUpdateAuthority?: boolean;
+    takerCoversMakerFee?: boolean;
+    claimRoyalties?: boolean;
+    bridgedMetadataProgramId?: PublicKey;
+    remainderToCollector?: boolean;
+    skipMismatchedCreatorMints?: boolean;
+    // the payer pays the buy side fee on top instead of it being taken out of the principal
+    buySideAdditive?: boolean;
+    // a fork of the Metaplex token metadata program, e.g. on a custom cluster
+    metadataProgramId?: PublicKey;
+    // royalties of creators who cannot receive accrue to their royalty claim
+    escrowUnreceivableRoyalties?: boolean;
+    // part of the fees the payer keeps, e.g. for a loyalty program
+    rebateBasisPoints?: number;
     payer?: PublicKey;
     authority?: PublicKey;
   }
@@ -35,6 +101,38 @@ export const withInit = async (
         takerFeeBasisPoints: params.takerFeeBasisPoints,
         includeSellerFeeBasisPoints: params.includeSellerFeeBasisPoints,
         royaltyFeeShare: params.royaltyFeeShare ?? null,
+        flatFeeAmount: params.flatFeeAmount ?? null,
+        royaltyConversionRateBounds: params.royaltyConversionRateBounds ?? null,
+        strictRemainingAccounts: params.strictRemainingAccounts ?? false,
+        authorityThreshold: params.authorityThreshold ?? null,
+        admins: params.admins ?? [],
+        maxRoyaltyBasisPoints: params.maxRoyaltyBasisPoints ?? null,
+        clampRoyalties: params.clampRoyalties ?? false,
+        payoutOrder: params.payoutOrder ?? PayoutOrder.CreatorsFirst,
+        royaltyMinPrice: params.royaltyMinPrice ?? null,
+        quoteConversionRateBounds: params.quoteConversionRateBounds ?? null,
+        handlePrimarySales: params.handlePrimarySales ?? false,
+        minPaymentIntervalSlots: params.minPaymentIntervalSlots ?? null,
+        maxCreatorsFee: params.maxCreatorsFee ?? null,
+        royaltyFloorBasisPoints: params.royaltyFloorBasisPoints ?? null,
+        takerFeeCollector: params.takerFeeCollectorId ?? null,
+        requiredCollection: params.requiredCollectionId ?? null,
+        sellerFeeWithinFees: params.sellerFeeWithinFees ?? false,
+        feeMode: params.feeMode ?? FeeMode.BasisPoints,
+        recordPayments: params.recordPayments ?? false,
+        primarySaleTarget: params.primarySaleTargetId ?? null,
+        fallbackRoyaltyToUpdateAuthority:
+          params.fallbackRoyaltyToUpdateAuthority ?? false,
+        takerCoversMakerFee: params.takerCoversMakerFee ?? false,
+        claimRoyalties: params.claimRoyalties ?? false,
+        bridgedMetadataProgram: params.bridgedMetadataProgramId ?? null,
+        remainderToCollector: params.remainderToCollector ?? false,
+        skipMismatchedCreatorMints: params.skipMismatchedCreatorMints ?? false,
+        buySideAdditive: params.buySideAdditive ?? false,
+        metadataProgramId: params.metadataProgramId ?? null,
+        escrowUnreceivableRoyalties:
+          params.escrowUnreceivableRoyalties ?? false,
+        rebateBasisPoints: params.rebateBasisPoints ?? null,
       })
       .accounts({
         paymentManager: findPaymentManagerAddress(params.paymentManagerName),
@@ -57,6 +155,7 @@ export const withManagePayment = async (
     payerTokenAccountId: PublicKey;
     feeCollectorTokenAccountId: PublicKey;
     paymentTokenAccountId: PublicKey;
+    paymentMintId: PublicKey;
   }
 ): Promise<Transaction> => {
   return transaction.add(
@@ -67,6 +166,40 @@ export const withManagePayment = async (
         payerTokenAccount: params.payerTokenAccountId,
         feeCollectorTokenAccount: params.feeCollectorTokenAccountId,
         paymentTokenAccount: params.paymentTokenAccountId,
+        paymentMint: params.paymentMintId,
+        payer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction()
+  );
+};
+
+export const withHandlePaymentWithQuote = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    quoteAmount: BN;
+    conversionRate: BN;
+    payerTokenAccountId: PublicKey;
+    feeCollectorTokenAccountId: PublicKey;
+    paymentTokenAccountId: PublicKey;
+    paymentMintId: PublicKey;
+  }
+): Promise<Transaction> => {
+  return transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.handlePaymentWithQuote({
+        quoteAmount: params.quoteAmount,
+        conversionRate: params.conversionRate,
+      })
+      .accounts({
+        paymentManager: findPaymentManagerAddress(params.paymentManagerName),
+        payerTokenAccount: params.payerTokenAccountId,
+        feeCollectorTokenAccount: params.feeCollectorTokenAccountId,
+        paymentTokenAccount: params.paymentTokenAccountId,
+        paymentMint: params.paymentMintId,
         payer: wallet.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
@@ -81,17 +214,210 @@ export const withHandlePaymentWithRoyalties = async (
   params: {
     paymentManagerName: string;
     paymentAmount: BN;
+    exactOutput?: boolean;
     mintId: PublicKey;
     paymentMintId: PublicKey;
     payerTokenAccountId: PublicKey;
     feeCollectorTokenAccountId: PublicKey;
-    paymentTokenAccountId: PublicKey;
+    paymentTokenAccountId?: PublicKey;
+    // fees and royalties are debited from this account instead of the payer token account
+    feeSourceTokenAccountId?: PublicKey;
     buySideTokenAccountId?: PublicKey;
     excludeCretors?: string[];
+    protocolTreasuryTokenAccountId?: PublicKey;
+    primarySaleTargetTokenAccountId?: PublicKey;
+    escrowSellerId?: PublicKey;
+    fillFraction?: number;
+    // the payment fails when the payer would be debited more in total
+    maxTotalSpend?: BN;
+    // creatorHash of the creators the mint is expected to have
+    expectedCreatorHash?: number[];
+    // a paymentAmount above it is settled at expectedAmount and the excess is
+    // refunded to the payer, a paymentAmount below it is rejected
+    expectedAmount?: BN;
+    // the seller's proceeds net of the maker fee are paid to their token
+    // account instead of the payment token account
+    sellerId?: PublicKey;
+    makerTokenAccountId?: PublicKey;
+    // tells the payment record of this payment apart from the payer's other
+    // payments for the mint, a random nonce when not set
+    recordNonce?: BN;
+    // the fees are reported in stable value at the price of this price oracle
+    priceOracleId?: PublicKey;
+    // every payout asserts its token account was credited exactly its amount
+    checked?: boolean;
   }
 ): Promise<Transaction> => {
   const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  const checkPaymentManager = await tryGetAccount(() =>
+    getPaymentManager(connection, paymentManagerId)
+  );
+  // a partial fill settles its basis points of the payment amount
+  const filledAmount =
+    params.fillFraction !== undefined
+      ? params.paymentAmount
+          .muln(params.fillFraction)
+          .divn(BASIS_POINTS_DIVISOR)
+      : params.paymentAmount;
+  // payers are only tracked when the payment manager throttles payments
+  const rateLimited = !!checkPaymentManager?.parsed.minPaymentIntervalSlots;
+  // payments are only recorded when the payment manager asks for it
+  const recordsPayments = !!checkPaymentManager?.parsed.recordPayments;
+  // payer volume is only tracked when the payment manager has fee tiers
+  const tracksVolume = !!checkPaymentManager?.parsed.feeTiers.length;
+  // royalties are held in the royalty escrow until creators claim them
+  const claimsRoyalties =
+    !!checkPaymentManager?.parsed.claimRoyalties ||
+    !!checkPaymentManager?.parsed.escrowUnreceivableRoyalties;
+  // the system program is only needed when an account is created
+  const createsAccounts =
+    rateLimited ||
+    recordsPayments ||
+    tracksVolume ||
+    claimsRoyalties ||
+    !!params.escrowSellerId;
+  const takerFeeCollectorId = checkPaymentManager?.parsed.takerFeeCollector;
+  // the fee exemption is only passed when the payer has one
+  const exemptPayerId = findExemptPayerAddress(
+    paymentManagerId,
+    wallet.publicKey
+  );
+  const checkExemptPayer = await tryGetAccount(() =>
+    getExemptPayer(connection, exemptPayerId)
+  );
+
+  const remainingAccounts =
+    await withRemainingAccountsForHandlePaymentWithRoyalties(
+      new Transaction(),
+      connection,
+      wallet,
+      params.mintId,
+      params.paymentMintId,
+      params.buySideTokenAccountId,
+      params.excludeCretors ?? [],
+      paymentManagerId,
+      filledAmount,
+      takerFeeCollectorId
+        ? await findAta(params.paymentMintId, takerFeeCollectorId, true)
+        : undefined
+    );
+  // the royalty policy of the mint's verified collection overrides the
+  // royalty terms of the payment manager
+  const collectionPolicyId = await findMintCollectionPolicyAddress(
+    connection,
+    params.mintId,
+    checkPaymentManager?.parsed.metadataProgramId
+  );
+  const recordNonce = params.recordNonce ?? new BN(randomBytes(8));
+  const method = params.checked
+    ? "handlePaymentWithRoyaltiesChecked"
+    : "handlePaymentWithRoyalties";
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods[method]({
+        paymentAmount: params.paymentAmount,
+        exactOutput: params.exactOutput ?? false,
+        escrowSeller: params.escrowSellerId ?? null,
+        fillFraction: params.fillFraction ?? null,
+        maxTotalSpend: params.maxTotalSpend ?? null,
+        expectedCreatorHash: params.expectedCreatorHash ?? null,
+        expectedAmount: params.expectedAmount ?? null,
+        seller: params.sellerId ?? null,
+        recordNonce,
+      })
+      .accounts({
+        paymentManager: paymentManagerId,
+        payerTokenAccount: params.payerTokenAccountId,
+        feeCollectorTokenAccount: params.feeCollectorTokenAccountId,
+        paymentTokenAccount:
+          params.paymentTokenAccountId ?? PAYMENT_MANAGER_ADDRESS,
+        feeSourceTokenAccount:
+          params.feeSourceTokenAccountId ?? PAYMENT_MANAGER_ADDRESS,
+        makerTokenAccount:
+          params.makerTokenAccountId ?? PAYMENT_MANAGER_ADDRESS,
+        paymentMint: params.paymentMintId,
+        mint: params.mintId,
+        mintMetadata: findMintMetadataAddress(
+          params.mintId,
+          checkPaymentManager?.parsed.metadataProgramId
+        ),
+        creatorConfig: findCreatorConfigAddress(
+          paymentManagerId,
+          params.mintId
+        ),
+        // the program id stands in for the optional protocol accounts
+        protocolConfig: params.protocolTreasuryTokenAccountId
+          ? findProtocolConfigAddress()
+          : PAYMENT_MANAGER_ADDRESS,
+        protocolTreasuryTokenAccount:
+          params.protocolTreasuryTokenAccountId ?? PAYMENT_MANAGER_ADDRESS,
+        primarySaleTargetTokenAccount:
+          params.primarySaleTargetTokenAccountId ?? PAYMENT_MANAGER_ADDRESS,
+        payerRateLimit: rateLimited
+          ? findPayerRateLimitAddress(paymentManagerId, wallet.publicKey)
+          : PAYMENT_MANAGER_ADDRESS,
+        payerStats: tracksVolume
+          ? findPayerStatsAddress(paymentManagerId, wallet.publicKey)
+          : PAYMENT_MANAGER_ADDRESS,
+        proceedsEscrow: params.escrowSellerId
+          ? findProceedsEscrowAddress(
+              paymentManagerId,
+              params.paymentMintId,
+              params.escrowSellerId
+            )
+          : PAYMENT_MANAGER_ADDRESS,
+        paymentRecord: recordsPayments
+          ? findPaymentRecordAddress(
+              paymentManagerId,
+              wallet.publicKey,
+              params.mintId,
+              recordNonce
+            )
+          : PAYMENT_MANAGER_ADDRESS,
+        exemptPayer: checkExemptPayer ? exemptPayerId : PAYMENT_MANAGER_ADDRESS,
+        royaltyEscrow: claimsRoyalties
+          ? findRoyaltyEscrowAddress(paymentManagerId, params.paymentMintId)
+          : PAYMENT_MANAGER_ADDRESS,
+        priceOracle: params.priceOracleId ?? PAYMENT_MANAGER_ADDRESS,
+        collectionPolicy: collectionPolicyId ?? PAYMENT_MANAGER_ADDRESS,
+        payer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: createsAccounts
+          ? SystemProgram.programId
+          : PAYMENT_MANAGER_ADDRESS,
+      })
+      .remainingAccounts(remainingAccounts)
+      .instruction()
+  );
+  return transaction;
+};
 
+/**
+ * Pre-flights the account set of withHandlePaymentWithRoyalties, simulate the
+ * transaction to find out whether the payment would be accepted
+ */
+export const withValidatePaymentAccounts = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    paymentAmount: BN;
+    mintId: PublicKey;
+    paymentMintId: PublicKey;
+    payerTokenAccountId: PublicKey;
+    feeCollectorTokenAccountId: PublicKey;
+    paymentTokenAccountId: PublicKey;
+    buySideTokenAccountId?: PublicKey;
+    excludeCretors?: string[];
+    expectedCreatorHash?: number[];
+  }
+): Promise<Transaction> => {
+  const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  const checkPaymentManager = await tryGetAccount(() =>
+    getPaymentManager(connection, paymentManagerId)
+  );
+  const takerFeeCollectorId = checkPaymentManager?.parsed.takerFeeCollector;
   const remainingAccounts =
     await withRemainingAccountsForHandlePaymentWithRoyalties(
       new Transaction(),
@@ -100,11 +426,24 @@ export const withHandlePaymentWithRoyalties = async (
       params.mintId,
       params.paymentMintId,
       params.buySideTokenAccountId,
-      params.excludeCretors ?? []
+      params.excludeCretors ?? [],
+      paymentManagerId,
+      params.paymentAmount,
+      takerFeeCollectorId
+        ? await findAta(params.paymentMintId, takerFeeCollectorId, true)
+        : undefined
     );
+  const collectionPolicyId = await findMintCollectionPolicyAddress(
+    connection,
+    params.mintId,
+    checkPaymentManager?.parsed.metadataProgramId
+  );
   transaction.add(
     await paymentManagerProgram(connection, wallet)
-      .methods.handlePaymentWithRoyalties(params.paymentAmount)
+      .methods.validatePaymentAccounts(
+        params.paymentAmount,
+        params.expectedCreatorHash ?? null
+      )
       .accounts({
         paymentManager: paymentManagerId,
         payerTokenAccount: params.payerTokenAccountId,
@@ -112,7 +451,82 @@ export const withHandlePaymentWithRoyalties = async (
         paymentTokenAccount: params.paymentTokenAccountId,
         paymentMint: params.paymentMintId,
         mint: params.mintId,
-        mintMetadata: findMintMetadataId(params.mintId),
+        mintMetadata: findMintMetadataAddress(
+          params.mintId,
+          checkPaymentManager?.parsed.metadataProgramId
+        ),
+        creatorConfig: findCreatorConfigAddress(
+          paymentManagerId,
+          params.mintId
+        ),
+        collectionPolicy: collectionPolicyId ?? PAYMENT_MANAGER_ADDRESS,
+      })
+      .remainingAccounts(remainingAccounts)
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withHandlePaymentWithConvertedRoyalties = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    paymentAmount: BN;
+    conversionRate: BN;
+    mintId: PublicKey;
+    paymentMintId: PublicKey;
+    royaltyMintId: PublicKey;
+    payerTokenAccountId: PublicKey;
+    royaltyPayerTokenAccountId: PublicKey;
+    feeCollectorTokenAccountId: PublicKey;
+    paymentTokenAccountId: PublicKey;
+    buySideTokenAccountId?: PublicKey;
+    excludeCretors?: string[];
+  }
+): Promise<Transaction> => {
+  const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  const checkPaymentManager = await tryGetAccount(() =>
+    getPaymentManager(connection, paymentManagerId)
+  );
+
+  // creators are paid in the royalty mint
+  const remainingAccounts =
+    await withRemainingAccountsForHandlePaymentWithRoyalties(
+      new Transaction(),
+      connection,
+      wallet,
+      params.mintId,
+      params.royaltyMintId,
+      params.buySideTokenAccountId,
+      params.excludeCretors ?? [],
+      paymentManagerId,
+      params.paymentAmount
+    );
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.handlePaymentWithConvertedRoyalties({
+        paymentAmount: params.paymentAmount,
+        conversionRate: params.conversionRate,
+      })
+      .accounts({
+        paymentManager: paymentManagerId,
+        payerTokenAccount: params.payerTokenAccountId,
+        feeCollectorTokenAccount: params.feeCollectorTokenAccountId,
+        paymentTokenAccount: params.paymentTokenAccountId,
+        royaltyPayerTokenAccount: params.royaltyPayerTokenAccountId,
+        paymentMint: params.paymentMintId,
+        royaltyMint: params.royaltyMintId,
+        mint: params.mintId,
+        mintMetadata: findMintMetadataAddress(
+          params.mintId,
+          checkPaymentManager?.parsed.metadataProgramId
+        ),
+        creatorConfig: findCreatorConfigAddress(
+          paymentManagerId,
+          params.mintId
+        ),
         payer: wallet.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
@@ -137,6 +551,9 @@ export const withHandleNativePaymentWithRoyalties = async (
   }
 ): Promise<Transaction> => {
   const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  const checkPaymentManager = await tryGetAccount(() =>
+    getPaymentManager(connection, paymentManagerId)
+  );
 
   const remainingAccounts =
     await withRemainingAccountsForHandlePaymentWithRoyalties(
@@ -146,7 +563,9 @@ export const withHandleNativePaymentWithRoyalties = async (
       params.mintId,
       PublicKey.default,
       params.buySideTokenAccountId,
-      params.excludeCretors ?? []
+      params.excludeCretors ?? [],
+      paymentManagerId,
+      params.paymentAmount
     );
 
   transaction.add(
@@ -158,7 +577,14 @@ export const withHandleNativePaymentWithRoyalties = async (
         paymentTarget: params.paymentTargetId,
         payer: wallet.publicKey,
         mint: params.mintId,
-        mintMetadata: findMintMetadataId(params.mintId),
+        mintMetadata: findMintMetadataAddress(
+          params.mintId,
+          checkPaymentManager?.parsed.metadataProgramId
+        ),
+        creatorConfig: findCreatorConfigAddress(
+          paymentManagerId,
+          params.mintId
+        ),
         systemProgram: SystemProgram.programId,
       })
       .remainingAccounts(remainingAccounts)
@@ -167,6 +593,173 @@ export const withHandleNativePaymentWithRoyalties = async (
   return transaction;
 };
 
+export const withSetCreatorConfig = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    mintId: PublicKey;
+    creators: { address: PublicKey; share: number }[];
+    collectionAuthority?: PublicKey;
+    payer?: PublicKey;
+  }
+): Promise<[Transaction, PublicKey]> => {
+  const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  const checkPaymentManager = await tryGetAccount(() =>
+    getPaymentManager(connection, paymentManagerId)
+  );
+  const creatorConfigId = findCreatorConfigAddress(
+    paymentManagerId,
+    params.mintId
+  );
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.setCreatorConfig({
+        creators: params.creators,
+      })
+      .accounts({
+        creatorConfig: creatorConfigId,
+        paymentManager: paymentManagerId,
+        mint: params.mintId,
+        mintMetadata: findMintMetadataAddress(
+          params.mintId,
+          checkPaymentManager?.parsed.metadataProgramId
+        ),
+        collectionAuthority: params.collectionAuthority ?? wallet.publicKey,
+        payer: params.payer ?? wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction()
+  );
+  return [transaction, creatorConfigId];
+};
+
+export const withInitProtocolConfig = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    protocolFeeBasisPoints: number;
+    treasury: PublicKey;
+    authority?: PublicKey;
+    payer?: PublicKey;
+  }
+): Promise<[Transaction, PublicKey]> => {
+  const protocolConfigId = findProtocolConfigAddress();
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.initProtocolConfig({
+        authority: params.authority ?? wallet.publicKey,
+        protocolFeeBasisPoints: params.protocolFeeBasisPoints,
+        treasury: params.treasury,
+      })
+      .accounts({
+        protocolConfig: protocolConfigId,
+        program: PAYMENT_MANAGER_ADDRESS,
+        programData: findProgramDataAddress(),
+        upgradeAuthority: wallet.publicKey,
+        payer: params.payer ?? wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction()
+  );
+  return [transaction, protocolConfigId];
+};
+
+export const withSetProtocolConfig = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    protocolFeeBasisPoints: number;
+    treasury: PublicKey;
+    authority?: PublicKey;
+  }
+): Promise<[Transaction, PublicKey]> => {
+  const protocolConfigId = findProtocolConfigAddress();
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.setProtocolConfig({
+        authority: params.authority ?? wallet.publicKey,
+        protocolFeeBasisPoints: params.protocolFeeBasisPoints,
+        treasury: params.treasury,
+      })
+      .accounts({
+        protocolConfig: protocolConfigId,
+        authority: wallet.publicKey,
+      })
+      .instruction()
+  );
+  return [transaction, protocolConfigId];
+};
+
+export const withClaimProceeds = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    paymentMintId: PublicKey;
+    sellerTokenAccountId: PublicKey;
+  }
+): Promise<Transaction> => {
+  const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.claimProceeds()
+      .accounts({
+        paymentManager: paymentManagerId,
+        proceedsEscrow: findProceedsEscrowAddress(
+          paymentManagerId,
+          params.paymentMintId,
+          wallet.publicKey
+        ),
+        sellerTokenAccount: params.sellerTokenAccountId,
+        paymentMint: params.paymentMintId,
+        seller: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withClaimRoyalty = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    paymentMintId: PublicKey;
+    creatorTokenAccountId: PublicKey;
+  }
+): Promise<Transaction> => {
+  const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.claimRoyalty()
+      .accounts({
+        paymentManager: paymentManagerId,
+        royaltyClaim: findRoyaltyClaimAddress(
+          paymentManagerId,
+          params.paymentMintId,
+          wallet.publicKey
+        ),
+        royaltyEscrow: findRoyaltyEscrowAddress(
+          paymentManagerId,
+          params.paymentMintId
+        ),
+        creatorTokenAccount: params.creatorTokenAccountId,
+        paymentMint: params.paymentMintId,
+        creator: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction()
+  );
+  return transaction;
+};
+
 export const withClose = async (
   transaction: Transaction,
   connection: Connection,
@@ -174,6 +767,7 @@ export const withClose = async (
   params: {
     paymentManagerName: string;
     collectorId?: PublicKey;
+    adminSignerIds?: PublicKey[];
   }
 ): Promise<Transaction> => {
   transaction.add(
@@ -184,6 +778,7 @@ export const withClose = async (
         collector: params.collectorId ?? wallet.publicKey,
         closer: wallet.publicKey,
       })
+      .remainingAccounts(adminSignerAccounts(params.adminSignerIds))
       .instruction()
   );
   return transaction;
@@ -200,6 +795,43 @@ export const withUpdate = async (
     makerFeeBasisPoints?: number;
     takerFeeBasisPoints?: number;
     royaltyFeeShare?: BN;
+    flatFeeAmount?: BN;
+    royaltyConversionRateBounds?: {
+      minConversionRate: BN;
+      maxConversionRate: BN;
+    };
+    strictRemainingAccounts?: boolean;
+    authorityThreshold?: number;
+    admins?: PublicKey[];
+    maxRoyaltyBasisPoints?: number;
+    clampRoyalties?: boolean;
+    payoutOrder?: PayoutOrder;
+    royaltyMinPrice?: BN;
+    quoteConversionRateBounds?: {
+      minConversionRate: BN;
+      maxConversionRate: BN;
+    };
+    handlePrimarySales?: boolean;
+    minPaymentIntervalSlots?: BN;
+    maxCreatorsFee?: BN;
+    royaltyFloorBasisPoints?: number;
+    takerFeeCollectorId?: PublicKey;
+    requiredCollectionId?: PublicKey;
+    sellerFeeWithinFees?: boolean;
+    feeMode?: FeeMode;
+    recordPayments?: boolean;
+    primarySaleTargetId?: PublicKey;
+    fallbackRoyaltyToUpdateAuthority?: boolean;
+    takerCoversMakerFee?: boolean;
+    claimRoyalties?: boolean;
+    bridgedMetadataProgramId?: PublicKey;
+    remainderToCollector?: boolean;
+    skipMismatchedCreatorMints?: boolean;
+    buySideAdditive?: boolean;
+    metadataProgramId?: PublicKey;
+    escrowUnreceivableRoyalties?: boolean;
+    rebateBasisPoints?: number;
+    adminSignerIds?: PublicKey[];
   }
 ): Promise<Transaction> => {
   const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
@@ -226,9 +858,424 @@ export const withUpdate = async (
           checkPaymentManager.parsed.royaltyFeeShare ??
           params.royaltyFeeShare ??
           null,
+        flatFeeAmount:
+          params.flatFeeAmount ??
+          checkPaymentManager.parsed.flatFeeAmount ??
+          null,
+        royaltyConversionRateBounds:
+          params.royaltyConversionRateBounds ??
+          checkPaymentManager.parsed.royaltyConversionRateBounds ??
+          null,
+        strictRemainingAccounts:
+          params.strictRemainingAccounts ??
+          checkPaymentManager.parsed.strictRemainingAccounts,
+        authorityThreshold:
+          params.authorityThreshold ??
+          checkPaymentManager.parsed.authorityThreshold ??
+          null,
+        admins: params.admins ?? checkPaymentManager.parsed.admins,
+        maxRoyaltyBasisPoints:
+          params.maxRoyaltyBasisPoints ??
+          checkPaymentManager.parsed.maxRoyaltyBasisPoints ??
+          null,
+        clampRoyalties:
+          params.clampRoyalties ?? checkPaymentManager.parsed.clampRoyalties,
+        payoutOrder:
+          params.payoutOrder ?? checkPaymentManager.parsed.payoutOrder,
+        royaltyMinPrice:
+          params.royaltyMinPrice ??
+          checkPaymentManager.parsed.royaltyMinPrice ??
+          null,
+        quoteConversionRateBounds:
+          params.quoteConversionRateBounds ??
+          checkPaymentManager.parsed.quoteConversionRateBounds ??
+          null,
+        handlePrimarySales:
+          params.handlePrimarySales ??
+          checkPaymentManager.parsed.handlePrimarySales,
+        minPaymentIntervalSlots:
+          params.minPaymentIntervalSlots ??
+          checkPaymentManager.parsed.minPaymentIntervalSlots ??
+          null,
+        maxCreatorsFee:
+          params.maxCreatorsFee ??
+          checkPaymentManager.parsed.maxCreatorsFee ??
+          null,
+        royaltyFloorBasisPoints:
+          params.royaltyFloorBasisPoints ??
+          checkPaymentManager.parsed.royaltyFloorBasisPoints ??
+          null,
+        takerFeeCollector:
+          params.takerFeeCollectorId ??
+          checkPaymentManager.parsed.takerFeeCollector ??
+          null,
+        requiredCollection:
+          params.requiredCollectionId ??
+          checkPaymentManager.parsed.requiredCollection ??
+          null,
+        sellerFeeWithinFees:
+          params.sellerFeeWithinFees ??
+          checkPaymentManager.parsed.sellerFeeWithinFees,
+        feeMode: params.feeMode ?? checkPaymentManager.parsed.feeMode,
+        recordPayments:
+          params.recordPayments ?? checkPaymentManager.parsed.recordPayments,
+        primarySaleTarget:
+          params.primarySaleTargetId ??
+          checkPaymentManager.parsed.primarySaleTarget ??
+          null,
+        fallbackRoyaltyToUpdateAuthority:
+          params.fallbackRoyaltyToUpdateAuthority ??
+          checkPaymentManager.parsed.fallbackRoyaltyToUpdateAuthority,
+        takerCoversMakerFee:
+          params.takerCoversMakerFee ??
+          checkPaymentManager.parsed.takerCoversMakerFee,
+        claimRoyalties:
+          params.claimRoyalties ?? checkPaymentManager.parsed.claimRoyalties,
+        bridgedMetadataProgram:
+          params.bridgedMetadataProgramId ??
+          checkPaymentManager.parsed.bridgedMetadataProgram ??
+          null,
+        remainderToCollector:
+          params.remainderToCollector ??
+          checkPaymentManager.parsed.remainderToCollector,
+        skipMismatchedCreatorMints:
+          params.skipMismatchedCreatorMints ??
+          checkPaymentManager.parsed.skipMismatchedCreatorMints,
+        buySideAdditive:
+          params.buySideAdditive ?? checkPaymentManager.parsed.buySideAdditive,
+        metadataProgramId:
+          params.metadataProgramId ??
+          checkPaymentManager.parsed.metadataProgramId ??
+          null,
+        escrowUnreceivableRoyalties:
+          params.escrowUnreceivableRoyalties ??
+          checkPaymentManager.parsed.escrowUnreceivableRoyalties,
+        rebateBasisPoints:
+          params.rebateBasisPoints ??
+          checkPaymentManager.parsed.rebateBasisPoints ??
+          null,
+      })
+      .accounts({
+        paymentManager: paymentManagerId,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(adminSignerAccounts(params.adminSignerIds))
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withQueueFeeSchedule = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    makerFeeBasisPoints: number;
+    takerFeeBasisPoints: number;
+    activationTime: BN;
+    adminSignerIds?: PublicKey[];
+  }
+): Promise<Transaction> => {
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.queueFeeSchedule({
+        makerFeeBasisPoints: params.makerFeeBasisPoints,
+        takerFeeBasisPoints: params.takerFeeBasisPoints,
+        activationTime: params.activationTime,
+      })
+      .accounts({
+        paymentManager: findPaymentManagerAddress(params.paymentManagerName),
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(adminSignerAccounts(params.adminSignerIds))
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withSetIncludeSellerFee = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    includeSellerFeeBasisPoints: boolean;
+    adminSignerIds?: PublicKey[];
+  }
+): Promise<Transaction> => {
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.setIncludeSellerFee(params.includeSellerFeeBasisPoints)
+      .accounts({
+        paymentManager: findPaymentManagerAddress(params.paymentManagerName),
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(adminSignerAccounts(params.adminSignerIds))
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withSetFeeCollector = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    feeCollectorId: PublicKey;
+    adminSignerIds?: PublicKey[];
+  }
+): Promise<Transaction> => {
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.setFeeCollector()
+      .accounts({
+        paymentManager: findPaymentManagerAddress(params.paymentManagerName),
+        feeCollector: params.feeCollectorId,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(adminSignerAccounts(params.adminSignerIds))
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withSetExemptPayer = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    payerId: PublicKey;
+    exempt: boolean;
+    adminSignerIds?: PublicKey[];
+  }
+): Promise<Transaction> => {
+  const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.setExemptPayer({
+        payer: params.payerId,
+        exempt: params.exempt,
+      })
+      .accounts({
+        exemptPayer: findExemptPayerAddress(paymentManagerId, params.payerId),
+        paymentManager: paymentManagerId,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(adminSignerAccounts(params.adminSignerIds))
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withSetAcceptedMints = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    // an empty list accepts any payment mint
+    acceptedMintIds: PublicKey[];
+    adminSignerIds?: PublicKey[];
+  }
+): Promise<Transaction> => {
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.setAcceptedMints(params.acceptedMintIds)
+      .accounts({
+        paymentManager: findPaymentManagerAddress(params.paymentManagerName),
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(adminSignerAccounts(params.adminSignerIds))
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withSetFeeTiers = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    // sorted by increasing minVolume, an empty list charges every payer the base fees
+    feeTiers: {
+      minVolume: BN;
+      makerFeeBasisPoints: number;
+      takerFeeBasisPoints: number;
+    }[];
+    adminSignerIds?: PublicKey[];
+  }
+): Promise<Transaction> => {
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.setFeeTiers(params.feeTiers)
+      .accounts({
+        paymentManager: findPaymentManagerAddress(params.paymentManagerName),
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(adminSignerAccounts(params.adminSignerIds))
+      .instruction()
+  );
+  return transaction;
+};
+
+/**
+ * Moves an escrow owned by an address derived from seeds an earlier program
+ * version used into the royalty escrow of the payment mint, the balance is
+ * credited to the royalty claim of the creator
+ */
+export const withMigrateEscrow = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    paymentMintId: PublicKey;
+    oldEscrowId: PublicKey;
+    // without the bump, one of them has to be the payment manager address
+    oldSeeds: Buffer[];
+    creatorId: PublicKey;
+    adminSignerIds?: PublicKey[];
+  }
+): Promise<Transaction> => {
+  const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  const [oldEscrowAuthorityId] = PublicKey.findProgramAddressSync(
+    params.oldSeeds,
+    PAYMENT_MANAGER_ADDRESS
+  );
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.migrateEscrow({
+        oldSeeds: params.oldSeeds,
+        creator: params.creatorId,
       })
       .accounts({
         paymentManager: paymentManagerId,
+        oldEscrow: params.oldEscrowId,
+        royaltyEscrow: findRoyaltyEscrowAddress(
+          paymentManagerId,
+          params.paymentMintId
+        ),
+        royaltyClaim: findRoyaltyClaimAddress(
+          paymentManagerId,
+          params.paymentMintId,
+          params.creatorId
+        ),
+        paymentMint: params.paymentMintId,
+        payer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([
+        ...adminSignerAccounts(params.adminSignerIds),
+        // the authority of an escrow that is not its own authority
+        ...(oldEscrowAuthorityId.equals(params.oldEscrowId)
+          ? []
+          : [
+              {
+                pubkey: oldEscrowAuthorityId,
+                isSigner: false,
+                isWritable: false,
+              },
+            ]),
+      ])
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withSetPriceOracle = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+    paymentMintId: PublicKey;
+    // stable base units per payment mint base unit scaled by
+    // CONVERSION_RATE_DIVISOR
+    price: BN;
+    adminSignerIds?: PublicKey[];
+  }
+): Promise<Transaction> => {
+  const paymentManagerId = findPaymentManagerAddress(params.paymentManagerName);
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.setPriceOracle({ price: params.price })
+      .accounts({
+        priceOracle: findPriceOracleAddress(
+          paymentManagerId,
+          params.paymentMintId
+        ),
+        paymentManager: paymentManagerId,
+        paymentMint: params.paymentMintId,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(adminSignerAccounts(params.adminSignerIds))
+      .instruction()
+  );
+  return transaction;
+};
+
+export const withSetCollectionPolicy = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    collectionMintId: PublicKey;
+    enforceRoyalties: boolean;
+    enforcedBasisPoints: number;
+    allowWaivers: boolean;
+    collectionAuthority?: PublicKey;
+    payer?: PublicKey;
+  }
+): Promise<[Transaction, PublicKey]> => {
+  const collectionPolicyId = findCollectionPolicyAddress(
+    params.collectionMintId
+  );
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.setCollectionPolicy({
+        enforceRoyalties: params.enforceRoyalties,
+        enforcedBasisPoints: params.enforcedBasisPoints,
+        allowWaivers: params.allowWaivers,
+      })
+      .accounts({
+        collectionPolicy: collectionPolicyId,
+        collectionMint: params.collectionMintId,
+        collectionMetadata: findMintMetadataAddress(params.collectionMintId),
+        collectionAuthority: params.collectionAuthority ?? wallet.publicKey,
+        payer: params.payer ?? wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction()
+  );
+  return [transaction, collectionPolicyId];
+};
+
+export const withResizePaymentManager = async (
+  transaction: Transaction,
+  connection: Connection,
+  wallet: Wallet,
+  params: {
+    paymentManagerName: string;
+  }
+): Promise<Transaction> => {
+  transaction.add(
+    await paymentManagerProgram(connection, wallet)
+      .methods.resizePaymentManager()
+      .accounts({
+        paymentManager: findPaymentManagerAddress(params.paymentManagerName),
         payer: wallet.publicKey,
         systemProgram: SystemProgram.programId,
       })
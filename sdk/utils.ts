@@ -9,8 +9,41 @@ import {
   tryNull,
   withFindOrInitAssociatedTokenAccount,
 } from "@solana-nft-programs/common";
+import type BN from "bn.js";
+import { createHash } from "crypto";
 
-import { getPaymentManager } from "./accounts";
+import {
+  getCollectionPolicy,
+  getCreatorConfig,
+  getPaymentManager,
+} from "./accounts";
+import {
+  findCollectionPolicyAddress,
+  findCreatorConfigAddress,
+  findMintMetadataAddress,
+  findRoyaltyClaimAddress,
+} from "./pda";
+
+/**
+ * Finds the address of the royalty policy of the verified collection of a
+ * mint, payments of a mint with a verified collection have to pass it
+ * @returns
+ */
+export const findMintCollectionPolicyAddress = async (
+  connection: Connection,
+  mint: PublicKey,
+  metadataProgramId?: PublicKey | null
+): Promise<PublicKey | undefined> => {
+  const mintMetadata = await tryNull(
+    Metadata.fromAccountAddress(
+      connection,
+      findMintMetadataAddress(mint, metadataProgramId)
+    )
+  );
+  return mintMetadata?.collection?.verified
+    ? findCollectionPolicyAddress(mintMetadata.collection.key)
+    : undefined;
+};
 
 export const withRemainingAccountsForPayment = async (
   transaction: Transaction,
@@ -35,7 +68,8 @@ export const withRemainingAccountsForPayment = async (
       mint,
       paymentMint,
       buySideTokenAccountId,
-      [issuerId.toString()]
+      [issuerId.toString()],
+      paymentManagerId
     );
   const mintMetadataId = findMintMetadataId(mint);
   const paymentRemainingAccounts = [
@@ -145,12 +179,21 @@ export const withRemainingAccountsForHandlePaymentWithRoyalties = async (
   mint: PublicKey,
   paymentMint: PublicKey,
   buySideTokenAccountId?: PublicKey,
-  excludeCreators?: string[]
+  excludeCreators?: string[],
+  paymentManagerId?: PublicKey,
+  paymentAmount?: BN,
+  takerFeeCollectorTokenAccountId?: PublicKey
 ): Promise<AccountMeta[]> => {
   const remainingAccounts: AccountMeta[] = [];
+  const paymentManager = paymentManagerId
+    ? await tryNull(getPaymentManager(connection, paymentManagerId))
+    : null;
   let metaplexMintData: Metadata | undefined;
   try {
-    const mintMetadataId = findMintMetadataId(mint);
+    const mintMetadataId = findMintMetadataAddress(
+      mint,
+      paymentManager?.parsed.metadataProgramId
+    );
     metaplexMintData = await Metadata.fromAccountAddress(
       connection,
       mintMetadataId
@@ -158,41 +201,130 @@ export const withRemainingAccountsForHandlePaymentWithRoyalties = async (
   } catch (e) {
     // pass
   }
-  if (metaplexMintData && metaplexMintData.data.creators) {
-    for (const creator of metaplexMintData.data.creators) {
+  // a creator config set for the mint takes precedence over metadata creators
+  const creatorConfig = paymentManagerId
+    ? await tryNull(
+        getCreatorConfig(
+          connection,
+          findCreatorConfigAddress(paymentManagerId, mint)
+        )
+      )
+    : null;
+  const creators =
+    metaplexMintData && creatorConfig
+      ? creatorConfig.parsed.creators
+      : metaplexMintData?.data.creators;
+  // the policy of the mint's verified collection can forbid waiving royalties
+  const collectionPolicy = metaplexMintData?.collection?.verified
+    ? await tryNull(
+        getCollectionPolicy(
+          connection,
+          findCollectionPolicyAddress(metaplexMintData.collection.key)
+        )
+      )
+    : null;
+  // no creators are paid when royalties are waived below the royalty min price
+  // unless the creators receive primary sale proceeds
+  const primarySale =
+    !!paymentManager?.parsed.handlePrimarySales &&
+    !!metaplexMintData &&
+    !metaplexMintData.primarySaleHappened;
+  const royaltiesWaived =
+    !primarySale &&
+    collectionPolicy?.parsed.allowWaivers !== false &&
+    !!paymentManager?.parsed.royaltyMinPrice &&
+    !!paymentAmount?.lt(paymentManager.parsed.royaltyMinPrice);
+  if (creators && !royaltiesWaived) {
+    for (const creator of creators) {
       if (creator.share !== 0) {
         const creatorAddress = new PublicKey(creator.address);
-        if (paymentMint.toString() === PublicKey.default.toString()) {
+        if (paymentManagerId && paymentManager?.parsed.claimRoyalties) {
+          // the creator's royalty claim takes the place of their token account
+          remainingAccounts.push({
+            pubkey: findRoyaltyClaimAddress(
+              paymentManagerId,
+              paymentMint,
+              creatorAddress
+            ),
+            isSigner: false,
+            isWritable: true,
+          });
+        } else if (paymentMint.toString() === PublicKey.default.toString()) {
           remainingAccounts.push({
             pubkey: new PublicKey(creator.address),
             isSigner: false,
             isWritable: true,
           });
         } else {
-          const creatorMintTokenAccount = excludeCreators?.includes(
-            creator.address.toString()
-          )
-            ? await findAta(paymentMint, creatorAddress, true)
-            : await withFindOrInitAssociatedTokenAccount(
-                transaction,
-                connection,
-                paymentMint,
-                creatorAddress,
-                wallet.publicKey,
-                true
-              );
+          // a missing token account is left missing when its royalty can be
+          // escrowed instead
+          const escrowsRoyalty =
+            !!paymentManagerId &&
+            !!paymentManager?.parsed.escrowUnreceivableRoyalties;
+          const creatorMintTokenAccount =
+            escrowsRoyalty ||
+            excludeCreators?.includes(creator.address.toString())
+              ? await findAta(paymentMint, creatorAddress, true)
+              : await withFindOrInitAssociatedTokenAccount(
+                  transaction,
+                  connection,
+                  paymentMint,
+                  creatorAddress,
+                  wallet.publicKey,
+                  true
+                );
           remainingAccounts.push({
             pubkey: creatorMintTokenAccount,
             isSigner: false,
             isWritable: true,
           });
+          if (paymentManagerId && escrowsRoyalty) {
+            // the creator's royalty claim follows their token account
+            remainingAccounts.push({
+              pubkey: findRoyaltyClaimAddress(
+                paymentManagerId,
+                paymentMint,
+                creatorAddress
+              ),
+              isSigner: false,
+              isWritable: true,
+            });
+          }
         }
       }
     }
+  } else if (
+    metaplexMintData &&
+    !royaltiesWaived &&
+    !paymentMint.equals(PublicKey.default) &&
+    paymentManager?.parsed.fallbackRoyaltyToUpdateAuthority
+  ) {
+    // without creators the update authority is paid in their place
+    remainingAccounts.push({
+      pubkey: await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        connection,
+        paymentMint,
+        metaplexMintData.updateAuthority,
+        wallet.publicKey,
+        true
+      ),
+      isSigner: false,
+      isWritable: true,
+    });
   }
 
   return [
     ...remainingAccounts,
+    ...(takerFeeCollectorTokenAccountId
+      ? [
+          {
+            pubkey: takerFeeCollectorTokenAccountId,
+            isSigner: false,
+            isWritable: true,
+          },
+        ]
+      : []),
     ...(buySideTokenAccountId
       ? [
           {
@@ -204,3 +336,34 @@ export const withRemainingAccountsForHandlePaymentWithRoyalties = async (
       : []),
   ];
 };
+
+/**
+ * Additional admin signers for payment managers with an authority threshold
+ */
+export const adminSignerAccounts = (
+  adminSignerIds: PublicKey[] = []
+): AccountMeta[] =>
+  adminSignerIds.map((adminSignerId) => ({
+    pubkey: adminSignerId,
+    isSigner: true,
+    isWritable: false,
+  }));
+
+/**
+ * Hash of a creator set the way the program computes it, sha256 of each
+ * address followed by its share in metadata order. Passed as the expected
+ * creator hash so a payment fails when the creators changed since listing
+ */
+export const creatorHash = (
+  creators: { address: PublicKey; share: number }[] | null
+): number[] => [
+  ...createHash("sha256")
+    .update(
+      Buffer.concat(
+        (creators ?? []).map(({ address, share }) =>
+          Buffer.concat([address.toBuffer(), Buffer.from([share])])
+        )
+      )
+    )
+    .digest(),
+];
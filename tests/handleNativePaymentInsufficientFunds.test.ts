@@ -0,0 +1,108 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { createMint, executeTransaction } from "@solana-nft-programs/common";
+
+import { findPaymentManagerAddress } from "../sdk/pda";
+import {
+  withHandleNativePaymentWithRoyalties,
+  withInit,
+} from "../sdk/transaction";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle native payment with insufficient funds", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const paymentAmount = new BN(LAMPORTS_PER_SOL);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+
+  // provider wallet pays the transaction fee, the payer only funds the payment
+  const fundedPayer = async (lamports: number) => {
+    const payer = Keypair.generate();
+    const airdrop = await provider.connection.requestAirdrop(
+      payer.publicKey,
+      lamports
+    );
+    await provider.connection.confirmTransaction(airdrop);
+    return payer;
+  };
+
+  const handlePayment = async (payer: Keypair) => {
+    const transaction = new web3.Transaction();
+    await withHandleNativePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      new Wallet(payer),
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        feeCollectorId: feeCollector.publicKey,
+        paymentTargetId: paymentReceiver.publicKey,
+        excludeCretors: [],
+      }
+    );
+    return executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true, signers: [payer] }
+    );
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    // mint without metadata so no creators are paid
+    [, mintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Payer with exactly enough lamports succeeds", async () => {
+    const rentExemptMinimum =
+      await provider.connection.getMinimumBalanceForRentExemption(0);
+    const payer = await fundedPayer(
+      paymentAmount.add(takerFee).toNumber() + rentExemptMinimum
+    );
+    await handlePayment(payer);
+    expect(await provider.connection.getBalance(payer.publicKey)).toEqual(
+      rentExemptMinimum
+    );
+  });
+
+  it("Payer just short of enough lamports fails", async () => {
+    const rentExemptMinimum =
+      await provider.connection.getMinimumBalanceForRentExemption(0);
+    const payer = await fundedPayer(
+      paymentAmount.add(takerFee).toNumber() + rentExemptMinimum - 1
+    );
+    await expect(handlePayment(payer)).rejects.toThrow();
+  });
+});
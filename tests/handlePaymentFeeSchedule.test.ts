@@ -0,0 +1,179 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { getPaymentManager } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import {
+  withInit,
+  withManagePayment,
+  withQueueFeeSchedule,
+} from "../sdk/transaction";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with a queued fee schedule", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const PENDING_MAKER_FEE = new BN(200);
+  const PENDING_TAKER_FEE = new BN(100);
+  const ACTIVATION_DELAY_SECONDS = 5;
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const PAYMENT_AMOUNT = new BN(1000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let activationTime: number;
+  let provider: SolanaProvider;
+
+  const blockTime = async () =>
+    (await provider.connection.getBlockTime(
+      await provider.connection.getSlot()
+    )) ?? 0;
+
+  const handlePayment = async () => {
+    const transaction = new web3.Transaction();
+    const payerTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      provider.wallet.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    const feeCollectorTokenAccountId =
+      await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        provider.connection,
+        paymentMintId,
+        feeCollector.publicKey,
+        provider.wallet.publicKey,
+        true
+      );
+    const paymentTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      paymentReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    const accountIds = [
+      payerTokenAccountId,
+      feeCollectorTokenAccountId,
+      paymentTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await withManagePayment(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      paymentAmount: PAYMENT_AMOUNT,
+      payerTokenAccountId,
+      feeCollectorTokenAccountId,
+      paymentTokenAccountId,
+      paymentMintId,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return after.map((amount, i) => amount - (before[i] ?? 0));
+  };
+
+  const expectFees = (deltas: number[], makerFeeBps: BN, takerFeeBps: BN) => {
+    const makerFee = PAYMENT_AMOUNT.mul(makerFeeBps).div(BASIS_POINTS_DIVISOR);
+    const takerFee = PAYMENT_AMOUNT.mul(takerFeeBps).div(BASIS_POINTS_DIVISOR);
+    expect(-(deltas[0] ?? 0)).toEqual(PAYMENT_AMOUNT.add(takerFee).toNumber());
+    expect(deltas[1]).toEqual(makerFee.add(takerFee).toNumber());
+    expect(deltas[2]).toEqual(PAYMENT_AMOUNT.sub(makerFee).toNumber());
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Queue fee schedule", async () => {
+    activationTime = (await blockTime()) + ACTIVATION_DELAY_SECONDS;
+    const transaction = new web3.Transaction();
+    await withQueueFeeSchedule(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        makerFeeBasisPoints: PENDING_MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: PENDING_TAKER_FEE.toNumber(),
+        activationTime: new BN(activationTime),
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(
+      paymentManagerData.parsed.pendingFeeSchedule?.activationTime.toNumber()
+    ).toEqual(activationTime);
+  });
+
+  it("Current fees apply before activation", async () => {
+    expectFees(await handlePayment(), MAKER_FEE, TAKER_FEE);
+
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(paymentManagerData.parsed.pendingFeeSchedule).not.toBeNull();
+  });
+
+  it("Queued fees apply after activation", async () => {
+    while ((await blockTime()) <= activationTime) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+    expectFees(await handlePayment(), PENDING_MAKER_FEE, PENDING_TAKER_FEE);
+
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(paymentManagerData.parsed.makerFeeBasisPoints).toEqual(
+      PENDING_MAKER_FEE.toNumber()
+    );
+    expect(paymentManagerData.parsed.pendingFeeSchedule).toBeNull();
+  }, 30000);
+});
@@ -0,0 +1,123 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { withInit, withManagePayment } from "../sdk/transaction";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with mismatched mint", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const PAYMENT_AMOUNT = new BN(1000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let otherMintId: PublicKey;
+  let provider: SolanaProvider;
+
+  // pays into a payment token account of the given mint
+  const handlePayment = async (
+    paymentTokenMintId: PublicKey,
+    instructionMintId = paymentMintId
+  ) => {
+    const transaction = new web3.Transaction();
+    const payerTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      provider.wallet.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    const feeCollectorTokenAccountId =
+      await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        provider.connection,
+        paymentMintId,
+        feeCollector.publicKey,
+        provider.wallet.publicKey,
+        true
+      );
+    const paymentTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentTokenMintId,
+      paymentReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await withManagePayment(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      paymentAmount: PAYMENT_AMOUNT,
+      payerTokenAccountId,
+      feeCollectorTokenAccountId,
+      paymentTokenAccountId,
+      paymentMintId: instructionMintId,
+    });
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true }
+    );
+    return getTokenAmount(provider.connection, paymentTokenAccountId);
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    [, otherMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey, amount: 1 }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Rejects a payment token account of another mint", async () => {
+    await expect(handlePayment(otherMintId)).rejects.toThrow();
+  });
+
+  it("Rejects a payment mint the token accounts do not hold", async () => {
+    await expect(handlePayment(paymentMintId, otherMintId)).rejects.toThrow();
+  });
+
+  it("Pays token accounts of the payment mint", async () => {
+    const received = await handlePayment(paymentMintId);
+    expect(received).toEqual(
+      PAYMENT_AMOUNT.sub(PAYMENT_AMOUNT.mul(MAKER_FEE).divn(10000)).toNumber()
+    );
+  });
+});
@@ -0,0 +1,151 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { getPaymentManager } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withInit, withManagePayment } from "../sdk/transaction";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with flat fee", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const FLAT_FEE_AMOUNT = new BN(25);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+
+  const tokenCreator = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+  });
+
+  it("Create payment manager", async () => {
+    const transaction = new web3.Transaction();
+
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+      flatFeeAmount: FLAT_FEE_AMOUNT,
+    });
+
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+
+    const checkPaymentManagerId = findPaymentManagerAddress(paymentManagerName);
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      checkPaymentManagerId
+    );
+    expect(paymentManagerData.parsed.name).toEqual(paymentManagerName);
+    expect(paymentManagerData.parsed.flatFeeAmount?.toNumber()).toEqual(
+      FLAT_FEE_AMOUNT.toNumber()
+    );
+  });
+
+  for (const paymentAmount of [new BN(0), new BN(1000), new BN(1000000)]) {
+    it(`Flat fee charged for payment amount ${paymentAmount.toString()}`, async () => {
+      const transaction = new web3.Transaction();
+
+      const payerTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        provider.connection,
+        paymentMintId,
+        provider.wallet.publicKey,
+        provider.wallet.publicKey,
+        true
+      );
+      const feeCollectorTokenAccountId =
+        await withFindOrInitAssociatedTokenAccount(
+          transaction,
+          provider.connection,
+          paymentMintId,
+          feeCollector.publicKey,
+          provider.wallet.publicKey,
+          true
+        );
+      const paymentTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        provider.connection,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        provider.wallet.publicKey,
+        true
+      );
+
+      const beforePayerAmount = await getTokenAmount(
+        provider.connection,
+        payerTokenAccountId
+      );
+      const beforeFeeCollectorAmount = await getTokenAmount(
+        provider.connection,
+        feeCollectorTokenAccountId
+      );
+      const beforePaymentAmount = await getTokenAmount(
+        provider.connection,
+        paymentTokenAccountId
+      );
+
+      await withManagePayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        {
+          paymentManagerName,
+          paymentAmount,
+          payerTokenAccountId,
+          feeCollectorTokenAccountId,
+          paymentTokenAccountId,
+          paymentMintId,
+        }
+      );
+      await executeTransaction(
+        provider.connection,
+        transaction,
+        provider.wallet
+      );
+
+      expect(
+        beforePayerAmount -
+          (await getTokenAmount(provider.connection, payerTokenAccountId))
+      ).toEqual(paymentAmount.add(FLAT_FEE_AMOUNT).toNumber());
+      expect(
+        (await getTokenAmount(
+          provider.connection,
+          feeCollectorTokenAccountId
+        )) - beforeFeeCollectorAmount
+      ).toEqual(FLAT_FEE_AMOUNT.toNumber());
+      expect(
+        (await getTokenAmount(provider.connection, paymentTokenAccountId)) -
+          beforePaymentAmount
+      ).toEqual(paymentAmount.toNumber());
+    });
+  }
+});
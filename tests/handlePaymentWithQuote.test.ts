@@ -0,0 +1,169 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { CONVERSION_RATE_DIVISOR } from "../sdk";
+import { withHandlePaymentWithQuote, withInit } from "../sdk/transaction";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with quote", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  // 1.5 payment mint base units per quote base unit
+  const CONVERSION_RATE = new BN(CONVERSION_RATE_DIVISOR).muln(3).divn(2);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let otherMintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const handlePayment = async (
+    quoteAmount: BN,
+    conversionRate: BN,
+    paymentTokenMintId = paymentMintId
+  ) => {
+    const transaction = new web3.Transaction();
+    const payerTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      provider.wallet.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    const feeCollectorTokenAccountId =
+      await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        provider.connection,
+        paymentMintId,
+        feeCollector.publicKey,
+        provider.wallet.publicKey,
+        true
+      );
+    const paymentTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentTokenMintId,
+      paymentReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    const accountIds = [
+      payerTokenAccountId,
+      feeCollectorTokenAccountId,
+      paymentTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await withHandlePaymentWithQuote(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        quoteAmount,
+        conversionRate,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        paymentMintId,
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return {
+      payer: (before[0] ?? 0) - (after[0] ?? 0),
+      feeCollector: (after[1] ?? 0) - (before[1] ?? 0),
+      paymentReceiver: (after[2] ?? 0) - (before[2] ?? 0),
+    };
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    [, otherMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey, amount: 1 }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+      quoteConversionRateBounds: {
+        minConversionRate: new BN(CONVERSION_RATE_DIVISOR),
+        maxConversionRate: new BN(CONVERSION_RATE_DIVISOR).muln(2),
+      },
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Converts the quote amount into the payment amount", async () => {
+    const quoteAmount = new BN(1000);
+    const paymentAmount = new BN(1500);
+    const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const received = await handlePayment(quoteAmount, CONVERSION_RATE);
+    expect(received.payer).toEqual(paymentAmount.add(takerFee).toNumber());
+    expect(received.feeCollector).toEqual(makerFee.add(takerFee).toNumber());
+    expect(received.paymentReceiver).toEqual(
+      paymentAmount.sub(makerFee).toNumber()
+    );
+  });
+
+  it("Rounds the payment amount up", async () => {
+    // 1001 * 1.5 = 1501.5
+    const quoteAmount = new BN(1001);
+    const paymentAmount = new BN(1502);
+    const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const received = await handlePayment(quoteAmount, CONVERSION_RATE);
+    expect(received.payer).toEqual(paymentAmount.add(takerFee).toNumber());
+    expect(received.paymentReceiver).toEqual(
+      paymentAmount.sub(makerFee).toNumber()
+    );
+  });
+
+  it("Rejects a conversion rate outside the bounds", async () => {
+    await expect(
+      handlePayment(new BN(1000), new BN(CONVERSION_RATE_DIVISOR).muln(3))
+    ).rejects.toThrow();
+  });
+
+  it("Rejects a payment token account of another mint", async () => {
+    await expect(
+      handlePayment(new BN(1000), CONVERSION_RATE, otherMintId)
+    ).rejects.toThrow();
+  });
+});
@@ -0,0 +1,167 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties and an additive buy side fee", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const buySideReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let buySideTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const buySideFee = paymentAmount
+    .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+    .divn(BASIS_POINTS_DIVISOR);
+
+  const initPaymentManager = async (
+    buySideAdditive: boolean
+  ): Promise<string> => {
+    const paymentManagerName = Math.random().toString(36).slice(2, 7);
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+      buySideAdditive,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    return paymentManagerName;
+  };
+
+  // returns the payer, payment target, fee collector and buy side deltas
+  const handlePayment = async (paymentManagerName: string) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        buySideTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [
+      payerTokenAccountId,
+      paymentTokenAccountId,
+      feeCollectorTokenAccountId,
+      buySideTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    return (
+      await Promise.all(
+        accountIds.map((id) => getTokenAmount(provider.connection, id))
+      )
+    ).map((amount, i) => amount - (before[i] ?? 0));
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: null,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    buySideTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Buy side fee is carved out of the principal by default", async () => {
+    const [payerDelta, paymentDelta, feeCollectorDelta, buySideDelta] =
+      await handlePayment(await initPaymentManager(false));
+    expect(payerDelta).toEqual(-paymentAmount.add(takerFee).toNumber());
+    expect(paymentDelta).toEqual(
+      paymentAmount.sub(makerFee).sub(buySideFee).toNumber()
+    );
+    expect(feeCollectorDelta).toEqual(makerFee.add(takerFee).toNumber());
+    expect(buySideDelta).toEqual(buySideFee.toNumber());
+  });
+
+  it("Additive buy side fee is charged to the payer", async () => {
+    const [payerDelta, paymentDelta, feeCollectorDelta, buySideDelta] =
+      await handlePayment(await initPaymentManager(true));
+    expect(payerDelta).toEqual(
+      -paymentAmount.add(takerFee).add(buySideFee).toNumber()
+    );
+    // the payment target only pays the maker fee
+    expect(paymentDelta).toEqual(paymentAmount.sub(makerFee).toNumber());
+    expect(feeCollectorDelta).toEqual(makerFee.add(takerFee).toNumber());
+    expect(buySideDelta).toEqual(buySideFee.toNumber());
+  });
+});
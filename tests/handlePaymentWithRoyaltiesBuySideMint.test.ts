@@ -0,0 +1,159 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties and a wrong mint buy side", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const buySideReceiver = Keypair.generate();
+  let otherMintId: PublicKey;
+  let buySideTokenAccountId: PublicKey;
+  let otherMintBuySideTokenAccountId: PublicKey;
+
+  const handlePayment = async (buySideId: PublicKey) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName),
+        buySideId
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+        buySideTokenAccountId: buySideId,
+      }
+    );
+    return executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true }
+    );
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    [, otherMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: null,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    buySideTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    otherMintBuySideTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      otherMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Buy side token account of another mint is rejected", async () => {
+    await expect(handlePayment(otherMintBuySideTokenAccountId)).rejects.toThrow(
+      "0x179f" // InvalidBuySideMint
+    );
+    expect(
+      await getTokenAmount(provider.connection, otherMintBuySideTokenAccountId)
+    ).toEqual(0);
+  });
+
+  it("Buy side token account of the payment mint is paid", async () => {
+    await handlePayment(buySideTokenAccountId);
+    expect(
+      await getTokenAmount(provider.connection, buySideTokenAccountId)
+    ).toEqual(
+      paymentAmount
+        .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+        .divn(BASIS_POINTS_DIVISOR)
+        .toNumber()
+    );
+  });
+});
@@ -0,0 +1,187 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import {
+  calculateCreatorFunds,
+  createNftWithCreators,
+  getTokenAmount,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties buy side reconciliation", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const ROYALTEE_FEE_SHARE = new BN(4500);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const paymentAmount = new BN(1000);
+  const sellerFeeBasisPoints = 100;
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creators = [
+    { address: Keypair.generate().publicKey, share: 15 },
+    { address: Keypair.generate().publicKey, share: 30 },
+    { address: Keypair.generate().publicKey, share: 55 },
+  ];
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const buySideReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const sellerFee = paymentAmount
+    .mul(new BN(sellerFeeBasisPoints))
+    .div(BASIS_POINTS_DIVISOR);
+  const totalFees = makerFee.add(takerFee).add(sellerFee);
+  const totalCreatorsFee = makerFee
+    .add(takerFee)
+    .mul(ROYALTEE_FEE_SHARE)
+    .div(BASIS_POINTS_DIVISOR)
+    .add(sellerFee);
+  const feesPaidOut = calculateCreatorFunds(
+    totalCreatorsFee,
+    creators.map(({ share }) => share)
+  ).reduce((sum, funds) => sum.add(funds), new BN(0));
+  const buySideFee = paymentAmount
+    .mul(new BN(DEFAULT_BUY_SIDE_FEE_SHARE))
+    .div(BASIS_POINTS_DIVISOR);
+
+  const handlePayment = async (buySideTokenAccountId?: PublicKey) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    const accountIds = [
+      payerTokenAccountId,
+      feeCollectorTokenAccountId,
+      paymentTokenAccountId,
+      ...(buySideTokenAccountId ? [buySideTokenAccountId] : []),
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId: mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: payerTokenAccountId,
+        feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+        paymentTokenAccountId: paymentTokenAccountId,
+        buySideTokenAccountId: buySideTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    const deltas = after.map((amount, i) => amount - (before[i] ?? 0));
+    return {
+      payer: -(deltas[0] ?? 0),
+      feeCollector: deltas[1] ?? 0,
+      paymentTarget: deltas[2] ?? 0,
+      buySide: deltas[3] ?? 0,
+    };
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      { sellerFeeBasisPoints, creators }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+      royaltyFeeShare: ROYALTEE_FEE_SHARE,
+    });
+    await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Buy side account present", async () => {
+    const buySideTokenAccountId = await findAta(
+      paymentMintId,
+      buySideReceiver.publicKey,
+      true
+    );
+    const deltas = await handlePayment(buySideTokenAccountId);
+    expect(deltas.buySide).toEqual(buySideFee.toNumber());
+    expect(deltas.feeCollector).toEqual(totalFees.sub(feesPaidOut).toNumber());
+    expect(deltas.paymentTarget).toEqual(
+      paymentAmount.add(takerFee).sub(totalFees).sub(buySideFee).toNumber()
+    );
+    expect(deltas.payer).toEqual(paymentAmount.add(takerFee).toNumber());
+  });
+
+  it("Buy side account absent", async () => {
+    const deltas = await handlePayment();
+    expect(deltas.feeCollector).toEqual(
+      totalFees.add(buySideFee).sub(feesPaidOut).toNumber()
+    );
+    expect(deltas.paymentTarget).toEqual(
+      paymentAmount.add(takerFee).sub(totalFees).sub(buySideFee).toNumber()
+    );
+    expect(deltas.payer).toEqual(paymentAmount.add(takerFee).toNumber());
+  });
+});
@@ -0,0 +1,147 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import {
+  calculateCreatorFunds,
+  createNftWithCreators,
+  getTokenAmount,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties asserting the payout balances", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const ROYALTY_FEE_SHARE = new BN(5000);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const totalFees = makerFee.add(takerFee);
+  const totalCreatorsFee = totalFees
+    .mul(ROYALTY_FEE_SHARE)
+    .divn(BASIS_POINTS_DIVISOR);
+  const buySideFee = paymentAmount
+    .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+    .divn(BASIS_POINTS_DIVISOR);
+  // the token creator is listed first with a share of 0
+  const [, creator1Fee, creator2Fee] = calculateCreatorFunds(
+    totalCreatorsFee,
+    [0, 60, 40]
+  );
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: [
+          { address: creator1.publicKey, share: 60 },
+          { address: creator2.publicKey, share: 40 },
+        ],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Checked payment settles every payout in full", async () => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+        checked: true,
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+
+    // a mint taking a cut of transfers would fail with BalanceMismatch
+    expect(
+      await getTokenAmount(
+        provider.connection,
+        await findAta(paymentMintId, creator1.publicKey, true)
+      )
+    ).toEqual(creator1Fee?.toNumber());
+    expect(
+      await getTokenAmount(
+        provider.connection,
+        await findAta(paymentMintId, creator2.publicKey, true)
+      )
+    ).toEqual(creator2Fee?.toNumber());
+    expect(
+      await getTokenAmount(provider.connection, feeCollectorTokenAccountId)
+    ).toEqual(totalFees.sub(totalCreatorsFee).add(buySideFee).toNumber());
+    expect(
+      await getTokenAmount(provider.connection, paymentTokenAccountId)
+    ).toEqual(paymentAmount.sub(makerFee).sub(buySideFee).toNumber());
+  });
+});
@@ -0,0 +1,183 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { DEFAULT_MAX_ROYALTY_BASIS_POINTS } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties with clamped seller fee", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const ROYALTEE_FEE_SHARE = new BN(4500);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const MAX_ROYALTY_BASIS_POINTS = 2000;
+  const paymentAmount = new BN(1000);
+  // degenerate seller fee taking the whole payment
+  const sellerFeeBasisPoints = 10000;
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const clampedPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const defaultClampedPaymentManagerName = Math.random()
+    .toString(36)
+    .slice(2, 7);
+  const rejectingPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const creatorFee = (maxRoyaltyBasisPoints: number) =>
+    makerFee
+      .add(takerFee)
+      .mul(ROYALTEE_FEE_SHARE)
+      .div(BASIS_POINTS_DIVISOR)
+      .add(
+        paymentAmount
+          .mul(new BN(maxRoyaltyBasisPoints))
+          .div(BASIS_POINTS_DIVISOR)
+      );
+
+  const handlePayment = async (paymentManagerName: string) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId: mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: payerTokenAccountId,
+        feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+        paymentTokenAccountId: paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    return transaction;
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints,
+        creators: [{ address: creator.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    for (const [paymentManagerName, maxRoyaltyBasisPoints, clampRoyalties] of [
+      [clampedPaymentManagerName, MAX_ROYALTY_BASIS_POINTS, true],
+      [defaultClampedPaymentManagerName, undefined, true],
+      [rejectingPaymentManagerName, MAX_ROYALTY_BASIS_POINTS, false],
+    ] as const) {
+      await withInit(transaction, provider.connection, provider.wallet, {
+        paymentManagerName,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: TAKER_FEE.toNumber(),
+        includeSellerFeeBasisPoints: true,
+        royaltyFeeShare: ROYALTEE_FEE_SHARE,
+        maxRoyaltyBasisPoints,
+        clampRoyalties,
+      });
+    }
+    await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      creator.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  for (const [name, paymentManagerName, maxRoyaltyBasisPoints] of [
+    ["configured", clampedPaymentManagerName, MAX_ROYALTY_BASIS_POINTS],
+    [
+      "default",
+      defaultClampedPaymentManagerName,
+      DEFAULT_MAX_ROYALTY_BASIS_POINTS,
+    ],
+  ] as const) {
+    it(`Seller fee clamped to ${name} max royalty basis points`, async () => {
+      const creatorTokenAccountId = await findAta(
+        paymentMintId,
+        creator.publicKey,
+        true
+      );
+      const beforeCreatorAmount = await getTokenAmount(
+        provider.connection,
+        creatorTokenAccountId
+      );
+      const transaction = await handlePayment(paymentManagerName);
+      await executeTransaction(
+        provider.connection,
+        transaction,
+        provider.wallet
+      );
+      expect(
+        (await getTokenAmount(provider.connection, creatorTokenAccountId)) -
+          beforeCreatorAmount
+      ).toEqual(creatorFee(maxRoyaltyBasisPoints).toNumber());
+    });
+  }
+
+  it("Seller fee above max royalty basis points rejected", async () => {
+    const transaction = await handlePayment(rejectingPaymentManagerName);
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+});
@@ -0,0 +1,305 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, PAYMENT_MANAGER_ADDRESS } from "../sdk";
+import { getCollectionPolicy } from "../sdk/accounts";
+import { findCollectionPolicyAddress } from "../sdk/pda";
+import {
+  withHandlePaymentWithRoyalties,
+  withInit,
+  withSetCollectionPolicy,
+  withValidatePaymentAccounts,
+} from "../sdk/transaction";
+import {
+  createNftWithCreators,
+  getTokenAmount,
+  verifyCollection,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties under a collection policy", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const ENFORCED_FEE = 500;
+  const paymentAmount = new BN(10000);
+  // royalties are waived for every payment in this test without the policy
+  const royaltyMinPrice = new BN(20000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let collectionId: PublicKey;
+  let collectionPolicyId: PublicKey;
+  let creatorTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  // the payment manager does not include the seller fee, the policy enforces
+  // it at ENFORCED_FEE on top of the royalty share of the maker-taker fees
+  const royaltyFee = paymentAmount
+    .mul(MAKER_FEE.add(TAKER_FEE))
+    .divn(BASIS_POINTS_DIVISOR)
+    .divn(2);
+  const enforcedFee = paymentAmount
+    .muln(ENFORCED_FEE)
+    .divn(BASIS_POINTS_DIVISOR);
+
+  const createNft = (collectionId?: PublicKey) =>
+    createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 200,
+        creators: [{ address: creator.publicKey, share: 100 }],
+        collectionId,
+      }
+    );
+
+  // swaps the collection policy for the program id
+  const omitCollectionPolicy = (transaction: web3.Transaction) => {
+    for (const instruction of transaction.instructions) {
+      if (!instruction.programId.equals(PAYMENT_MANAGER_ADDRESS)) continue;
+      for (const key of instruction.keys) {
+        if (key.pubkey.equals(collectionPolicyId)) {
+          key.pubkey = PAYMENT_MANAGER_ADDRESS;
+        }
+      }
+    }
+  };
+
+  // omitPolicy swaps the collection policy for the program id
+  const handlePayment = async (mintId: PublicKey, omitPolicy = false) => {
+    const transaction = new web3.Transaction();
+    const feeCollectorTokenAccountId =
+      await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        provider.connection,
+        paymentMintId,
+        feeCollector.publicKey,
+        provider.wallet.publicKey,
+        true
+      );
+    const paymentTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      paymentReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    if (omitPolicy) omitCollectionPolicy(transaction);
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true }
+    );
+  };
+
+  const createVerifiedNft = async () => {
+    const mintId = await createNft(collectionId);
+    await verifyCollection(
+      provider.connection,
+      tokenCreator,
+      mintId,
+      collectionId
+    );
+    return mintId;
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    collectionId = await createNft();
+    collectionPolicyId = findCollectionPolicyAddress(collectionId);
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+      royaltyMinPrice,
+    });
+    creatorTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      creator.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Royalties are waived without a policy", async () => {
+    await handlePayment(await createVerifiedNft());
+    expect(
+      await getTokenAmount(provider.connection, creatorTokenAccountId)
+    ).toEqual(0);
+  });
+
+  it("Only the collection authority sets the policy", async () => {
+    const transaction = new web3.Transaction();
+    await withSetCollectionPolicy(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        collectionMintId: collectionId,
+        enforceRoyalties: true,
+        enforcedBasisPoints: ENFORCED_FEE,
+        allowWaivers: false,
+      }
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow("0x1771");
+  });
+
+  it("Collection authority sets the policy", async () => {
+    const transaction = new web3.Transaction();
+    await withSetCollectionPolicy(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        collectionMintId: collectionId,
+        enforceRoyalties: true,
+        enforcedBasisPoints: ENFORCED_FEE,
+        allowWaivers: false,
+        collectionAuthority: tokenCreator.publicKey,
+      }
+    );
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { signers: [tokenCreator] }
+    );
+    const collectionPolicy = await getCollectionPolicy(
+      provider.connection,
+      collectionPolicyId
+    );
+    expect(collectionPolicy.parsed.collection.toString()).toEqual(
+      collectionId.toString()
+    );
+    expect(collectionPolicy.parsed.enforceRoyalties).toEqual(true);
+    expect(collectionPolicy.parsed.enforcedBasisPoints).toEqual(ENFORCED_FEE);
+    expect(collectionPolicy.parsed.allowWaivers).toEqual(false);
+  });
+
+  it("Policy enforces royalties the payment manager would waive", async () => {
+    await handlePayment(await createVerifiedNft());
+    // the seller fee is charged at the enforced rate, above the metadata's
+    expect(
+      await getTokenAmount(provider.connection, creatorTokenAccountId)
+    ).toEqual(royaltyFee.add(enforcedFee).toNumber());
+  });
+
+  it("Payer cannot leave out the policy", async () => {
+    const mintId = await createVerifiedNft();
+    await expect(handlePayment(mintId, true)).rejects.toThrow("0x17a1");
+  });
+
+  it("Validation cannot leave out the policy", async () => {
+    const mintId = await createVerifiedNft();
+    const transaction = await withValidatePaymentAccounts(
+      new web3.Transaction(),
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId: await findAta(
+          paymentMintId,
+          feeCollector.publicKey,
+          true
+        ),
+        paymentTokenAccountId: await findAta(
+          paymentMintId,
+          paymentReceiver.publicKey,
+          true
+        ),
+      }
+    );
+    // the creator token account the policy enforces is validated
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true }
+    );
+    omitCollectionPolicy(transaction);
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow("0x17a1"); // InvalidCollectionPolicy
+  });
+
+  it("Policy does not govern an unverified mint", async () => {
+    const creatorAmount = await getTokenAmount(
+      provider.connection,
+      creatorTokenAccountId
+    );
+    await handlePayment(await createNft(collectionId));
+    // royalties are still waived below the royalty min price
+    expect(
+      await getTokenAmount(provider.connection, creatorTokenAccountId)
+    ).toEqual(creatorAmount);
+  });
+});
@@ -0,0 +1,200 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { getCreatorConfig } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import {
+  withHandlePaymentWithRoyalties,
+  withInit,
+  withSetCreatorConfig,
+} from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties from creator config", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const ROYALTEE_FEE_SHARE = new BN(4500);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const paymentAmount = new BN(1000);
+  const sellerFeeBasisPoints = 100;
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const metadataCreator = Keypair.generate();
+  const configCreator = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const totalCreatorsFee = makerFee
+    .add(takerFee)
+    .mul(ROYALTEE_FEE_SHARE)
+    .div(BASIS_POINTS_DIVISOR)
+    .add(
+      paymentAmount.mul(new BN(sellerFeeBasisPoints)).div(BASIS_POINTS_DIVISOR)
+    );
+
+  // returns the amounts received by the metadata and config creators
+  const handlePayment = async () => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const creatorTokenAccountIds = await Promise.all(
+      [metadataCreator, configCreator].map(({ publicKey }) =>
+        findAta(paymentMintId, publicKey, true)
+      )
+    );
+    const before = await Promise.all(
+      creatorTokenAccountIds.map((id) =>
+        getTokenAmount(provider.connection, id)
+      )
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId: mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+        paymentTokenAccountId: paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      creatorTokenAccountIds.map((id) =>
+        getTokenAmount(provider.connection, id)
+      )
+    );
+    return {
+      metadataCreator: (after[0] ?? 0) - (before[0] ?? 0),
+      configCreator: (after[1] ?? 0) - (before[1] ?? 0),
+    };
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints,
+        creators: [{ address: metadataCreator.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+      royaltyFeeShare: ROYALTEE_FEE_SHARE,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Metadata creators paid without creator config", async () => {
+    const received = await handlePayment();
+    expect(received.metadataCreator).toEqual(totalCreatorsFee.toNumber());
+    expect(received.configCreator).toEqual(0);
+  });
+
+  it("Set creator config with non update authority fails", async () => {
+    const transaction = new web3.Transaction();
+    await withSetCreatorConfig(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        mintId,
+        creators: [{ address: configCreator.publicKey, share: 100 }],
+      }
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+
+  it("Set creator config", async () => {
+    const transaction = new web3.Transaction();
+    const [, creatorConfigId] = await withSetCreatorConfig(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        mintId,
+        creators: [{ address: configCreator.publicKey, share: 100 }],
+        collectionAuthority: tokenCreator.publicKey,
+      }
+    );
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { signers: [tokenCreator] }
+    );
+
+    const creatorConfig = await getCreatorConfig(
+      provider.connection,
+      creatorConfigId
+    );
+    expect(creatorConfig.parsed.mint.toString()).toEqual(mintId.toString());
+    expect(creatorConfig.parsed.creators.length).toEqual(1);
+  });
+
+  it("Creator config creators paid instead of metadata creators", async () => {
+    const received = await handlePayment();
+    expect(received.metadataCreator).toEqual(0);
+    expect(received.configCreator).toEqual(totalCreatorsFee.toNumber());
+  });
+});
@@ -0,0 +1,139 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import { Metadata } from "@metaplex-foundation/mpl-token-metadata";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  findMintMetadataId,
+} from "@solana-nft-programs/common";
+
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { creatorHash, withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties and an expected creator hash", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const SELLER_FEE = new BN(500);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const handlePayment = async (expectedCreatorHash: number[]) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+        expectedCreatorHash,
+      }
+    );
+    return executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true }
+    );
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: SELLER_FEE.toNumber(),
+        creators: [
+          { address: creator1.publicKey, share: 60 },
+          { address: creator2.publicKey, share: 40 },
+        ],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Payment with the hash of the metadata creators succeeds", async () => {
+    const metadata = await Metadata.fromAccountAddress(
+      provider.connection,
+      findMintMetadataId(mintId)
+    );
+    await handlePayment(creatorHash(metadata.data.creators));
+  });
+
+  it("Payment with the hash of other creators fails", async () => {
+    // the shares the mint was listed with before its metadata changed
+    await expect(
+      handlePayment(
+        creatorHash([
+          { address: tokenCreator.publicKey, share: 0 },
+          { address: creator1.publicKey, share: 40 },
+          { address: creator2.publicKey, share: 60 },
+        ])
+      )
+    ).rejects.toThrow("0x179d"); // CreatorHashMismatch
+  });
+
+  it("Payment with the hash of no creators fails", async () => {
+    await expect(handlePayment(creatorHash(null))).rejects.toThrow("0x179d");
+  });
+});
@@ -0,0 +1,145 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  findMintMetadataId,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties and an empty metadata account", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const buySideReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let buySideTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    // a mint without metadata, its metadata address holds no data
+    [, mintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      // a seller fee would be charged if the mint had metadata
+      includeSellerFeeBasisPoints: true,
+    });
+    buySideTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Only maker, taker and buy side fees are applied", async () => {
+    expect(
+      await provider.connection.getAccountInfo(findMintMetadataId(mintId))
+    ).toBeNull();
+
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        buySideTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [
+      payerTokenAccountId,
+      feeCollectorTokenAccountId,
+      buySideTokenAccountId,
+      paymentTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const [payerDelta, feeCollectorDelta, buySideDelta, paymentDelta] = (
+      await Promise.all(
+        accountIds.map((id) => getTokenAmount(provider.connection, id))
+      )
+    ).map((amount, i) => amount - (before[i] ?? 0));
+
+    const makerFee = paymentAmount.mul(MAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+    const takerFee = paymentAmount.mul(TAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+    const buySideFee = paymentAmount
+      .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+      .divn(BASIS_POINTS_DIVISOR);
+    expect(payerDelta).toEqual(-paymentAmount.add(takerFee).toNumber());
+    // no royalty share is split off the maker and taker fees
+    expect(feeCollectorDelta).toEqual(makerFee.add(takerFee).toNumber());
+    expect(buySideDelta).toEqual(buySideFee.toNumber());
+    // no seller fee is taken out of the principal
+    expect(paymentDelta).toEqual(
+      paymentAmount.sub(makerFee).sub(buySideFee).toNumber()
+    );
+  });
+});
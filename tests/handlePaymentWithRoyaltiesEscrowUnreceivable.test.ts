@@ -0,0 +1,244 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { getRoyaltyClaim } from "../sdk/accounts";
+import {
+  findPaymentManagerAddress,
+  findRoyaltyClaimAddress,
+  findRoyaltyEscrowAddress,
+} from "../sdk/pda";
+import {
+  withClaimRoyalty,
+  withHandlePaymentWithRoyalties,
+  withInit,
+} from "../sdk/transaction";
+import {
+  calculateCreatorFunds,
+  createNftWithCreators,
+  getTokenAmount,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties escrowed for creators who cannot receive", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const SELLER_FEE = new BN(500);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const ROYALTY_FEE_SHARE = new BN(5000);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let creator1TokenAccountId: PublicKey;
+  let royaltyEscrowId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const totalCreatorsFee = makerFee
+    .add(takerFee)
+    .mul(ROYALTY_FEE_SHARE)
+    .div(BASIS_POINTS_DIVISOR)
+    .add(paymentAmount.mul(SELLER_FEE).div(BASIS_POINTS_DIVISOR));
+  // the token creator is listed first with a share of 0
+  const [, creator1Fee, creator2Fee] = calculateCreatorFunds(
+    totalCreatorsFee,
+    [0, 60, 40]
+  );
+
+  const handlePayment = async () => {
+    const transaction = new web3.Transaction();
+    const feeCollectorTokenAccountId =
+      await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        provider.connection,
+        paymentMintId,
+        feeCollector.publicKey,
+        provider.wallet.publicKey,
+        true
+      );
+    const paymentTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      paymentReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  };
+
+  const claimedAmount = async (creator: Keypair): Promise<number> =>
+    (
+      await getRoyaltyClaim(
+        provider.connection,
+        findRoyaltyClaimAddress(
+          findPaymentManagerAddress(paymentManagerName),
+          paymentMintId,
+          creator.publicKey
+        )
+      )
+    ).parsed.amount.toNumber();
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: SELLER_FEE.toNumber(),
+        creators: [
+          { address: creator1.publicKey, share: 60 },
+          { address: creator2.publicKey, share: 40 },
+        ],
+      }
+    );
+    royaltyEscrowId = findRoyaltyEscrowAddress(
+      findPaymentManagerAddress(paymentManagerName),
+      paymentMintId
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+      escrowUnreceivableRoyalties: true,
+    });
+    // only creator1 has a token account for the payment mint
+    creator1TokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      creator1.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Royalty of a creator without a token account is escrowed", async () => {
+    await handlePayment();
+    expect(
+      await getTokenAmount(provider.connection, creator1TokenAccountId)
+    ).toEqual(creator1Fee?.toNumber());
+    // the sale goes through and creator2's royalty accrues to their claim
+    expect(
+      await provider.connection.getAccountInfo(
+        await findAta(paymentMintId, creator2.publicKey, true)
+      )
+    ).toBeNull();
+    expect(await claimedAmount(creator2)).toEqual(creator2Fee?.toNumber());
+    expect(
+      await getTokenAmount(provider.connection, royaltyEscrowId)
+    ).toEqual(creator2Fee?.toNumber());
+    // creator1 was paid directly and has no claim
+    expect(
+      await provider.connection.getAccountInfo(
+        findRoyaltyClaimAddress(
+          findPaymentManagerAddress(paymentManagerName),
+          paymentMintId,
+          creator1.publicKey
+        )
+      )
+    ).toBeNull();
+  });
+
+  it("Creator claims their escrowed royalty", async () => {
+    const transaction = new web3.Transaction();
+    const creator2TokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      creator2.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await withClaimRoyalty(
+      transaction,
+      provider.connection,
+      new Wallet(creator2),
+      {
+        paymentManagerName,
+        paymentMintId,
+        creatorTokenAccountId: creator2TokenAccountId,
+      }
+    );
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { signers: [creator2] }
+    );
+    expect(
+      await getTokenAmount(provider.connection, creator2TokenAccountId)
+    ).toEqual(creator2Fee?.toNumber());
+    expect(await claimedAmount(creator2)).toEqual(0);
+    expect(
+      await getTokenAmount(provider.connection, royaltyEscrowId)
+    ).toEqual(0);
+  });
+
+  it("Creator who can receive again is paid directly", async () => {
+    await handlePayment();
+    const creator2TokenAccountId = await findAta(
+      paymentMintId,
+      creator2.publicKey,
+      true
+    );
+    expect(
+      await getTokenAmount(provider.connection, creator2TokenAccountId)
+    ).toEqual(creator2Fee?.muln(2).toNumber());
+    expect(await claimedAmount(creator2)).toEqual(0);
+  });
+});
@@ -0,0 +1,206 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties with exact output", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const sellerFeeBasisPoints = 333;
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const waivedPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const ROYALTY_MIN_PRICE = new BN(10000);
+  const feeCollector = Keypair.generate();
+  const creator = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints,
+        creators: [{ address: creator.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    for (const [name, royaltyMinPrice] of [
+      [paymentManagerName, undefined],
+      [waivedPaymentManagerName, ROYALTY_MIN_PRICE],
+    ] as const) {
+      await withInit(transaction, provider.connection, provider.wallet, {
+        paymentManagerName: name,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: TAKER_FEE.toNumber(),
+        includeSellerFeeBasisPoints: true,
+        royaltyMinPrice,
+      });
+    }
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  // returns what the payer paid, the payment target netted and the creator got
+  const pay = async (name: string, netAmount: BN) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(name)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    const creatorTokenAccountId = await findAta(
+      paymentMintId,
+      creator.publicKey,
+      true
+    );
+    const accountIds = [
+      payerTokenAccountId,
+      paymentTokenAccountId,
+      creatorTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName: name,
+        paymentAmount: netAmount,
+        exactOutput: true,
+        mintId: mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: payerTokenAccountId,
+        feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+        paymentTokenAccountId: paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return {
+      payer: (before[0] ?? 0) - (after[0] ?? 0),
+      paymentReceiver: (after[1] ?? 0) - (before[1] ?? 0),
+      creator: (after[2] ?? 0) - (before[2] ?? 0),
+    };
+  };
+
+  for (const netAmount of [new BN(1), new BN(997), new BN(1000000)]) {
+    it(`Payment target nets exactly ${netAmount.toString()}`, async () => {
+      const transaction = new web3.Transaction();
+      const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+        await withRemainingAccountsForPayment(
+          transaction,
+          provider.connection,
+          provider.wallet,
+          mintId,
+          paymentMintId,
+          paymentReceiver.publicKey,
+          findPaymentManagerAddress(paymentManagerName)
+        );
+      const payerTokenAccountId = await findAta(
+        paymentMintId,
+        provider.wallet.publicKey,
+        true
+      );
+      const beforePayerAmount = await getTokenAmount(
+        provider.connection,
+        payerTokenAccountId
+      );
+      const beforePaymentAmount = await getTokenAmount(
+        provider.connection,
+        paymentTokenAccountId
+      );
+      await withHandlePaymentWithRoyalties(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        {
+          paymentManagerName,
+          paymentAmount: netAmount,
+          exactOutput: true,
+          mintId: mintId,
+          paymentMintId: paymentMintId,
+          payerTokenAccountId: payerTokenAccountId,
+          feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+          paymentTokenAccountId: paymentTokenAccountId,
+          excludeCretors: [],
+        }
+      );
+      await executeTransaction(
+        provider.connection,
+        transaction,
+        provider.wallet
+      );
+
+      expect(
+        (await getTokenAmount(provider.connection, paymentTokenAccountId)) -
+          beforePaymentAmount
+      ).toEqual(netAmount.toNumber());
+      // the payer covers the fees on top of the net amount
+      expect(
+        beforePayerAmount -
+          (await getTokenAmount(provider.connection, payerTokenAccountId))
+      ).toBeGreaterThanOrEqual(netAmount.toNumber());
+    });
+  }
+
+  it("Waived royalties are not grossed up", async () => {
+    const netAmount = new BN(1000);
+    // grossed up for the maker fee and the buy side fee only, 1000 / 0.945
+    const paymentAmount = new BN(1059);
+    expect(paymentAmount.lt(ROYALTY_MIN_PRICE)).toBe(true);
+    const received = await pay(waivedPaymentManagerName, netAmount);
+    expect(received.paymentReceiver).toEqual(netAmount.toNumber());
+    expect(received.creator).toEqual(0);
+    expect(received.payer).toEqual(
+      paymentAmount.add(paymentAmount.mul(TAKER_FEE).divn(10000)).toNumber()
+    );
+  });
+});
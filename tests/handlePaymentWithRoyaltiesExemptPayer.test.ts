@@ -0,0 +1,183 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { getExemptPayer } from "../sdk/accounts";
+import { findExemptPayerAddress, findPaymentManagerAddress } from "../sdk/pda";
+import {
+  withHandlePaymentWithRoyalties,
+  withInit,
+  withSetExemptPayer,
+} from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties by an exempt payer", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 500,
+        creators: [{ address: creator1.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  const setExempt = async (exempt: boolean) => {
+    const transaction = new web3.Transaction();
+    await withSetExemptPayer(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      { paymentManagerName, payerId: provider.wallet.publicKey, exempt }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  };
+
+  // returns what the payer paid, the fee collector, the creator and the
+  // payment target received
+  const pay = async (): Promise<number[]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [
+      payerTokenAccountId,
+      feeCollectorTokenAccountId,
+      await findAta(paymentMintId, creator1.publicKey, true),
+      paymentTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return after.map((amount, i) =>
+      i === 0 ? (before[i] ?? 0) - amount : amount - (before[i] ?? 0)
+    );
+  };
+
+  it("Exempt payer pays the full amount with no fees", async () => {
+    await setExempt(true);
+    const exemptPayer = await getExemptPayer(
+      provider.connection,
+      findExemptPayerAddress(
+        findPaymentManagerAddress(paymentManagerName),
+        provider.wallet.publicKey
+      )
+    );
+    expect(exemptPayer.parsed.exempt).toEqual(true);
+
+    const [paid, feeCollectorDelta, creatorDelta, paymentDelta] = await pay();
+    expect(paid).toEqual(paymentAmount.toNumber());
+    expect(feeCollectorDelta).toEqual(0);
+    expect(creatorDelta).toEqual(0);
+    expect(paymentDelta).toEqual(paymentAmount.toNumber());
+  });
+
+  it("Payer pays fees again once no longer exempt", async () => {
+    await setExempt(false);
+    const [paid, feeCollectorDelta, creatorDelta, paymentDelta] = await pay();
+    expect(paid).toBeGreaterThan(paymentAmount.toNumber());
+    expect(feeCollectorDelta).toBeGreaterThan(0);
+    expect(creatorDelta).toBeGreaterThan(0);
+    expect(paymentDelta).toBeLessThan(paymentAmount.toNumber());
+  });
+
+  it("Only the authority can exempt a payer", async () => {
+    const other = Keypair.generate();
+    const airdrop = await provider.connection.requestAirdrop(
+      other.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdrop);
+    const transaction = new web3.Transaction();
+    await withSetExemptPayer(
+      transaction,
+      provider.connection,
+      new Wallet(other),
+      { paymentManagerName, payerId: other.publicKey, exempt: true }
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, new Wallet(other), {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+});
@@ -0,0 +1,187 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import {
+  calculateCreatorFunds,
+  createNftWithCreators,
+  getTokenAmount,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Fee collector residual of a payment with royalties", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const SELLER_FEE = new BN(500);
+  const ROYALTY_FEE_SHARE = new BN(5000);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const buySideReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let buySideTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerTakerFees = paymentAmount
+    .mul(MAKER_FEE.add(TAKER_FEE))
+    .divn(BASIS_POINTS_DIVISOR);
+  const buySideFee = paymentAmount
+    .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+    .divn(BASIS_POINTS_DIVISOR);
+
+  // what is left to the fee collector once the creators and the buy side are
+  // paid, the seller fee is charged on top of the maker-taker fees
+  const expectedFeeCollectorFee = (
+    hasCreators: boolean,
+    hasBuySide: boolean,
+    sellerFeeBasisPoints: BN
+  ): BN => {
+    const sellerFee = paymentAmount
+      .mul(sellerFeeBasisPoints)
+      .divn(BASIS_POINTS_DIVISOR);
+    const totalCreatorsFee = makerTakerFees
+      .mul(ROYALTY_FEE_SHARE)
+      .divn(BASIS_POINTS_DIVISOR)
+      .add(sellerFee);
+    const creatorsPaid = hasCreators
+      ? calculateCreatorFunds(totalCreatorsFee, [0, 60, 40]).reduce(
+          (sum, amount) => sum.add(amount),
+          new BN(0)
+        )
+      : new BN(0);
+    return makerTakerFees
+      .add(sellerFee)
+      .add(hasBuySide ? new BN(0) : buySideFee)
+      .sub(creatorsPaid);
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+    });
+    buySideTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  for (const hasCreators of [true, false]) {
+    for (const hasBuySide of [true, false]) {
+      for (const sellerFeeBasisPoints of [new BN(0), SELLER_FEE]) {
+        it(`Creators ${hasCreators ? "present" : "absent"}, buy side ${
+          hasBuySide ? "present" : "absent"
+        }, seller fee ${sellerFeeBasisPoints.toString()}`, async () => {
+          const mintId = await createNftWithCreators(
+            provider.connection,
+            tokenCreator,
+            provider.wallet.publicKey,
+            {
+              sellerFeeBasisPoints: sellerFeeBasisPoints.toNumber(),
+              creators: hasCreators
+                ? [
+                    { address: creator1.publicKey, share: 60 },
+                    { address: creator2.publicKey, share: 40 },
+                  ]
+                : null,
+            }
+          );
+          const transaction = new web3.Transaction();
+          const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+            await withRemainingAccountsForPayment(
+              transaction,
+              provider.connection,
+              provider.wallet,
+              mintId,
+              paymentMintId,
+              paymentReceiver.publicKey,
+              findPaymentManagerAddress(paymentManagerName)
+            );
+          await withHandlePaymentWithRoyalties(
+            transaction,
+            provider.connection,
+            provider.wallet,
+            {
+              paymentManagerName,
+              paymentAmount,
+              mintId,
+              paymentMintId,
+              payerTokenAccountId: await findAta(
+                paymentMintId,
+                provider.wallet.publicKey,
+                true
+              ),
+              feeCollectorTokenAccountId,
+              paymentTokenAccountId,
+              buySideTokenAccountId: hasBuySide
+                ? buySideTokenAccountId
+                : undefined,
+              excludeCretors: [],
+            }
+          );
+          const before = await getTokenAmount(
+            provider.connection,
+            feeCollectorTokenAccountId
+          );
+          await executeTransaction(
+            provider.connection,
+            transaction,
+            provider.wallet
+          );
+          const after = await getTokenAmount(
+            provider.connection,
+            feeCollectorTokenAccountId
+          );
+          expect(after - before).toEqual(
+            expectedFeeCollectorFee(
+              hasCreators,
+              hasBuySide,
+              sellerFeeBasisPoints
+            ).toNumber()
+          );
+        });
+      }
+    }
+  }
+});
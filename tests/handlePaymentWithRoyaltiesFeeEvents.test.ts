@@ -0,0 +1,214 @@
+import { BN, BorshCoder, EventParser, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import {
+  BASIS_POINTS_DIVISOR,
+  DEFAULT_BUY_SIDE_FEE_SHARE,
+  PAYMENT_MANAGER_ADDRESS,
+  PAYMENT_MANAGER_IDL,
+} from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { calculateCreatorFunds, createNftWithCreators } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+type FeeEvent = {
+  name: string;
+  data: { paymentManager: PublicKey; amount: BN; recipient: PublicKey };
+};
+
+describe("Handle payment with royalties emitting an event per fee", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const ROYALTY_FEE_SHARE = new BN(5000);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const buySideReceiver = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let buySideTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const totalFees = makerFee.add(takerFee);
+  const totalCreatorsFee = totalFees
+    .mul(ROYALTY_FEE_SHARE)
+    .divn(BASIS_POINTS_DIVISOR);
+  const buySideFee = paymentAmount
+    .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+    .divn(BASIS_POINTS_DIVISOR);
+  // the token creator is listed first with a share of 0
+  const [, creator1Fee, creator2Fee] = calculateCreatorFunds(
+    totalCreatorsFee,
+    [0, 60, 40]
+  );
+
+  // returns the events the payment emitted
+  const handlePayment = async (): Promise<FeeEvent[]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        buySideTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const txid = await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet
+    );
+    const parsedTransaction = await provider.connection.getTransaction(txid, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const eventParser = new EventParser(
+      PAYMENT_MANAGER_ADDRESS,
+      new BorshCoder(PAYMENT_MANAGER_IDL)
+    );
+    return [
+      ...eventParser.parseLogs(parsedTransaction?.meta?.logMessages ?? []),
+    ] as FeeEvent[];
+  };
+
+  const eventsNamed = (events: FeeEvent[], name: string) =>
+    events
+      .filter((event) => event.name === name)
+      .map(({ data }) => ({
+        amount: data.amount.toNumber(),
+        recipient: data.recipient.toString(),
+      }));
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: [
+          { address: creator1.publicKey, share: 60 },
+          { address: creator2.publicKey, share: 40 },
+        ],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    buySideTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Each fee is reported with its amount and recipient", async () => {
+    const events = await handlePayment();
+    const paymentManagerId = findPaymentManagerAddress(paymentManagerName);
+    expect(
+      events.every(
+        ({ data }) =>
+          data.paymentManager.toString() === paymentManagerId.toString()
+      )
+    ).toBeTruthy();
+
+    expect(eventsNamed(events, "MakerFeeCharged")).toEqual([
+      {
+        amount: makerFee.toNumber(),
+        recipient: feeCollector.publicKey.toString(),
+      },
+    ]);
+    expect(eventsNamed(events, "TakerFeeCharged")).toEqual([
+      {
+        amount: takerFee.toNumber(),
+        recipient: feeCollector.publicKey.toString(),
+      },
+    ]);
+    expect(eventsNamed(events, "SellerFeeDistributed")).toEqual([
+      {
+        amount: creator1Fee?.toNumber(),
+        recipient: creator1.publicKey.toString(),
+      },
+      {
+        amount: creator2Fee?.toNumber(),
+        recipient: creator2.publicKey.toString(),
+      },
+    ]);
+    expect(eventsNamed(events, "BuySideFeePaid")).toEqual([
+      {
+        amount: buySideFee.toNumber(),
+        recipient: buySideReceiver.publicKey.toString(),
+      },
+    ]);
+    expect(eventsNamed(events, "FeeCollectorPaid")).toEqual([
+      {
+        amount: totalFees.sub(totalCreatorsFee).toNumber(),
+        recipient: feeCollector.publicKey.toString(),
+      },
+    ]);
+  });
+});
@@ -0,0 +1,149 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties without any fee", () => {
+  // rounds down to a buy side fee of zero
+  const paymentAmount = new BN(100);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: null,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: 0,
+      takerFeeBasisPoints: 0,
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Whole payment is a single transfer to the payment target", async () => {
+    expect(
+      paymentAmount
+        .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+        .divn(BASIS_POINTS_DIVISOR)
+        .toNumber()
+    ).toEqual(0);
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const payerBefore = await getTokenAmount(
+      provider.connection,
+      payerTokenAccountId
+    );
+    const txid = await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet
+    );
+
+    const parsedTransaction = await provider.connection.getParsedTransaction(
+      txid,
+      { commitment: "confirmed", maxSupportedTransactionVersion: 0 }
+    );
+    const transfers = (parsedTransaction?.meta?.innerInstructions ?? [])
+      .filter(({ index }) => index === transaction.instructions.length - 1)
+      .flatMap(({ instructions }) => instructions)
+      .map((instruction) =>
+        "parsed" in instruction && instruction.program === "spl-token"
+          ? (instruction.parsed as {
+              type: string;
+              info: { destination: string; tokenAmount: { amount: string } };
+            })
+          : null
+      )
+      .filter((parsed) => parsed?.type === "transferChecked");
+    expect(transfers.length).toEqual(1);
+    expect(transfers[0]?.info.destination).toEqual(
+      paymentTokenAccountId.toString()
+    );
+    expect(transfers[0]?.info.tokenAmount.amount).toEqual(
+      paymentAmount.toString()
+    );
+
+    expect(
+      await getTokenAmount(provider.connection, paymentTokenAccountId)
+    ).toEqual(paymentAmount.toNumber());
+    expect(
+      await getTokenAmount(provider.connection, feeCollectorTokenAccountId)
+    ).toEqual(0);
+    expect(
+      await getTokenAmount(provider.connection, payerTokenAccountId)
+    ).toEqual(payerBefore - paymentAmount.toNumber());
+  });
+});
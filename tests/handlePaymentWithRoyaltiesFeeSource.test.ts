@@ -0,0 +1,240 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import {
+  ACCOUNT_SIZE,
+  createInitializeAccountInstruction,
+  createTransferInstruction,
+  getMinimumBalanceForRentExemptAccount,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL, SystemProgram } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import {
+  calculateCreatorFunds,
+  createNftWithCreators,
+  getTokenAmount,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties paid from a fee source account", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const SELLER_FEE = new BN(500);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const ROYALTY_FEE_SHARE = new BN(5000);
+  const FEE_BUDGET = new BN(100000);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  // a second token account of the payer holding the fee budget
+  const feeSource = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let otherMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const sellerFee = paymentAmount.mul(SELLER_FEE).div(BASIS_POINTS_DIVISOR);
+  const totalCreatorsFee = makerFee
+    .add(takerFee)
+    .mul(ROYALTY_FEE_SHARE)
+    .div(BASIS_POINTS_DIVISOR)
+    .add(sellerFee);
+  // the token creator is listed first with a share of 0
+  const [, creator1Fee, creator2Fee] = calculateCreatorFunds(
+    totalCreatorsFee,
+    [0, 60, 40]
+  );
+
+  const createTokenAccount = async (
+    tokenAccount: Keypair,
+    tokenMintId: PublicKey,
+    amount: BN
+  ) => {
+    const transaction = new web3.Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: provider.wallet.publicKey,
+        newAccountPubkey: tokenAccount.publicKey,
+        space: ACCOUNT_SIZE,
+        lamports: await getMinimumBalanceForRentExemptAccount(
+          provider.connection
+        ),
+        programId: TOKEN_PROGRAM_ID,
+      }),
+      createInitializeAccountInstruction(
+        tokenAccount.publicKey,
+        tokenMintId,
+        provider.wallet.publicKey
+      )
+    );
+    if (amount.gtn(0)) {
+      transaction.add(
+        createTransferInstruction(
+          await findAta(tokenMintId, provider.wallet.publicKey, true),
+          tokenAccount.publicKey,
+          provider.wallet.publicKey,
+          BigInt(amount.toString())
+        )
+      );
+    }
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { signers: [tokenAccount] }
+    );
+  };
+
+  const handlePayment = async (feeSourceTokenAccountId: PublicKey) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeSourceTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    return [
+      transaction,
+      paymentTokenAccountId,
+      feeCollectorTokenAccountId,
+    ] as const;
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    [, otherMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: SELLER_FEE.toNumber(),
+        creators: [
+          { address: creator1.publicKey, share: 60 },
+          { address: creator2.publicKey, share: 40 },
+        ],
+      }
+    );
+    await createTokenAccount(feeSource, paymentMintId, FEE_BUDGET);
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Fees and royalties are debited from the fee source", async () => {
+    const [transaction, paymentTokenAccountId, feeCollectorTokenAccountId] =
+      await handlePayment(feeSource.publicKey);
+    const accountIds = [
+      await findAta(paymentMintId, provider.wallet.publicKey, true),
+      feeSource.publicKey,
+      paymentTokenAccountId,
+      feeCollectorTokenAccountId,
+      await findAta(paymentMintId, creator1.publicKey, true),
+      await findAta(paymentMintId, creator2.publicKey, true),
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const [
+      payerDelta,
+      feeSourceDelta,
+      paymentDelta,
+      feeCollectorDelta,
+      creator1Delta,
+      creator2Delta,
+    ] = (
+      await Promise.all(
+        accountIds.map((id) => getTokenAmount(provider.connection, id))
+      )
+    ).map((amount, i) => amount - (before[i] ?? 0));
+
+    const totalFees = makerFee.add(takerFee).add(sellerFee);
+    const principal = paymentAmount.add(takerFee).sub(totalFees);
+    // the payer token account only covers the principal
+    expect(payerDelta).toEqual(-principal.toNumber());
+    expect(paymentDelta).toEqual(principal.toNumber());
+    // the fee source covers every fee
+    expect(feeSourceDelta).toEqual(-totalFees.toNumber());
+    expect(creator1Delta).toEqual(creator1Fee?.toNumber());
+    expect(creator2Delta).toEqual(creator2Fee?.toNumber());
+    expect(feeCollectorDelta).toEqual(
+      totalFees.sub(totalCreatorsFee).toNumber()
+    );
+  });
+
+  it("Fee source of another mint", async () => {
+    const otherFeeSource = Keypair.generate();
+    await createTokenAccount(otherFeeSource, otherMintId, new BN(0));
+    const [transaction] = await handlePayment(otherFeeSource.publicKey);
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow("0x1790"); // InvalidTokenAccountMint
+  });
+});
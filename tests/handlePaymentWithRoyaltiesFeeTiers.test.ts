@@ -0,0 +1,189 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { getPayerStats } from "../sdk/accounts";
+import { findPayerStatsAddress, findPaymentManagerAddress } from "../sdk/pda";
+import {
+  withHandlePaymentWithRoyalties,
+  withInit,
+  withSetFeeTiers,
+} from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties and volume fee tiers", () => {
+  const MAKER_FEE = 500;
+  const TAKER_FEE = 300;
+  const TIER_VOLUME = new BN(15000);
+  const TIER_MAKER_FEE = 200;
+  const TIER_TAKER_FEE = 100;
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    // no creators so the fee collector keeps the maker and taker fees
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      { sellerFeeBasisPoints: 0, creators: null }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE,
+      takerFeeBasisPoints: TAKER_FEE,
+      includeSellerFeeBasisPoints: false,
+    });
+    await withSetFeeTiers(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeTiers: [
+        {
+          minVolume: TIER_VOLUME,
+          makerFeeBasisPoints: TIER_MAKER_FEE,
+          takerFeeBasisPoints: TIER_TAKER_FEE,
+        },
+      ],
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  // returns what the payer paid and the fee collector received
+  const pay = async (): Promise<[number, number]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [payerTokenAccountId, feeCollectorTokenAccountId];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return [
+      (before[0] ?? 0) - (after[0] ?? 0),
+      (after[1] ?? 0) - (before[1] ?? 0),
+    ];
+  };
+
+  const payerVolume = async (): Promise<number> =>
+    (
+      await getPayerStats(
+        provider.connection,
+        findPayerStatsAddress(
+          findPaymentManagerAddress(paymentManagerName),
+          provider.wallet.publicKey
+        )
+      )
+    ).parsed.volume.toNumber();
+
+  const fees = (makerFee: number, takerFee: number): [number, number] => [
+    paymentAmount.muln(10000 + takerFee).divn(10000).toNumber(),
+    paymentAmount
+      .muln(makerFee)
+      .divn(10000)
+      .add(paymentAmount.muln(takerFee).divn(10000))
+      .toNumber(),
+  ];
+
+  it("Payments below the tier volume pay the base fees", async () => {
+    expect(await pay()).toEqual(fees(MAKER_FEE, TAKER_FEE));
+    expect(await payerVolume()).toEqual(paymentAmount.toNumber());
+    // the payment crossing the tier volume is still charged the base fees
+    expect(await pay()).toEqual(fees(MAKER_FEE, TAKER_FEE));
+    expect(await payerVolume()).toEqual(paymentAmount.muln(2).toNumber());
+  });
+
+  it("Payments past the tier volume pay the tier fees", async () => {
+    expect(await payerVolume()).toBeGreaterThanOrEqual(
+      TIER_VOLUME.toNumber()
+    );
+    expect(await pay()).toEqual(fees(TIER_MAKER_FEE, TIER_TAKER_FEE));
+    expect(await payerVolume()).toEqual(paymentAmount.muln(3).toNumber());
+  });
+
+  it("Fee tiers have to be sorted by volume", async () => {
+    const transaction = new web3.Transaction();
+    await withSetFeeTiers(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeTiers: [
+        {
+          minVolume: TIER_VOLUME,
+          makerFeeBasisPoints: TIER_MAKER_FEE,
+          takerFeeBasisPoints: TIER_TAKER_FEE,
+        },
+        {
+          minVolume: TIER_VOLUME.divn(2),
+          makerFeeBasisPoints: TIER_MAKER_FEE,
+          takerFeeBasisPoints: TIER_TAKER_FEE,
+        },
+      ],
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow("0x1798"); // InvalidFeeTiers
+  });
+});
@@ -0,0 +1,191 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+/**
+ * Reference walk through the fee waterfall of a payment with royalties. Every
+ * amount is written out instead of computed so that a change to any stage of
+ * the fee math fails this test.
+ *
+ * Payment manager: maker fee 500 bps, taker fee 300 bps, royalty fee share
+ * 5000 bps of the maker-taker fees, seller fee included and additive.
+ * Mint: seller fee 500 bps, creators 50 / 30 / 20.
+ * Payment amount: 10_070
+ *
+ * 1. maker fee          10_070 * 500 / 10_000          =    503 (503.5)
+ * 2. taker fee          10_070 * 300 / 10_000          =    302 (302.1)
+ * 3. seller fee         10_070 * 500 / 10_000          =    503 (503.5)
+ *    total fees         503 + 302 + 503                =  1_308
+ * 4. creators fee       (503 + 302) * 5000 / 10_000    =    402 (402.5)
+ *                       + seller fee 503               =    905
+ *    creator split      905 * 50 / 100                 =    452 (452.5)
+ *                       905 * 30 / 100                 =    271 (271.5)
+ *                       905 * 20 / 100                 =    181
+ *                       remainder 905 - 904            =      1
+ *                       to the first creator           -> 453 / 271 / 181
+ * 5. buy side fee       10_070 * 50 / 10_000           =     50 (50.35)
+ * 6. fee collector      1_308 + 50 - 905 - 50          =    403
+ * 7. target             10_070 + 302 - 1_308 - 50      =  9_014
+ *
+ * The payer is debited the payment plus the taker fee, 10_372, which is
+ * 9_014 + 453 + 271 + 181 + 50 + 403.
+ */
+describe("Handle payment with royalties fee waterfall", () => {
+  const paymentAmount = new BN(10_070);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const creator3 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const buySideReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let buySideTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 500,
+        creators: [
+          { address: creator1.publicKey, share: 50 },
+          { address: creator2.publicKey, share: 30 },
+          { address: creator3.publicKey, share: 20 },
+        ],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: 500,
+      takerFeeBasisPoints: 300,
+      royaltyFeeShare: new BN(5000),
+      includeSellerFeeBasisPoints: true,
+    });
+    buySideTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Every stage pays out the documented amount", async () => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        buySideTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const balances = {
+      payer: payerTokenAccountId,
+      creator1: await findAta(paymentMintId, creator1.publicKey, true),
+      creator2: await findAta(paymentMintId, creator2.publicKey, true),
+      creator3: await findAta(paymentMintId, creator3.publicKey, true),
+      buySide: buySideTokenAccountId,
+      feeCollector: feeCollectorTokenAccountId,
+      target: paymentTokenAccountId,
+    };
+    const readBalances = async () =>
+      Object.fromEntries(
+        await Promise.all(
+          Object.entries(balances).map(async ([name, id]) => [
+            name,
+            await getTokenAmount(provider.connection, id),
+          ])
+        )
+      ) as Record<keyof typeof balances, number>;
+
+    const before = await readBalances();
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await readBalances();
+    const delta = (name: keyof typeof balances) => after[name] - before[name];
+
+    // the payer covers the payment and the taker fee
+    expect(delta("payer")).toEqual(-10_372);
+    // 4. creators fee of 905, the remainder unit goes to the first creator
+    expect(delta("creator1")).toEqual(453);
+    expect(delta("creator2")).toEqual(271);
+    expect(delta("creator3")).toEqual(181);
+    // 5. buy side fee
+    expect(delta("buySide")).toEqual(50);
+    // 6. fee collector keeps the fees the creators and the buy side do not receive
+    expect(delta("feeCollector")).toEqual(403);
+    // 7. target receives the principal
+    expect(delta("target")).toEqual(9_014);
+    // nothing is created or lost along the way
+    expect(
+      delta("creator1") +
+        delta("creator2") +
+        delta("creator3") +
+        delta("buySide") +
+        delta("feeCollector") +
+        delta("target")
+    ).toEqual(-delta("payer"));
+  });
+});
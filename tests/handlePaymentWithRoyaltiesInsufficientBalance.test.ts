@@ -0,0 +1,123 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties with insufficient balance", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const paymentAmount = new BN(1000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+
+  // provider wallet pays the transaction fee, the payer only funds the payment
+  const handlePayment = async (payerAmount: number) => {
+    const payer = Keypair.generate();
+    const [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: payer.publicKey, amount: payerAmount }
+    );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      payer.publicKey,
+      true
+    );
+
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      new Wallet(payer),
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true, signers: [payer] }
+    );
+    return getTokenAmount(provider.connection, payerTokenAccountId);
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    // mint without metadata so no creators are paid
+    [, mintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Payer with exactly enough balance succeeds", async () => {
+    const payerAmount = paymentAmount.add(takerFee).toNumber();
+    expect(await handlePayment(payerAmount)).toEqual(0);
+  });
+
+  it("Payer covering the fees but not the principal fails", async () => {
+    const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    await expect(
+      handlePayment(makerFee.add(takerFee).muln(2).toNumber())
+    ).rejects.toThrow("0x1785");
+  });
+
+  it("Payer just short of enough balance fails", async () => {
+    await expect(
+      handlePayment(paymentAmount.add(takerFee).toNumber() - 1)
+    ).rejects.toThrow("0x1785");
+  });
+});
@@ -0,0 +1,167 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties to the maker token account", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const seller = Keypair.generate();
+  const otherSeller = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let feeCollectorTokenAccountId: PublicKey;
+  let makerTokenAccountId: PublicKey;
+  let otherMakerTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  const handlePayment = async (
+    sellerId: PublicKey,
+    makerTokenAccountId?: PublicKey
+  ) => {
+    const transaction = new web3.Transaction();
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        excludeCretors: [],
+        sellerId,
+        makerTokenAccountId,
+      }
+    );
+    return executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true }
+    );
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: null,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    feeCollectorTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      feeCollector.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    makerTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      seller.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    otherMakerTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      otherSeller.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Maker fee is deducted from the seller's proceeds", async () => {
+    const before = await getTokenAmount(
+      provider.connection,
+      makerTokenAccountId
+    );
+    const feeCollectorBefore = await getTokenAmount(
+      provider.connection,
+      feeCollectorTokenAccountId
+    );
+    await handlePayment(seller.publicKey, makerTokenAccountId);
+
+    const makerFee = paymentAmount.mul(MAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+    const takerFee = paymentAmount.mul(TAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+    // without a buy side account the buy side fee goes to the fee collector
+    const buySideFee = paymentAmount
+      .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+      .divn(BASIS_POINTS_DIVISOR);
+    expect(
+      (await getTokenAmount(provider.connection, makerTokenAccountId)) - before
+    ).toEqual(paymentAmount.sub(makerFee).sub(buySideFee).toNumber());
+    expect(
+      (await getTokenAmount(provider.connection, feeCollectorTokenAccountId)) -
+        feeCollectorBefore
+    ).toEqual(makerFee.add(takerFee).add(buySideFee).toNumber());
+  });
+
+  it("Maker token account of another seller is rejected", async () => {
+    await expect(
+      handlePayment(seller.publicKey, otherMakerTokenAccountId)
+    ).rejects.toThrow(
+      "0x179e" // InvalidMakerTokenAccount
+    );
+  });
+
+  it("Seller without a maker token account is rejected", async () => {
+    await expect(handlePayment(seller.publicKey)).rejects.toThrow(
+      "0x179e" // InvalidMakerTokenAccount
+    );
+  });
+});
@@ -0,0 +1,151 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties with malformed buy side account", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const paymentAmount = new BN(1000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const lenientPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const strictPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+
+  const tokenCreator = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const handlePayment = async (paymentManagerName: string) => {
+    const transaction = new web3.Transaction();
+    const paymentManagerId = findPaymentManagerAddress(paymentManagerName);
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        paymentManagerId
+      );
+    const payerTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      provider.wallet.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId: mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: payerTokenAccountId,
+        feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+        paymentTokenAccountId: paymentTokenAccountId,
+        // a mint is not a valid token account
+        buySideTokenAccountId: paymentMintId,
+        excludeCretors: [],
+      }
+    );
+    return {
+      transaction,
+      feeCollectorTokenAccountId,
+    };
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+
+    [, mintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    for (const [paymentManagerName, strictRemainingAccounts] of [
+      [lenientPaymentManagerName, false],
+      [strictPaymentManagerName, true],
+    ] as const) {
+      await withInit(transaction, provider.connection, provider.wallet, {
+        paymentManagerName,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: TAKER_FEE.toNumber(),
+        includeSellerFeeBasisPoints: false,
+        strictRemainingAccounts,
+      });
+    }
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Lenient mode pays buy side fee to fee collector", async () => {
+    const { transaction, feeCollectorTokenAccountId } = await handlePayment(
+      lenientPaymentManagerName
+    );
+    const beforeFeeCollectorAmount = await getTokenAmount(
+      provider.connection,
+      feeCollectorTokenAccountId
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+
+    const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const buySideFee = paymentAmount
+      .mul(new BN(DEFAULT_BUY_SIDE_FEE_SHARE))
+      .div(BASIS_POINTS_DIVISOR);
+    expect(
+      (await getTokenAmount(
+        provider.connection,
+        feeCollectorTokenAccountId
+      )) - beforeFeeCollectorAmount
+    ).toEqual(makerFee.add(takerFee).add(buySideFee).toNumber());
+  });
+
+  it("Strict mode rejects malformed buy side account", async () => {
+    const { transaction } = await handlePayment(strictPaymentManagerName);
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+});
@@ -0,0 +1,190 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import {
+  Metadata,
+  PROGRAM_ID as TOKEN_METADATA_PROGRAM_ID,
+} from "@metaplex-foundation/mpl-token-metadata";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  findMintMetadataId,
+} from "@solana-nft-programs/common";
+
+import { DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import {
+  calculateCreatorFunds,
+  createNftWithCreators,
+  getTokenAmount,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties against a metadata account", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const ROYALTEE_FEE_SHARE = new BN(4500);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const paymentAmount = new BN(11111);
+  const sellerFeeBasisPoints = 750;
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const creator3 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints,
+        creators: [
+          { address: creator1.publicKey, share: 15 },
+          { address: creator2.publicKey, share: 30 },
+          { address: creator3.publicKey, share: 55 },
+        ],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+      royaltyFeeShare: ROYALTEE_FEE_SHARE,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Metadata account is written by the token metadata program", async () => {
+    const metadataAccountInfo = await provider.connection.getAccountInfo(
+      findMintMetadataId(mintId)
+    );
+    expect(metadataAccountInfo?.owner.toString()).toEqual(
+      TOKEN_METADATA_PROGRAM_ID.toString()
+    );
+    if (!metadataAccountInfo) throw new Error("Missing metadata account");
+    const [metadata] = Metadata.fromAccountInfo(metadataAccountInfo);
+    expect(metadata.mint.toString()).toEqual(mintId.toString());
+    expect(metadata.data.sellerFeeBasisPoints).toEqual(sellerFeeBasisPoints);
+    expect(
+      metadata.data.creators?.map(({ address, share }) => [
+        address.toString(),
+        share,
+      ])
+    ).toEqual([
+      [tokenCreator.publicKey.toString(), 0],
+      [creator1.publicKey.toString(), 15],
+      [creator2.publicKey.toString(), 30],
+      [creator3.publicKey.toString(), 55],
+    ]);
+  });
+
+  it("Creators are paid the split read from the metadata account", async () => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    const accountIds = [
+      payerTokenAccountId,
+      feeCollectorTokenAccountId,
+      await findAta(paymentMintId, creator1.publicKey, true),
+      await findAta(paymentMintId, creator2.publicKey, true),
+      await findAta(paymentMintId, creator3.publicKey, true),
+      paymentTokenAccountId,
+    ];
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    const deltas = after.map((amount, i) => amount - (before[i] ?? 0));
+
+    const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const sellerFee = paymentAmount
+      .mul(new BN(sellerFeeBasisPoints))
+      .div(BASIS_POINTS_DIVISOR);
+    const buySideFee = paymentAmount
+      .mul(new BN(DEFAULT_BUY_SIDE_FEE_SHARE))
+      .div(BASIS_POINTS_DIVISOR);
+    const totalFees = makerFee.add(takerFee).add(sellerFee);
+    const totalCreatorsFee = makerFee
+      .add(takerFee)
+      .mul(ROYALTEE_FEE_SHARE)
+      .div(BASIS_POINTS_DIVISOR)
+      .add(sellerFee);
+    // the token creator is listed first with a share of 0
+    const [, creator1Funds, creator2Funds, creator3Funds] =
+      calculateCreatorFunds(totalCreatorsFee, [0, 15, 30, 55]);
+
+    expect(-(deltas[0] ?? 0)).toEqual(paymentAmount.add(takerFee).toNumber());
+    expect(deltas[1]).toEqual(
+      totalFees.add(buySideFee).sub(totalCreatorsFee).toNumber()
+    );
+    expect(deltas[2]).toEqual(creator1Funds?.toNumber());
+    expect(deltas[3]).toEqual(creator2Funds?.toNumber());
+    expect(deltas[4]).toEqual(creator3Funds?.toNumber());
+    expect(deltas[5]).toEqual(
+      paymentAmount.add(takerFee).sub(totalFees).sub(buySideFee).toNumber()
+    );
+  });
+});
@@ -0,0 +1,154 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import {
+  PROGRAM_ID as TOKEN_METADATA_PROGRAM_ID,
+} from "@metaplex-foundation/mpl-token-metadata";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR } from "../sdk";
+import { getPaymentManager } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties under a metadata program", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const SELLER_FEE = new BN(500);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const metaplexPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const forkPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const forkProgramId = Keypair.generate().publicKey;
+  const feeCollector = Keypair.generate();
+  const creator = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  // returns the amount the creator received
+  const handlePayment = async (paymentManagerName: string) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const creatorTokenAccountId = await findAta(
+      paymentMintId,
+      creator.publicKey,
+      true
+    );
+    const before = await getTokenAmount(
+      provider.connection,
+      creatorTokenAccountId
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await getTokenAmount(
+      provider.connection,
+      creatorTokenAccountId
+    );
+    return after - before;
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: SELLER_FEE.toNumber(),
+        creators: [{ address: creator.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    for (const [paymentManagerName, metadataProgramId] of [
+      [metaplexPaymentManagerName, TOKEN_METADATA_PROGRAM_ID],
+      [forkPaymentManagerName, forkProgramId],
+    ] as const) {
+      await withInit(transaction, provider.connection, provider.wallet, {
+        paymentManagerName,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: TAKER_FEE.toNumber(),
+        includeSellerFeeBasisPoints: true,
+        metadataProgramId,
+      });
+    }
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Init stores the metadata program", async () => {
+    const paymentManager = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(forkPaymentManagerName)
+    );
+    expect(paymentManager.parsed.metadataProgramId?.toString()).toEqual(
+      forkProgramId.toString()
+    );
+  });
+
+  it("Royalties are read from the Metaplex program", async () => {
+    const creatorDelta = await handlePayment(metaplexPaymentManagerName);
+    expect(creatorDelta).toBeGreaterThanOrEqual(
+      paymentAmount.mul(SELLER_FEE).divn(BASIS_POINTS_DIVISOR).toNumber()
+    );
+  });
+
+  it("Metaplex metadata is ignored under a forked program", async () => {
+    // the metadata address is derived from the fork, which holds no metadata
+    // for the mint, so no royalties are paid
+    const creatorDelta = await handlePayment(forkPaymentManagerName);
+    expect(creatorDelta).toEqual(0);
+  });
+});
@@ -0,0 +1,144 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import { TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { PAYMENT_MANAGER_ADDRESS } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with a mismatched token program", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(1000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 500,
+        creators: [{ address: creator1.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  const paymentTransaction = async (
+    tokenProgramId: PublicKey
+  ): Promise<[web3.Transaction, PublicKey]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    // swap the token program of the payment instruction only
+    for (const instruction of transaction.instructions) {
+      if (!instruction.programId.equals(PAYMENT_MANAGER_ADDRESS)) continue;
+      for (const key of instruction.keys) {
+        if (key.pubkey.equals(TOKEN_PROGRAM_ID)) key.pubkey = tokenProgramId;
+      }
+    }
+    return [transaction, paymentTokenAccountId];
+  };
+
+  it("Token program that does not own the payment mint fails", async () => {
+    const [transaction, paymentTokenAccountId] = await paymentTransaction(
+      TOKEN_2022_PROGRAM_ID
+    );
+    const before = await getTokenAmount(
+      provider.connection,
+      paymentTokenAccountId
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+    expect(
+      await getTokenAmount(provider.connection, paymentTokenAccountId)
+    ).toEqual(before);
+  });
+
+  it("Token program that owns the payment mint succeeds", async () => {
+    const [transaction, paymentTokenAccountId] = await paymentTransaction(
+      TOKEN_PROGRAM_ID
+    );
+    const before = await getTokenAmount(
+      provider.connection,
+      paymentTokenAccountId
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    expect(
+      await getTokenAmount(provider.connection, paymentTokenAccountId)
+    ).toBeGreaterThan(before);
+  });
+});
@@ -0,0 +1,160 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties without creators", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const SELLER_FEE = new BN(600);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const additivePaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const sellerFee = paymentAmount.mul(SELLER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const buySideFee = paymentAmount
+    .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+    .divn(BASIS_POINTS_DIVISOR);
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: SELLER_FEE.toNumber(),
+        creators: null,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    for (const [name, sellerFeeWithinFees] of [
+      [additivePaymentManagerName, false],
+      [paymentManagerName, true],
+    ] as const) {
+      await withInit(transaction, provider.connection, provider.wallet, {
+        paymentManagerName: name,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: TAKER_FEE.toNumber(),
+        includeSellerFeeBasisPoints: true,
+        sellerFeeWithinFees,
+      });
+    }
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  // returns what the payer paid and what the fee collector and target received
+  const pay = async (name: string): Promise<number[]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(name)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName: name,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [
+      payerTokenAccountId,
+      feeCollectorTokenAccountId,
+      paymentTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return [
+      (before[0] ?? 0) - (after[0] ?? 0),
+      (after[1] ?? 0) - (before[1] ?? 0),
+      (after[2] ?? 0) - (before[2] ?? 0),
+    ];
+  };
+
+  it("Fee collector absorbs an additive seller fee", async () => {
+    const [paid, feeCollectorDelta, paymentDelta] = await pay(
+      additivePaymentManagerName
+    );
+    expect(feeCollectorDelta).toEqual(
+      makerFee.add(takerFee).add(sellerFee).add(buySideFee).toNumber()
+    );
+    expect(paymentDelta).toEqual(
+      paymentAmount.sub(makerFee).sub(sellerFee).sub(buySideFee).toNumber()
+    );
+    expect(paid).toEqual(paymentAmount.add(takerFee).toNumber());
+  });
+
+  it("Fee collector gets no non-additive seller fee", async () => {
+    const [paid, feeCollectorDelta, paymentDelta] = await pay(
+      paymentManagerName
+    );
+    expect(feeCollectorDelta).toEqual(
+      makerFee.add(takerFee).add(buySideFee).toNumber()
+    );
+    expect(paymentDelta).toEqual(
+      paymentAmount.sub(makerFee).sub(buySideFee).toNumber()
+    );
+    expect(paid).toEqual(paymentAmount.add(takerFee).toNumber());
+  });
+});
@@ -0,0 +1,167 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties without a fee collector fee", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  // the creators are paid the whole of the maker-taker fees
+  const ROYALTY_FEE_SHARE = new BN(BASIS_POINTS_DIVISOR);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator = Keypair.generate();
+  const buySideReceiver = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let buySideTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const buySideFee = paymentAmount
+    .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+    .divn(BASIS_POINTS_DIVISOR);
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: [{ address: creator.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+      royaltyFeeShare: ROYALTY_FEE_SHARE,
+    });
+    buySideTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Fee collector transfer is skipped when nothing is left", async () => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        buySideTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const txid = await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet
+    );
+
+    const parsedTransaction = await provider.connection.getParsedTransaction(
+      txid,
+      { commitment: "confirmed", maxSupportedTransactionVersion: 0 }
+    );
+    const destinations = (parsedTransaction?.meta?.innerInstructions ?? [])
+      .filter(({ index }) => index === transaction.instructions.length - 1)
+      .flatMap(({ instructions }) => instructions)
+      .map((instruction) =>
+        "parsed" in instruction && instruction.program === "spl-token"
+          ? (instruction.parsed as {
+              type: string;
+              info: { destination: string };
+            })
+          : null
+      )
+      .filter((parsed) => parsed?.type === "transferChecked")
+      .map((parsed) => parsed?.info.destination);
+    // the creator, the buy side and the payment target are paid, no one else
+    expect(destinations).toEqual([
+      (await findAta(paymentMintId, creator.publicKey, true)).toString(),
+      buySideTokenAccountId.toString(),
+      paymentTokenAccountId.toString(),
+    ]);
+
+    expect(
+      await getTokenAmount(provider.connection, feeCollectorTokenAccountId)
+    ).toEqual(0);
+    expect(
+      await getTokenAmount(
+        provider.connection,
+        await findAta(paymentMintId, creator.publicKey, true)
+      )
+    ).toEqual(makerFee.add(takerFee).toNumber());
+    expect(
+      await getTokenAmount(provider.connection, buySideTokenAccountId)
+    ).toEqual(buySideFee.toNumber());
+    expect(
+      await getTokenAmount(provider.connection, paymentTokenAccountId)
+    ).toEqual(paymentAmount.sub(makerFee).sub(buySideFee).toNumber());
+  });
+});
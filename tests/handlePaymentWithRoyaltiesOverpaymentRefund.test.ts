@@ -0,0 +1,149 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties refunding an overpayment", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const expectedAmount = new BN(10000);
+  const takerFee = (amount: BN) =>
+    amount.mul(TAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+
+  // returns the payer and payment target deltas
+  const handlePayment = async (paymentAmount: BN): Promise<number[]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+        expectedAmount,
+      }
+    );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    const paymentTokenAccountBefore = await getTokenAmount(
+      provider.connection,
+      paymentTokenAccountId
+    );
+    const payerBefore = await getTokenAmount(
+      provider.connection,
+      payerTokenAccountId
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const payerAfter = await getTokenAmount(
+      provider.connection,
+      payerTokenAccountId
+    );
+    const paymentTokenAccountAfter = await getTokenAmount(
+      provider.connection,
+      paymentTokenAccountId
+    );
+    return [
+      payerBefore - payerAfter,
+      paymentTokenAccountAfter - paymentTokenAccountBefore,
+    ];
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: null,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Overpayment is refunded with the fees on it", async () => {
+    const [payerDelta, targetDelta] = await handlePayment(
+      expectedAmount.muln(2)
+    );
+    // the payer is debited as if it had paid the expected amount
+    expect(payerDelta).toEqual(
+      expectedAmount.add(takerFee(expectedAmount)).toNumber()
+    );
+    const [, expectedTargetDelta] = await handlePayment(expectedAmount);
+    expect(targetDelta).toEqual(expectedTargetDelta);
+  });
+
+  it("Underpayment is rejected", async () => {
+    await expect(handlePayment(expectedAmount.subn(1))).rejects.toThrow(
+      "0x17a2" // Underpayment
+    );
+  });
+});
@@ -0,0 +1,202 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  tryGetAccount,
+} from "@solana-nft-programs/common";
+
+import { getPaymentRecord } from "../sdk/accounts";
+import {
+  findPaymentManagerAddress,
+  findPaymentRecordAddress,
+} from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties with a payment record", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(1000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const recordingPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 500,
+        creators: [{ address: creator1.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    for (const [name, recordPayments] of [
+      [recordingPaymentManagerName, true],
+      [paymentManagerName, false],
+    ] as const) {
+      await withInit(transaction, provider.connection, provider.wallet, {
+        paymentManagerName: name,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: TAKER_FEE.toNumber(),
+        includeSellerFeeBasisPoints: true,
+        recordPayments,
+      });
+    }
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  const pay = async (
+    name: string,
+    recordNonce: BN
+  ): Promise<[number, number]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(name)
+      );
+    const payerTokenAccountId = await findAta(
+      paymentMintId,
+      provider.wallet.publicKey,
+      true
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName: name,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId,
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+        recordNonce,
+      }
+    );
+    const beforePayerAmount = await getTokenAmount(
+      provider.connection,
+      payerTokenAccountId
+    );
+    const beforePaymentAmount = await getTokenAmount(
+      provider.connection,
+      paymentTokenAccountId
+    );
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true }
+    );
+    return [
+      beforePayerAmount -
+        (await getTokenAmount(provider.connection, payerTokenAccountId)),
+      (await getTokenAmount(provider.connection, paymentTokenAccountId)) -
+        beforePaymentAmount,
+    ];
+  };
+
+  const getRecord = (name: string, recordNonce: BN) =>
+    getPaymentRecord(
+      provider.connection,
+      findPaymentRecordAddress(
+        findPaymentManagerAddress(name),
+        provider.wallet.publicKey,
+        mintId,
+        recordNonce
+      )
+    );
+
+  it("Payment record is written for each payment", async () => {
+    const [paidAmount, receivedAmount] = await pay(
+      recordingPaymentManagerName,
+      new BN(1)
+    );
+    const paymentManagerId = findPaymentManagerAddress(
+      recordingPaymentManagerName
+    );
+    const paymentRecord = await getRecord(
+      recordingPaymentManagerName,
+      new BN(1)
+    );
+    expect(paymentRecord.parsed.paymentManager.toString()).toEqual(
+      paymentManagerId.toString()
+    );
+    expect(paymentRecord.parsed.payer.toString()).toEqual(
+      provider.wallet.publicKey.toString()
+    );
+    expect(paymentRecord.parsed.mint.toString()).toEqual(mintId.toString());
+    expect(paymentRecord.parsed.nonce.toNumber()).toEqual(1);
+    expect(paymentRecord.parsed.paymentAmount.toNumber()).toEqual(
+      paymentAmount.toNumber()
+    );
+    expect(paymentRecord.parsed.totalFees.toNumber()).toEqual(
+      paidAmount - receivedAmount
+    );
+    expect(paymentRecord.parsed.timestamp.toNumber()).toBeGreaterThan(0);
+  });
+
+  it("Another payment of the mint keeps the earlier record", async () => {
+    await pay(recordingPaymentManagerName, new BN(2));
+    const firstRecord = await getRecord(recordingPaymentManagerName, new BN(1));
+    const secondRecord = await getRecord(
+      recordingPaymentManagerName,
+      new BN(2)
+    );
+    expect(firstRecord.parsed.nonce.toNumber()).toEqual(1);
+    expect(secondRecord.parsed.nonce.toNumber()).toEqual(2);
+  });
+
+  it("A recorded nonce cannot be reused", async () => {
+    await expect(
+      pay(recordingPaymentManagerName, new BN(1))
+    ).rejects.toThrow();
+  });
+
+  it("No payment record without recording payments", async () => {
+    await pay(paymentManagerName, new BN(1));
+    const paymentRecord = await tryGetAccount(() =>
+      getRecord(paymentManagerName, new BN(1))
+    );
+    expect(paymentRecord).toBeNull();
+  });
+});
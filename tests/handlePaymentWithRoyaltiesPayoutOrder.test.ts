@@ -0,0 +1,168 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { PayoutOrder } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties payout order", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(1000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const creatorsFirstPaymentManagerName = Math.random()
+    .toString(36)
+    .slice(2, 7);
+  const principalFirstPaymentManagerName = Math.random()
+    .toString(36)
+    .slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  // destinations of the token transfers made by the program, in order
+  const handlePayment = async (paymentManagerName: string) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId: mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+        paymentTokenAccountId: paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const txid = await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet
+    );
+    const parsedTransaction = await provider.connection.getParsedTransaction(
+      txid,
+      { commitment: "confirmed", maxSupportedTransactionVersion: 0 }
+    );
+    const transferDestinations = (
+      parsedTransaction?.meta?.innerInstructions ?? []
+    )
+      .filter(({ index }) => index === transaction.instructions.length - 1)
+      .flatMap(({ instructions }) => instructions)
+      .map((instruction) =>
+        "parsed" in instruction && instruction.program === "spl-token"
+          ? (instruction.parsed as {
+              type: string;
+              info: { destination: string };
+            })
+          : null
+      )
+      .filter((parsed) => parsed?.type === "transfer")
+      .map((parsed) => parsed?.info.destination);
+    return {
+      transferDestinations,
+      paymentTokenAccountId,
+      creatorTokenAccountId: await findAta(
+        paymentMintId,
+        creator.publicKey,
+        true
+      ),
+    };
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 100,
+        creators: [{ address: creator.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    for (const [paymentManagerName, payoutOrder] of [
+      [creatorsFirstPaymentManagerName, PayoutOrder.CreatorsFirst],
+      [principalFirstPaymentManagerName, PayoutOrder.PrincipalFirst],
+    ] as const) {
+      await withInit(transaction, provider.connection, provider.wallet, {
+        paymentManagerName,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: TAKER_FEE.toNumber(),
+        includeSellerFeeBasisPoints: true,
+        payoutOrder,
+      });
+    }
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Creators first pays principal last", async () => {
+    const {
+      transferDestinations,
+      paymentTokenAccountId,
+      creatorTokenAccountId,
+    } = await handlePayment(creatorsFirstPaymentManagerName);
+    expect(transferDestinations[0]).toEqual(creatorTokenAccountId.toString());
+    expect(transferDestinations[transferDestinations.length - 1]).toEqual(
+      paymentTokenAccountId.toString()
+    );
+  });
+
+  it("Principal first pays principal before creators", async () => {
+    const {
+      transferDestinations,
+      paymentTokenAccountId,
+      creatorTokenAccountId,
+    } = await handlePayment(principalFirstPaymentManagerName);
+    expect(transferDestinations[0]).toEqual(paymentTokenAccountId.toString());
+    expect(transferDestinations[1]).toEqual(creatorTokenAccountId.toString());
+  });
+});
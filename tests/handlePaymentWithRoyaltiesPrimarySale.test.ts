@@ -0,0 +1,229 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import {
+  createUpdateMetadataAccountV2Instruction,
+} from "@metaplex-foundation/mpl-token-metadata";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL, Transaction } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  findMintMetadataId,
+} from "@solana-nft-programs/common";
+
+import { DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import {
+  calculateCreatorFunds,
+  createNftWithCreators,
+  getTokenAmount,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties for primary sales", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const ROYALTEE_FEE_SHARE = new BN(4500);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const PAYMENT_AMOUNT = new BN(100000);
+  const sellerFeeBasisPoints = 500;
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creators = [
+    { keypair: Keypair.generate(), share: 60 },
+    { keypair: Keypair.generate(), share: 40 },
+  ];
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let primaryMintId: PublicKey;
+  let secondaryMintId: PublicKey;
+  let provider: SolanaProvider;
+
+  // returns the amounts received by the creators, the fee collector and the
+  // payment receiver
+  const handlePayment = async (mintId: PublicKey) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const accountIds = [
+      ...(await Promise.all(
+        creators.map(({ keypair }) =>
+          findAta(paymentMintId, keypair.publicKey, true)
+        )
+      )),
+      feeCollectorTokenAccountId,
+      paymentTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount: PAYMENT_AMOUNT,
+        mintId: mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+        paymentTokenAccountId: paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    const received = after.map((amount, i) => amount - (before[i] ?? 0));
+    return {
+      creators: received.slice(0, creators.length),
+      feeCollector: received[creators.length] ?? 0,
+      paymentReceiver: received[creators.length + 1] ?? 0,
+    };
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    const nftParams = {
+      sellerFeeBasisPoints,
+      creators: creators.map(({ keypair, share }) => ({
+        address: keypair.publicKey,
+        share,
+      })),
+    };
+    primaryMintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      nftParams
+    );
+    secondaryMintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      nftParams
+    );
+    await executeTransaction(
+      provider.connection,
+      new Transaction().add(
+        createUpdateMetadataAccountV2Instruction(
+          {
+            metadata: findMintMetadataId(secondaryMintId),
+            updateAuthority: tokenCreator.publicKey,
+          },
+          {
+            updateMetadataAccountArgsV2: {
+              data: null,
+              updateAuthority: null,
+              primarySaleHappened: true,
+              isMutable: null,
+            },
+          }
+        )
+      ),
+      new Wallet(tokenCreator)
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+      royaltyFeeShare: ROYALTEE_FEE_SHARE,
+      handlePrimarySales: true,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Primary sale proceeds go to the creators", async () => {
+    const makerFee = PAYMENT_AMOUNT.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const takerFee = PAYMENT_AMOUNT.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const buySideFee = PAYMENT_AMOUNT.mul(
+      new BN(DEFAULT_BUY_SIDE_FEE_SHARE)
+    ).div(BASIS_POINTS_DIVISOR);
+    const proceeds = PAYMENT_AMOUNT.sub(makerFee).sub(buySideFee);
+    const received = await handlePayment(primaryMintId);
+    expect(received.creators).toEqual(
+      calculateCreatorFunds(
+        proceeds,
+        creators.map(({ share }) => share)
+      ).map((amount) => amount.toNumber())
+    );
+    expect(received.feeCollector).toEqual(
+      makerFee.add(takerFee).add(buySideFee).toNumber()
+    );
+    expect(received.paymentReceiver).toEqual(0);
+  });
+
+  it("Secondary sale pays royalties to the creators", async () => {
+    const makerFee = PAYMENT_AMOUNT.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const takerFee = PAYMENT_AMOUNT.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const sellerFee = PAYMENT_AMOUNT.mul(new BN(sellerFeeBasisPoints)).div(
+      BASIS_POINTS_DIVISOR
+    );
+    const buySideFee = PAYMENT_AMOUNT.mul(
+      new BN(DEFAULT_BUY_SIDE_FEE_SHARE)
+    ).div(BASIS_POINTS_DIVISOR);
+    const totalCreatorsFee = makerFee
+      .add(takerFee)
+      .mul(ROYALTEE_FEE_SHARE)
+      .div(BASIS_POINTS_DIVISOR)
+      .add(sellerFee);
+    const received = await handlePayment(secondaryMintId);
+    expect(received.creators).toEqual(
+      calculateCreatorFunds(
+        totalCreatorsFee,
+        creators.map(({ share }) => share)
+      ).map((amount) => amount.toNumber())
+    );
+    expect(received.feeCollector).toEqual(
+      makerFee
+        .add(takerFee)
+        .add(sellerFee)
+        .add(buySideFee)
+        .sub(totalCreatorsFee)
+        .toNumber()
+    );
+    expect(received.paymentReceiver).toEqual(
+      PAYMENT_AMOUNT.sub(makerFee)
+        .sub(sellerFee)
+        .sub(buySideFee)
+        .toNumber()
+    );
+  });
+});
@@ -0,0 +1,177 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import {
+  createUpdateMetadataAccountV2Instruction,
+} from "@metaplex-foundation/mpl-token-metadata";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  findMintMetadataId,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties with a primary sale target", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(1000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const primarySaleTarget = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let primaryMintId: PublicKey;
+  let secondaryMintId: PublicKey;
+  let primarySaleTargetTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    const nftParams = {
+      sellerFeeBasisPoints: 500,
+      creators: [{ address: creator1.publicKey, share: 100 }],
+    };
+    primaryMintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      nftParams
+    );
+    secondaryMintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      nftParams
+    );
+    // the secondary mint has had its primary sale
+    await executeTransaction(
+      provider.connection,
+      new web3.Transaction().add(
+        createUpdateMetadataAccountV2Instruction(
+          {
+            metadata: findMintMetadataId(secondaryMintId),
+            updateAuthority: tokenCreator.publicKey,
+          },
+          {
+            updateMetadataAccountArgsV2: {
+              data: null,
+              updateAuthority: null,
+              primarySaleHappened: true,
+              isMutable: null,
+            },
+          }
+        )
+      ),
+      new Wallet(tokenCreator)
+    );
+
+    const transaction = new web3.Transaction();
+    primarySaleTargetTokenAccountId =
+      await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        provider.connection,
+        paymentMintId,
+        primarySaleTarget.publicKey,
+        provider.wallet.publicKey,
+        true
+      );
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+      primarySaleTargetId: primarySaleTarget.publicKey,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  const pay = async (mintId: PublicKey): Promise<[number, number]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        primarySaleTargetTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [primarySaleTargetTokenAccountId, paymentTokenAccountId];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return [
+      (after[0] ?? 0) - (before[0] ?? 0),
+      (after[1] ?? 0) - (before[1] ?? 0),
+    ];
+  };
+
+  it("Primary sale proceeds go to the primary sale target", async () => {
+    const [primarySaleTargetDelta, paymentDelta] = await pay(primaryMintId);
+    expect(primarySaleTargetDelta).toBeGreaterThan(0);
+    expect(paymentDelta).toEqual(0);
+  });
+
+  it("Secondary sale proceeds go to the payment target", async () => {
+    const [primarySaleTargetDelta, paymentDelta] = await pay(secondaryMintId);
+    expect(primarySaleTargetDelta).toEqual(0);
+    expect(paymentDelta).toBeGreaterThan(0);
+  });
+
+  it("Both sales pay the same principal", async () => {
+    const [primarySaleTargetDelta] = await pay(primaryMintId);
+    const [, paymentDelta] = await pay(secondaryMintId);
+    expect(primarySaleTargetDelta).toEqual(paymentDelta);
+  });
+});
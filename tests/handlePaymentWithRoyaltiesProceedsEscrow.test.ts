@@ -0,0 +1,196 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import {
+  findPaymentManagerAddress,
+  findProceedsEscrowAddress,
+} from "../sdk/pda";
+import {
+  withClaimProceeds,
+  withHandlePaymentWithRoyalties,
+  withInit,
+} from "../sdk/transaction";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties into a proceeds escrow", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const paymentAmount = new BN(1000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const seller = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let proceedsEscrowId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const buySideFee = paymentAmount
+    .mul(new BN(DEFAULT_BUY_SIDE_FEE_SHARE))
+    .div(BASIS_POINTS_DIVISOR);
+  const principal = paymentAmount.sub(makerFee).sub(buySideFee);
+
+  const handlePayment = async () => {
+    const transaction = new web3.Transaction();
+    const feeCollectorTokenAccountId =
+      await withFindOrInitAssociatedTokenAccount(
+        transaction,
+        provider.connection,
+        paymentMintId,
+        feeCollector.publicKey,
+        provider.wallet.publicKey,
+        true
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        escrowSellerId: seller.publicKey,
+        excludeCretors: [],
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    // mint without metadata so no creators are paid
+    [, mintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey }
+    );
+    proceedsEscrowId = findProceedsEscrowAddress(
+      findPaymentManagerAddress(paymentManagerName),
+      paymentMintId,
+      seller.publicKey
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Principal is deposited into the escrow", async () => {
+    await handlePayment();
+    // the seller has no token account for the payment mint yet
+    expect(
+      await provider.connection.getAccountInfo(
+        await findAta(paymentMintId, seller.publicKey, true)
+      )
+    ).toBeNull();
+    expect(
+      await getTokenAmount(provider.connection, proceedsEscrowId)
+    ).toEqual(principal.toNumber());
+
+    await handlePayment();
+    expect(
+      await getTokenAmount(provider.connection, proceedsEscrowId)
+    ).toEqual(principal.muln(2).toNumber());
+  });
+
+  it("Seller claims the proceeds", async () => {
+    const transaction = new web3.Transaction();
+    const sellerTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      seller.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await withClaimProceeds(
+      transaction,
+      provider.connection,
+      new Wallet(seller),
+      { paymentManagerName, paymentMintId, sellerTokenAccountId }
+    );
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { signers: [seller] }
+    );
+
+    expect(
+      await getTokenAmount(provider.connection, sellerTokenAccountId)
+    ).toEqual(principal.muln(2).toNumber());
+    expect(
+      await provider.connection.getAccountInfo(proceedsEscrowId)
+    ).toBeNull();
+  });
+
+  it("Escrow is created again for the next payment", async () => {
+    await handlePayment();
+    expect(
+      await getTokenAmount(provider.connection, proceedsEscrowId)
+    ).toEqual(principal.toNumber());
+  });
+
+  it("Only the seller can claim", async () => {
+    const transaction = new web3.Transaction();
+    await withClaimProceeds(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentMintId,
+        sellerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+      }
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+});
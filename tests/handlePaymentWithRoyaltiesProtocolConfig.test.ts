@@ -0,0 +1,215 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { getProtocolConfig } from "../sdk/accounts";
+import {
+  findPaymentManagerAddress,
+  findProtocolConfigAddress,
+} from "../sdk/pda";
+import {
+  withHandlePaymentWithRoyalties,
+  withInit,
+  withInitProtocolConfig,
+  withSetProtocolConfig,
+} from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties with protocol config", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const PAYMENT_AMOUNT = new BN(100000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerNames = [
+    Math.random().toString(36).slice(2, 7),
+    Math.random().toString(36).slice(2, 7),
+  ];
+  const feeCollector = Keypair.generate();
+  const treasury = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let treasuryTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  // initializes the protocol config on the first call
+  const setProtocolConfig = async (
+    protocolFeeBasisPoints: number,
+    wallet: Wallet = provider.wallet
+  ) => {
+    const protocolConfigInfo = await provider.connection.getAccountInfo(
+      findProtocolConfigAddress()
+    );
+    const withProtocolConfig = protocolConfigInfo
+      ? withSetProtocolConfig
+      : withInitProtocolConfig;
+    const [transaction] = await withProtocolConfig(
+      new web3.Transaction(),
+      provider.connection,
+      wallet,
+      { protocolFeeBasisPoints, treasury: treasury.publicKey }
+    );
+    await executeTransaction(provider.connection, transaction, wallet, {
+      silent: true,
+    });
+  };
+
+  // returns the amounts received by the treasury and the fee collector
+  const handlePayment = async (paymentManagerName: string) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const accountIds = [treasuryTokenAccountId, feeCollectorTokenAccountId];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount: PAYMENT_AMOUNT,
+        mintId: mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+        paymentTokenAccountId: paymentTokenAccountId,
+        excludeCretors: [],
+        protocolTreasuryTokenAccountId: treasuryTokenAccountId,
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return {
+      treasury: (after[0] ?? 0) - (before[0] ?? 0),
+      feeCollector: (after[1] ?? 0) - (before[1] ?? 0),
+    };
+  };
+
+  const expectedAmounts = (protocolFeeBasisPoints: number) => {
+    const makerFee = PAYMENT_AMOUNT.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const takerFee = PAYMENT_AMOUNT.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+    const buySideFee = PAYMENT_AMOUNT.mul(
+      new BN(DEFAULT_BUY_SIDE_FEE_SHARE)
+    ).div(BASIS_POINTS_DIVISOR);
+    const protocolFee = PAYMENT_AMOUNT.mul(new BN(protocolFeeBasisPoints)).div(
+      BASIS_POINTS_DIVISOR
+    );
+    return {
+      treasury: protocolFee.toNumber(),
+      feeCollector: makerFee
+        .add(takerFee)
+        .add(buySideFee)
+        .sub(protocolFee)
+        .toNumber(),
+    };
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    [, mintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey, amount: 1 }
+    );
+
+    const transaction = new web3.Transaction();
+    treasuryTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      treasury.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    for (const paymentManagerName of paymentManagerNames) {
+      await withInit(transaction, provider.connection, provider.wallet, {
+        paymentManagerName,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: TAKER_FEE.toNumber(),
+        includeSellerFeeBasisPoints: false,
+      });
+    }
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Only the upgrade authority initializes the protocol config", async () => {
+    await expect(
+      setProtocolConfig(100, new Wallet(tokenCreator))
+    ).rejects.toThrow();
+  });
+
+  it("Protocol fee applies to every payment manager", async () => {
+    await setProtocolConfig(100);
+    for (const paymentManagerName of paymentManagerNames) {
+      const received = await handlePayment(paymentManagerName);
+      expect(received).toEqual(expectedAmounts(100));
+    }
+  });
+
+  it("Changing the protocol config affects every payment manager", async () => {
+    await setProtocolConfig(200);
+    const protocolConfig = await getProtocolConfig(
+      provider.connection,
+      findProtocolConfigAddress()
+    );
+    expect(protocolConfig.parsed.protocolFeeBasisPoints).toEqual(200);
+    for (const paymentManagerName of paymentManagerNames) {
+      const received = await handlePayment(paymentManagerName);
+      expect(received).toEqual(expectedAmounts(200));
+    }
+  });
+
+  it("Only the protocol authority updates the protocol config", async () => {
+    await expect(
+      setProtocolConfig(0, new Wallet(tokenCreator))
+    ).rejects.toThrow();
+    const protocolConfig = await getProtocolConfig(
+      provider.connection,
+      findProtocolConfigAddress()
+    );
+    expect(protocolConfig.parsed.protocolFeeBasisPoints).toEqual(200);
+  });
+});
@@ -0,0 +1,174 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import {
+  calculateCreatorFunds,
+  createNftWithCreators,
+  getTokenAmount,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties and the remainder to the fee collector", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const SELLER_FEE = new BN(500);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const ROYALTY_FEE_SHARE = new BN(5000);
+  // a creators fee of 905 the shares do not divide
+  const paymentAmount = new BN(10070);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const creator3 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const totalCreatorsFee = makerFee
+    .add(takerFee)
+    .mul(ROYALTY_FEE_SHARE)
+    .div(BASIS_POINTS_DIVISOR)
+    .add(paymentAmount.mul(SELLER_FEE).div(BASIS_POINTS_DIVISOR));
+  // the token creator is listed first with a share of 0
+  const shares = [0, 50, 30, 20];
+
+  const initPaymentManager = async (remainderToCollector: boolean) => {
+    const paymentManagerName = Math.random().toString(36).slice(2, 7);
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+      remainderToCollector,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    return paymentManagerName;
+  };
+
+  // returns what the fee collector and each creator received
+  const pay = async (paymentManagerName: string): Promise<number[]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [
+      feeCollectorTokenAccountId,
+      ...(await Promise.all(
+        [creator1, creator2, creator3].map((creator) =>
+          findAta(paymentMintId, creator.publicKey, true)
+        )
+      )),
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return after.map((amount, i) => amount - (before[i] ?? 0));
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: SELLER_FEE.toNumber(),
+        creators: [
+          { address: creator1.publicKey, share: 50 },
+          { address: creator2.publicKey, share: 30 },
+          { address: creator3.publicKey, share: 20 },
+        ],
+      }
+    );
+  });
+
+  it("Remainder is handed out to the creators or left to the fee collector", async () => {
+    const [, ...creatorFunds] = calculateCreatorFunds(totalCreatorsFee, shares);
+    const [, ...roundedDownCreatorFunds] = calculateCreatorFunds(
+      totalCreatorsFee,
+      shares,
+      true
+    );
+    const sum = (amounts: BN[]) =>
+      amounts.reduce((total, amount) => total.add(amount), new BN(0));
+    const remainder = sum(creatorFunds).sub(sum(roundedDownCreatorFunds));
+    expect(remainder.toNumber()).toBeGreaterThan(0);
+
+    const [feeCollectorDelta, ...creatorDeltas] = await pay(
+      await initPaymentManager(false)
+    );
+    expect(creatorDeltas).toEqual(creatorFunds.map((fund) => fund.toNumber()));
+
+    const [remainderFeeCollectorDelta, ...remainderCreatorDeltas] = await pay(
+      await initPaymentManager(true)
+    );
+    expect(remainderCreatorDeltas).toEqual(
+      roundedDownCreatorFunds.map((fund) => fund.toNumber())
+    );
+    // the fee collector receives exactly what the creators no longer do
+    expect(remainderFeeCollectorDelta - (feeCollectorDelta ?? 0)).toEqual(
+      remainder.toNumber()
+    );
+  });
+});
@@ -0,0 +1,157 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties with royalty floor", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const ROYALTEE_FEE_SHARE = new BN(4500);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const ROYALTY_FLOOR_BASIS_POINTS = 300;
+  const paymentAmount = new BN(100000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  const mintIds: { [sellerFeeBasisPoints: number]: PublicKey } = {};
+  let provider: SolanaProvider;
+
+  // returns the amounts received by the creator and the fee collector
+  const handlePayment = async (mintId: PublicKey) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    const accountIds = [
+      await findAta(paymentMintId, creator.publicKey, true),
+      feeCollectorTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId: mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId: feeCollectorTokenAccountId,
+        paymentTokenAccountId: paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return {
+      creator: (after[0] ?? 0) - (before[0] ?? 0),
+      feeCollector: (after[1] ?? 0) - (before[1] ?? 0),
+    };
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    for (const sellerFeeBasisPoints of [100, 500]) {
+      mintIds[sellerFeeBasisPoints] = await createNftWithCreators(
+        provider.connection,
+        tokenCreator,
+        provider.wallet.publicKey,
+        {
+          sellerFeeBasisPoints,
+          creators: [{ address: creator.publicKey, share: 100 }],
+        }
+      );
+    }
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+      royaltyFeeShare: ROYALTEE_FEE_SHARE,
+      royaltyFloorBasisPoints: ROYALTY_FLOOR_BASIS_POINTS,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  for (const [name, sellerFeeBasisPoints, effectiveBasisPoints] of [
+    ["below", 100, ROYALTY_FLOOR_BASIS_POINTS],
+    ["above", 500, 500],
+] as [string, number, number][]) {
+    it(`Seller fee ${name} the royalty floor`, async () => {
+      const mintId = mintIds[sellerFeeBasisPoints];
+      if (!mintId) throw new Error("No mint for seller fee");
+      const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+      const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+      const sellerFee = paymentAmount
+        .mul(new BN(effectiveBasisPoints))
+        .div(BASIS_POINTS_DIVISOR);
+      const buySideFee = paymentAmount
+        .mul(new BN(DEFAULT_BUY_SIDE_FEE_SHARE))
+        .div(BASIS_POINTS_DIVISOR);
+      const totalCreatorsFee = makerFee
+        .add(takerFee)
+        .mul(ROYALTEE_FEE_SHARE)
+        .div(BASIS_POINTS_DIVISOR)
+        .add(sellerFee);
+      const received = await handlePayment(mintId);
+      expect(received.creator).toEqual(totalCreatorsFee.toNumber());
+      expect(received.feeCollector).toEqual(
+        makerFee
+          .add(takerFee)
+          .add(sellerFee)
+          .add(buySideFee)
+          .sub(totalCreatorsFee)
+          .toNumber()
+      );
+    });
+  }
+});
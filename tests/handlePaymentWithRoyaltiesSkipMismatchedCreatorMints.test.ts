@@ -0,0 +1,196 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import {
+  calculateCreatorFunds,
+  createNftWithCreators,
+  getTokenAmount,
+} from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties to a creator token account of another mint", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const BASIS_POINTS_DIVISOR = new BN(10000);
+  const ROYALTY_FEE_SHARE = new BN(5000);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const creator2 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  // creator2 still holds a token account of the mint they are moving away from
+  let creator2OtherMintTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).div(BASIS_POINTS_DIVISOR);
+  const totalCreatorsFee = makerFee
+    .add(takerFee)
+    .mul(ROYALTY_FEE_SHARE)
+    .div(BASIS_POINTS_DIVISOR);
+  // the token creator is listed first with a share of 0
+  const [, creator1Fee, creator2Fee] = calculateCreatorFunds(
+    totalCreatorsFee,
+    [0, 60, 40]
+  );
+
+  const initPaymentManager = async (skipMismatchedCreatorMints: boolean) => {
+    const paymentManagerName = Math.random().toString(36).slice(2, 7);
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+      skipMismatchedCreatorMints,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    return paymentManagerName;
+  };
+
+  // returns what the fee collector, creator1 and creator2's other mint token account received
+  const pay = async (paymentManagerName: string): Promise<number[]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [creator2.publicKey.toString()],
+      }
+    );
+    // creator2's token account of the other mint takes the place of their payment mint one
+    const creator2TokenAccountId = await findAta(
+      paymentMintId,
+      creator2.publicKey,
+      true
+    );
+    const paymentInstruction =
+      transaction.instructions[transaction.instructions.length - 1];
+    paymentInstruction?.keys.forEach((key) => {
+      if (key.pubkey.equals(creator2TokenAccountId)) {
+        key.pubkey = creator2OtherMintTokenAccountId;
+      }
+    });
+
+    const accountIds = [
+      feeCollectorTokenAccountId,
+      await findAta(paymentMintId, creator1.publicKey, true),
+      creator2OtherMintTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet, {
+      silent: true,
+    });
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return after.map((amount, i) => amount - (before[i] ?? 0));
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    const [, otherMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: [
+          { address: creator1.publicKey, share: 60 },
+          { address: creator2.publicKey, share: 40 },
+        ],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    creator2OtherMintTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      otherMintId,
+      creator2.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Strict mode fails the payment", async () => {
+    await expect(pay(await initPaymentManager(false))).rejects.toThrow(
+      "0x1790"
+    ); // InvalidTokenAccountMint
+  });
+
+  it("Lenient mode routes the creator's share to the fee collector", async () => {
+    const [feeCollectorDelta, creator1Delta, creator2Delta] = await pay(
+      await initPaymentManager(true)
+    );
+    expect(creator1Delta).toEqual(creator1Fee?.toNumber());
+    expect(creator2Delta).toEqual(0);
+    expect(feeCollectorDelta).toEqual(
+      makerFee
+        .add(takerFee)
+        .sub(creator1Fee ?? new BN(0))
+        .toNumber()
+    );
+    expect(creator2Fee?.toNumber()).toBeGreaterThan(0);
+  });
+});
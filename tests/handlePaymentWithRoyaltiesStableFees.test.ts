@@ -0,0 +1,175 @@
+import { BN, BorshCoder, EventParser, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import {
+  BASIS_POINTS_DIVISOR,
+  CONVERSION_RATE_DIVISOR,
+  DEFAULT_BUY_SIDE_FEE_SHARE,
+  PAYMENT_MANAGER_ADDRESS,
+  PAYMENT_MANAGER_IDL,
+} from "../sdk";
+import { findPaymentManagerAddress, findPriceOracleAddress } from "../sdk/pda";
+import {
+  withHandlePaymentWithRoyalties,
+  withInit,
+  withSetPriceOracle,
+} from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties reporting fees in stable value", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  // a mocked price of 2.5 stable units per payment mint unit
+  const PRICE = new BN(CONVERSION_RATE_DIVISOR).muln(5).divn(2);
+  // the maker fee and the buy side fee are taken out of the payment, the
+  // taker fee is paid on top
+  const totalFees = paymentAmount
+    .mul(MAKER_FEE.add(TAKER_FEE))
+    .add(paymentAmount.muln(DEFAULT_BUY_SIDE_FEE_SHARE))
+    .divn(BASIS_POINTS_DIVISOR);
+
+  // returns the stable fee events the payment emitted
+  const handlePayment = async (priceOracleId?: PublicKey) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+        priceOracleId,
+      }
+    );
+    const txid = await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet
+    );
+    const parsedTransaction = await provider.connection.getTransaction(txid, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const eventParser = new EventParser(
+      PAYMENT_MANAGER_ADDRESS,
+      new BorshCoder(PAYMENT_MANAGER_IDL)
+    );
+    return [
+      ...eventParser.parseLogs(parsedTransaction?.meta?.logMessages ?? []),
+    ].filter(({ name }) => name === "PaymentFeesInStable");
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: null,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await withSetPriceOracle(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentMintId,
+        price: PRICE,
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Fees are reported at the price oracle's price", async () => {
+    const events = await handlePayment(
+      findPriceOracleAddress(
+        findPaymentManagerAddress(paymentManagerName),
+        paymentMintId
+      )
+    );
+    expect(events.length).toEqual(1);
+    const data = events[0]?.data as {
+      totalFees: BN;
+      price: BN;
+      feesInStable: BN;
+    };
+    expect(data.totalFees.toNumber()).toEqual(totalFees.toNumber());
+    expect(data.price.toString()).toEqual(PRICE.toString());
+    expect(data.feesInStable.toNumber()).toEqual(
+      totalFees.mul(PRICE).divn(CONVERSION_RATE_DIVISOR).toNumber()
+    );
+    expect(data.feesInStable.toNumber()).toEqual(
+      totalFees.muln(5).divn(2).toNumber()
+    );
+  });
+
+  it("No stable value is reported without the price oracle", async () => {
+    const events = await handlePayment();
+    expect(events.length).toEqual(0);
+  });
+});
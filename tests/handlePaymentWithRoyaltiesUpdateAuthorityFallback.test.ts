@@ -0,0 +1,155 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties to the update authority", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const SELLER_FEE = new BN(600);
+  const ROYALTEE_FEE_SHARE = new BN(4500);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const fallbackPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  // the update authority of the mint
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  const makerFee = paymentAmount.mul(MAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const takerFee = paymentAmount.mul(TAKER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const sellerFee = paymentAmount.mul(SELLER_FEE).divn(BASIS_POINTS_DIVISOR);
+  const creatorsFee = makerFee
+    .add(takerFee)
+    .mul(ROYALTEE_FEE_SHARE)
+    .divn(BASIS_POINTS_DIVISOR)
+    .add(sellerFee);
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: SELLER_FEE.toNumber(),
+        creators: null,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    for (const [name, fallbackRoyaltyToUpdateAuthority] of [
+      [fallbackPaymentManagerName, true],
+      [paymentManagerName, false],
+    ] as const) {
+      await withInit(transaction, provider.connection, provider.wallet, {
+        paymentManagerName: name,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE.toNumber(),
+        takerFeeBasisPoints: TAKER_FEE.toNumber(),
+        includeSellerFeeBasisPoints: true,
+        royaltyFeeShare: ROYALTEE_FEE_SHARE,
+        fallbackRoyaltyToUpdateAuthority,
+      });
+    }
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  // returns what the update authority and the fee collector received
+  const pay = async (name: string): Promise<[number, number]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(name)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName: name,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [
+      await findAta(paymentMintId, tokenCreator.publicKey, true),
+      feeCollectorTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return [
+      (after[0] ?? 0) - (before[0] ?? 0),
+      (after[1] ?? 0) - (before[1] ?? 0),
+    ];
+  };
+
+  it("Update authority is paid the creators fee", async () => {
+    const [updateAuthorityDelta, feeCollectorDelta] = await pay(
+      fallbackPaymentManagerName
+    );
+    expect(updateAuthorityDelta).toEqual(creatorsFee.toNumber());
+    const [, unroutedFeeCollectorDelta] = await pay(paymentManagerName);
+    expect(feeCollectorDelta).toEqual(
+      unroutedFeeCollectorDelta - creatorsFee.toNumber()
+    );
+  });
+
+  it("Fee collector keeps the creators fee without the fallback", async () => {
+    const [updateAuthorityDelta, feeCollectorDelta] = await pay(
+      paymentManagerName
+    );
+    expect(updateAuthorityDelta).toEqual(0);
+    expect(feeCollectorDelta).toBeGreaterThan(creatorsFee.toNumber());
+  });
+});
@@ -0,0 +1,181 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { BASIS_POINTS_DIVISOR, DEFAULT_BUY_SIDE_FEE_SHARE } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withHandlePaymentWithRoyalties, withInit } from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Handle payment with royalties and a zero buy side fee", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const buySideReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let buySideTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  // returns the destinations of the token transfers and the buy side delta
+  const handlePayment = async (
+    paymentAmount: BN
+  ): Promise<[string[], number]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        buySideTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const before = await getTokenAmount(
+      provider.connection,
+      buySideTokenAccountId
+    );
+    const txid = await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet
+    );
+    const after = await getTokenAmount(
+      provider.connection,
+      buySideTokenAccountId
+    );
+    const parsedTransaction = await provider.connection.getParsedTransaction(
+      txid,
+      { commitment: "confirmed", maxSupportedTransactionVersion: 0 }
+    );
+    const transferDestinations = (
+      parsedTransaction?.meta?.innerInstructions ?? []
+    )
+      .filter(({ index }) => index === transaction.instructions.length - 1)
+      .flatMap(({ instructions }) => instructions)
+      .map((instruction) =>
+        "parsed" in instruction && instruction.program === "spl-token"
+          ? (instruction.parsed as {
+              type: string;
+              info: { destination: string };
+            })
+          : null
+      )
+      .filter((parsed) => parsed?.type === "transferChecked")
+      .map((parsed) => parsed?.info.destination ?? "");
+    return [transferDestinations, after - before];
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 0,
+        creators: null,
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    buySideTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      buySideReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Buy side fee is transferred when it is not zero", async () => {
+    const paymentAmount = new BN(10000);
+    const [transferDestinations, buySideDelta] = await handlePayment(
+      paymentAmount
+    );
+    expect(buySideDelta).toEqual(
+      paymentAmount
+        .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+        .divn(BASIS_POINTS_DIVISOR)
+        .toNumber()
+    );
+    expect(transferDestinations).toContain(buySideTokenAccountId.toString());
+  });
+
+  it("No transfer is made for a zero buy side fee", async () => {
+    // rounds down to a buy side fee of zero
+    const paymentAmount = new BN(100);
+    expect(
+      paymentAmount
+        .muln(DEFAULT_BUY_SIDE_FEE_SHARE)
+        .divn(BASIS_POINTS_DIVISOR)
+        .toNumber()
+    ).toEqual(0);
+    const [transferDestinations, buySideDelta] = await handlePayment(
+      paymentAmount
+    );
+    expect(buySideDelta).toEqual(0);
+    expect(transferDestinations).not.toContain(
+      buySideTokenAccountId.toString()
+    );
+    // only the fee collector and the payment target are paid
+    expect(transferDestinations.length).toEqual(2);
+  });
+});
@@ -0,0 +1,263 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import { createTransferInstruction } from "@solana/spl-token";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { PAYMENT_MANAGER_ADDRESS } from "../sdk";
+import { getRoyaltyClaim } from "../sdk/accounts";
+import {
+  findPaymentManagerAddress,
+  findRoyaltyClaimAddress,
+  findRoyaltyEscrowAddress,
+} from "../sdk/pda";
+import {
+  withClaimRoyalty,
+  withInit,
+  withMigrateEscrow,
+} from "../sdk/transaction";
+import { getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Migrate an escrow derived from old seeds", () => {
+  const ESCROW_AMOUNT = new BN(12345);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const stranger = Keypair.generate();
+  const creator = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let provider: SolanaProvider;
+
+  // seeds an earlier program version derived its royalty escrow from
+  const oldSeeds = () => [
+    Buffer.from("legacy-royalty-escrow"),
+    findPaymentManagerAddress(paymentManagerName).toBuffer(),
+    paymentMintId.toBuffer(),
+  ];
+
+  // funds a token account owned by the address derived from the seeds
+  const createOldEscrow = async (seeds: Buffer[]): Promise<PublicKey> => {
+    const [oldEscrowAuthorityId] = PublicKey.findProgramAddressSync(
+      seeds,
+      PAYMENT_MANAGER_ADDRESS
+    );
+    const transaction = new web3.Transaction();
+    const oldEscrowId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      oldEscrowAuthorityId,
+      provider.wallet.publicKey,
+      true
+    );
+    transaction.add(
+      createTransferInstruction(
+        await findAta(paymentMintId, provider.wallet.publicKey, true),
+        oldEscrowId,
+        provider.wallet.publicKey,
+        BigInt(ESCROW_AMOUNT.toString())
+      )
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    return oldEscrowId;
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    for (const keypair of [tokenCreator, stranger, creator]) {
+      const airdrop = await provider.connection.requestAirdrop(
+        keypair.publicKey,
+        LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdrop);
+    }
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: 500,
+      takerFeeBasisPoints: 300,
+      includeSellerFeeBasisPoints: true,
+      claimRoyalties: true,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Only the authority can migrate an escrow", async () => {
+    const oldEscrowId = await createOldEscrow(oldSeeds());
+    const transaction = new web3.Transaction();
+    await withMigrateEscrow(
+      transaction,
+      provider.connection,
+      new Wallet(stranger),
+      {
+        paymentManagerName,
+        paymentMintId,
+        oldEscrowId,
+        oldSeeds: oldSeeds(),
+        creatorId: creator.publicKey,
+      }
+    );
+    await expect(
+      executeTransaction(
+        provider.connection,
+        transaction,
+        new Wallet(stranger),
+        { silent: true }
+      )
+    ).rejects.toThrow();
+  });
+
+  it("Old seeds have to include the payment manager", async () => {
+    const seeds = [
+      Buffer.from("legacy-royalty-escrow"),
+      paymentMintId.toBuffer(),
+    ];
+    const oldEscrowId = await createOldEscrow(seeds);
+    const transaction = new web3.Transaction();
+    await withMigrateEscrow(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      paymentMintId,
+      oldEscrowId,
+      oldSeeds: seeds,
+      creatorId: creator.publicKey,
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow("0x179b"); // InvalidEscrowSeeds
+  });
+
+  it("Escrows derived from live seeds cannot be migrated", async () => {
+    const seeds = [
+      Buffer.from("proceeds-escrow"),
+      findPaymentManagerAddress(paymentManagerName).toBuffer(),
+      paymentMintId.toBuffer(),
+      provider.wallet.publicKey.toBuffer(),
+    ];
+    const oldEscrowId = await createOldEscrow(seeds);
+    const transaction = new web3.Transaction();
+    await withMigrateEscrow(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      paymentMintId,
+      oldEscrowId,
+      oldSeeds: seeds,
+      creatorId: creator.publicKey,
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow("0x179b"); // InvalidEscrowSeeds
+  });
+
+  it("Live seeds split over several seeds cannot be migrated", async () => {
+    // derives the live proceeds escrow of the payer
+    const seeds = [
+      Buffer.from("proceeds-"),
+      Buffer.from("escrow"),
+      findPaymentManagerAddress(paymentManagerName).toBuffer(),
+      paymentMintId.toBuffer(),
+      provider.wallet.publicKey.toBuffer(),
+    ];
+    const oldEscrowId = await createOldEscrow(seeds);
+    const transaction = new web3.Transaction();
+    await withMigrateEscrow(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      paymentMintId,
+      oldEscrowId,
+      oldSeeds: seeds,
+      creatorId: creator.publicKey,
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow("0x179b"); // InvalidEscrowSeeds
+  });
+
+  it("Balance moves to the royalty escrow and is claimable", async () => {
+    const oldEscrowId = await findAta(
+      paymentMintId,
+      PublicKey.findProgramAddressSync(oldSeeds(), PAYMENT_MANAGER_ADDRESS)[0],
+      true
+    );
+    // funded once by the first test
+    expect(await getTokenAmount(provider.connection, oldEscrowId)).toEqual(
+      ESCROW_AMOUNT.toNumber()
+    );
+    const transaction = new web3.Transaction();
+    await withMigrateEscrow(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      paymentMintId,
+      oldEscrowId,
+      oldSeeds: oldSeeds(),
+      creatorId: creator.publicKey,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+
+    expect(
+      await getTokenAmount(
+        provider.connection,
+        findRoyaltyEscrowAddress(
+          findPaymentManagerAddress(paymentManagerName),
+          paymentMintId
+        )
+      )
+    ).toEqual(ESCROW_AMOUNT.toNumber());
+    // the old escrow is closed
+    expect(await provider.connection.getAccountInfo(oldEscrowId)).toBeNull();
+    const royaltyClaim = await getRoyaltyClaim(
+      provider.connection,
+      findRoyaltyClaimAddress(
+        findPaymentManagerAddress(paymentManagerName),
+        paymentMintId,
+        creator.publicKey
+      )
+    );
+    expect(royaltyClaim.parsed.amount.toNumber()).toEqual(
+      ESCROW_AMOUNT.toNumber()
+    );
+
+    const claimTransaction = new web3.Transaction();
+    const creatorTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      claimTransaction,
+      provider.connection,
+      paymentMintId,
+      creator.publicKey,
+      creator.publicKey
+    );
+    await withClaimRoyalty(
+      claimTransaction,
+      provider.connection,
+      new Wallet(creator),
+      { paymentManagerName, paymentMintId, creatorTokenAccountId }
+    );
+    await executeTransaction(
+      provider.connection,
+      claimTransaction,
+      new Wallet(creator)
+    );
+    expect(
+      await getTokenAmount(provider.connection, creatorTokenAccountId)
+    ).toEqual(ESCROW_AMOUNT.toNumber());
+  });
+});
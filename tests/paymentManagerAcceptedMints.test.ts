@@ -0,0 +1,162 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { getPaymentManager } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import {
+  withHandlePaymentWithRoyalties,
+  withInit,
+  withSetAcceptedMints,
+} from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Accepted payment mints", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const MAX_ACCEPTED_MINTS = 8;
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let acceptedMintId: PublicKey;
+  let otherMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, acceptedMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    [, otherMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      { sellerFeeBasisPoints: 0, creators: null }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  const setAcceptedMints = async (acceptedMintIds: PublicKey[]) => {
+    const transaction = new web3.Transaction();
+    await withSetAcceptedMints(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      { paymentManagerName, acceptedMintIds }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet, {
+      silent: true,
+    });
+  };
+
+  const pay = async (paymentMintId: PublicKey) => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet, {
+      silent: true,
+    });
+  };
+
+  it("Any mint is accepted by default", async () => {
+    await pay(acceptedMintId);
+    await pay(otherMintId);
+  });
+
+  it("Only listed mints are accepted", async () => {
+    await setAcceptedMints([acceptedMintId]);
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(
+      paymentManagerData.parsed.acceptedMints.map((id) => id.toString())
+    ).toEqual([acceptedMintId.toString()]);
+
+    await pay(acceptedMintId);
+    await expect(pay(otherMintId)).rejects.toThrow("0x1794"); // UnsupportedPaymentMint
+  });
+
+  it("Clearing the list accepts any mint again", async () => {
+    await setAcceptedMints([]);
+    await pay(otherMintId);
+  });
+
+  it("The list is bounded", async () => {
+    await expect(
+      setAcceptedMints(
+        Array.from(
+          { length: MAX_ACCEPTED_MINTS + 1 },
+          () => Keypair.generate().publicKey
+        )
+      )
+    ).rejects.toThrow("0x1795"); // TooManyAcceptedMints
+  });
+});
@@ -0,0 +1,120 @@
+import { BN, web3 } from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { executeTransaction, tryGetAccount } from "@solana-nft-programs/common";
+
+import { getPaymentManager } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withInit, withUpdate } from "../sdk/transaction";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Payment manager fee config limits", () => {
+  const MAKER_FEE = 5000;
+  const TAKER_FEE = 300;
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const overCommittedPaymentManagerName = Math.random()
+    .toString(36)
+    .slice(2, 7);
+  const feeCollector = Keypair.generate();
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+  });
+
+  it("Create payment manager with over committed fees fails", async () => {
+    // 5000 maker + 5000 default max royalty + 50 buy side exceeds the payment
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName: overCommittedPaymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE,
+      takerFeeBasisPoints: TAKER_FEE,
+      includeSellerFeeBasisPoints: true,
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+    expect(
+      await tryGetAccount(() =>
+        getPaymentManager(
+          provider.connection,
+          findPaymentManagerAddress(overCommittedPaymentManagerName)
+        )
+      )
+    ).toBeNull();
+  });
+
+  it("Create payment manager with royalty share over 100% fails", async () => {
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName: overCommittedPaymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE,
+      takerFeeBasisPoints: TAKER_FEE,
+      includeSellerFeeBasisPoints: false,
+      royaltyFeeShare: new BN(10001),
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+
+  it("Create payment manager with the default fee collector fails", async () => {
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName: overCommittedPaymentManagerName,
+      feeCollectorId: PublicKey.default,
+      makerFeeBasisPoints: 500,
+      takerFeeBasisPoints: TAKER_FEE,
+      includeSellerFeeBasisPoints: false,
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+
+  it("Create payment manager within the limit", async () => {
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE,
+      takerFeeBasisPoints: TAKER_FEE,
+      includeSellerFeeBasisPoints: true,
+      maxRoyaltyBasisPoints: 4950,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(paymentManagerData.parsed.maxRoyaltyBasisPoints).toEqual(4950);
+  });
+
+  it("Update to over committed fees fails", async () => {
+    const transaction = new web3.Transaction();
+    await withUpdate(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      maxRoyaltyBasisPoints: 4951,
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(paymentManagerData.parsed.maxRoyaltyBasisPoints).toEqual(4950);
+  });
+});
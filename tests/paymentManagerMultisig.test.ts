@@ -0,0 +1,117 @@
+import { web3 } from "@coral-xyz/anchor";
+import { Keypair } from "@solana/web3.js";
+import { executeTransaction, tryGetAccount } from "@solana-nft-programs/common";
+
+import { getPaymentManager } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withClose, withInit, withUpdate } from "../sdk/transaction";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Multisig payment manager authority", () => {
+  const MAKER_FEE = 500;
+  const TAKER_FEE = 300;
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const adminA = Keypair.generate();
+  const adminB = Keypair.generate();
+  const adminC = Keypair.generate();
+  const admins = [adminA, adminB, adminC];
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+  });
+
+  it("Create payment manager", async () => {
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE,
+      takerFeeBasisPoints: TAKER_FEE,
+      includeSellerFeeBasisPoints: false,
+      authorityThreshold: 2,
+      admins: admins.map((admin) => admin.publicKey),
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(paymentManagerData.parsed.authorityThreshold).toEqual(2);
+    expect(paymentManagerData.parsed.admins.length).toEqual(admins.length);
+  });
+
+  it("Update with authority only fails", async () => {
+    const transaction = new web3.Transaction();
+    await withUpdate(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      strictRemainingAccounts: true,
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+
+  it("Update with a single admin signer fails", async () => {
+    const transaction = new web3.Transaction();
+    await withUpdate(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      strictRemainingAccounts: true,
+      adminSignerIds: [adminA.publicKey],
+    });
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+        signers: [adminA],
+      })
+    ).rejects.toThrow();
+  });
+
+  it("Update with threshold admin signers succeeds", async () => {
+    const transaction = new web3.Transaction();
+    await withUpdate(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      strictRemainingAccounts: true,
+      adminSignerIds: [adminA.publicKey, adminC.publicKey],
+    });
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { signers: [adminA, adminC] }
+    );
+
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(paymentManagerData.parsed.strictRemainingAccounts).toEqual(true);
+  });
+
+  it("Close with threshold admin signers succeeds", async () => {
+    const transaction = new web3.Transaction();
+    await withClose(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      adminSignerIds: [adminB.publicKey, adminC.publicKey],
+    });
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { signers: [adminB, adminC] }
+    );
+
+    const paymentManagerData = await tryGetAccount(() =>
+      getPaymentManager(
+        provider.connection,
+        findPaymentManagerAddress(paymentManagerName)
+      )
+    );
+    expect(paymentManagerData).toEqual(null);
+  });
+});
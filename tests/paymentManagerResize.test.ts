@@ -0,0 +1,106 @@
+import { web3 } from "@coral-xyz/anchor";
+import { Keypair } from "@solana/web3.js";
+import { executeTransaction } from "@solana-nft-programs/common";
+
+import { getPaymentManager } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import {
+  withResizePaymentManager,
+  withSetFeeCollector,
+} from "../sdk/transaction";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+// loaded by the test validator from tests/fixtures/legacy-payment-manager.json
+// in the original layout, owned by the test key
+describe("Resize legacy payment manager", () => {
+  const paymentManagerName = "legacy-manager";
+  const paymentManagerId = findPaymentManagerAddress(paymentManagerName);
+  const LEGACY_SIZE = 136;
+  const feeCollector = Keypair.generate();
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+  });
+
+  const setFeeCollector = async () => {
+    const transaction = new web3.Transaction();
+    await withSetFeeCollector(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      { paymentManagerName, feeCollectorId: feeCollector.publicKey }
+    );
+    return executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true }
+    );
+  };
+
+  const resize = async () => {
+    const transaction = new web3.Transaction();
+    await withResizePaymentManager(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      { paymentManagerName }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  };
+
+  it("Legacy payment manager cannot be loaded", async () => {
+    const accountInfo = await provider.connection.getAccountInfo(
+      paymentManagerId
+    );
+    expect(accountInfo?.data.length).toEqual(LEGACY_SIZE);
+    await expect(
+      getPaymentManager(provider.connection, paymentManagerId)
+    ).rejects.toThrow();
+    await expect(setFeeCollector()).rejects.toThrow();
+  });
+
+  it("Resize keeps the legacy fields", async () => {
+    await resize();
+    const accountInfo = await provider.connection.getAccountInfo(
+      paymentManagerId
+    );
+    expect(accountInfo?.data.length).toBeGreaterThan(LEGACY_SIZE);
+
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      paymentManagerId
+    );
+    expect(paymentManagerData.parsed.name).toEqual(paymentManagerName);
+    expect(paymentManagerData.parsed.authority.toString()).toEqual(
+      provider.wallet.publicKey.toString()
+    );
+    expect(paymentManagerData.parsed.makerFeeBasisPoints).toEqual(500);
+    expect(paymentManagerData.parsed.takerFeeBasisPoints).toEqual(300);
+    expect(paymentManagerData.parsed.includeSellerFeeBasisPoints).toEqual(
+      true
+    );
+    expect(paymentManagerData.parsed.royaltyFeeShare).toEqual(null);
+    expect(paymentManagerData.parsed.sellerFeeWithinFees).toEqual(false);
+  });
+
+  it("Resized payment manager can be administered", async () => {
+    await setFeeCollector();
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      paymentManagerId
+    );
+    expect(paymentManagerData.parsed.feeCollector.toString()).toEqual(
+      feeCollector.publicKey.toString()
+    );
+  });
+
+  it("Resizing again is a no-op", async () => {
+    const before = await provider.connection.getAccountInfo(paymentManagerId);
+    await resize();
+    const after = await provider.connection.getAccountInfo(paymentManagerId);
+    expect(after?.data.length).toEqual(before?.data.length);
+  });
+});
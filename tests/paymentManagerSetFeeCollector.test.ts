@@ -0,0 +1,189 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { getPaymentManager } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import {
+  withHandlePaymentWithRoyalties,
+  withInit,
+  withSetFeeCollector,
+} from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Set fee collector", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const oldFeeCollector = Keypair.generate();
+  const newFeeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 500,
+        creators: [{ address: creator1.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: oldFeeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: true,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  // returns what the old and the new fee collector received
+  const pay = async (): Promise<[number, number]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [
+      await findAta(paymentMintId, oldFeeCollector.publicKey, true),
+      await findAta(paymentMintId, newFeeCollector.publicKey, true),
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return [
+      (after[0] ?? 0) - (before[0] ?? 0),
+      (after[1] ?? 0) - (before[1] ?? 0),
+    ];
+  };
+
+  it("Payments after the change go to the new fee collector", async () => {
+    const [oldFeeCollectorFees, unchangedFees] = await pay();
+    expect(oldFeeCollectorFees).toBeGreaterThan(0);
+    expect(unchangedFees).toEqual(0);
+
+    const transaction = new web3.Transaction();
+    await withSetFeeCollector(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      { paymentManagerName, feeCollectorId: newFeeCollector.publicKey }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(paymentManagerData.parsed.feeCollector.toString()).toEqual(
+      newFeeCollector.publicKey.toString()
+    );
+
+    expect(await pay()).toEqual([0, oldFeeCollectorFees]);
+  });
+
+  it("Token account as fee collector fails", async () => {
+    const transaction = new web3.Transaction();
+    await withSetFeeCollector(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        feeCollectorId: await findAta(
+          paymentMintId,
+          newFeeCollector.publicKey,
+          true
+        ),
+      }
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+
+  it("Only the authority can set the fee collector", async () => {
+    const other = Keypair.generate();
+    const airdrop = await provider.connection.requestAirdrop(
+      other.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdrop);
+    const transaction = new web3.Transaction();
+    await withSetFeeCollector(
+      transaction,
+      provider.connection,
+      new Wallet(other),
+      { paymentManagerName, feeCollectorId: other.publicKey }
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, new Wallet(other), {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+});
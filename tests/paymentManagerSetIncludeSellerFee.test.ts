@@ -0,0 +1,172 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+} from "@solana-nft-programs/common";
+
+import { getPaymentManager } from "../sdk/accounts";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import {
+  withHandlePaymentWithRoyalties,
+  withInit,
+  withSetIncludeSellerFee,
+} from "../sdk/transaction";
+import { withRemainingAccountsForPayment } from "../sdk/utils";
+import { createNftWithCreators, getTokenAmount } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Set include seller fee", () => {
+  const MAKER_FEE = new BN(500);
+  const TAKER_FEE = new BN(300);
+  const paymentAmount = new BN(10000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator1 = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let mintId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 600,
+        creators: [{ address: creator1.publicKey, share: 100 }],
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE.toNumber(),
+      takerFeeBasisPoints: TAKER_FEE.toNumber(),
+      includeSellerFeeBasisPoints: false,
+    });
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  const setIncludeSellerFee = async (includeSellerFeeBasisPoints: boolean) => {
+    const transaction = new web3.Transaction();
+    await withSetIncludeSellerFee(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      { paymentManagerName, includeSellerFeeBasisPoints }
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const paymentManagerData = await getPaymentManager(
+      provider.connection,
+      findPaymentManagerAddress(paymentManagerName)
+    );
+    expect(paymentManagerData.parsed.includeSellerFeeBasisPoints).toEqual(
+      includeSellerFeeBasisPoints
+    );
+  };
+
+  // returns what the creator and the payment target received
+  const pay = async (): Promise<[number, number]> => {
+    const transaction = new web3.Transaction();
+    const [paymentTokenAccountId, feeCollectorTokenAccountId, _accounts] =
+      await withRemainingAccountsForPayment(
+        transaction,
+        provider.connection,
+        provider.wallet,
+        mintId,
+        paymentMintId,
+        paymentReceiver.publicKey,
+        findPaymentManagerAddress(paymentManagerName)
+      );
+    await withHandlePaymentWithRoyalties(
+      transaction,
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName,
+        paymentAmount,
+        mintId,
+        paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId,
+        paymentTokenAccountId,
+        excludeCretors: [],
+      }
+    );
+    const accountIds = [
+      await findAta(paymentMintId, creator1.publicKey, true),
+      paymentTokenAccountId,
+    ];
+    const before = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    const after = await Promise.all(
+      accountIds.map((id) => getTokenAmount(provider.connection, id))
+    );
+    return [
+      (after[0] ?? 0) - (before[0] ?? 0),
+      (after[1] ?? 0) - (before[1] ?? 0),
+    ];
+  };
+
+  it("Toggling the seller fee changes the next payment", async () => {
+    const [excludedCreatorDelta, excludedPaymentDelta] = await pay();
+    await setIncludeSellerFee(true);
+    const [includedCreatorDelta, includedPaymentDelta] = await pay();
+    expect(includedCreatorDelta).toBeGreaterThan(excludedCreatorDelta);
+    // the seller fee is taken from the maker-taker fees, not the principal
+    expect(includedPaymentDelta).toEqual(excludedPaymentDelta);
+
+    await setIncludeSellerFee(false);
+    expect(await pay()).toEqual([excludedCreatorDelta, excludedPaymentDelta]);
+  });
+
+  it("Only the authority can toggle the seller fee", async () => {
+    const other = Keypair.generate();
+    const airdrop = await provider.connection.requestAirdrop(
+      other.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdrop);
+    const transaction = new web3.Transaction();
+    await withSetIncludeSellerFee(
+      transaction,
+      provider.connection,
+      new Wallet(other),
+      { paymentManagerName, includeSellerFeeBasisPoints: true }
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, new Wallet(other), {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+});
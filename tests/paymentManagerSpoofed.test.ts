@@ -0,0 +1,133 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { paymentManagerProgram } from "../sdk";
+import { findPaymentManagerAddress } from "../sdk/pda";
+import { withInit } from "../sdk/transaction";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Spoofed payment manager", () => {
+  const MAKER_FEE = 500;
+  const TAKER_FEE = 300;
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let payerTokenAccountId: PublicKey;
+  let feeCollectorTokenAccountId: PublicKey;
+  let paymentTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [payerTokenAccountId, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+
+    const transaction = new web3.Transaction();
+    await withInit(transaction, provider.connection, provider.wallet, {
+      paymentManagerName,
+      feeCollectorId: feeCollector.publicKey,
+      makerFeeBasisPoints: MAKER_FEE,
+      takerFeeBasisPoints: TAKER_FEE,
+      includeSellerFeeBasisPoints: false,
+    });
+    feeCollectorTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      feeCollector.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    paymentTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      paymentReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+
+  it("Handle payment with a non payment manager account fails", async () => {
+    const transaction = new web3.Transaction();
+    transaction.add(
+      await paymentManagerProgram(provider.connection, provider.wallet)
+        .methods.managePayment(new BN(1000))
+        .accounts({
+          paymentManager: feeCollectorTokenAccountId,
+          payerTokenAccount: payerTokenAccountId,
+          feeCollectorTokenAccount: feeCollectorTokenAccountId,
+          paymentTokenAccount: paymentTokenAccountId,
+          payer: provider.wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .instruction()
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+
+  it("Close with a non payment manager account fails", async () => {
+    const transaction = new web3.Transaction();
+    transaction.add(
+      await paymentManagerProgram(provider.connection, provider.wallet)
+        .methods.close()
+        .accounts({
+          paymentManager: payerTokenAccountId,
+          collector: provider.wallet.publicKey,
+          closer: provider.wallet.publicKey,
+        })
+        .instruction()
+    );
+    await expect(
+      executeTransaction(provider.connection, transaction, provider.wallet, {
+        silent: true,
+      })
+    ).rejects.toThrow();
+  });
+
+  it("Handle payment with the real payment manager succeeds", async () => {
+    const transaction = new web3.Transaction();
+    transaction.add(
+      await paymentManagerProgram(provider.connection, provider.wallet)
+        .methods.managePayment(new BN(1000))
+        .accounts({
+          paymentManager: findPaymentManagerAddress(paymentManagerName),
+          payerTokenAccount: payerTokenAccountId,
+          feeCollectorTokenAccount: feeCollectorTokenAccountId,
+          paymentTokenAccount: paymentTokenAccountId,
+          payer: provider.wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .instruction()
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+  });
+});
@@ -0,0 +1,162 @@
+import { BN, Wallet } from "@coral-xyz/anchor";
+import {
+  createCreateMasterEditionV3Instruction,
+  createCreateMetadataAccountV2Instruction,
+  createVerifyCollectionInstruction,
+} from "@metaplex-foundation/mpl-token-metadata";
+import { getAccount } from "@solana/spl-token";
+import type { Connection, Keypair, PublicKey } from "@solana/web3.js";
+import { Transaction } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findMintEditionId,
+  findMintMetadataId,
+} from "@solana-nft-programs/common";
+
+import { CREATOR_SHARE_DIVISOR } from "../sdk";
+
+/**
+ * Returns the balance of a token account or 0 if it does not exist yet
+ */
+export const getTokenAmount = async (
+  connection: Connection,
+  tokenAccountId: PublicKey
+): Promise<number> => {
+  try {
+    return Number((await getAccount(connection, tokenAccountId)).amount);
+  } catch (e) {
+    return 0;
+  }
+};
+
+/**
+ * Creates an NFT with metadata and a master edition for the given creators
+ */
+export const createNftWithCreators = async (
+  connection: Connection,
+  tokenCreator: Keypair,
+  target: PublicKey,
+  params: {
+    sellerFeeBasisPoints: number;
+    creators: { address: PublicKey; share: number }[] | null;
+    collectionId?: PublicKey;
+  }
+): Promise<PublicKey> => {
+  const [, mintId] = await createMint(connection, new Wallet(tokenCreator), {
+    target,
+  });
+  const metadataId = findMintMetadataId(mintId);
+  const masterEditionId = findMintEditionId(mintId);
+  const transaction = new Transaction().add(
+    createCreateMetadataAccountV2Instruction(
+      {
+        metadata: metadataId,
+        mint: mintId,
+        mintAuthority: tokenCreator.publicKey,
+        payer: tokenCreator.publicKey,
+        updateAuthority: tokenCreator.publicKey,
+      },
+      {
+        createMetadataAccountArgsV2: {
+          isMutable: true,
+          data: {
+            name: "test",
+            symbol: "TST",
+            uri: "http://test/",
+            sellerFeeBasisPoints: params.sellerFeeBasisPoints,
+            creators: params.creators
+              ? [
+                  {
+                    address: tokenCreator.publicKey,
+                    verified: true,
+                    share: 0,
+                  },
+                  ...params.creators.map(({ address, share }) => ({
+                    address,
+                    verified: false,
+                    share,
+                  })),
+                ]
+              : null,
+            collection: params.collectionId
+              ? { key: params.collectionId, verified: false }
+              : null,
+            uses: null,
+          },
+        },
+      }
+    ),
+    createCreateMasterEditionV3Instruction(
+      {
+        edition: masterEditionId,
+        mint: mintId,
+        updateAuthority: tokenCreator.publicKey,
+        mintAuthority: tokenCreator.publicKey,
+        metadata: metadataId,
+        payer: tokenCreator.publicKey,
+      },
+      {
+        createMasterEditionArgs: {
+          maxSupply: new BN(0),
+        },
+      }
+    )
+  );
+  await executeTransaction(connection, transaction, new Wallet(tokenCreator));
+  return mintId;
+};
+
+/**
+ * Splits the total creators fee between creators the same way the program does,
+ * handing out the rounding remainder one unit at a time in creator order
+ */
+export const calculateCreatorFunds = (
+  totalCreatorsFee: BN,
+  shares: number[],
+  remainderToCollector = false
+): BN[] => {
+  // what is left once each share is rounded down, kept by the fee collector
+  // with remainderToCollector
+  let creatorsFeeRemainder = remainderToCollector
+    ? new BN(0)
+    : totalCreatorsFee.sub(
+        shares.reduce(
+          (sum, share) =>
+            sum.add(totalCreatorsFee.muln(share).divn(CREATOR_SHARE_DIVISOR)),
+          new BN(0)
+        )
+      );
+  return shares.map((share) => {
+    if (share === 0) return new BN(0);
+    const remainderAmount = creatorsFeeRemainder.gtn(0) ? 1 : 0;
+    creatorsFeeRemainder = creatorsFeeRemainder.subn(remainderAmount);
+    return totalCreatorsFee
+      .muln(share)
+      .divn(CREATOR_SHARE_DIVISOR)
+      .addn(remainderAmount);
+  });
+};
+
+/**
+ * Verifies the collection of an NFT created with createNftWithCreators, the
+ * token creator is the update authority of both the NFT and the collection
+ */
+export const verifyCollection = async (
+  connection: Connection,
+  tokenCreator: Keypair,
+  mintId: PublicKey,
+  collectionId: PublicKey
+): Promise<void> => {
+  const transaction = new Transaction().add(
+    createVerifyCollectionInstruction({
+      metadata: findMintMetadataId(mintId),
+      collectionAuthority: tokenCreator.publicKey,
+      payer: tokenCreator.publicKey,
+      collectionMint: collectionId,
+      collection: findMintMetadataId(collectionId),
+      collectionMasterEditionAccount: findMintEditionId(collectionId),
+    })
+  );
+  await executeTransaction(connection, transaction, new Wallet(tokenCreator));
+};
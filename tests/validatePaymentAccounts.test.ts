@@ -0,0 +1,235 @@
+import { BN, Wallet, web3 } from "@coral-xyz/anchor";
+import type { PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  executeTransaction,
+  findAta,
+  withFindOrInitAssociatedTokenAccount,
+} from "@solana-nft-programs/common";
+
+import { withInit, withValidatePaymentAccounts } from "../sdk/transaction";
+import { creatorHash } from "../sdk/utils";
+import { createNftWithCreators } from "./utils";
+import type { SolanaProvider } from "./workspace";
+import { getProvider } from "./workspace";
+
+describe("Validate payment accounts", () => {
+  const MAKER_FEE = 500;
+  const TAKER_FEE = 300;
+  const PAYMENT_AMOUNT = new BN(1000);
+  const RECIPIENT_START_PAYMENT_AMOUNT = new BN(10000000000);
+  const paymentManagerName = Math.random().toString(36).slice(2, 7);
+  const fallbackPaymentManagerName = Math.random().toString(36).slice(2, 7);
+  const feeCollector = Keypair.generate();
+  const creator = Keypair.generate();
+  const tokenCreator = Keypair.generate();
+  const paymentReceiver = Keypair.generate();
+  let paymentMintId: PublicKey;
+  let otherMintId: PublicKey;
+  let mintId: PublicKey;
+  let creatorlessMintId: PublicKey;
+  let feeCollectorTokenAccountId: PublicKey;
+  let paymentTokenAccountId: PublicKey;
+  let provider: SolanaProvider;
+
+  const validatePaymentAccounts = async (params?: {
+    paymentManagerName?: string;
+    mintId?: PublicKey;
+    feeCollectorTokenAccountId?: PublicKey;
+    paymentTokenAccountId?: PublicKey;
+    buySideTokenAccountId?: PublicKey;
+    expectedCreatorHash?: number[];
+  }) => {
+    const transaction = await withValidatePaymentAccounts(
+      new web3.Transaction(),
+      provider.connection,
+      provider.wallet,
+      {
+        paymentManagerName: params?.paymentManagerName ?? paymentManagerName,
+        paymentAmount: PAYMENT_AMOUNT,
+        mintId: params?.mintId ?? mintId,
+        paymentMintId: paymentMintId,
+        payerTokenAccountId: await findAta(
+          paymentMintId,
+          provider.wallet.publicKey,
+          true
+        ),
+        feeCollectorTokenAccountId:
+          params?.feeCollectorTokenAccountId ?? feeCollectorTokenAccountId,
+        paymentTokenAccountId:
+          params?.paymentTokenAccountId ?? paymentTokenAccountId,
+        buySideTokenAccountId: params?.buySideTokenAccountId,
+        expectedCreatorHash: params?.expectedCreatorHash,
+      }
+    );
+    await executeTransaction(
+      provider.connection,
+      transaction,
+      provider.wallet,
+      { silent: true }
+    );
+  };
+
+  beforeAll(async () => {
+    provider = await getProvider();
+    const airdropCreator = await provider.connection.requestAirdrop(
+      tokenCreator.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdropCreator);
+
+    [, paymentMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      {
+        target: provider.wallet.publicKey,
+        amount: RECIPIENT_START_PAYMENT_AMOUNT.toNumber(),
+      }
+    );
+    [, otherMintId] = await createMint(
+      provider.connection,
+      new Wallet(tokenCreator),
+      { target: provider.wallet.publicKey, amount: 1 }
+    );
+    mintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      {
+        sellerFeeBasisPoints: 100,
+        creators: [{ address: creator.publicKey, share: 100 }],
+      }
+    );
+    creatorlessMintId = await createNftWithCreators(
+      provider.connection,
+      tokenCreator,
+      provider.wallet.publicKey,
+      { sellerFeeBasisPoints: 100, creators: null }
+    );
+
+    const transaction = new web3.Transaction();
+    feeCollectorTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      feeCollector.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    paymentTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      paymentReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      creator.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    // the update authority of the creatorless mint
+    await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      paymentMintId,
+      tokenCreator.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+
+    const initTransaction = new web3.Transaction();
+    for (const [name, fallbackRoyaltyToUpdateAuthority] of [
+      [paymentManagerName, false],
+      [fallbackPaymentManagerName, true],
+    ] as const) {
+      await withInit(initTransaction, provider.connection, provider.wallet, {
+        paymentManagerName: name,
+        feeCollectorId: feeCollector.publicKey,
+        makerFeeBasisPoints: MAKER_FEE,
+        takerFeeBasisPoints: TAKER_FEE,
+        includeSellerFeeBasisPoints: true,
+        strictRemainingAccounts: true,
+        fallbackRoyaltyToUpdateAuthority,
+      });
+    }
+    await executeTransaction(
+      provider.connection,
+      initTransaction,
+      provider.wallet
+    );
+  });
+
+  it("Valid account set", async () => {
+    await validatePaymentAccounts();
+  });
+
+  it("Fee collector token account of another owner", async () => {
+    await expect(
+      validatePaymentAccounts({
+        feeCollectorTokenAccountId: paymentTokenAccountId,
+      })
+    ).rejects.toThrow("0x1770"); // InvalidFeeCollectorTokenAccount
+  });
+
+  it("Payment token account of another mint", async () => {
+    const transaction = new web3.Transaction();
+    const otherTokenAccountId = await withFindOrInitAssociatedTokenAccount(
+      transaction,
+      provider.connection,
+      otherMintId,
+      paymentReceiver.publicKey,
+      provider.wallet.publicKey,
+      true
+    );
+    await executeTransaction(provider.connection, transaction, provider.wallet);
+    await expect(
+      validatePaymentAccounts({ paymentTokenAccountId: otherTokenAccountId })
+    ).rejects.toThrow("0x1790"); // InvalidTokenAccountMint
+  });
+
+  it("Payment token account that is the fee collector token account", async () => {
+    await expect(
+      validatePaymentAccounts({
+        paymentTokenAccountId: feeCollectorTokenAccountId,
+      })
+    ).rejects.toThrow("0x1796"); // TargetEqualsFeeCollector
+  });
+
+  it("Buy side account that is not a token account", async () => {
+    await expect(
+      validatePaymentAccounts({
+        buySideTokenAccountId: Keypair.generate().publicKey,
+      })
+    ).rejects.toThrow("0x1779"); // InvalidBuySideAccount
+  });
+
+  it("Expected creator hash of the mint's creators", async () => {
+    await validatePaymentAccounts({
+      // the token creator is listed first with no share
+      expectedCreatorHash: creatorHash([
+        { address: tokenCreator.publicKey, share: 0 },
+        { address: creator.publicKey, share: 100 },
+      ]),
+    });
+  });
+
+  it("Expected creator hash of other creators", async () => {
+    await expect(
+      validatePaymentAccounts({ expectedCreatorHash: creatorHash(null) })
+    ).rejects.toThrow("0x179d"); // CreatorHashMismatch
+  });
+
+  it("Update authority token account of a mint without creators", async () => {
+    await validatePaymentAccounts({
+      paymentManagerName: fallbackPaymentManagerName,
+      mintId: creatorlessMintId,
+    });
+  });
+});